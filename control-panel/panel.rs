@@ -1,7 +1,7 @@
 use crate::font::{self, Font};
 use gmio::{
     atlas::{AtlasBuilder, AtlasRef},
-    render::{Renderer, RendererOptions},
+    render::{Backend, Renderer, RendererOptions},
     window::{Event, Window, WindowBuilder},
 };
 use shared::{
@@ -185,7 +185,7 @@ impl ControlPanel {
         let mut window = wb.build()?;
         let clear_colour = Colour::new(220.0 / 255.0, 220.0 / 255.0, 220.0 / 255.0);
         let mut renderer = Renderer::new(
-            (),
+            Backend::OpenGL,
             &RendererOptions { size: (WINDOW_WIDTH, WINDOW_HEIGHT), interpolate_pixels: false, ..Default::default() },
             &window,
             clear_colour,
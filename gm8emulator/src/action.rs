@@ -100,11 +100,13 @@ impl std::fmt::Debug for GmlBody {
 
 impl Tree {
     /// Turn a list of gm8exe CodeActions into an Action tree.
-    pub fn from_list(list: &[CodeAction], compiler: &mut Compiler) -> Result<Self, String> {
+    /// Actions which fail to compile are replaced with code raising the error when they're executed,
+    /// and the error messages are pushed to `errors`.
+    pub fn from_list(list: &[CodeAction], compiler: &mut Compiler, errors: &mut Vec<String>) -> Self {
         let mut iter = list.iter().enumerate().peekable();
         let mut output = Vec::new();
-        Self::from_iter(&mut iter, compiler, false, &mut output)?;
-        Ok(Self(output))
+        Self::from_iter(&mut iter, compiler, false, &mut output, errors);
+        Self(output)
     }
 
     fn from_iter<'a, T>(
@@ -112,8 +114,8 @@ impl Tree {
         compiler: &mut Compiler,
         single_group: bool,
         output: &mut Vec<Action>,
-    ) -> Result<(), String>
-    where
+        errors: &mut Vec<String>,
+    ) where
         T: Iterator<Item = (usize, &'a CodeAction)>,
     {
         // If we're only iterating a single group of actions, and the first is not a BEGIN_GROUP action,
@@ -130,11 +132,11 @@ impl Tree {
                     // If the action we got is a condition then immediately parse its if/else bodies from the iterator
                     let if_else = if action.is_condition {
                         let mut if_body = Vec::new();
-                        Self::from_iter(iter, compiler, true, &mut if_body)?;
+                        Self::from_iter(iter, compiler, true, &mut if_body, errors);
                         let mut else_body = Vec::new();
                         if let Some((_, CodeAction { action_kind: kind::ELSE, .. })) = iter.peek() {
                             iter.next(); // skip "else"
-                            Self::from_iter(iter, compiler, true, &mut else_body)?;
+                            Self::from_iter(iter, compiler, true, &mut else_body, errors);
                         }
                         Some((if_body.into_boxed_slice(), else_body.into_boxed_slice()))
                     } else {
@@ -148,56 +150,53 @@ impl Tree {
                         // For the FUNCTION execution type, a kernel function name is provided in the action's fn_name.
                         // This is compiled to a function pointer.
                         execution_type::FUNCTION => {
-                            if let Some((_, f_ptr, _)) = str::from_utf8(&action.fn_name.0)
+                            let body = match str::from_utf8(&action.fn_name.0)
                                 .ok()
                                 .and_then(|fn_name| mappings::FUNCTIONS.iter().find(|(n, _, _)| n == &fn_name))
                             {
-                                output.push(Action {
-                                    index: i,
-                                    target: if action.applies_to_something { Some(action.applies_to) } else { None },
-                                    relative: action.is_relative,
-                                    invert_condition: action.invert_condition,
-                                    body: Body::Normal {
-                                        args: Self::compile_params(
-                                            compiler,
-                                            &action.param_strings,
-                                            &action.param_types,
-                                            action.param_count,
-                                        )?,
-                                        body: GmlBody::Function(*f_ptr),
-                                        if_else,
-                                    },
-                                });
-                            } else {
-                                return Err(format!("Unknown function: {} in action {}", action.fn_name, i))
-                            }
+                                Some((_, f_ptr, _)) => Self::compile_params(
+                                    compiler,
+                                    &action.param_strings,
+                                    &action.param_types,
+                                    action.param_count,
+                                )
+                                .map(|args| (args, GmlBody::Function(*f_ptr))),
+                                None => Err(format!("unknown function {}", action.fn_name)),
+                            };
+                            output.push(Action {
+                                index: i,
+                                target: if action.applies_to_something { Some(action.applies_to) } else { None },
+                                relative: action.is_relative,
+                                invert_condition: action.invert_condition,
+                                body: Self::normal_body(body, if_else, i, errors),
+                            });
                         },
 
                         // Execution type CODE is a bit special depending on the action kind..
                         execution_type::CODE | _ => {
                             // The action's code is provided by its fn_code, so compile that.
+                            let body = Self::compile_params(
+                                compiler,
+                                &action.param_strings,
+                                &action.param_types,
+                                action.param_count,
+                            )
+                            .and_then(|args| {
+                                Ok((args, GmlBody::Code(compiler.compile(&action.fn_code.0).map_err(|e| e.message)?)))
+                            });
                             output.push(Action {
                                 index: i,
                                 target: if action.applies_to_something { Some(action.applies_to) } else { None },
                                 relative: action.is_relative,
                                 invert_condition: action.invert_condition,
-                                body: Body::Normal {
-                                    args: Self::compile_params(
-                                        compiler,
-                                        &action.param_strings,
-                                        &action.param_types,
-                                        action.param_count,
-                                    )?,
-                                    body: GmlBody::Code(compiler.compile(&action.fn_code.0).map_err(|e| e.message)?),
-                                    if_else,
-                                },
+                                body: Self::normal_body(body, if_else, i, errors),
                             });
                         },
                     }
                 },
 
                 kind::BEGIN_GROUP => {
-                    Self::from_iter(iter, compiler, true, output)?;
+                    Self::from_iter(iter, compiler, true, output, errors);
                 },
 
                 kind::EXIT => {
@@ -212,16 +211,17 @@ impl Tree {
 
                 kind::REPEAT => {
                     let mut body = Vec::new();
-                    Self::from_iter(iter, compiler, true, &mut body)?;
+                    Self::from_iter(iter, compiler, true, &mut body, errors);
+                    let count = match compiler.compile_expression(&action.param_strings[0].0) {
+                        Ok(count) => count,
+                        Err(e) => Node::RuntimeError { error: Self::report_error(e.message, i, errors) },
+                    };
                     output.push(Action {
                         index: i,
                         target: if action.applies_to_something { Some(action.applies_to) } else { None },
                         relative: action.is_relative,
                         invert_condition: action.invert_condition,
-                        body: Body::Repeat {
-                            count: compiler.compile_expression(&action.param_strings[0].0).map_err(|e| e.message)?,
-                            body: body.into_boxed_slice(),
-                        },
+                        body: Body::Repeat { count, body: body.into_boxed_slice() },
                     });
                 },
 
@@ -234,11 +234,15 @@ impl Tree {
                         target: if action.applies_to_something { Some(action.applies_to) } else { None },
                         relative: action.is_relative,
                         invert_condition: action.invert_condition,
-                        body: Body::Normal {
-                            args: Box::new([]),
-                            body: GmlBody::Code(compiler.compile(&code).map_err(|e| e.message)?),
-                            if_else: None,
-                        },
+                        body: Self::normal_body(
+                            compiler
+                                .compile(&code)
+                                .map(|c| (Box::new([]) as _, GmlBody::Code(c)))
+                                .map_err(|e| e.message),
+                            None,
+                            i,
+                            errors,
+                        ),
                     });
                 },
 
@@ -248,11 +252,15 @@ impl Tree {
                         target: if action.applies_to_something { Some(action.applies_to) } else { None },
                         relative: action.is_relative,
                         invert_condition: action.invert_condition,
-                        body: Body::Normal {
-                            args: Box::new([]),
-                            body: GmlBody::Code(compiler.compile(&action.param_strings[0].0).map_err(|e| e.message)?),
-                            if_else: None,
-                        },
+                        body: Self::normal_body(
+                            compiler
+                                .compile(&action.param_strings[0].0)
+                                .map(|c| (Box::new([]) as _, GmlBody::Code(c)))
+                                .map_err(|e| e.message),
+                            None,
+                            i,
+                            errors,
+                        ),
                     });
                 },
 
@@ -264,8 +272,32 @@ impl Tree {
                 break
             }
         }
+    }
+
+    /// Builds a Body::Normal from a compile result. If compiling failed, the error is reported,
+    /// and the body is replaced with code which raises it when executed.
+    fn normal_body(
+        compiled: Result<(Box<[Node]>, GmlBody), String>,
+        if_else: Option<(Box<[Action]>, Box<[Action]>)>,
+        index: usize,
+        errors: &mut Vec<String>,
+    ) -> Body {
+        match compiled {
+            Ok((args, body)) => Body::Normal { args, body, if_else },
+            Err(message) => Body::Normal {
+                args: Box::new([]),
+                body: GmlBody::Code(Rc::new([Instruction::RuntimeError {
+                    error: Self::report_error(message, index, errors),
+                }])),
+                if_else,
+            },
+        }
+    }
 
-        Ok(())
+    fn report_error(message: String, index: usize, errors: &mut Vec<String>) -> gml::Error {
+        let message = format!("action {}: {}", index, message);
+        errors.push(message.clone());
+        gml::Error::CompileError(message)
     }
 
     fn compile_params(
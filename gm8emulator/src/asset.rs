@@ -4,6 +4,7 @@ pub mod object;
 pub mod path;
 pub mod room;
 pub mod script;
+pub mod sound;
 pub mod sprite;
 pub mod timeline;
 pub mod trigger;
@@ -14,12 +15,19 @@ pub use object::Object;
 pub use path::Path;
 pub use room::Room;
 pub use script::Script;
+pub use sound::Sound;
 pub use sprite::Sprite;
 pub use timeline::Timeline;
 pub use trigger::Trigger;
 
+use flate2::read::ZlibDecoder;
+use image::RgbaImage;
 use serde::{Deserialize, Serialize};
-use std::fmt::{self, Display};
+use std::{
+    convert::TryInto,
+    fmt::{self, Display},
+    io::Read,
+};
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Type {
@@ -53,3 +61,70 @@ impl Display for Type {
         }
     }
 }
+
+/// Reads the .gmspr and .gmbck files GM8 saves single sprites and backgrounds as.
+/// These start with a version number, and everything after it is compressed with zlib.
+pub struct ResourceReader {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl ResourceReader {
+    pub fn new(file: &[u8]) -> Result<Self, String> {
+        match file.get(..4).map(|v| u32::from_le_bytes(v.try_into().unwrap())) {
+            Some(800) => (),
+            Some(version) => return Err(format!("unsupported file version {}", version)),
+            None => return Err("file is empty".into()),
+        }
+        let mut data = Vec::new();
+        ZlibDecoder::new(&file[4..]).read_to_end(&mut data).map_err(|e| format!("couldn't decompress file: {}", e))?;
+        Ok(Self { data, pos: 0 })
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&[u8], String> {
+        let bytes = self.data.get(self.pos..self.pos + len).ok_or("file is cut off")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, String> {
+        self.bytes(4).map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+    }
+
+    pub fn i32(&mut self) -> Result<i32, String> {
+        self.bytes(4).map(|b| i32::from_le_bytes(b.try_into().unwrap()))
+    }
+
+    /// Reads an image, which has a version number and its size first, then BGRA pixels unless it's empty.
+    pub fn image(&mut self) -> Result<RgbaImage, String> {
+        let _version = self.u32()?;
+        let (width, height) = (self.u32()?, self.u32()?);
+        if width == 0 || height == 0 {
+            return Ok(RgbaImage::new(0, 0))
+        }
+        let len = self.u32()? as usize;
+        if len != width as usize * height as usize * 4 {
+            return Err(format!("{}x{} image has {} bytes of pixels", width, height, len))
+        }
+        let mut pixels = self.bytes(len)?.to_vec();
+        pixels.chunks_exact_mut(4).for_each(|p| p.swap(0, 2));
+        Ok(RgbaImage::from_vec(width, height, pixels).unwrap())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use flate2::{write::ZlibEncoder, Compression};
+    use std::io::Write;
+
+    /// Makes a .gmspr or .gmbck file out of little-endian numbers and the given pixels.
+    pub fn resource_file(numbers: &[i32], pixels: &[u8], trailer: &[i32]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        numbers.iter().for_each(|n| encoder.write_all(&n.to_le_bytes()).unwrap());
+        encoder.write_all(pixels).unwrap();
+        trailer.iter().for_each(|n| encoder.write_all(&n.to_le_bytes()).unwrap());
+        let mut file = 800u32.to_le_bytes().to_vec();
+        file.extend(encoder.finish().unwrap());
+        file
+    }
+}
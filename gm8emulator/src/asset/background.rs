@@ -1,6 +1,8 @@
-use crate::game::string::RCStr;
+use crate::{asset::ResourceReader, game::string::RCStr};
 use gmio::render::AtlasRef;
+use image::{Rgba, RgbaImage};
 use serde::{Deserialize, Serialize};
+use shared::types::Colour;
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Background {
@@ -9,3 +11,73 @@ pub struct Background {
     pub height: u32,
     pub atlas_ref: Option<AtlasRef>,
 }
+
+/// Reads the image from a .gmbck file, as loaded by background_add_background and background_replace_background.
+/// Before the image, it has its own version number and the tileset settings, which aren't used at runtime.
+pub fn read_gmbck(file: &[u8]) -> Result<RgbaImage, String> {
+    let mut reader = ResourceReader::new(file)?;
+    for _ in 0..8 {
+        reader.u32()?;
+    }
+    reader.image()
+}
+
+/// Makes an image fading from col1 to col2, as done by background_create_gradient.
+/// The kinds are horizontal, vertical, rectangle, ellipse, double horizontal and double vertical.
+/// The shaped and double kinds have col1 on the outside and col2 in the middle.
+pub fn make_gradient(width: u32, height: u32, col1: Colour, col2: Colour, kind: i32) -> RgbaImage {
+    let (r1, g1, b1): (u8, u8, u8) = col1.into();
+    let (r2, g2, b2): (u8, u8, u8) = col2.into();
+    let lerp = |a: u8, b: u8, t: f64| (f64::from(a) + (f64::from(b) - f64::from(a)) * t).round() as u8;
+    let part = |n: f64, d: f64| if d > 0.0 { n / d } else { 0.0 };
+    let half_w = f64::from(width.max(1) - 1) / 2.0;
+    let half_h = f64::from(height.max(1) - 1) / 2.0;
+    RgbaImage::from_fn(width, height, |x, y| {
+        let dx = part((f64::from(x) - half_w).abs(), half_w);
+        let dy = part((f64::from(y) - half_h).abs(), half_h);
+        let t = match kind {
+            0 => part(f64::from(x), half_w * 2.0),
+            1 => part(f64::from(y), half_h * 2.0),
+            2 => 1.0 - dx.max(dy),
+            3 => 1.0 - dx.hypot(dy).min(1.0),
+            4 => 1.0 - dx,
+            _ => 1.0 - dy,
+        };
+        Rgba([lerp(r1, r2, t), lerp(g1, g2, t), lerp(b1, b2, t), 255])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{make_gradient, read_gmbck};
+    use crate::asset::tests::resource_file;
+    use image::Rgba;
+
+    #[test]
+    fn gmbck() {
+        let file = resource_file(&[710, 1, 16, 16, 0, 0, 0, 0, 800, 1, 2, 8], &[1, 2, 3, 4, 5, 6, 7, 8], &[]);
+        let image = read_gmbck(&file).unwrap();
+        assert_eq!(image.dimensions(), (1, 2));
+        assert_eq!(image.get_pixel(0, 1), &Rgba([7, 6, 5, 8]));
+        let wrong_size = resource_file(&[710, 0, 16, 16, 0, 0, 0, 0, 800, 2, 2, 8], &[0; 8], &[]);
+        assert!(read_gmbck(&wrong_size).is_err());
+    }
+
+    #[test]
+    fn gradients() {
+        let black = 0x000000.into();
+        let white = 0xFFFFFF.into();
+        let horizontal = make_gradient(5, 3, black, white, 0);
+        assert_eq!(horizontal.get_pixel(0, 2), &Rgba([0, 0, 0, 255]));
+        assert_eq!(horizontal.get_pixel(2, 0), &Rgba([128, 128, 128, 255]));
+        assert_eq!(horizontal.get_pixel(4, 1), &Rgba([255, 255, 255, 255]));
+        let vertical = make_gradient(3, 5, black, white, 1);
+        assert_eq!(vertical.get_pixel(2, 4), &Rgba([255, 255, 255, 255]));
+        let ellipse = make_gradient(5, 5, black, white, 3);
+        assert_eq!(ellipse.get_pixel(2, 2), &Rgba([255, 255, 255, 255]));
+        assert_eq!(ellipse.get_pixel(0, 0), &Rgba([0, 0, 0, 255]));
+        let double = make_gradient(5, 1, black, white, 4);
+        assert_eq!(double.get_pixel(0, 0), double.get_pixel(4, 0));
+        assert_eq!(double.get_pixel(2, 0), &Rgba([255, 255, 255, 255]));
+    }
+}
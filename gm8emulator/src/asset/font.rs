@@ -4,7 +4,12 @@ use gmio::{
     atlas::AtlasBuilder,
     render::{AtlasRef, Renderer},
 };
+use rusttype::Scale;
 use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Font {
@@ -53,6 +58,223 @@ impl Font {
             _ => default,
         }
     }
+
+    /// Gets characters `first` to `last` of this font, substituting its first character for any it doesn't have.
+    pub fn chars_in_range(&self, first: u8, last: u8) -> Box<[Character]> {
+        (first..=last).map(|c| self.get_char(c).unwrap_or(self.chars[0])).collect()
+    }
+
+    /// Frees the graphics of a font that owns them.
+    pub fn delete_graphics(&self, renderer: &mut Renderer) {
+        for c in self.chars.iter() {
+            renderer.delete_sprite(c.atlas_ref);
+        }
+    }
+}
+
+/// A TrueType font face picked out by `FontFiles::find`.
+pub struct FontFile {
+    data: Vec<u8>,
+    index: u32,
+    fake_bold: bool,
+    fake_italic: bool,
+}
+
+/// The font faces font_add and font_replace can use. The font files are only read through once, when this is made.
+pub struct FontFiles {
+    faces: Vec<Face>,
+}
+
+struct Face {
+    path: PathBuf,
+    index: u32,
+    family: String,
+    bold: bool,
+    italic: bool,
+}
+
+impl FontFiles {
+    /// Finds the fonts next to the game, then the ones in the system's font directories if `system` is set.
+    pub fn scan(program_directory: &Path, system: bool) -> Self {
+        let mut files = Vec::new();
+        list_font_files(program_directory, false, &mut files);
+        if system {
+            for dir in system_font_directories() {
+                list_font_files(&dir, true, &mut files);
+            }
+        }
+        let mut faces = Vec::new();
+        for path in files {
+            let data = match fs::read(&path) {
+                Ok(data) => data,
+                Err(_) => continue,
+            };
+            for index in 0..ttf_parser::fonts_in_collection(&data).unwrap_or(1) {
+                if let Some(face) = ttf_parser::Font::from_data(&data, index) {
+                    if let Some(family) = face.family_name() {
+                        let (bold, italic) = (face.is_bold(), face.is_italic());
+                        faces.push(Face { path: path.clone(), index, family, bold, italic });
+                    }
+                }
+            }
+        }
+        Self { faces }
+    }
+
+    /// Looks for a font with the given family name, earlier directories first.
+    /// A face in the requested style is preferred, otherwise bold and italic get faked the way Windows does it.
+    pub fn find(&self, name: &str, bold: bool, italic: bool) -> Option<FontFile> {
+        let mut best: Option<(&Face, bool, bool)> = None;
+        for face in self.faces.iter().filter(|f| f.family.eq_ignore_ascii_case(name)) {
+            // styles can be faked but not taken away
+            if (face.bold && !bold) || (face.italic && !italic) {
+                continue
+            }
+            let (fake_bold, fake_italic) = (bold && !face.bold, italic && !face.italic);
+            let fakes = fake_bold as u8 + fake_italic as u8;
+            if best.map(|(_, b, i)| b as u8 + i as u8 > fakes).unwrap_or(true) {
+                best = Some((face, fake_bold, fake_italic));
+                if fakes == 0 {
+                    break
+                }
+            }
+        }
+        let (face, fake_bold, fake_italic) = best?;
+        let data = fs::read(&face.path).ok()?;
+        Some(FontFile { data, index: face.index, fake_bold, fake_italic })
+    }
+}
+
+fn system_font_directories() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if cfg!(target_os = "windows") {
+        if let Some(windir) = env::var_os("WINDIR") {
+            dirs.push(Path::new(&windir).join("Fonts"));
+        }
+        if let Some(local_app_data) = env::var_os("LOCALAPPDATA") {
+            dirs.push(Path::new(&local_app_data).join("Microsoft").join("Windows").join("Fonts"));
+        }
+    } else {
+        dirs.push("/usr/share/fonts".into());
+        dirs.push("/usr/local/share/fonts".into());
+        if cfg!(target_os = "macos") {
+            dirs.push("/Library/Fonts".into());
+            dirs.push("/System/Library/Fonts".into());
+        }
+        if let Some(home) = env::var_os("HOME") {
+            dirs.push(Path::new(&home).join(".local").join("share").join("fonts"));
+            dirs.push(Path::new(&home).join(".fonts"));
+        }
+    }
+    dirs
+}
+
+fn list_font_files(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) {
+    let mut entries = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect::<Vec<_>>(),
+        Err(_) => return,
+    };
+    // sorted so the same font gets picked every run
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            if recursive {
+                list_font_files(&path, true, files);
+            }
+        } else if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+            if ["ttf", "otf", "ttc"].iter().any(|x| ext.eq_ignore_ascii_case(x)) {
+                files.push(path);
+            }
+        }
+    }
+}
+
+impl FontFile {
+    /// Rasterises characters `first` to `last` at the given point size, returning them and the height of a line.
+    /// Like GM8's font bitmaps, every character is a full line high so they all line up at the top.
+    pub fn rasterise(
+        &self,
+        size: u32,
+        first: u8,
+        last: u8,
+        encoding: &'static Encoding,
+        renderer: &mut Renderer,
+    ) -> Result<(Box<[Character]>, u32), String> {
+        let font = rusttype::Font::try_from_bytes_and_index(&self.data, self.index).ok_or("Couldn't load font")?;
+        // GM8 sizes fonts by their em height at 96 DPI, but rusttype scales to ascent minus descent
+        let unscaled = font.v_metrics_unscaled();
+        let em_height = size as f32 * 96.0 / 72.0;
+        let scale = Scale::uniform(em_height * (unscaled.ascent - unscaled.descent) / f32::from(font.units_per_em()));
+        let v_metrics = font.v_metrics(scale);
+        let baseline = v_metrics.ascent.ceil() as i32;
+        let height = (baseline + (-v_metrics.descent).ceil() as i32).max(1) as usize;
+        let chars = (first..=last)
+            .map(|c| {
+                let decoded = encoding.decode_without_bom_handling(&[c]).0.chars().next().unwrap_or('\0');
+                let glyph = font.glyph(decoded).scaled(scale);
+                let mut offset = glyph.h_metrics().advance_width.round() as i32;
+                let glyph = glyph.positioned(rusttype::point(0.0, baseline as f32));
+                let (mut alpha, mut width, mut distance) = match glyph.pixel_bounding_box() {
+                    Some(bbox) => {
+                        let width = bbox.width() as usize;
+                        let mut alpha = vec![0; width * height];
+                        glyph.draw(|x, y, v| {
+                            let y = y as i32 + bbox.min.y;
+                            if y >= 0 && y < height as i32 {
+                                alpha[y as usize * width + x as usize] = (v * 255.0).round() as u8;
+                            }
+                        });
+                        (alpha, width, bbox.min.x)
+                    },
+                    None => (vec![0; height], 1, 0),
+                };
+                if self.fake_bold {
+                    alpha = embolden(&alpha, width, height);
+                    width += 1;
+                    offset += 1;
+                }
+                if self.fake_italic {
+                    let (slanted, slanted_width, shift) = slant(&alpha, width, height, baseline);
+                    alpha = slanted;
+                    width = slanted_width;
+                    distance += shift;
+                }
+                let mut data = Vec::with_capacity(alpha.len() * 4);
+                for a in alpha {
+                    data.extend_from_slice(&[0xFF, 0xFF, 0xFF, a]);
+                }
+                let atlas_ref = renderer.upload_sprite(data.into_boxed_slice(), width as _, height as _, 0, 0)?;
+                Ok(Character { offset, distance, atlas_ref })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok((chars, height as u32))
+    }
+}
+
+/// Widens a glyph's alpha map by a pixel to the right, which is how Windows fakes bold.
+fn embolden(alpha: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let mut out = vec![0; (width + 1) * height];
+    for (row, out_row) in alpha.chunks_exact(width).zip(out.chunks_exact_mut(width + 1)).take(height) {
+        for x in 0..=width {
+            let left = if x > 0 { row[x - 1] } else { 0 };
+            out_row[x] = left.max(row.get(x).copied().unwrap_or(0));
+        }
+    }
+    out
+}
+
+/// Shears a glyph's alpha map to the right around the baseline, which is how Windows fakes italic.
+/// Returns the new map, its width and how far the glyph's left edge moved.
+fn slant(alpha: &[u8], width: usize, height: usize, baseline: i32) -> (Vec<u8>, usize, i32) {
+    let shift = |row: usize| (f64::from(baseline - row as i32) * 0.2).round() as i32;
+    let (min_shift, max_shift) = (shift(height - 1).min(0), shift(0).max(0));
+    let new_width = width + (max_shift - min_shift) as usize;
+    let mut out = vec![0; new_width * height];
+    for (y, row) in alpha.chunks_exact(width).enumerate().take(height) {
+        let start = y * new_width + (shift(y) - min_shift) as usize;
+        out[start..start + width].copy_from_slice(row);
+    }
+    (out, new_width, min_shift)
 }
 
 pub fn load_default_font(atlases: &mut AtlasBuilder) -> Result<Font, String> {
@@ -126,3 +348,43 @@ pub fn create_chars_from_sprite(sprite: &Sprite, prop: bool, sep: i32, renderer:
     }
     chars.into_boxed_slice()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fake_bold() {
+        assert_eq!(embolden(&[0, 255, 0, 128, 0, 0], 3, 2), [0, 255, 255, 0, 128, 128, 0, 0]);
+    }
+
+    #[test]
+    fn fake_italic() {
+        let (alpha, width, shift) = slant(&[255; 10], 1, 10, 5);
+        assert_eq!(width, 3);
+        assert_eq!(shift, -1);
+        assert_eq!(alpha.chunks(3).map(|row| row.iter().position(|&a| a == 255).unwrap()).collect::<Vec<_>>(), [
+            2, 2, 2, 1, 1, 1, 1, 1, 0, 0
+        ]);
+    }
+
+    #[test]
+    fn font_styles() {
+        let dir = env::temp_dir().join("gm8emulator-font-styles");
+        fs::create_dir_all(&dir).unwrap();
+        let face = |file: &str, bold, italic| {
+            fs::write(dir.join(file), file).unwrap();
+            Face { path: dir.join(file), index: 0, family: "Arial".into(), bold, italic }
+        };
+        let files = FontFiles { faces: vec![face("bold", true, false), face("regular", false, false)] };
+        let regular = files.find("arial", false, false).unwrap();
+        assert_eq!((regular.data.as_slice(), regular.fake_bold, regular.fake_italic), (&b"regular"[..], false, false));
+        let bold_italic = files.find("ARIAL", true, true).unwrap();
+        assert_eq!(
+            (bold_italic.data.as_slice(), bold_italic.fake_bold, bold_italic.fake_italic),
+            (&b"bold"[..], false, true)
+        );
+        assert!(files.find("Arial Black", false, false).is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
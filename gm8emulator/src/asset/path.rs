@@ -31,6 +31,10 @@ impl Path {
     /// Generates a new set of control nodes for this Path and updates its start, end and length
     pub fn update(&mut self) {
         self.control_nodes.clear(); // since you can dynamically add path points...
+        // and remove all of them, in which case there are no control nodes to set these
+        self.length = Real::from(0.0);
+        self.start = Default::default();
+        self.end = Default::default();
         if self.curve {
             if let (Some(&first_point), Some(&last_point)) = (self.points.first(), self.points.last()) {
                 if !self.closed {
@@ -105,6 +109,73 @@ impl Path {
         }
     }
 
+    /// Returns the centre of the box around the path's points, which is what it gets mirrored, flipped, rotated and
+    /// scaled around
+    pub fn centre(&self) -> (Real, Real) {
+        match self.points.split_first() {
+            Some((first, rest)) => {
+                let (mut left, mut top, mut right, mut bottom) = (first.x, first.y, first.x, first.y);
+                for point in rest {
+                    left = left.min(point.x);
+                    top = top.min(point.y);
+                    right = right.max(point.x);
+                    bottom = bottom.max(point.y);
+                }
+                ((left + right) / Real::from(2.0), (top + bottom) / Real::from(2.0))
+            },
+            None => (Real::from(0.0), Real::from(0.0)),
+        }
+    }
+
+    /// Mirrors the path horizontally around its centre
+    pub fn mirror(&mut self) {
+        let (centre_x, _) = self.centre();
+        for point in self.points.iter_mut() {
+            point.x = centre_x - (point.x - centre_x);
+        }
+        self.update();
+    }
+
+    /// Flips the path vertically around its centre
+    pub fn flip(&mut self) {
+        let (_, centre_y) = self.centre();
+        for point in self.points.iter_mut() {
+            point.y = centre_y - (point.y - centre_y);
+        }
+        self.update();
+    }
+
+    /// Rotates the path counter-clockwise around its centre by the given angle in degrees
+    pub fn rotate(&mut self, angle: Real) {
+        let (centre_x, centre_y) = self.centre();
+        let (sin, cos) = (angle.to_radians().sin(), angle.to_radians().cos());
+        for point in self.points.iter_mut() {
+            let (x, y) = (point.x - centre_x, point.y - centre_y);
+            point.x = centre_x + x * cos + y * sin;
+            point.y = centre_y - x * sin + y * cos;
+        }
+        self.update();
+    }
+
+    /// Scales the path around its centre
+    pub fn scale(&mut self, xscale: Real, yscale: Real) {
+        let (centre_x, centre_y) = self.centre();
+        for point in self.points.iter_mut() {
+            point.x = centre_x + (point.x - centre_x) * xscale;
+            point.y = centre_y + (point.y - centre_y) * yscale;
+        }
+        self.update();
+    }
+
+    /// Moves every point in the path by the given amount
+    pub fn shift(&mut self, xshift: Real, yshift: Real) {
+        for point in self.points.iter_mut() {
+            point.x += xshift;
+            point.y += yshift;
+        }
+        self.update();
+    }
+
     /// Returns a Point on the path at the given offset, where 0 is the beginning and 1 is the end
     pub fn get_point(&self, offset: Real) -> Point {
         match &*self.control_nodes {
@@ -154,3 +225,51 @@ impl Point {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Path, Point};
+    use crate::math::Real;
+
+    fn path(points: &[(f64, f64)]) -> Path {
+        let mut path = Path {
+            name: "path".into(),
+            points: points.iter().map(|&(x, y)| Point { x: x.into(), y: y.into(), speed: 100.into() }).collect(),
+            control_nodes: Vec::new(),
+            length: Real::from(0.0),
+            curve: false,
+            closed: false,
+            precision: 4,
+            start: Default::default(),
+            end: Default::default(),
+        };
+        path.update();
+        path
+    }
+
+    fn coords(path: &Path) -> Vec<(f64, f64)> {
+        path.points.iter().map(|p| (p.x.into_inner().round(), p.y.into_inner().round())).collect()
+    }
+
+    #[test]
+    fn transforms() {
+        let mut p = path(&[(0.0, 0.0), (10.0, 0.0), (10.0, 20.0)]);
+        assert_eq!(p.centre(), (Real::from(5.0), Real::from(10.0)));
+        p.mirror();
+        assert_eq!(coords(&p), [(10.0, 0.0), (0.0, 0.0), (0.0, 20.0)]);
+        p.flip();
+        assert_eq!(coords(&p), [(10.0, 20.0), (0.0, 20.0), (0.0, 0.0)]);
+        // counter-clockwise on screen, where y goes down
+        p.rotate(Real::from(90.0));
+        assert_eq!(coords(&p), [(15.0, 5.0), (15.0, 15.0), (-5.0, 15.0)]);
+        p.scale(Real::from(0.5), Real::from(2.0));
+        assert_eq!(coords(&p), [(10.0, 0.0), (10.0, 20.0), (0.0, 20.0)]);
+        p.shift(Real::from(1.0), Real::from(-1.0));
+        assert_eq!(coords(&p), [(11.0, -1.0), (11.0, 19.0), (1.0, 19.0)]);
+        assert_eq!(p.length, Real::from(30.0));
+        p.points.clear();
+        p.update();
+        assert_eq!(p.length, Real::from(0.0));
+        assert_eq!((p.end.x, p.end.y), (Real::from(0.0), Real::from(0.0)));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
     game::{string::RCStr, Background, View},
     gml::runtime::Instruction,
+    math::Real,
     tile::Tile,
 };
 use serde::{Deserialize, Serialize};
@@ -35,3 +36,61 @@ pub struct Instance {
     pub id: ID,
     pub creation: Result<Rc<[Instruction]>, String>,
 }
+
+impl Room {
+    /// Creates an empty room with the same defaults as a new room in the GM8 editor.
+    pub fn new(name: RCStr) -> Self {
+        Self {
+            name,
+            caption: "".into(),
+            width: 640,
+            height: 480,
+            speed: 30,
+            persistent: false,
+            bg_colour: 0xC0C0C0.into(),
+            clear_screen: true,
+            creation_code: Ok(Rc::new([])),
+            backgrounds: vec![
+                Background {
+                    visible: false,
+                    is_foreground: false,
+                    background_id: -1,
+                    x_offset: Real::from(0.0),
+                    y_offset: Real::from(0.0),
+                    tile_horizontal: true,
+                    tile_vertical: true,
+                    hspeed: Real::from(0.0),
+                    vspeed: Real::from(0.0),
+                    xscale: Real::from(1.0),
+                    yscale: Real::from(1.0),
+                    blend: 0xFFFFFF,
+                    alpha: Real::from(1.0),
+                };
+                8
+            ],
+            views_enabled: false,
+            views: vec![
+                View {
+                    visible: false,
+                    source_x: 0,
+                    source_y: 0,
+                    source_w: 640,
+                    source_h: 480,
+                    port_x: 0,
+                    port_y: 0,
+                    port_w: 640,
+                    port_h: 480,
+                    angle: Real::from(0.0),
+                    follow_target: -1,
+                    follow_hborder: 32,
+                    follow_vborder: 32,
+                    follow_hspeed: -1,
+                    follow_vspeed: -1,
+                };
+                8
+            ],
+            instances: Vec::new(),
+            tiles: Vec::new(),
+        }
+    }
+}
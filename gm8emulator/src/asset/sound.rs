@@ -0,0 +1,240 @@
+use crate::{game::string::RCStr, math::Real};
+use gmio::audio::SAMPLE_RATE;
+use serde::{Deserialize, Serialize};
+use std::{convert::TryInto, io::Cursor, mem, os::raw::c_int, rc::Rc};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Sound {
+    pub name: RCStr,
+    pub kind: Kind,
+    pub extension: RCStr,
+    pub source: RCStr,
+    #[serde(skip)] // restored from the game when a savestate is loaded
+    pub data: Option<Rc<[u8]>>,
+    pub volume: Real,
+    pub pan: Real,
+    pub preload: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Kind {
+    Normal,
+    BackgroundMusic,
+    ThreeDimensional,
+    Multimedia,
+}
+
+impl From<Kind> for i32 {
+    fn from(kind: Kind) -> Self {
+        match kind {
+            Kind::Normal => 0,
+            Kind::BackgroundMusic => 1,
+            Kind::ThreeDimensional => 2,
+            Kind::Multimedia => 3,
+        }
+    }
+}
+
+/// A sound converted to stereo at the output sample rate, ready for mixing.
+pub struct Pcm {
+    /// Samples from -1 to 1. This is empty for sounds which couldn't be decoded.
+    pub frames: Box<[[f32; 2]]>,
+    /// Length in frames.
+    pub len: usize,
+}
+
+impl Pcm {
+    pub fn frame(&self, pos: usize) -> [f32; 2] {
+        self.frames.get(pos).copied().unwrap_or([0.0; 2])
+    }
+}
+
+/// Decodes a sound file. WAV, MP3 and Ogg Vorbis files are supported.
+pub fn decode(data: &[u8]) -> Result<Pcm, String> {
+    if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WAVE") {
+        decode_wav(data)
+    } else if data.starts_with(b"OggS") {
+        decode_ogg(data)
+    } else if data.starts_with(b"MThd") {
+        Err("MIDI files aren't supported".into())
+    } else if data.starts_with(b"ID3") || data.get(..2).map(|h| h[0] == 0xFF && h[1] & 0xE0 == 0xE0) == Some(true) {
+        decode_mp3(data)
+    } else {
+        Err("unknown sound format".into())
+    }
+}
+
+fn decode_wav(data: &[u8]) -> Result<Pcm, String> {
+    let u16_at = |pos: usize| data.get(pos..pos + 2).map(|b| u16::from_le_bytes(b.try_into().unwrap()));
+    let u32_at = |pos: usize| data.get(pos..pos + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()));
+
+    let mut format = None;
+    let mut samples = None;
+    let mut pos = 12;
+    while let (Some(id), Some(size)) = (data.get(pos..pos + 4), u32_at(pos + 4)) {
+        let start = pos + 8;
+        let end = (start + size as usize).min(data.len());
+        match id {
+            b"fmt " => {
+                let mut tag = u16_at(start).ok_or("WAV format is cut off")?;
+                if tag == 0xFFFE {
+                    // WAVE_FORMAT_EXTENSIBLE, where the real format starts the subformat GUID
+                    tag = u16_at(start + 24).ok_or("WAV format is cut off")?;
+                }
+                let channels = u16_at(start + 2).ok_or("WAV format is cut off")?;
+                let rate = u32_at(start + 4).ok_or("WAV format is cut off")?;
+                let bits = u16_at(start + 14).ok_or("WAV format is cut off")?;
+                format = Some((tag, channels, rate, bits));
+            },
+            b"data" => samples = Some(&data[start..end]),
+            _ => (),
+        }
+        // chunks are padded to an even size
+        pos = start + size as usize + (size as usize & 1);
+    }
+    let (tag, channels, rate, bits) = format.ok_or("WAV file has no format")?;
+    let samples = samples.ok_or("WAV file has no data")?;
+    if channels == 0 || rate == 0 {
+        return Err("WAV file has no channels".into())
+    }
+
+    let sample_size = usize::from(bits / 8);
+    let read: fn(&[u8]) -> f32 = match (tag, bits) {
+        (1, 8) => |b| (f32::from(b[0]) - 128.0) / 128.0,
+        (1, 16) => |b| f32::from(i16::from_le_bytes([b[0], b[1]])) / 32768.0,
+        (1, 24) => |b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8388608.0,
+        (1, 32) => |b| i32::from_le_bytes(b.try_into().unwrap()) as f32 / 2147483648.0,
+        (3, 32) => |b| f32::from_le_bytes(b.try_into().unwrap()),
+        (3, 64) => |b| f64::from_le_bytes(b.try_into().unwrap()) as f32,
+        _ => return Err(format!("unsupported WAV format {} with {} bits per sample", tag, bits)),
+    };
+    let source = samples
+        .chunks_exact(sample_size * usize::from(channels))
+        .map(|block| {
+            let left = read(&block[..sample_size]);
+            let right = if channels > 1 { read(&block[sample_size..sample_size * 2]) } else { left };
+            [left, right]
+        })
+        .collect::<Vec<_>>();
+    let frames = resample(&source, rate);
+    Ok(Pcm { len: frames.len(), frames })
+}
+
+/// Converts to the output sample rate by linear interpolation.
+fn resample(source: &[[f32; 2]], rate: u32) -> Box<[[f32; 2]]> {
+    if rate == SAMPLE_RATE || source.is_empty() {
+        return source.into()
+    }
+    let len = (source.len() as u64 * u64::from(SAMPLE_RATE)).div_ceil(u64::from(rate));
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * f64::from(rate) / f64::from(SAMPLE_RATE);
+            let a = source[pos as usize];
+            let b = source[(pos as usize + 1).min(source.len() - 1)];
+            let t = pos.fract() as f32;
+            [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]
+        })
+        .collect()
+}
+
+/// Converts interleaved samples to stereo frames, dropping any channels past the first two.
+fn to_stereo(samples: &[i16], channels: usize) -> impl Iterator<Item = [f32; 2]> + '_ {
+    samples.chunks_exact(channels).map(move |frame| {
+        let left = f32::from(frame[0]) / 32768.0;
+        let right = if channels > 1 { f32::from(frame[1]) / 32768.0 } else { left };
+        [left, right]
+    })
+}
+
+fn decode_mp3(data: &[u8]) -> Result<Pcm, String> {
+    use minimp3_sys::*;
+
+    let mut source = Vec::new();
+    let mut rate = None;
+    unsafe {
+        let mut decoder = mem::MaybeUninit::<mp3dec_t>::uninit();
+        mp3dec_init(decoder.as_mut_ptr());
+        let mut decoder = decoder.assume_init();
+        let mut pcm = [0i16; MINIMP3_MAX_SAMPLES_PER_FRAME as usize];
+        let mut info: mp3dec_frame_info_t = mem::zeroed();
+        let mut pos = 0;
+        while pos < data.len() {
+            let remaining = (data.len() - pos).min(c_int::MAX as usize) as c_int;
+            let samples =
+                mp3dec_decode_frame(&mut decoder, data[pos..].as_ptr(), remaining, pcm.as_mut_ptr(), &mut info);
+            if info.frame_bytes == 0 {
+                // no frames left
+                break
+            }
+            pos += info.frame_bytes as usize;
+            if samples > 0 && info.channels > 0 {
+                // sample rate changes partway through aren't supported, as they're practically never used
+                rate.get_or_insert(info.hz as u32);
+                let channels = info.channels as usize;
+                source.extend(to_stereo(&pcm[..samples as usize * channels], channels));
+            }
+        }
+    }
+    let rate = rate.ok_or("MP3 file has no frames")?;
+    let frames = resample(&source, rate);
+    Ok(Pcm { len: frames.len(), frames })
+}
+
+fn decode_ogg(data: &[u8]) -> Result<Pcm, String> {
+    let mut reader = lewton::inside_ogg::OggStreamReader::new(Cursor::new(data))
+        .map_err(|e| format!("unsupported OGG file: {}", e))?;
+    let channels = usize::from(reader.ident_hdr.audio_channels);
+    let rate = reader.ident_hdr.audio_sample_rate;
+    if channels == 0 || rate == 0 {
+        return Err("OGG file has no channels".into())
+    }
+    let mut source = Vec::new();
+    while let Some(samples) = reader.read_dec_packet_itl().map_err(|e| format!("broken OGG file: {}", e))? {
+        source.extend(to_stereo(&samples, channels));
+    }
+    let frames = resample(&source, rate);
+    Ok(Pcm { len: frames.len(), frames })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode;
+
+    #[test]
+    fn wav_resampled() {
+        // 22050Hz mono 16-bit, with four samples
+        let mut wav =
+            b"RIFF\0\0\0\0WAVEfmt \x10\0\0\0\x01\0\x01\0\x22\x56\0\0\x44\xac\0\0\x02\0\x10\0data\x08\0\0\0".to_vec();
+        for sample in &[0i16, 16384, -16384, 0] {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+        let pcm = decode(&wav).unwrap();
+        assert_eq!(pcm.len, 8);
+        assert_eq!(pcm.frame(1), [0.25, 0.25]);
+        assert_eq!(pcm.frame(2), [0.5, 0.5]);
+        assert_eq!(pcm.frame(7), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn mp3_decoded() {
+        // ten silent MPEG 1 layer 3 frames at 128kbps and 44100Hz, which are 417 bytes each without padding
+        let mut mp3 = Vec::new();
+        for _ in 0..10 {
+            mp3.extend_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+            mp3.resize(mp3.len() + 413, 0);
+        }
+        let pcm = decode(&mp3).unwrap();
+        assert_eq!(pcm.len, 11520);
+        assert_eq!(pcm.frames.len(), 11520);
+        assert_eq!(pcm.frame(5000), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn unsupported_formats() {
+        assert!(decode(b"MThd\0\0\0\x06\0\x01\0\x01\0\x60").is_err());
+        // an opus identification header, which isn't vorbis
+        assert!(decode(b"OggS\0\x02\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x01\x13OpusHead\x01\x02").is_err());
+        // frame syncs can turn up anywhere, so they only count as MP3 at the start
+        assert!(decode(b"not a sound \xFF\xFB\x90\x00").is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{game::string::RCStr, math::Real};
+use crate::{asset::ResourceReader, game::string::RCStr, math::Real};
 use gmio::render::AtlasRef;
 use image::{Pixel, RgbaImage};
 use serde::{Deserialize, Serialize};
@@ -17,6 +17,19 @@ pub struct Sprite {
     pub bbox_right: u32,
     pub bbox_top: u32,
     pub bbox_bottom: u32,
+    pub mask: MaskSettings,
+}
+
+/// The settings a sprite's collision masks are made with, so they can be made again when its frames change.
+/// Game files only have the masks themselves, so sprites loaded from them get the defaults.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct MaskSettings {
+    /// 0 for automatic, 1 for the full image, or anything else to use `bbox`.
+    pub bbox_mode: i32,
+    pub bbox: BoundingBox,
+    /// 0 for precise, 1 for a rectangle, 2 for an ellipse or 3 for a diamond.
+    pub kind: i32,
+    pub tolerance: u8,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -26,7 +39,7 @@ pub struct Frame {
     pub atlas_ref: AtlasRef,
 }
 
-#[derive(Clone, Copy, Serialize, Deserialize)]
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
 pub struct BoundingBox {
     pub left: u32,
     pub right: u32,
@@ -51,6 +64,89 @@ pub enum ColliderShape {
     Diamond,
 }
 
+impl Sprite {
+    /// Makes the collision masks again from the given frames using the sprite's mask settings, and updates the
+    /// bounding box to match.
+    pub fn update_colliders(&mut self, frames: &[RgbaImage]) {
+        let bbox = match self.mask.bbox_mode {
+            0 => None,
+            1 => Some(BoundingBox { left: 0, right: self.width - 1, top: 0, bottom: self.height - 1 }),
+            _ => Some(BoundingBox {
+                right: self.mask.bbox.right.min(self.width),
+                bottom: self.mask.bbox.bottom.min(self.height),
+                ..self.mask.bbox
+            }),
+        };
+        let tolerance = self.mask.tolerance;
+        self.colliders = match self.mask.kind {
+            0 => make_colliders_precise(frames, tolerance, self.per_frame_colliders),
+            kind => make_colliders_shaped(frames, tolerance, self.per_frame_colliders, bbox, match kind {
+                1 => Some(ColliderShape::Rectangle),
+                2 => Some(ColliderShape::Ellipse),
+                3 => Some(ColliderShape::Diamond),
+                _ => None,
+            }),
+        };
+        // the bounding box is set manually even with precise collision
+        if let Some(bbox) = bbox {
+            for c in &mut self.colliders {
+                c.bbox_left = bbox.left;
+                c.bbox_top = bbox.top;
+                c.bbox_right = bbox.right;
+                c.bbox_bottom = bbox.bottom;
+            }
+        }
+        self.bbox_left = self.colliders.iter().map(|c| c.bbox_left).min().unwrap();
+        self.bbox_top = self.colliders.iter().map(|c| c.bbox_top).min().unwrap();
+        self.bbox_right = self.colliders.iter().map(|c| c.bbox_right).max().unwrap();
+        self.bbox_bottom = self.colliders.iter().map(|c| c.bbox_bottom).max().unwrap();
+    }
+}
+
+/// A sprite read from a .gmspr file, as loaded by sprite_add_sprite and sprite_replace_sprite.
+pub struct SpriteFile {
+    pub origin_x: i32,
+    pub origin_y: i32,
+    /// These are all the same size, and there's at least one.
+    pub frames: Vec<RgbaImage>,
+    pub per_frame_colliders: bool,
+    pub mask: MaskSettings,
+}
+
+impl SpriteFile {
+    /// Reads a .gmspr file. It has its own version number, the origin and the frames, then the mask settings.
+    pub fn read(file: &[u8]) -> Result<Self, String> {
+        let mut reader = ResourceReader::new(file)?;
+        let _version = reader.u32()?;
+        let (origin_x, origin_y) = (reader.i32()?, reader.i32()?);
+        let frame_count = reader.u32()?;
+        let mut frames = (0..frame_count).map(|_| reader.image()).collect::<Result<Vec<_>, _>>()?;
+        let kind = reader.i32()?;
+        let tolerance = reader.i32()?.clamp(0, 255) as u8;
+        let per_frame_colliders = reader.u32()? != 0;
+        let bbox_mode = reader.i32()?;
+        let mut side = || reader.i32().map(|x| x.max(0) as u32);
+        let (left, right, bottom, top) = (side()?, side()?, side()?, side()?);
+
+        let (width, height) =
+            frames.iter().map(|f| f.dimensions()).find(|&(w, h)| w > 0 && h > 0).ok_or("sprite has no images")?;
+        for frame in frames.iter_mut() {
+            if frame.width() == 0 {
+                *frame = RgbaImage::new(width, height);
+            } else {
+                scale(frame, width, height);
+            }
+        }
+        Ok(Self {
+            origin_x,
+            origin_y,
+            frames,
+            per_frame_colliders,
+            mask: MaskSettings { bbox_mode, bbox: BoundingBox { left, right, top, bottom }, kind, tolerance },
+        })
+    }
+}
+
 pub fn process_image(image: &mut RgbaImage, removeback: bool, smooth: bool, fill_transparent: bool) {
     if fill_transparent {
         // if the image is completely transparent, make it completely opaque
@@ -298,6 +394,27 @@ pub fn scale(input: &mut RgbaImage, width: u32, height: u32) {
     }
 }
 
+// used for sprite_set_alpha_from_sprite, the source is stretched to fit and its intensity becomes the alpha
+pub fn set_alpha_from(image: &mut RgbaImage, source: &RgbaImage) {
+    let mut source = source.clone();
+    scale(&mut source, image.width(), image.height());
+    for (px, src) in image.pixels_mut().zip(source.pixels()) {
+        px[3] = ((u32::from(src[0]) + u32::from(src[1]) + u32::from(src[2])) / 3) as u8;
+    }
+}
+
+// used for sprite_save_strip, puts all the frames next to each other left to right
+pub fn strip(frames: &[RgbaImage]) -> RgbaImage {
+    let height = frames.iter().map(|f| f.height()).max().unwrap_or(0);
+    let mut output = RgbaImage::new(frames.iter().map(|f| f.width()).sum(), height);
+    let mut x = 0;
+    for f in frames {
+        image::imageops::replace(&mut output, f, x, 0);
+        x += f.width();
+    }
+    output
+}
+
 impl Sprite {
     fn get_image_index(&self, image_index: Real) -> Option<usize> {
         (image_index.floor().into_inner() as isize).checked_rem_euclid(self.frames.len() as isize).map(|x| x as usize)
@@ -318,3 +435,51 @@ impl Sprite {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{set_alpha_from, strip, SpriteFile};
+    use crate::asset::tests::resource_file;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn gmspr() {
+        // two 2x1 frames, the second of them empty, with a rectangle mask and a manual bounding box
+        let file = resource_file(&[800, 3, -1, 2, 800, 2, 1, 8], &[0x10, 0x20, 0x30, 0xFF, 0, 0, 0, 0], &[
+            800, 0, 0, 1, 40, 1, 2, 1, 0, 0, 0,
+        ]);
+        let sprite = SpriteFile::read(&file).unwrap();
+        assert_eq!((sprite.origin_x, sprite.origin_y), (3, -1));
+        assert_eq!(sprite.frames.len(), 2);
+        assert_eq!(sprite.frames[0].get_pixel(0, 0), &Rgba([0x30, 0x20, 0x10, 0xFF]));
+        assert_eq!(sprite.frames[1].dimensions(), (2, 1));
+        assert_eq!((sprite.mask.kind, sprite.mask.tolerance, sprite.mask.bbox_mode), (1, 40, 2));
+        assert_eq!((sprite.mask.bbox.left, sprite.mask.bbox.right), (1, 0));
+        assert!(sprite.per_frame_colliders);
+
+        assert!(SpriteFile::read(&file[..file.len() - 4]).is_err());
+        assert!(SpriteFile::read(&resource_file(&[800, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0], &[], &[])).is_err());
+    }
+
+    #[test]
+    fn alpha_from_image() {
+        let mut image = RgbaImage::from_pixel(4, 2, Rgba([10, 20, 30, 255]));
+        let mut source = RgbaImage::from_pixel(2, 1, Rgba([0, 0, 0, 255]));
+        source.put_pixel(1, 0, Rgba([255, 255, 90, 255]));
+        set_alpha_from(&mut image, &source);
+        assert_eq!(image.get_pixel(0, 0), &Rgba([10, 20, 30, 0]));
+        assert_eq!(image.get_pixel(1, 1), &Rgba([10, 20, 30, 0]));
+        assert_eq!(image.get_pixel(2, 0), &Rgba([10, 20, 30, 200]));
+        assert_eq!(image.get_pixel(3, 1), &Rgba([10, 20, 30, 200]));
+    }
+
+    #[test]
+    fn frame_strip() {
+        let frames =
+            [RgbaImage::from_pixel(2, 2, Rgba([1, 1, 1, 255])), RgbaImage::from_pixel(2, 2, Rgba([2, 2, 2, 255]))];
+        let image = strip(&frames);
+        assert_eq!(image.dimensions(), (4, 2));
+        assert_eq!(image.get_pixel(1, 1), &Rgba([1, 1, 1, 255]));
+        assert_eq!(image.get_pixel(2, 0), &Rgba([2, 2, 2, 255]));
+    }
+}
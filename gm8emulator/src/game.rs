@@ -1,13 +1,19 @@
+pub mod audio;
 pub mod background;
+pub mod codecache;
+pub mod console;
+pub mod diagnostics;
 pub mod draw;
 pub mod events;
 pub mod external;
+pub mod framedump;
 pub mod gm_save;
 pub mod includedfile;
 pub mod model;
 pub mod movement;
 pub mod particle;
 pub mod pathfinding;
+pub mod registry;
 pub mod replay;
 pub mod savestate;
 pub mod string;
@@ -16,17 +22,18 @@ pub mod transition;
 pub mod view;
 
 pub use background::Background;
+pub use codecache::{CodeCache, CompiledCode};
 pub use replay::Replay;
 pub use savestate::SaveState;
 pub use view::View;
 
 use crate::{
-    action::Tree,
     asset::{
         self,
         font::{Character, Font},
         path::{self, Path},
         room::{self, Room},
+        sound::{self, Sound},
         sprite::{Collider, Frame, Sprite},
         trigger::{self, Trigger},
         Object, Script, Timeline,
@@ -43,7 +50,8 @@ use encoding_rs::Encoding;
 use gm8exe::asset::PascalString;
 use gmio::{
     atlas::AtlasBuilder,
-    render::{Renderer, RendererOptions, Scaling},
+    audio::AudioOutput,
+    render::{Backend, Renderer, RendererOptions, Scaling},
     window::{self, Window, WindowBuilder},
 };
 use includedfile::IncludedFile;
@@ -57,7 +65,7 @@ use shared::{
 use std::{
     borrow::Cow,
     cell::{Cell, RefCell},
-    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     convert::TryFrom,
     fs::File,
     io::{BufReader, Write},
@@ -87,6 +95,14 @@ pub struct Game {
     pub show_room_colour: bool,
 
     pub externals: Vec<Option<external::External>>,
+    pub external_stubs: external::registry::Stubs,
+
+    pub audio: audio::Mixer,
+    pub audio_output: AudioOutput,
+    pub sound_dlls: external::sound::SoundDlls,
+    pub dll39: external::dll39::State,
+    pub dll39_sockets: external::dll39::Sockets,
+    pub registry: registry::Registry,
 
     pub last_instance_id: ID,
     pub last_tile_id: ID,
@@ -120,6 +136,7 @@ pub struct Game {
     pub ds_precision: Real,
 
     pub default_font: Font,
+    pub font_files: Option<asset::font::FontFiles>, // scanned on the first font_add or font_replace
     pub draw_font_id: ID,
     pub draw_colour: Colour,
     pub draw_alpha: Real,
@@ -134,6 +151,9 @@ pub struct Game {
     pub uninit_args_are_zero: bool,
 
     pub potential_step_settings: pathfinding::PotentialStepSettings,
+    pub mp_grids: HandleList<pathfinding::MpGrid>,
+
+    pub persistent_rooms: HashMap<ID, PersistentRoom>,
 
     pub fps: u32,                 // initially 0
     pub transition_kind: i32,     // default 0
@@ -174,6 +194,12 @@ pub struct Game {
     pub play_type: PlayType,
     pub stored_events: VecDeque<replay::Event>,
 
+    // stdin GML console, if enabled
+    pub console: Option<console::Console>,
+
+    // frames being saved for video encoding, if enabled
+    pub frame_dump: Option<framedump::FrameDump>,
+
     // winit windowing
     pub window: Window,
     pub window_border: bool,
@@ -209,6 +235,22 @@ pub enum SceneChange {
     End,      // End the game
 }
 
+/// The state a persistent room was left in, which gets restored instead of the room's defaults when it's entered again
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PersistentRoom {
+    pub caption: RCStr,
+    pub width: u32,
+    pub height: u32,
+    pub speed: u32,
+    pub bg_colour: Colour,
+    pub clear_screen: bool,
+    pub backgrounds: Vec<Background>,
+    pub views_enabled: bool,
+    pub views: Vec<View>,
+    pub instances: Vec<Instance>,
+    pub tiles: Vec<tile::Tile>,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Assets {
     pub backgrounds: Vec<Option<Box<asset::Background>>>,
@@ -217,10 +259,10 @@ pub struct Assets {
     pub paths: Vec<Option<Box<Path>>>,
     pub rooms: Vec<Option<Box<Room>>>,
     pub scripts: Vec<Option<Box<Script>>>,
+    pub sounds: Vec<Option<Box<Sound>>>,
     pub sprites: Vec<Option<Box<Sprite>>>,
     pub timelines: Vec<Option<Box<Timeline>>>,
     pub triggers: Vec<Option<Box<Trigger>>>,
-    // todo
 }
 
 impl From<PascalString> for RCStr {
@@ -238,6 +280,11 @@ impl Game {
         temp_dir: Option<PathBuf>,
         encoding: &'static Encoding,
         play_type: PlayType,
+        registry: registry::Registry,
+        strict_gml: bool,
+        code_cache: Option<CodeCache>,
+        headless: bool,
+        renderer_backend: Backend,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         // Parse file path
         let mut file_path2 = file_path.clone();
@@ -255,6 +302,9 @@ impl Game {
         println!("param_string: {}", param_string);
         println!("program_directory: {}", program_directory);
 
+        // this has to be ready before any game code runs, since it could define externals straight away
+        let external_stubs = external::registry::Stubs::load(&file_path.with_extension("externals.ini"))?;
+
         // Improve framepacing on Windows
         #[cfg(target_os = "windows")]
         unsafe {
@@ -453,7 +503,7 @@ impl Game {
         let (width, height) = options.size;
         let window_border = !settings.dont_draw_border;
         let window_icons = !settings.dont_show_buttons;
-        let wb = WindowBuilder::new().with_size(width, height).with_style(if play_type == PlayType::Record {
+        let style = if play_type == PlayType::Record {
             window::Style::Regular
         } else {
             match (window_border, window_icons) {
@@ -461,12 +511,13 @@ impl Game {
                 (true, false) => window::Style::Undecorated,
                 (false, _) => window::Style::Borderless,
             }
-        });
+        };
+        let wb = WindowBuilder::new().with_size(width, height).with_style(style).with_headless(headless);
 
         // TODO: specific flags here (make wb mutable)
 
         let window = wb.build().expect("oh no");
-        let mut renderer = Renderer::new((), &options, &window, settings.clear_colour.into())?;
+        let mut renderer = Renderer::new(renderer_backend, &options, &window, settings.clear_colour.into())?;
 
         let mut atlases = AtlasBuilder::new(renderer.max_texture_size() as _);
 
@@ -530,6 +581,7 @@ impl Game {
                         bbox_right,
                         bbox_top,
                         bbox_bottom,
+                        mask: Default::default(),
                     }))
                 })
                 .transpose()
@@ -644,84 +696,97 @@ impl Game {
             })
             .collect();
 
-        // Code compiling starts here. The order in which things are compiled is important for
-        // keeping savestates compatible. This isn't 100% accurate right now, but it's mostly right.
+        // Compile all the game's code, or load it from the cache if it's been compiled before
+        let code = match code_cache.as_ref().and_then(|cache| cache.load()) {
+            Some(code) => code,
+            None => {
+                let code = CompiledCode::compile(compiler, &triggers, &scripts, &timelines, &objects, &rooms);
+                if let Some(cache) = code_cache.as_ref() {
+                    cache.store(&code);
+                }
+                code
+            },
+        };
+        let CompiledCode {
+            compiler,
+            triggers: trigger_code,
+            scripts: script_code,
+            timelines: timeline_code,
+            objects: object_code,
+            rooms: room_code,
+            report: compile_report,
+        } = code;
+        if !compile_report.is_empty() {
+            if strict_gml {
+                return Err(compile_report.to_string().into())
+            }
+            eprintln!("Warning: {}", compile_report);
+        }
 
         let triggers = triggers
             .into_iter()
-            .map(|t| {
-                t.map(|b| {
-                    let condition = match compiler.compile(&b.condition.0) {
-                        Ok(s) => s,
-                        Err(e) => return Err(format!("Compiler error in trigger {}: {}", b.name, e)),
-                    };
-                    Ok(Box::new(Trigger { name: b.name.into(), condition, moment: b.moment.into() }))
-                })
-                .transpose()
+            .zip(trigger_code)
+            .map(|(t, condition)| {
+                t.zip(condition)
+                    .map(|(b, condition)| Box::new(Trigger { name: b.name.into(), condition, moment: b.moment.into() }))
             })
-            .collect::<Result<Vec<_>, _>>()?;
+            .collect::<Vec<_>>();
 
         let scripts = scripts
             .into_iter()
-            .map(|t| {
-                t.map(|b| {
-                    let compiled = match compiler.compile(&b.source.0) {
-                        Ok(s) => s,
-                        Err(e) => return Err(format!("Compiler error in script {}: {}", b.name, e)),
-                    };
-                    Ok(Box::new(Script { name: b.name.into(), source: b.source.into(), compiled }))
-                })
-                .transpose()
+            .zip(script_code)
+            .map(|(t, compiled)| {
+                t.zip(compiled)
+                    .map(|(b, compiled)| Box::new(Script { name: b.name.into(), source: b.source.into(), compiled }))
             })
-            .collect::<Result<Vec<_>, _>>()?;
+            .collect::<Vec<_>>();
 
         let timelines = timelines
             .into_iter()
-            .map(|t| {
-                t.map(|b| {
-                    let mut moments: BTreeMap<i32, Rc<RefCell<Tree>>> = BTreeMap::new();
-                    for (moment, actions) in b.moments.iter() {
-                        match Tree::from_list(actions, &mut compiler) {
-                            Ok(t) => {
-                                moments.insert(*moment as i32, Rc::new(RefCell::new(t)));
-                            },
-                            Err(e) => {
-                                return Err(format!("Compiler error in timeline {} moment {}: {}", b.name, moment, e))
-                            },
-                        };
-                    }
-                    Ok(Box::new(Timeline { name: b.name.into(), moments: Rc::new(RefCell::new(moments)) }))
+            .zip(timeline_code)
+            .map(|(t, moments)| {
+                t.zip(moments).map(|(b, moments)| {
+                    Box::new(Timeline { name: b.name.into(), moments: Rc::new(RefCell::new(moments)) })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let sounds = sounds
+            .into_iter()
+            .map(|o| {
+                o.map(|b| {
+                    use gm8exe::asset::sound::SoundKind;
+                    Box::new(Sound {
+                        name: b.name.into(),
+                        kind: match b.kind {
+                            SoundKind::Normal => sound::Kind::Normal,
+                            SoundKind::BackgroundMusic => sound::Kind::BackgroundMusic,
+                            SoundKind::ThreeDimensional => sound::Kind::ThreeDimensional,
+                            SoundKind::Multimedia => sound::Kind::Multimedia,
+                        },
+                        extension: b.extension.into(),
+                        source: b.source.into(),
+                        data: b.data.map(Rc::from),
+                        volume: Real::from(b.volume),
+                        pan: Real::from(b.pan),
+                        preload: b.preload,
+                    })
                 })
-                .transpose()
             })
-            .collect::<Result<Vec<_>, _>>()?;
+            .collect::<Vec<_>>();
 
         let objects = {
             let mut object_parents: Vec<Option<i32>> = Vec::with_capacity(objects.len());
             let mut objects = objects
                 .into_iter()
-                .map(|o| {
+                .zip(object_code)
+                .map(|(o, events)| {
                     object_parents.push(match &o {
                         Some(b) => Some(b.parent_index),
                         None => None,
                     });
-                    o.map(|b| {
-                        let mut events: [HashMap<u32, Rc<RefCell<Tree>>>; 12] = std::default::Default::default();
-                        for ((i, map), input) in events.iter_mut().enumerate().zip(b.events.iter()) {
-                            map.reserve(input.len());
-                            for (sub, actions) in input {
-                                map.insert(*sub, match Tree::from_list(actions, &mut compiler) {
-                                    Ok(t) => Rc::new(RefCell::new(t)),
-                                    Err(e) => {
-                                        return Err(format!(
-                                            "Compiler error in object {} event {},{}: {}",
-                                            b.name, i, sub, e
-                                        ))
-                                    },
-                                });
-                            }
-                        }
-                        Ok(Box::new(Object {
+                    o.zip(events).map(|(b, events)| {
+                        Box::new(Object {
                             name: b.name.into(),
                             solid: b.solid,
                             visible: b.visible,
@@ -732,11 +797,10 @@ impl Game {
                             parent_index: b.parent_index,
                             events,
                             children: Rc::new(RefCell::new(HashSet::new())),
-                        }))
+                        })
                     })
-                    .transpose()
                 })
-                .collect::<Result<Vec<_>, _>>()?;
+                .collect::<Vec<_>>();
 
             // Populate identity lists
             for (i, object) in objects.iter_mut().enumerate().filter_map(|(i, x)| x.as_mut().map(|x| (i, x))) {
@@ -764,11 +828,9 @@ impl Game {
 
         let rooms = rooms
             .into_iter()
-            .map(|t| {
-                t.map(|b| {
-                    let creation_code = compiler
-                        .compile(&b.creation_code.0)
-                        .map_err(|e| format!("Compiler error in room {} creation code: {}", b.name, e));
+            .zip(room_code)
+            .map(|(t, code)| {
+                t.zip(code).map(|(b, code)| {
                     let width = b.width;
                     let height = b.height;
                     Box::new(Room {
@@ -780,7 +842,7 @@ impl Game {
                         persistent: b.persistent,
                         bg_colour: (b.bg_colour.r, b.bg_colour.g, b.bg_colour.b).into(),
                         clear_screen: b.clear_screen,
-                        creation_code,
+                        creation_code: code.creation_code,
                         backgrounds: b
                             .backgrounds
                             .into_iter()
@@ -843,14 +905,13 @@ impl Game {
                         instances: b
                             .instances
                             .into_iter()
-                            .map(|i| room::Instance {
+                            .zip(code.instances)
+                            .map(|(i, creation)| room::Instance {
                                 x: i.x,
                                 y: i.y,
                                 object: i.object,
                                 id: i.id,
-                                creation: compiler.compile(&i.creation_code.0).map_err(|e| {
-                                    format!("Compiler error in creation code of instance {}: {}", i.id, e)
-                                }),
+                                creation,
                             })
                             .collect::<Vec<_>>()
                             .into(),
@@ -900,10 +961,17 @@ impl Game {
             renderer: renderer,
             background_colour: settings.clear_colour.into(),
             externals: Vec::new(),
+            external_stubs,
+            audio: audio::Mixer::new(),
+            sound_dlls: Default::default(),
+            dll39: Default::default(),
+            dll39_sockets: Default::default(),
+            registry,
+            audio_output: AudioOutput::null(),
             room_colour: room1_colour,
             show_room_colour: room1_show_colour,
             input_manager: InputManager::new(),
-            assets: Assets { backgrounds, fonts, objects, paths, rooms, scripts, sprites, timelines, triggers },
+            assets: Assets { backgrounds, fonts, objects, paths, rooms, scripts, sounds, sprites, timelines, triggers },
             event_holders,
             custom_draw_objects,
             views_enabled: false,
@@ -930,6 +998,7 @@ impl Game {
             grids: HandleList::new(),
             ds_precision: Real::from(0.00000001),
             default_font,
+            font_files: None,
             draw_font_id: -1,
             draw_colour: Colour::new(0.0, 0.0, 0.0),
             draw_alpha: Real::from(1.0),
@@ -945,6 +1014,8 @@ impl Game {
             uninit_fields_are_zero: settings.zero_uninitialized_vars,
             uninit_args_are_zero: !settings.error_on_uninitialized_args,
             potential_step_settings: Default::default(),
+            mp_grids: HandleList::new(),
+            persistent_rooms: HashMap::new(),
             transition_kind: 0,
             transition_steps: 80,
             cursor_sprite: -1,
@@ -981,6 +1052,8 @@ impl Game {
             scaling,
             play_type,
             stored_events: VecDeque::new(),
+            console: None,
+            frame_dump: None,
 
             // load_room sets this
             unscaled_width: 0,
@@ -1136,7 +1209,7 @@ impl Game {
     }
 
     pub fn load_room(&mut self, room_id: i32) -> Result<(), Box<dyn std::error::Error>> {
-        let room = if let Some(Some(room)) = self.assets.rooms.get(room_id as usize) {
+        let mut room = if let Some(Some(room)) = self.assets.rooms.get(room_id as usize) {
             room.clone()
         } else {
             return Err(format!("Tried to load non-existent room with id {}", room_id).into())
@@ -1176,11 +1249,47 @@ impl Game {
             self.run_instance_event(ev::OTHER, 5, instance, instance, None)?;
         }
 
+        // Back up the room being left if it's persistent
+        if !self.game_start && self.assets.rooms.get_asset(self.room_id).map(|r| r.persistent).unwrap_or(false) {
+            let mut tiles = Vec::new();
+            let mut iter = self.tile_list.iter_by_insertion();
+            while let Some(tile) = iter.next(&self.tile_list) {
+                tiles.push(self.tile_list.get(tile).clone());
+            }
+            self.persistent_rooms.insert(self.room_id, PersistentRoom {
+                caption: self.caption.clone(),
+                width: self.room_width as _,
+                height: self.room_height as _,
+                speed: self.room_speed,
+                bg_colour: self.room_colour,
+                clear_screen: self.show_room_colour,
+                backgrounds: self.backgrounds.clone(),
+                views_enabled: self.views_enabled,
+                views: self.views.clone(),
+                instances: self.instance_list.clone_with(|instance| !instance.persistent.get()),
+                tiles,
+            });
+        }
+
         // Delete non-persistent instances and all tiles
-        // TODO: back up remaining instances and put them at the END of insertion order after making new ones
         self.instance_list.remove_with(|instance| !instance.persistent.get());
         self.tile_list.clear();
 
+        // If the new room was left while persistent, pick up where it was left off rather than starting it over
+        let stored_instances = self.persistent_rooms.remove(&room_id).map(|state| {
+            room.caption = state.caption;
+            room.width = state.width;
+            room.height = state.height;
+            room.speed = state.speed;
+            room.bg_colour = state.bg_colour;
+            room.clear_screen = state.clear_screen;
+            room.backgrounds = state.backgrounds;
+            room.views_enabled = state.views_enabled;
+            room.views = state.views;
+            room.tiles = state.tiles;
+            state.instances
+        });
+
         // Update renderer
         let (view_width, view_height) = {
             if !room.views_enabled {
@@ -1238,27 +1347,47 @@ impl Game {
 
         // Load all instances in new room, unless they already exist due to persistence
         let mut new_handles: Vec<(usize, &asset::room::Instance)> = Vec::new();
-        for instance in room.instances.iter() {
-            if self.instance_list.get_by_instid(instance.id).is_none() {
-                // Get object
-                let object = match self.assets.objects.get(instance.object as usize) {
-                    Some(&Some(ref o)) => o.as_ref(),
-                    _ => return Err(format!("Instance of invalid Object in room {}", room.name).into()),
-                };
+        let mut restored_count = 0;
+        let restored = stored_instances.is_some();
+        if let Some(instances) = stored_instances {
+            for instance in instances {
+                if self.instance_list.get_by_instid(instance.id.get()).is_none() {
+                    let inactive = instance.state.get() == InstanceState::Inactive;
+                    instance.state.set(InstanceState::Active);
+                    let handle = self.instance_list.insert(instance);
+                    if inactive {
+                        self.instance_list.deactivate(handle);
+                    }
+                    restored_count += 1;
+                }
+            }
+        } else {
+            for instance in room.instances.iter() {
+                if self.instance_list.get_by_instid(instance.id).is_none() {
+                    // Get object
+                    let object = match self.assets.objects.get(instance.object as usize) {
+                        Some(&Some(ref o)) => o.as_ref(),
+                        _ => return Err(format!("Instance of invalid Object in room {}", room.name).into()),
+                    };
 
-                // Add instance to list
-                new_handles.push((
-                    self.instance_list.insert(Instance::new(
-                        instance.id as _,
-                        Real::from(instance.x),
-                        Real::from(instance.y),
-                        instance.object,
-                        object,
-                    )),
-                    instance,
-                ));
+                    // Add instance to list
+                    new_handles.push((
+                        self.instance_list.insert(Instance::new(
+                            instance.id as _,
+                            Real::from(instance.x),
+                            Real::from(instance.y),
+                            instance.object,
+                            object,
+                        )),
+                        instance,
+                    ));
+                }
             }
         }
+
+        // Persistent instances carried over from the last room go after the ones that belong to this room
+        self.instance_list.move_older_to_back(new_handles.len() + restored_count);
+
         for (handle, instance) in &new_handles {
             if self.instance_list.get(*handle).is_active() {
                 // Run this instance's room creation code
@@ -1290,23 +1419,26 @@ impl Game {
             self.game_start = false;
         }
 
-        // Run room creation code
-        let dummy_instance =
-            self.instance_list.insert_dummy(Instance::new_dummy(self.assets.objects.get_asset(0).map(|x| x.as_ref())));
-        self.execute(&room.creation_code?, &mut Context {
-            this: dummy_instance,
-            other: dummy_instance,
-            event_action: 0,
-            relative: false,
-            event_type: 11,
-            event_number: 0,
-            event_object: 0,
-            arguments: Default::default(),
-            argument_count: 0,
-            locals: Default::default(),
-            return_value: Default::default(),
-        })?;
-        self.instance_list.remove_dummy(dummy_instance);
+        // Run room creation code, unless the room's been restored
+        if !restored {
+            let dummy_instance = self
+                .instance_list
+                .insert_dummy(Instance::new_dummy(self.assets.objects.get_asset(0).map(|x| x.as_ref())));
+            self.execute(&room.creation_code?, &mut Context {
+                this: dummy_instance,
+                other: dummy_instance,
+                event_action: 0,
+                relative: false,
+                event_type: 11,
+                event_number: 0,
+                event_object: 0,
+                arguments: Default::default(),
+                argument_count: 0,
+                locals: Default::default(),
+                return_value: Default::default(),
+            })?;
+            self.instance_list.remove_dummy(dummy_instance);
+        }
 
         // Run room start event for each instance
         let mut iter = self.instance_list.iter_by_insertion();
@@ -1386,6 +1518,7 @@ impl Game {
         // Clear some stored variables
         self.instance_list = InstanceList::new();
         self.globals = DummyFieldHolder::new();
+        self.persistent_rooms.clear();
         self.game_start = true;
 
         // Go to first room
@@ -1616,6 +1749,7 @@ impl Game {
             self.process_window_events();
 
             self.frame()?;
+            self.process_console();
             match self.scene_change {
                 Some(SceneChange::Room(id)) => self.load_room(id)?,
                 Some(SceneChange::Restart) => self.restart()?,
@@ -1628,6 +1762,10 @@ impl Game {
                 break Ok(self.run_game_end_events()?)
             }
 
+            // a frame's sound and picture go out together, so dumps of them stay in sync
+            self.advance_audio();
+            self.dump_frame()?;
+
             // frame limiter
             let diff = Instant::now().duration_since(time_now);
             let duration = Duration::new(0, 1_000_000_000u32 / self.room_speed);
@@ -1654,7 +1792,12 @@ impl Game {
     }
 
     // Create a TAS for this game
-    pub fn record(&mut self, project_path: PathBuf, tcp_port: u16) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn record(
+        &mut self,
+        project_path: PathBuf,
+        tcp_port: u16,
+        start_registry: registry::Registry,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         use gmio::window::Event;
 
         // Helper fn: Instance -> InstanceDetails
@@ -1699,7 +1842,7 @@ impl Game {
         stream.set_nonblocking(true)?;
         let mut read_buffer: Vec<u8> = Vec::new();
 
-        let mut replay = Replay::new(self.spoofed_time_nanos.unwrap_or(0), self.rand.seed());
+        let mut replay = Replay::new(self.spoofed_time_nanos.unwrap_or(0), self.rand.seed(), start_registry);
 
         // Wait for a Hello, then send an update
         loop {
@@ -1803,6 +1946,7 @@ impl Game {
                             Some(SceneChange::End) => self.restart()?,
                             None => (),
                         }
+                        self.advance_audio();
                         for ev in self.stored_events.iter() {
                             frame.events.push(ev.clone());
                         }
@@ -1998,6 +2142,10 @@ impl Game {
                 break Ok(self.run_game_end_events()?)
             }
 
+            // a frame's sound and picture go out together, so dumps of them stay in sync
+            self.advance_audio();
+            self.dump_frame()?;
+
             // frame limiter
             let diff = Instant::now().duration_since(time_now);
             let duration = Duration::new(0, 1_000_000_000u32 / self.room_speed);
@@ -2594,6 +2742,15 @@ impl Game {
         None
     }
 
+    // Checks if an instance is colliding with an object or instance, where `other` is the instance `other` refers to
+    pub fn check_collision_object(&self, inst: usize, other: usize, object_id: i32) -> bool {
+        match object_id {
+            gml::SELF => false,
+            gml::OTHER => self.check_collision(inst, other),
+            object_id => self.find_instance_with(object_id, |target| self.check_collision(inst, target)).is_some(),
+        }
+    }
+
     /// Finds an instance that matches the predicate.
     /// `object_id` can be -3 for `all`, an object ID, or an instance ID.
     /// The predicate should take an instance handle as an argument, and return true if it matches.
@@ -2641,6 +2798,159 @@ impl Game {
     }
 }
 
+#[cfg(test)]
+impl Game {
+    /// Makes an empty headless game with no assets and no room, for tests which need to run GML.
+    pub fn for_tests() -> Self {
+        let options = RendererOptions { size: (64, 64), ..Default::default() };
+        let window = WindowBuilder::new().with_size(64, 64).with_headless(true).build().unwrap();
+        let mut renderer = Renderer::new(Backend::Software, &options, &window, Colour::new(0.0, 0.0, 0.0)).unwrap();
+        let mut atlases = AtlasBuilder::new(renderer.max_texture_size() as _);
+        let particle_shapes = particle::load_shapes(&mut atlases);
+        let default_font = asset::font::load_default_font(&mut atlases).unwrap();
+        renderer.push_atlases(atlases).unwrap();
+        Self {
+            compiler: Compiler::new(),
+            text_files: HandleArray::new(),
+            binary_files: HandleArray::new(),
+            instance_list: InstanceList::new(),
+            tile_list: TileList::new(),
+            rand: Random::with_seed(0),
+            renderer,
+            background_colour: Colour::new(0.0, 0.0, 0.0),
+            externals: Vec::new(),
+            external_stubs: Default::default(),
+            audio: audio::Mixer::new(),
+            sound_dlls: Default::default(),
+            dll39: Default::default(),
+            dll39_sockets: Default::default(),
+            registry: registry::Registry::new(),
+            audio_output: AudioOutput::null(),
+            room_colour: Colour::new(0.0, 0.0, 0.0),
+            show_room_colour: true,
+            input_manager: InputManager::new(),
+            assets: Assets {
+                backgrounds: Vec::new(),
+                fonts: Vec::new(),
+                objects: Vec::new(),
+                paths: Vec::new(),
+                rooms: Vec::new(),
+                scripts: Vec::new(),
+                sounds: Vec::new(),
+                sprites: Vec::new(),
+                timelines: Vec::new(),
+                triggers: Vec::new(),
+            },
+            event_holders: Default::default(),
+            custom_draw_objects: HashSet::new(),
+            views_enabled: false,
+            view_current: 0,
+            views: Vec::new(),
+            backgrounds: Vec::new(),
+            particles: particle::Manager::new(particle_shapes),
+            room_id: -1,
+            room_width: 64,
+            room_height: 64,
+            room_order: Box::new([]),
+            room_speed: 30,
+            scene_change: None,
+            user_transitions: HashMap::new(),
+            constants: Vec::new(),
+            globals: DummyFieldHolder::new(),
+            globalvars: HashSet::new(),
+            game_start: false,
+            stacks: HandleList::new(),
+            queues: HandleList::new(),
+            lists: HandleList::new(),
+            maps: HandleList::new(),
+            priority_queues: HandleList::new(),
+            grids: HandleList::new(),
+            ds_precision: Real::from(0.00000001),
+            default_font,
+            font_files: None,
+            draw_font_id: -1,
+            draw_colour: Colour::new(0.0, 0.0, 0.0),
+            draw_alpha: Real::from(1.0),
+            draw_halign: draw::Halign::Left,
+            draw_valign: draw::Valign::Top,
+            surfaces: Vec::new(),
+            surface_target: None,
+            models: Vec::new(),
+            model_matrix_stack: Vec::new(),
+            auto_draw: true,
+            last_instance_id: 100000,
+            last_tile_id: 10000000,
+            uninit_fields_are_zero: false,
+            uninit_args_are_zero: true,
+            potential_step_settings: Default::default(),
+            mp_grids: HandleList::new(),
+            persistent_rooms: HashMap::new(),
+            transition_kind: 0,
+            transition_steps: 80,
+            cursor_sprite: -1,
+            cursor_sprite_frame: 0,
+            score: 0,
+            score_capt: "Score: ".to_string().into(),
+            lives: -1,
+            lives_capt: "Lives: ".to_string().into(),
+            health: Real::from(100.0),
+            health_capt: "Health: ".to_string().into(),
+            game_id: 0,
+            program_directory: "".into(),
+            temp_directory: "".into(),
+            included_files: Vec::new(),
+            gm_version: Version::GameMaker8_0,
+            open_ini: None,
+            open_file: None,
+            file_finder: None,
+            spoofed_time_nanos: Some(0),
+            fps: 0,
+            parameters: Vec::new(),
+            encoding: encoding_rs::WINDOWS_1252,
+            esc_close_game: true,
+            caption: "".to_string().into(),
+            caption_stale: false,
+            score_capt_d: false,
+            lives_capt_d: false,
+            health_capt_d: false,
+            error_occurred: false,
+            error_last: "".to_string().into(),
+            window,
+            window_border: true,
+            window_icons: true,
+            scaling: Scaling::Fixed(1.0),
+            play_type: PlayType::Normal,
+            stored_events: VecDeque::new(),
+            console: None,
+            frame_dump: None,
+            unscaled_width: 64,
+            unscaled_height: 64,
+        }
+    }
+
+    /// Compiles and runs some GML as if it were a script called on a dummy instance, returning what it returned.
+    pub fn run_for_tests(&mut self, source: &str) -> gml::Result<gml::Value> {
+        let instructions = self.compiler.compile(source.as_bytes()).map_err(|e| gml::Error::CompileError(e.message))?;
+        let dummy_instance = self.instance_list.insert_dummy(Instance::new_dummy(None));
+        let mut context = Context {
+            this: dummy_instance,
+            other: dummy_instance,
+            event_action: 0,
+            relative: false,
+            event_type: 0,
+            event_number: 0,
+            event_object: 0,
+            arguments: Default::default(),
+            argument_count: 0,
+            locals: Default::default(),
+            return_value: Default::default(),
+        };
+        let result = self.execute(&instructions, &mut context);
+        self.instance_list.remove_dummy(dummy_instance);
+        result.map(|_| context.return_value)
+    }
+}
+
 pub trait GetAsset<T> {
     fn get_asset(&self, index: ID) -> Option<&T>;
     fn get_asset_mut(&mut self, index: ID) -> Option<&mut T>;
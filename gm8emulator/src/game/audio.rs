@@ -0,0 +1,368 @@
+//! Sound playback.
+//!
+//! Sound is mixed a frame at a time, with the number of samples coming from `room_speed` rather than the clock,
+//! so which sounds are playing only ever depends on what the game did. That keeps `sound_isplaying` deterministic
+//! for replays, and it's also why the mixer's state goes into savestates. It also means that after any number of
+//! frames, exactly as much sound has been written as those frames take to show (rounded down to a whole sample), so
+//! writing it to a WAV file gives audio which stays in sync with a frame dump of the same run.
+
+use crate::{
+    asset::sound::{self, Kind, Pcm, Sound},
+    game::{Game, GetAsset},
+    math::Real,
+};
+use gmio::audio::{AudioOutput, Frame, SAMPLE_RATE};
+use serde::{Deserialize, Serialize};
+use shared::types::ID;
+use std::{collections::HashMap, mem, rc::Rc};
+
+/// Where a playing sound comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Source {
+    /// A sound resource.
+    Asset(ID),
+    /// A sound file loaded by one of the built-in sound DLLs.
+    File(ID),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Mixer {
+    channels: Vec<Channel>,
+    fades: Vec<Fade>,
+    global_volume: Real,
+    /// Leftover sample time from previous frames, in 1/`clock_speed` samples.
+    sample_clock: u32,
+    clock_speed: u32,
+    next_instance: u32,
+
+    /// Decoded sounds, along with the data they were decoded from in case the sound gets replaced.
+    #[serde(skip)]
+    cache: HashMap<Source, (Rc<[u8]>, Rc<Pcm>)>,
+}
+
+/// One playing instance of a sound.
+#[derive(Clone, Serialize, Deserialize)]
+struct Channel {
+    instance: u32,
+    source: Source,
+    position: usize,
+    looping: bool,
+    paused: bool,
+    /// Applied on top of the sound's own volume and pan.
+    volume: Real,
+    pan: Real,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Fade {
+    sound: ID,
+    target: Real,
+    /// Samples left until the sound reaches the target volume.
+    remaining: u64,
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self {
+            channels: Vec::new(),
+            fades: Vec::new(),
+            global_volume: Real::from(1.0),
+            sample_clock: 0,
+            clock_speed: 1,
+            next_instance: 1,
+            cache: HashMap::new(),
+        }
+    }
+}
+
+fn get_sound<'a>(
+    sounds: &'a dyn GetAsset<Box<Sound>>,
+    files: &'a dyn GetAsset<Box<Sound>>,
+    source: Source,
+) -> Option<&'a Sound> {
+    match source {
+        Source::Asset(id) => sounds.get_asset(id),
+        Source::File(id) => files.get_asset(id),
+    }
+    .map(|sound| sound.as_ref())
+}
+
+impl Mixer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn pcm(&mut self, source: Source, sound: &Sound) -> Option<Rc<Pcm>> {
+        let data = sound.data.as_ref()?;
+        match self.cache.get(&source) {
+            Some((cached, pcm)) if Rc::ptr_eq(cached, data) => Some(pcm.clone()),
+            _ => {
+                let pcm = Rc::new(sound::decode(data).unwrap_or_else(|e| {
+                    eprintln!("Couldn't decode sound {}: {}", sound.name, e);
+                    Pcm { frames: Box::new([]), len: 0 }
+                }));
+                self.cache.insert(source, (data.clone(), pcm.clone()));
+                Some(pcm)
+            },
+        }
+    }
+
+    /// Length of a sound in samples, or 0 if it can't be played.
+    pub fn length(&mut self, source: Source, sound: &Sound) -> usize {
+        self.pcm(source, sound).map(|pcm| pcm.len).unwrap_or(0)
+    }
+
+    fn start(&mut self, source: Source, sound: &Sound, looping: bool) -> Option<u32> {
+        // make sure it's decoded now, rather than when the next frame gets mixed
+        self.pcm(source, sound)?;
+        let instance = self.next_instance;
+        self.next_instance = self.next_instance.wrapping_add(1).max(1);
+        self.channels.push(Channel {
+            instance,
+            source,
+            position: 0,
+            looping,
+            paused: false,
+            volume: Real::from(1.0),
+            pan: Real::from(0.0),
+        });
+        Some(instance)
+    }
+
+    /// Starts playing a sound. Background music and multimedia sounds replace any others of their kind.
+    pub fn play(&mut self, sounds: &dyn GetAsset<Box<Sound>>, id: ID, looping: bool) {
+        let sound = match sounds.get_asset(id) {
+            Some(sound) => sound,
+            None => return,
+        };
+        if sound.kind == Kind::BackgroundMusic || sound.kind == Kind::Multimedia {
+            self.channels.retain(|c| match c.source {
+                Source::Asset(other) => sounds.get_asset(other).map(|s| s.kind) != Some(sound.kind),
+                Source::File(_) => true,
+            });
+        }
+        self.start(Source::Asset(id), sound, looping);
+    }
+
+    /// Starts playing a sound file, returning a handle for the new instance of it.
+    pub fn play_file(&mut self, files: &dyn GetAsset<Box<Sound>>, id: ID, looping: bool) -> Option<u32> {
+        let sound = files.get_asset(id)?;
+        self.start(Source::File(id), sound, looping)
+    }
+
+    pub fn stop(&mut self, id: ID) {
+        self.stop_source(Source::Asset(id));
+        self.fades.retain(|f| f.sound != id);
+    }
+
+    pub fn stop_source(&mut self, source: Source) {
+        self.channels.retain(|c| c.source != source);
+    }
+
+    pub fn stop_all(&mut self) {
+        self.channels.clear();
+        self.fades.clear();
+    }
+
+    pub fn is_playing(&self, id: ID) -> bool {
+        self.is_source_playing(Source::Asset(id))
+    }
+
+    pub fn is_source_playing(&self, source: Source) -> bool {
+        self.channels.iter().any(|c| c.source == source)
+    }
+
+    /// Handles of every playing instance of a sound.
+    pub fn instances(&self, source: Source) -> Vec<u32> {
+        self.channels.iter().filter(|c| c.source == source).map(|c| c.instance).collect()
+    }
+
+    fn channel(&self, instance: u32) -> Option<&Channel> {
+        self.channels.iter().find(|c| c.instance == instance)
+    }
+
+    fn channel_mut(&mut self, instance: u32) -> Option<&mut Channel> {
+        self.channels.iter_mut().find(|c| c.instance == instance)
+    }
+
+    pub fn instance_source(&self, instance: u32) -> Option<Source> {
+        self.channel(instance).map(|c| c.source)
+    }
+
+    pub fn is_instance_playing(&self, instance: u32) -> bool {
+        self.channel(instance).is_some()
+    }
+
+    pub fn stop_instance(&mut self, instance: u32) {
+        self.channels.retain(|c| c.instance != instance);
+    }
+
+    pub fn is_instance_paused(&self, instance: u32) -> bool {
+        self.channel(instance).map(|c| c.paused).unwrap_or(false)
+    }
+
+    pub fn set_instance_paused(&mut self, instance: u32, paused: bool) {
+        if let Some(channel) = self.channel_mut(instance) {
+            channel.paused = paused;
+        }
+    }
+
+    pub fn is_instance_looping(&self, instance: u32) -> bool {
+        self.channel(instance).map(|c| c.looping).unwrap_or(false)
+    }
+
+    pub fn instance_volume(&self, instance: u32) -> Real {
+        self.channel(instance).map(|c| c.volume).unwrap_or_else(|| Real::from(0.0))
+    }
+
+    pub fn set_instance_volume(&mut self, instance: u32, volume: Real) {
+        if let Some(channel) = self.channel_mut(instance) {
+            channel.volume = volume;
+        }
+    }
+
+    pub fn instance_pan(&self, instance: u32) -> Real {
+        self.channel(instance).map(|c| c.pan).unwrap_or_else(|| Real::from(0.0))
+    }
+
+    pub fn set_instance_pan(&mut self, instance: u32, pan: Real) {
+        if let Some(channel) = self.channel_mut(instance) {
+            channel.pan = pan;
+        }
+    }
+
+    /// How far through its sound an instance is, in samples.
+    pub fn instance_position(&self, instance: u32) -> usize {
+        self.channel(instance).map(|c| c.position).unwrap_or(0)
+    }
+
+    pub fn set_instance_position(&mut self, instance: u32, position: usize) {
+        if let Some(channel) = self.channel_mut(instance) {
+            channel.position = position;
+        }
+    }
+
+    /// Changes a sound's volume gradually over the given number of milliseconds.
+    pub fn fade(&mut self, id: ID, target: Real, time: u32) {
+        self.fades.retain(|f| f.sound != id);
+        let remaining = u64::from(time) * u64::from(SAMPLE_RATE) / 1000;
+        self.fades.push(Fade { sound: id, target, remaining });
+    }
+
+    pub fn set_global_volume(&mut self, volume: Real) {
+        self.global_volume = volume;
+    }
+
+    /// How many samples the next frame lasts for at the given room speed.
+    fn frame_samples(&mut self, room_speed: u32) -> usize {
+        let room_speed = room_speed.max(1);
+        if room_speed != self.clock_speed {
+            // the leftover time is less than a sample, so rounding it here can't make the sound drift by more than that
+            self.sample_clock =
+                (u64::from(self.sample_clock) * u64::from(room_speed) / u64::from(self.clock_speed)) as u32;
+            self.clock_speed = room_speed;
+        }
+        self.sample_clock += SAMPLE_RATE;
+        let len = self.sample_clock / room_speed;
+        self.sample_clock %= room_speed;
+        len as usize
+    }
+
+    /// Mixes one frame's worth of sound, and moves every sound along by that much.
+    pub fn advance(
+        &mut self,
+        sounds: &mut dyn GetAsset<Box<Sound>>,
+        files: &dyn GetAsset<Box<Sound>>,
+        room_speed: u32,
+        output: &mut AudioOutput,
+    ) {
+        let len = self.frame_samples(room_speed);
+
+        let mut mix = vec![[0.0f32; 2]; len];
+        let mut channels = mem::take(&mut self.channels);
+        channels.retain_mut(|channel| {
+            let sound = match get_sound(sounds, files, channel.source) {
+                Some(sound) => sound,
+                None => return false,
+            };
+            let pcm = match self.pcm(channel.source, sound) {
+                Some(pcm) => pcm,
+                None => return false,
+            };
+            if channel.paused {
+                return true
+            }
+            let volume = (sound.volume * channel.volume * self.global_volume).into_inner() as f32;
+            let pan = (sound.pan + channel.pan).into_inner().clamp(-1.0, 1.0) as f32;
+            let gain = [volume * (1.0 - pan.max(0.0)), volume * (1.0 + pan.min(0.0))];
+            for out in mix.iter_mut() {
+                if channel.position >= pcm.len {
+                    if !channel.looping {
+                        return false
+                    }
+                    if pcm.len == 0 {
+                        // there's nothing to loop, so it'll "play" until it's stopped
+                        break
+                    }
+                    channel.position = 0;
+                }
+                let frame = pcm.frame(channel.position);
+                out[0] += frame[0] * gain[0];
+                out[1] += frame[1] * gain[1];
+                channel.position += 1;
+            }
+            channel.looping || channel.position < pcm.len
+        });
+        self.channels = channels;
+
+        for fade in &mut self.fades {
+            let step = fade.remaining.min(len as u64);
+            if let Some(sound) = sounds.get_asset_mut(fade.sound) {
+                sound.volume = if step == fade.remaining {
+                    fade.target
+                } else {
+                    sound.volume + (fade.target - sound.volume) * Real::from(step as f64 / fade.remaining as f64)
+                };
+            }
+            fade.remaining -= step;
+        }
+        self.fades.retain(|f| f.remaining > 0);
+
+        let frames = mix
+            .into_iter()
+            .map(|[left, right]| {
+                let convert = |s: f32| (s.clamp(-1.0, 1.0) * 32767.0).round() as i16;
+                [convert(left), convert(right)]
+            })
+            .collect::<Vec<Frame>>();
+        if let Err(e) = output.write(&frames) {
+            eprintln!("Failed to write audio: {}", e);
+        }
+    }
+}
+
+impl Game {
+    /// Mixes the sound for the frame which just ran. This needs to happen once per frame for sounds to end on time.
+    pub fn advance_audio(&mut self) {
+        self.audio.advance(&mut self.assets.sounds, &self.sound_dlls.files, self.room_speed, &mut self.audio_output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mixer;
+
+    #[test]
+    fn frame_samples() {
+        let mut mixer = Mixer::new();
+        // 44100 isn't a multiple of 17, so some frames get a sample more than others
+        let samples = (0..17).map(|_| mixer.frame_samples(17)).collect::<Vec<_>>();
+        assert_eq!(samples[..2], [2594, 2594]);
+        assert_eq!(samples.iter().sum::<usize>(), 44100);
+        let samples = (0..3).map(|_| mixer.frame_samples(30)).collect::<Vec<_>>();
+        assert_eq!(samples, [1470; 3]);
+        // changing speed part way through a sample
+        assert_eq!(mixer.frame_samples(17), 2594);
+        assert_eq!((0..60).map(|_| mixer.frame_samples(60)).sum::<usize>(), 44100);
+    }
+}
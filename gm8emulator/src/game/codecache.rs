@@ -0,0 +1,250 @@
+//! On-disk cache of a game's compiled GML, so that it doesn't all have to be recompiled on every launch.
+//!
+//! Cache files are named after a hash of the game's exe. Each one starts with a hash of the emulator's own
+//! executable, so that any change to the emulator invalidates everything it cached before.
+
+use crate::{
+    action::Tree,
+    game::diagnostics::CompileReport,
+    gml::{self, runtime::Instruction, Compiler},
+};
+use gm8exe::asset::{
+    Object as ExeObject, Room as ExeRoom, Script as ExeScript, Timeline as ExeTimeline, Trigger as ExeTrigger,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    fs::{self, File},
+    hash::Hasher,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+    rc::Rc,
+};
+
+/// All the GML code in a game, compiled, along with the Compiler state after compiling it.
+/// Each list is indexed the same way as the asset list its code came from.
+#[derive(Serialize, Deserialize)]
+pub struct CompiledCode {
+    pub compiler: Compiler,
+    pub triggers: Vec<Option<Rc<[Instruction]>>>,
+    pub scripts: Vec<Option<Rc<[Instruction]>>>,
+    pub timelines: Vec<Option<BTreeMap<i32, Rc<RefCell<Tree>>>>>,
+    pub objects: Vec<Option<ObjectEvents>>,
+    pub rooms: Vec<Option<RoomCode>>,
+    pub report: CompileReport,
+}
+
+pub type ObjectEvents = [HashMap<u32, Rc<RefCell<Tree>>>; 12];
+
+/// The compiled creation code of a room and of each instance in it.
+#[derive(Serialize, Deserialize)]
+pub struct RoomCode {
+    pub creation_code: Result<Rc<[Instruction]>, String>,
+    pub instances: Vec<Result<Rc<[Instruction]>, String>>,
+}
+
+impl CompiledCode {
+    /// Compiles all the code in a game. Code which fails to compile is replaced with code raising the error,
+    /// as GM8 only reports it when it's run, and the error is added to the report.
+    ///
+    /// The order in which things are compiled is important for keeping savestates compatible.
+    /// This isn't 100% accurate right now, but it's mostly right.
+    pub fn compile(
+        mut compiler: Compiler,
+        triggers: &[Option<Box<ExeTrigger>>],
+        scripts: &[Option<Box<ExeScript>>],
+        timelines: &[Option<Box<ExeTimeline>>],
+        objects: &[Option<Box<ExeObject>>],
+        rooms: &[Option<Box<ExeRoom>>],
+    ) -> Self {
+        let mut report = CompileReport::new();
+        fn compile_or_report(
+            compiler: &mut Compiler,
+            source: &[u8],
+            report: &mut CompileReport,
+            location: impl FnOnce() -> String,
+        ) -> Rc<[Instruction]> {
+            compiler.compile(source).unwrap_or_else(|e| {
+                report.push(location(), e.message.clone());
+                Rc::new([Instruction::RuntimeError { error: gml::Error::CompileError(e.message) }])
+            })
+        }
+
+        let triggers = triggers
+            .iter()
+            .map(|t| {
+                t.as_ref().map(|b| {
+                    compile_or_report(&mut compiler, &b.condition.0, &mut report, || format!("trigger {}", b.name))
+                })
+            })
+            .collect();
+
+        let scripts = scripts
+            .iter()
+            .map(|t| {
+                t.as_ref().map(|b| {
+                    compile_or_report(&mut compiler, &b.source.0, &mut report, || format!("script {}", b.name))
+                })
+            })
+            .collect();
+
+        let timelines = timelines
+            .iter()
+            .map(|t| {
+                t.as_ref().map(|b| {
+                    let mut moments = BTreeMap::new();
+                    for (moment, actions) in b.moments.iter() {
+                        let mut errors = Vec::new();
+                        let tree = Tree::from_list(actions, &mut compiler, &mut errors);
+                        report.extend(format_args!("timeline {} moment {}", b.name, moment), errors);
+                        moments.insert(*moment as i32, Rc::new(RefCell::new(tree)));
+                    }
+                    moments
+                })
+            })
+            .collect();
+
+        let objects = objects
+            .iter()
+            .map(|o| {
+                o.as_ref().map(|b| {
+                    let mut events: ObjectEvents = Default::default();
+                    for ((i, map), input) in events.iter_mut().enumerate().zip(b.events.iter()) {
+                        map.reserve(input.len());
+                        for (sub, actions) in input {
+                            let mut errors = Vec::new();
+                            let tree = Tree::from_list(actions, &mut compiler, &mut errors);
+                            report.extend(format_args!("object {} event {},{}", b.name, i, sub), errors);
+                            map.insert(*sub, Rc::new(RefCell::new(tree)));
+                        }
+                    }
+                    events
+                })
+            })
+            .collect();
+
+        let rooms = rooms
+            .iter()
+            .map(|t| {
+                t.as_ref().map(|b| {
+                    let creation_code = compiler.compile(&b.creation_code.0).map_err(|e| {
+                        report.push(format!("room {} creation code", b.name), e.message.clone());
+                        format!("Compiler error in room {} creation code: {}", b.name, e)
+                    });
+                    let instances = b
+                        .instances
+                        .iter()
+                        .map(|i| {
+                            compiler.compile(&i.creation_code.0).map_err(|e| {
+                                report.push(
+                                    format!("room {} creation code of instance {}", b.name, i.id),
+                                    e.message.clone(),
+                                );
+                                format!("Compiler error in creation code of instance {}: {}", i.id, e)
+                            })
+                        })
+                        .collect();
+                    RoomCode { creation_code, instances }
+                })
+            })
+            .collect();
+
+        Self { compiler, triggers, scripts, timelines, objects, rooms, report }
+    }
+}
+
+/// Location of a game's cache file.
+pub struct CodeCache {
+    path: PathBuf,
+    emulator_hash: u64,
+}
+
+impl CodeCache {
+    /// Sets up a cache for the given game exe, to be stored in the given directory.
+    /// Returns None if the emulator's own executable couldn't be read.
+    pub fn new(mut dir: PathBuf, exe: &[u8]) -> Option<Self> {
+        let emulator_hash = hash(&fs::read(std::env::current_exe().ok()?).ok()?);
+        dir.push(format!("{:016x}.bin", hash(exe)));
+        Some(Self { path: dir, emulator_hash })
+    }
+
+    /// Loads the cached code, if there is any and it was written by this exact build of the emulator.
+    pub fn load(&self) -> Option<CompiledCode> {
+        let mut reader = BufReader::new(File::open(&self.path).ok()?);
+        let emulator_hash = bincode::deserialize_from::<_, u64>(&mut reader).ok()?;
+        if emulator_hash == self.emulator_hash { bincode::deserialize_from(&mut reader).ok() } else { None }
+    }
+
+    /// Writes the given code to the cache. Failing to do so is not fatal, it'll just be recompiled next time.
+    pub fn store(&self, code: &CompiledCode) {
+        let result = self
+            .path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| File::create(&self.path))
+            .map_err(bincode::Error::from)
+            .and_then(|f| {
+                let mut writer = BufWriter::new(f);
+                bincode::serialize_into(&mut writer, &self.emulator_hash)?;
+                bincode::serialize_into(&mut writer, code)
+            });
+        if let Err(e) = result {
+            eprintln!("Could not write code cache {:?}: {}", self.path, e);
+        }
+    }
+}
+
+fn hash(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(data);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gm8exe::asset::PascalString;
+
+    fn compiled() -> CompiledCode {
+        let script = |name: &[u8], source: &[u8]| {
+            Some(Box::new(ExeScript { name: PascalString(name.into()), source: PascalString(source.into()) }))
+        };
+        let scripts = [script(b"scr_add", b"return argument0 + 2"), None, script(b"scr_broken", b"return (")];
+        CompiledCode::compile(Compiler::new(), &[], &scripts, &[], &[], &[])
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = temp_dir("gm8emulator-codecache-round-trip");
+        let cache = CodeCache::new(dir.clone(), b"game").unwrap();
+        assert!(cache.load().is_none());
+        let code = compiled();
+        assert_eq!(code.report.len(), 1);
+        cache.store(&code);
+        let loaded = cache.load().unwrap();
+        assert_eq!(bincode::serialize(&loaded).unwrap(), bincode::serialize(&code).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalidation() {
+        let dir = temp_dir("gm8emulator-codecache-invalidation");
+        let cache = CodeCache::new(dir.clone(), b"game").unwrap();
+        cache.store(&compiled());
+
+        // a different exe gets its own file
+        assert!(CodeCache::new(dir.clone(), b"other game").unwrap().load().is_none());
+        // and a different build of the emulator can't use what this one cached
+        let rebuilt = CodeCache { path: cache.path.clone(), emulator_hash: cache.emulator_hash.wrapping_add(1) };
+        assert!(rebuilt.load().is_none());
+        assert!(cache.load().is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
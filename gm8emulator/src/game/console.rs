@@ -0,0 +1,177 @@
+//! Interactive GML console, for poking at a running game from stdin.
+//!
+//! Each line is run between frames. A line may start with `@target`, where `target` is a GML expression
+//! resolving to an object or instance ID, to run the code as the first matching instance. Otherwise the
+//! code runs globally, in the scope of a dummy instance, same as constant expressions do.
+//!
+//! Lines are compiled as statements first, and then as an expression if that fails, so both
+//! `global.boss_hp = 1` and `global.boss_hp` do the obvious thing.
+
+use crate::{
+    game::{Game, GetAsset},
+    gml::{self, Context, Value},
+    instance::Instance,
+};
+use std::{
+    io::{self, BufRead, Write},
+    sync::mpsc,
+    thread,
+};
+
+/// Receives lines typed into stdin on a separate thread, so that reading them never blocks the game.
+pub struct Console {
+    receiver: mpsc::Receiver<String>,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let stdin = io::stdin();
+            for line in stdin.lock().lines() {
+                match line {
+                    Ok(line) => {
+                        if sender.send(line).is_err() {
+                            break
+                        }
+                    },
+                    Err(_) => break,
+                }
+            }
+        });
+        prompt();
+        Self { receiver }
+    }
+
+    /// Returns all lines which have been entered since the last call, without blocking.
+    pub fn pending_lines(&self) -> Vec<String> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+fn prompt() {
+    print!("> ");
+    io::stdout().flush().ok();
+}
+
+/// Splits an optional `@target` prefix off a console line.
+fn split_target(line: &str) -> (Option<&str>, &str) {
+    let line = line.trim();
+    if let Some(rest) = line.strip_prefix('@') {
+        match rest.find(char::is_whitespace) {
+            Some(pos) => (Some(&rest[..pos]), rest[pos..].trim_start()),
+            None => (Some(rest), ""),
+        }
+    } else {
+        (None, line)
+    }
+}
+
+impl Game {
+    /// Runs every line entered into the console since the last call, printing the results.
+    pub fn process_console(&mut self) {
+        let lines = match self.console.as_ref() {
+            Some(console) => console.pending_lines(),
+            None => return,
+        };
+        for line in lines {
+            match self.console_eval(&line) {
+                Ok(Some(value)) => println!("{}", value),
+                Ok(None) => (),
+                Err(e) => println!("{}", e),
+            }
+            prompt();
+        }
+    }
+
+    /// Compiles and runs a single console line, returning the resulting value if there is one.
+    pub fn console_eval(&mut self, line: &str) -> Result<Option<Value>, String> {
+        let (target, code) = split_target(line);
+        if code.is_empty() {
+            return Ok(None)
+        }
+
+        let instance = match target {
+            Some(target) => Some(self.console_target(target)?),
+            None => None,
+        };
+
+        let code = self.encode_str_maybe(code).ok_or("console input could not be encoded")?.into_owned();
+        let result = match self.compiler.compile(&code) {
+            Ok(instrs) => self.console_run(instance, |game, context| {
+                game.execute(&instrs, context)?;
+                Ok(match context.return_value {
+                    Value::Real(r) if r.into_inner() == 0.0 => None,
+                    ref value => Some(value.clone()),
+                })
+            }),
+            Err(statement_error) => match self.compiler.compile_expression(&code) {
+                Ok(node) => self.console_run(instance, |game, context| game.eval(&node, context).map(Some)),
+                Err(_) => return Err(format!("compile error: {}", statement_error)),
+            },
+        };
+        result.map_err(|e| format!("runtime error: {}", e))
+    }
+
+    /// Resolves a console `@target` to an instance handle.
+    fn console_target(&mut self, target: &str) -> Result<usize, String> {
+        let expr = self
+            .compiler
+            .compile_expression(target.as_bytes())
+            .map_err(|e| format!("compile error in target: {}", e))?;
+        let id = self
+            .console_run(None, |game, context| game.eval(&expr, context))
+            .map_err(|e| format!("runtime error in target: {}", e))?;
+        let id = match id {
+            Value::Real(r) => r.round(),
+            Value::Str(_) => return Err("target must be an object or instance ID".into()),
+        };
+        self.find_instance_with(id, |_| true).ok_or_else(|| format!("no instance matches target {}", id))
+    }
+
+    /// Runs a closure with a Context scoped to the given instance, or to a dummy instance if there isn't one.
+    fn console_run<T>(
+        &mut self,
+        instance: Option<usize>,
+        f: impl FnOnce(&mut Self, &mut Context) -> gml::Result<T>,
+    ) -> gml::Result<T> {
+        let (handle, event_object) = match instance {
+            Some(handle) => (handle, self.instance_list.get(handle).object_index.get()),
+            None => {
+                let dummy = Instance::new_dummy(self.assets.objects.get_asset(0).map(|x| x.as_ref()));
+                (self.instance_list.insert_dummy(dummy), 0)
+            },
+        };
+        let mut context = Context {
+            this: handle,
+            other: handle,
+            event_action: 0,
+            relative: false,
+            event_type: 0,
+            event_number: 0,
+            event_object,
+            arguments: Default::default(),
+            argument_count: 0,
+            locals: Default::default(),
+            return_value: Default::default(),
+        };
+        let result = f(self, &mut context);
+        if instance.is_none() {
+            self.instance_list.remove_dummy(handle);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_target;
+
+    #[test]
+    fn target_prefix() {
+        assert_eq!(split_target("global.boss_hp = 1"), (None, "global.boss_hp = 1"));
+        assert_eq!(split_target("  @100001 x += 16 "), (Some("100001"), "x += 16"));
+        assert_eq!(split_target("@obj_player\thp"), (Some("obj_player"), "hp"));
+        assert_eq!(split_target("@obj_player"), (Some("obj_player"), ""));
+    }
+}
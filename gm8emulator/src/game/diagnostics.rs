@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Every compile error found while loading a game's code, along with where it came from.
+#[derive(Default, Serialize, Deserialize)]
+pub struct CompileReport {
+    errors: Vec<(String, String)>,
+}
+
+impl CompileReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an error. The location should name the asset, such as "script scr_move".
+    pub fn push(&mut self, location: impl Into<String>, message: impl Into<String>) {
+        self.errors.push((location.into(), message.into()))
+    }
+
+    /// Records several errors which all came from the same location.
+    pub fn extend(&mut self, location: impl fmt::Display, messages: Vec<String>) {
+        for message in messages {
+            self.push(location.to_string(), message);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+}
+
+impl fmt::Display for CompileReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} compile error{}:", self.len(), if self.len() == 1 { "" } else { "s" })?;
+        for (location, message) in &self.errors {
+            write!(f, "\n  in {}: {}", location, message)?;
+        }
+        Ok(())
+    }
+}
@@ -1,11 +1,11 @@
 use crate::{
     asset::{font, Font},
-    game::{string::RCStr, Game, GetAsset, Version},
+    game::{string::RCStr, Game, GetAsset, PlayType, Version},
     gml,
     math::Real,
 };
 use serde::{Deserialize, Serialize};
-use std::fmt::Write;
+use std::{fmt::Write, path::Path};
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum Halign {
@@ -578,4 +578,44 @@ impl Game {
             cursor_y += line_height;
         }
     }
+
+    /// Makes a font for font_add and font_replace from a TrueType font, with an empty name.
+    /// Only fonts next to the game are used when recording, replaying or running headless, so the machine doesn't
+    /// affect the run. If the font isn't found, the default font's characters are used instead.
+    pub fn load_font(
+        &mut self,
+        sys_name: RCStr,
+        size: u32,
+        bold: bool,
+        italic: bool,
+        first: u8,
+        last: u8,
+    ) -> Result<Font, String> {
+        if self.font_files.is_none() {
+            let system = self.play_type == PlayType::Normal && !self.window.is_headless();
+            let program_directory = self.decode_str(self.program_directory.as_ref()).into_owned();
+            self.font_files = Some(font::FontFiles::scan(Path::new(&program_directory), system));
+        }
+        let font_file = self.font_files.as_ref().unwrap().find(&self.decode_str(sys_name.as_ref()), bold, italic);
+        let (chars, tallest_char_height, own_graphics) = match font_file {
+            Some(file) => {
+                let (chars, height) = file.rasterise(size, first, last, self.encoding, &mut self.renderer)?;
+                (chars, height, true)
+            },
+            None => (self.default_font.chars_in_range(first, last), self.default_font.tallest_char_height, false),
+        };
+        Ok(Font {
+            name: "".into(),
+            sys_name,
+            charset: 1, // DEFAULT_CHARSET
+            size,
+            bold,
+            italic,
+            first,
+            last,
+            tallest_char_height,
+            chars,
+            own_graphics,
+        })
+    }
 }
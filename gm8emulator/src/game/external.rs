@@ -1,4 +1,7 @@
+pub mod dll39;
 mod dummy;
+pub mod registry;
+pub mod sound;
 mod win32;
 mod win64;
 
@@ -26,7 +29,8 @@ cfg_if! {
 
 pub enum Call {
     DummyNull(dll::ValueType),
-    DummyOne,
+    Stub(Value),
+    Builtin(registry::Function),
     DllCall(platform::ExternalImpl),
 }
 
@@ -58,7 +62,15 @@ Spec required for ExternalImpl {
  */
 
 impl External {
-    pub fn new(info: DefineInfo, disable_sound: bool, encoding: &'static Encoding) -> Result<Self, String> {
+    /// Sets up an external function, using a built-in version or a stub where there is one.
+    /// If `native_sound` is set, sound DLL functions call the real DLL where it can be loaded, which is only wanted
+    /// in normal play, since the real DLLs aren't deterministic and their state can't go in savestates.
+    pub fn new(
+        info: DefineInfo,
+        stubs: &registry::Stubs,
+        encoding: &'static Encoding,
+        native_sound: bool,
+    ) -> Result<Self, String> {
         if info.arg_types.len() > 4 && info.arg_types.contains(&dll::ValueType::Str) {
             return Err("DLL functions with more than 4 arguments cannot have string arguments".into())
         }
@@ -71,31 +83,77 @@ impl External {
             .to_string_lossy()
             .to_string();
         dll_name_lower.make_ascii_lowercase();
-        let call = match dll_name_lower.as_str() {
-            "gmfmodsimple.dll" if disable_sound && info.fn_name.as_ref() == b"FMODSoundAdd" => Call::DummyOne,
-            "gmfmodsimple.dll" | "ssound.dll" | "supersound.dll" | "sxms-3.dll" if disable_sound => {
-                Call::DummyNull(info.res_type)
-            },
-            _ => Call::DllCall(platform::ExternalImpl::new(&info, encoding)?),
+        if native_sound && sound::DLLS.contains(&dll_name_lower.as_str()) {
+            if let Ok(external) = platform::ExternalImpl::new(&info, encoding) {
+                return Ok(Self { call: Call::DllCall(external), info })
+            }
+        }
+        let call = if let Some(function) = registry::find(&dll_name_lower, &info) {
+            Call::Builtin(function)
+        } else if let Some(value) = stubs.get(&dll_name_lower, info.fn_name.as_ref()) {
+            Call::Stub(convert_result(value.clone(), info.res_type))
+        } else {
+            match dll_name_lower.as_str() {
+                // anything the built-in sound DLLs don't have does nothing, and tracker music can't be played
+                name if sound::DLLS.contains(&name) => {
+                    eprintln!(
+                        "Warning: {} from {} isn't supported by the built-in sound engine, so it does nothing",
+                        info.fn_name, dll_name_lower
+                    );
+                    Call::DummyNull(info.res_type)
+                },
+                _ => Call::DllCall(
+                    platform::ExternalImpl::new(&info, encoding)
+                        .map_err(|e| format!("{} (it can be stubbed out in the game's .externals.ini file)", e))?,
+                ),
+            }
         };
         Ok(Self { call, info })
     }
 
-    pub fn call(&self, args: &[Value]) -> gml::Result<Value> {
+    /// The built-in function replacing this one, if there is one.
+    /// These need the game to be called, so calling them is up to `external_call`.
+    pub fn builtin(&self) -> Option<registry::Function> {
+        match self.call {
+            Call::Builtin(function) => Some(function),
+            _ => None,
+        }
+    }
+
+    /// Converts the arguments to the types the function was defined with.
+    /// Returns None, with a warning, if it was defined with a different number of them.
+    pub fn convert_args<'a>(&'a self, args: &'a [Value]) -> Option<impl Iterator<Item = Value> + 'a> {
         if args.len() != self.info.arg_types.len() {
             eprintln!(
                 "Warning: call to external function {} from {} with an invalid argument count was ignored",
                 self.info.fn_name, self.info.dll_name
             );
-            Ok(Default::default())
+            None
         } else {
-            let args = args.iter().zip(&self.info.arg_types).map(|(v, t)| match t {
+            Some(args.iter().zip(&self.info.arg_types).map(|(v, t)| match t {
                 dll::ValueType::Real => f64::from(v.clone()).into(),
                 dll::ValueType::Str => RCStr::from(v.clone()).into(),
-            });
-            self.call.call(args)
+            }))
         }
     }
+
+    pub fn call(&self, args: &[Value]) -> gml::Result<Value> {
+        match self.convert_args(args) {
+            Some(args) => self.call.call(args),
+            None => Ok(Default::default()),
+        }
+    }
+}
+
+/// Converts a built-in function's result to the type the function was defined with.
+pub fn convert_result(value: Value, res_type: dll::ValueType) -> Value {
+    match (res_type, value) {
+        (dll::ValueType::Str, value @ Value::Real(_)) => value.repr().into(),
+        (dll::ValueType::Real, Value::Str(s)) => {
+            String::from_utf8_lossy(s.as_ref()).trim().parse::<f64>().unwrap_or(0.0).into()
+        },
+        (_, value) => value,
+    }
 }
 
 impl Call {
@@ -105,7 +163,11 @@ impl Call {
                 dll::ValueType::Real => Ok(0.into()),
                 dll::ValueType::Str => Ok("".into()),
             },
-            Call::DummyOne => Ok(1.into()),
+            Call::Stub(value) => Ok(value.clone()),
+            Call::Builtin(_) => Err(gml::Error::FunctionError(
+                "external_call".into(),
+                "built-in DLL functions must be called through the game".into(),
+            )),
             Call::DllCall(call) => {
                 call.call(args).map_err(|e| gml::Error::FunctionError("external_call".into(), e.into()))
             },
@@ -0,0 +1,893 @@
+//! A built-in version of 39dll, the networking DLL most GM8 multiplayer games use.
+//!
+//! Buffers are part of the game's state, so they're kept in savestates like data structures are. Sockets can't be,
+//! so anything which goes through the network is recorded as a replay event instead: replays get the same results
+//! and messages back without opening any sockets at all.
+
+use super::registry::{Builtin, Function};
+use crate::{
+    game::{replay::Event, Game, PlayType},
+    gml::{self, Value},
+    handleman::{HandleList, HandleManager},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket},
+    time::Duration,
+};
+
+const DLLS: &[&str] = &["39dll.dll"];
+
+const fn builtin(name: &'static [u8], arg_count: usize, function: Function) -> Builtin {
+    Builtin { dlls: DLLS, name, arg_count: Some(arg_count), function }
+}
+
+pub const BUILTINS: &[Builtin] = &[
+    builtin(b"sockstart", 0, Game::dll39_sockstart),
+    builtin(b"sockexit", 0, Game::dll39_sockexit),
+    builtin(b"tcpconnect", 3, Game::dll39_tcpconnect),
+    builtin(b"tcplisten", 3, Game::dll39_tcplisten),
+    builtin(b"tcpaccept", 2, Game::dll39_tcpaccept),
+    builtin(b"tcpip", 1, Game::dll39_tcpip),
+    builtin(b"tcpconnected", 1, Game::dll39_tcpconnected),
+    builtin(b"udpconnect", 2, Game::dll39_udpconnect),
+    builtin(b"setnagle", 2, Game::dll39_setnagle),
+    builtin(b"setformat", 3, Game::dll39_setformat),
+    builtin(b"setsync", 2, Game::dll39_setsync),
+    builtin(b"closesock", 1, Game::dll39_closesock),
+    builtin(b"socklasterror", 1, Game::dll39_socklasterror),
+    builtin(b"myhost", 0, Game::dll39_myhost),
+    builtin(b"hostip", 1, Game::dll39_hostip),
+    builtin(b"compareip", 2, Game::dll39_compareip),
+    builtin(b"lastinIP", 0, Game::dll39_lastinip),
+    builtin(b"lastinPort", 0, Game::dll39_lastinport),
+    builtin(b"sendmessage", 4, Game::dll39_sendmessage),
+    builtin(b"receivemessage", 3, Game::dll39_receivemessage),
+    builtin(b"peekmessage", 3, Game::dll39_peekmessage),
+    builtin(b"netconnected", 0, Game::dll39_netconnected),
+    builtin(b"writebyte", 2, Game::dll39_writebyte),
+    builtin(b"writeshort", 2, Game::dll39_writeshort),
+    builtin(b"writeushort", 2, Game::dll39_writeushort),
+    builtin(b"writeint", 2, Game::dll39_writeint),
+    builtin(b"writeuint", 2, Game::dll39_writeuint),
+    builtin(b"writefloat", 2, Game::dll39_writefloat),
+    builtin(b"writedouble", 2, Game::dll39_writedouble),
+    builtin(b"writechars", 2, Game::dll39_writechars),
+    builtin(b"writestring", 2, Game::dll39_writestring),
+    builtin(b"readbyte", 1, Game::dll39_readbyte),
+    builtin(b"readshort", 1, Game::dll39_readshort),
+    builtin(b"readushort", 1, Game::dll39_readushort),
+    builtin(b"readint", 1, Game::dll39_readint),
+    builtin(b"readuint", 1, Game::dll39_readuint),
+    builtin(b"readfloat", 1, Game::dll39_readfloat),
+    builtin(b"readdouble", 1, Game::dll39_readdouble),
+    builtin(b"readchars", 2, Game::dll39_readchars),
+    builtin(b"readstring", 1, Game::dll39_readstring),
+    builtin(b"getpos", 2, Game::dll39_getpos),
+    builtin(b"setpos", 2, Game::dll39_setpos),
+    builtin(b"clearbuffer", 1, Game::dll39_clearbuffer),
+    builtin(b"buffsize", 1, Game::dll39_buffsize),
+    builtin(b"bytesleft", 1, Game::dll39_bytesleft),
+    builtin(b"createbuffer", 0, Game::dll39_createbuffer),
+    builtin(b"freebuffer", 1, Game::dll39_freebuffer),
+    builtin(b"bufferexists", 1, Game::dll39_bufferexists),
+    builtin(b"copybuffer", 2, Game::dll39_copybuffer),
+    builtin(b"copybuffer2", 4, Game::dll39_copybuffer2),
+    builtin(b"adler32", 1, Game::dll39_adler32),
+];
+
+/// The socket error 39dll gives when there's nothing to receive yet.
+const WOULD_BLOCK: i32 = 10035;
+
+/// Error codes as Winsock has them, since that's what games check for.
+fn error_code(error: &io::Error) -> i32 {
+    match error.kind() {
+        io::ErrorKind::WouldBlock => WOULD_BLOCK,
+        io::ErrorKind::AddrInUse => 10048,
+        io::ErrorKind::AddrNotAvailable => 10049,
+        io::ErrorKind::ConnectionAborted => 10053,
+        io::ErrorKind::ConnectionReset => 10054,
+        io::ErrorKind::NotConnected => 10057,
+        io::ErrorKind::TimedOut => 10060,
+        io::ErrorKind::ConnectionRefused => 10061,
+        _ => error.raw_os_error().unwrap_or(-1),
+    }
+}
+
+/// A message a socket has received, which is recorded so it can be given back during replays.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+    /// The message's size, or 0 if the connection closed, or negative if there's no message.
+    pub result: i32,
+    pub data: Vec<u8>,
+    /// Where a UDP message came from.
+    pub sender: Option<(String, u16)>,
+}
+
+impl Message {
+    fn none() -> Self {
+        Self { result: -1, data: Vec::new(), sender: None }
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Buffer {
+    data: Vec<u8>,
+    read_pos: usize,
+}
+
+impl Buffer {
+    fn write(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    /// Reads up to `count` bytes, fewer if the buffer runs out.
+    fn read(&mut self, count: usize) -> &[u8] {
+        let start = self.read_pos.min(self.data.len());
+        let end = start.saturating_add(count).min(self.data.len());
+        self.read_pos = end;
+        &self.data[start..end]
+    }
+
+    /// Reads a fixed-size value, which is zero if the buffer runs out.
+    fn read_array<const N: usize>(&mut self) -> [u8; N] {
+        let mut bytes = [0; N];
+        let read = self.read(N);
+        bytes[..read.len()].copy_from_slice(read);
+        bytes
+    }
+
+    fn read_string(&mut self) -> Vec<u8> {
+        let start = self.read_pos.min(self.data.len());
+        let len = self.data[start..].iter().position(|&b| b == 0).unwrap_or(self.data.len() - start);
+        let string = self.data[start..start + len].to_vec();
+        // skip the terminator too
+        self.read_pos = (start + len + 1).min(self.data.len());
+        string
+    }
+
+    fn clear(&mut self) {
+        self.data.clear();
+        self.read_pos = 0;
+    }
+}
+
+/// 39dll's buffers. Buffer 0 always exists, and it's the one games use when they don't say otherwise.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct State {
+    buffers: HandleList<Buffer>,
+    last_in: (String, u16),
+}
+
+impl Default for State {
+    fn default() -> Self {
+        let mut buffers = HandleList::new();
+        buffers.put(Buffer::default());
+        Self { buffers, last_in: (String::new(), 0) }
+    }
+}
+
+impl State {
+    /// Puts a received message in a buffer, and returns what `receivemessage` should.
+    pub fn take_message(&mut self, message: Message, buffer: i32) -> i32 {
+        if let Some(buffer) = self.buffers.get_mut(buffer) {
+            buffer.clear();
+            buffer.write(&message.data);
+        }
+        if let Some(sender) = message.sender {
+            self.last_in = sender;
+        }
+        message.result
+    }
+}
+
+enum SocketKind {
+    Tcp(TcpStream),
+    Listener(TcpListener),
+    Udp(UdpSocket),
+}
+
+struct Socket {
+    kind: SocketKind,
+    blocking: bool,
+    /// 0 for messages with a 2-byte length in front, 1 for messages ending with `separator`, or 2 for raw data.
+    format: i32,
+    separator: Vec<u8>,
+    /// Data which has been received but isn't a whole message yet.
+    incoming: Vec<u8>,
+    closed: bool,
+    last_error: i32,
+}
+
+impl Socket {
+    fn new(kind: SocketKind, blocking: bool) -> Self {
+        Self {
+            kind,
+            blocking,
+            format: 0,
+            separator: b"\r\n".to_vec(),
+            incoming: Vec::new(),
+            closed: false,
+            last_error: 0,
+        }
+    }
+
+    fn set_blocking(&mut self, blocking: bool) -> io::Result<()> {
+        self.blocking = blocking;
+        match &self.kind {
+            SocketKind::Tcp(stream) => stream.set_nonblocking(!blocking),
+            SocketKind::Listener(listener) => listener.set_nonblocking(!blocking),
+            SocketKind::Udp(socket) => socket.set_nonblocking(!blocking),
+        }
+    }
+
+    /// Finds the next whole message in what's been received so far, and takes it out unless it's only a peek.
+    fn next_message(&mut self, len: usize, peek: bool) -> Option<Vec<u8>> {
+        let (start, end, next) = match self.format {
+            0 => {
+                let size = usize::from(u16::from_le_bytes([*self.incoming.first()?, *self.incoming.get(1)?]));
+                if self.incoming.len() < 2 + size {
+                    return None
+                }
+                (2, 2 + size, 2 + size)
+            },
+            1 if !self.separator.is_empty() => {
+                let end = self.incoming.windows(self.separator.len()).position(|w| w == self.separator.as_slice())?;
+                (0, end, end + self.separator.len())
+            },
+            _ if self.incoming.is_empty() => return None,
+            _ => {
+                let end = if len == 0 { self.incoming.len() } else { len.min(self.incoming.len()) };
+                (0, end, end)
+            },
+        };
+        let message = self.incoming[start..end].to_vec();
+        if !peek {
+            self.incoming.drain(..next);
+        }
+        Some(message)
+    }
+
+    fn receive(&mut self, len: usize, peek: bool) -> Message {
+        match &mut self.kind {
+            SocketKind::Udp(socket) => {
+                let mut data = vec![0; 65536];
+                let result = if peek { socket.peek_from(&mut data) } else { socket.recv_from(&mut data) };
+                match result {
+                    Ok((size, from)) => {
+                        data.truncate(size);
+                        Message { result: size as i32, data, sender: Some((from.ip().to_string(), from.port())) }
+                    },
+                    Err(e) => {
+                        self.last_error = error_code(&e);
+                        Message::none()
+                    },
+                }
+            },
+            SocketKind::Tcp(_) => self.receive_tcp(len, peek),
+            SocketKind::Listener(_) => Message::none(),
+        }
+    }
+
+    fn receive_tcp(&mut self, len: usize, peek: bool) -> Message {
+        let mut chunk = [0; 8192];
+        loop {
+            if let Some(data) = self.next_message(len, peek) {
+                break Message { result: data.len() as i32, data, sender: None }
+            }
+            if self.closed {
+                break Message { result: 0, data: Vec::new(), sender: None }
+            }
+            let result = match &mut self.kind {
+                SocketKind::Tcp(stream) => stream.read(&mut chunk),
+                _ => break Message::none(),
+            };
+            match result {
+                Ok(0) => self.closed = true,
+                Ok(size) => self.incoming.extend_from_slice(&chunk[..size]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => {
+                    if e.kind() != io::ErrorKind::WouldBlock {
+                        self.closed = true;
+                    }
+                    self.last_error = error_code(&e);
+                    break Message::none()
+                },
+            }
+        }
+    }
+
+    fn send(&mut self, data: &[u8], to: Option<SocketAddr>) -> i32 {
+        let result = match &mut self.kind {
+            SocketKind::Tcp(stream) => {
+                let mut message = Vec::with_capacity(data.len() + 2);
+                match self.format {
+                    0 => {
+                        message.extend_from_slice(&(data.len() as u16).to_le_bytes());
+                        message.extend_from_slice(data);
+                    },
+                    1 => {
+                        message.extend_from_slice(data);
+                        message.extend_from_slice(&self.separator);
+                    },
+                    _ => message.extend_from_slice(data),
+                }
+                stream.write_all(&message).map(|()| data.len())
+            },
+            SocketKind::Udp(socket) => match to {
+                Some(addr) => socket.send_to(data, addr),
+                None => Err(io::ErrorKind::AddrNotAvailable.into()),
+            },
+            SocketKind::Listener(_) => Err(io::ErrorKind::NotConnected.into()),
+        };
+        match result {
+            Ok(size) => size as i32,
+            Err(e) => {
+                self.last_error = error_code(&e);
+                -1
+            },
+        }
+    }
+}
+
+/// Open sockets, which are given to the game as their index plus one. These don't go in savestates.
+pub struct Sockets(HandleList<Socket>);
+
+impl Default for Sockets {
+    fn default() -> Self {
+        Self(HandleList::new())
+    }
+}
+
+fn resolve(host: &str, port: u16) -> io::Result<SocketAddr> {
+    (host, port).to_socket_addrs()?.find(|a| a.is_ipv4()).ok_or_else(|| io::ErrorKind::AddrNotAvailable.into())
+}
+
+impl Sockets {
+    fn add(&mut self, kind: SocketKind, blocking: bool) -> i32 {
+        let mut socket = Socket::new(kind, blocking);
+        if let Err(e) = socket.set_blocking(blocking) {
+            socket.last_error = error_code(&e);
+        }
+        self.0.put(socket) + 1
+    }
+
+    fn get(&mut self, id: i32) -> Option<&mut Socket> {
+        self.0.get_mut(id - 1)
+    }
+
+    pub fn tcp_connect(&mut self, host: &str, port: u16, blocking: bool) -> i32 {
+        match resolve(host, port).and_then(|addr| TcpStream::connect_timeout(&addr, Duration::from_secs(5))) {
+            Ok(stream) => self.add(SocketKind::Tcp(stream), blocking),
+            Err(_) => -1,
+        }
+    }
+
+    pub fn tcp_listen(&mut self, port: u16, blocking: bool) -> i32 {
+        match TcpListener::bind(("0.0.0.0", port)) {
+            Ok(listener) => self.add(SocketKind::Listener(listener), blocking),
+            Err(_) => -1,
+        }
+    }
+
+    pub fn tcp_accept(&mut self, id: i32, blocking: bool) -> i32 {
+        let result = match self.get(id) {
+            Some(Socket { kind: SocketKind::Listener(listener), .. }) => listener.accept(),
+            _ => return -1,
+        };
+        match result {
+            Ok((stream, _)) => self.add(SocketKind::Tcp(stream), blocking),
+            Err(e) => {
+                self.get(id).unwrap().last_error = error_code(&e);
+                -1
+            },
+        }
+    }
+
+    pub fn udp_connect(&mut self, port: u16, blocking: bool) -> i32 {
+        match UdpSocket::bind(("0.0.0.0", port)) {
+            Ok(socket) => self.add(SocketKind::Udp(socket), blocking),
+            Err(_) => -1,
+        }
+    }
+
+    pub fn close(&mut self, id: i32) {
+        if let Some(Socket { kind: SocketKind::Tcp(stream), .. }) = self.get(id) {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        self.0.delete(id - 1);
+    }
+
+    pub fn close_all(&mut self) {
+        self.0 = HandleList::new();
+    }
+
+    pub fn peer_ip(&mut self, id: i32) -> String {
+        match self.get(id) {
+            Some(Socket { kind: SocketKind::Tcp(stream), .. }) => {
+                stream.peer_addr().map(|a| a.ip().to_string()).unwrap_or_default()
+            },
+            _ => String::new(),
+        }
+    }
+
+    pub fn is_connected(&mut self, id: i32) -> bool {
+        match self.get(id) {
+            Some(socket @ Socket { kind: SocketKind::Tcp(_), .. }) => !socket.closed,
+            _ => false,
+        }
+    }
+
+    pub fn set_nagle(&mut self, id: i32, nagle: bool) {
+        if let Some(Socket { kind: SocketKind::Tcp(stream), .. }) = self.get(id) {
+            let _ = stream.set_nodelay(!nagle);
+        }
+    }
+
+    pub fn set_format(&mut self, id: i32, format: i32, separator: &[u8]) {
+        if let Some(socket) = self.get(id) {
+            socket.format = format;
+            socket.separator = separator.to_vec();
+        }
+    }
+
+    pub fn set_blocking(&mut self, id: i32, blocking: bool) {
+        if let Some(socket) = self.get(id) {
+            if let Err(e) = socket.set_blocking(blocking) {
+                socket.last_error = error_code(&e);
+            }
+        }
+    }
+
+    pub fn last_error(&mut self, id: i32) -> i32 {
+        self.get(id).map(|s| s.last_error).unwrap_or(0)
+    }
+
+    pub fn send(&mut self, id: i32, host: &str, port: u16, data: &[u8]) -> i32 {
+        let to = resolve(host, port).ok();
+        self.get(id).map(|s| s.send(data, to)).unwrap_or(-1)
+    }
+
+    pub fn receive(&mut self, id: i32, len: usize, peek: bool) -> Message {
+        self.get(id).map(|s| s.receive(len, peek)).unwrap_or_else(Message::none)
+    }
+}
+
+/// Checks an IP address against a mask like "192.168.*.*".
+fn compare_ip(ip: &str, mask: &str) -> bool {
+    let (ip, mask) = (ip.split('.').collect::<Vec<_>>(), mask.split('.').collect::<Vec<_>>());
+    ip.len() == mask.len() && ip.iter().zip(&mask).all(|(part, mask)| *mask == "*" || part == mask)
+}
+
+fn real_arg(args: &[Value], index: usize) -> f64 {
+    args.get(index).cloned().map(f64::from).unwrap_or(0.0)
+}
+
+fn int_arg(args: &[Value], index: usize) -> i32 {
+    real_arg(args, index) as i32
+}
+
+fn bytes_arg(args: &[Value], index: usize) -> Vec<u8> {
+    match args.get(index) {
+        Some(Value::Str(s)) => s.as_ref().to_vec(),
+        _ => Vec::new(),
+    }
+}
+
+fn string_arg(args: &[Value], index: usize) -> String {
+    String::from_utf8_lossy(&bytes_arg(args, index)).into_owned()
+}
+
+impl Game {
+    /// Calls something which uses the network, recording its result or giving back the recorded one.
+    fn dll39_net_call(&mut self, name: &str, call: impl FnOnce(&mut Self) -> Value) -> gml::Result<Value> {
+        match self.play_type {
+            PlayType::Normal => Ok(call(self)),
+            PlayType::Record => {
+                let value = call(self);
+                self.stored_events.push_back(Event::Network(value.clone()));
+                Ok(value)
+            },
+            PlayType::Replay => match self.stored_events.pop_front() {
+                Some(Event::Network(value)) => Ok(value),
+                _ => Err(gml::Error::ReplayError(name.into())),
+            },
+        }
+    }
+
+    fn dll39_receive(&mut self, args: &[Value], peek: bool) -> gml::Result<Value> {
+        let (id, len, buffer) = (int_arg(args, 0), int_arg(args, 1).max(0) as usize, int_arg(args, 2));
+        let message = match self.play_type {
+            PlayType::Normal => self.dll39_sockets.receive(id, len, peek),
+            PlayType::Record => {
+                let message = self.dll39_sockets.receive(id, len, peek);
+                let Message { result, data, sender } = message.clone();
+                self.stored_events.push_back(Event::NetworkReceive(result, data, sender));
+                message
+            },
+            PlayType::Replay => match self.stored_events.pop_front() {
+                Some(Event::NetworkReceive(result, data, sender)) => Message { result, data, sender },
+                _ => return Err(gml::Error::ReplayError("receivemessage".into())),
+            },
+        };
+        Ok(self.dll39.take_message(message, buffer).into())
+    }
+
+    fn dll39_sockstart(&mut self, _args: &[Value]) -> gml::Result<Value> {
+        Ok(1.into())
+    }
+
+    fn dll39_sockexit(&mut self, _args: &[Value]) -> gml::Result<Value> {
+        self.dll39_sockets.close_all();
+        Ok(1.into())
+    }
+
+    fn dll39_netconnected(&mut self, _args: &[Value]) -> gml::Result<Value> {
+        Ok(1.into())
+    }
+
+    fn dll39_tcpconnect(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (host, port, blocking) = (string_arg(args, 0), int_arg(args, 1) as u16, int_arg(args, 2) == 0);
+        self.dll39_net_call("tcpconnect", |game| game.dll39_sockets.tcp_connect(&host, port, blocking).into())
+    }
+
+    fn dll39_tcplisten(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (port, blocking) = (int_arg(args, 0) as u16, int_arg(args, 2) == 0);
+        self.dll39_net_call("tcplisten", |game| game.dll39_sockets.tcp_listen(port, blocking).into())
+    }
+
+    fn dll39_tcpaccept(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (id, blocking) = (int_arg(args, 0), int_arg(args, 1) == 0);
+        self.dll39_net_call("tcpaccept", |game| game.dll39_sockets.tcp_accept(id, blocking).into())
+    }
+
+    fn dll39_tcpip(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let id = int_arg(args, 0);
+        self.dll39_net_call("tcpip", |game| game.dll39_sockets.peer_ip(id).into())
+    }
+
+    fn dll39_tcpconnected(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let id = int_arg(args, 0);
+        self.dll39_net_call("tcpconnected", |game| game.dll39_sockets.is_connected(id).into())
+    }
+
+    fn dll39_udpconnect(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (port, blocking) = (int_arg(args, 0) as u16, int_arg(args, 1) == 0);
+        self.dll39_net_call("udpconnect", |game| game.dll39_sockets.udp_connect(port, blocking).into())
+    }
+
+    fn dll39_setnagle(&mut self, args: &[Value]) -> gml::Result<Value> {
+        if self.play_type != PlayType::Replay {
+            self.dll39_sockets.set_nagle(int_arg(args, 0), int_arg(args, 1) != 0);
+        }
+        Ok(1.into())
+    }
+
+    fn dll39_setformat(&mut self, args: &[Value]) -> gml::Result<Value> {
+        if self.play_type != PlayType::Replay {
+            self.dll39_sockets.set_format(int_arg(args, 0), int_arg(args, 1), &bytes_arg(args, 2));
+        }
+        Ok(1.into())
+    }
+
+    fn dll39_setsync(&mut self, args: &[Value]) -> gml::Result<Value> {
+        if self.play_type != PlayType::Replay {
+            self.dll39_sockets.set_blocking(int_arg(args, 0), int_arg(args, 1) == 0);
+        }
+        Ok(1.into())
+    }
+
+    fn dll39_closesock(&mut self, args: &[Value]) -> gml::Result<Value> {
+        if self.play_type != PlayType::Replay {
+            self.dll39_sockets.close(int_arg(args, 0));
+        }
+        Ok(1.into())
+    }
+
+    fn dll39_socklasterror(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let id = int_arg(args, 0);
+        self.dll39_net_call("socklasterror", |game| game.dll39_sockets.last_error(id).into())
+    }
+
+    fn dll39_myhost(&mut self, _args: &[Value]) -> gml::Result<Value> {
+        Ok("localhost".into())
+    }
+
+    fn dll39_hostip(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let host = string_arg(args, 0);
+        self.dll39_net_call("hostip", |_| resolve(&host, 0).map(|a| a.ip().to_string()).unwrap_or_default().into())
+    }
+
+    fn dll39_compareip(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(compare_ip(&string_arg(args, 0), &string_arg(args, 1)).into())
+    }
+
+    fn dll39_lastinip(&mut self, _args: &[Value]) -> gml::Result<Value> {
+        Ok(self.dll39.last_in.0.clone().into())
+    }
+
+    fn dll39_lastinport(&mut self, _args: &[Value]) -> gml::Result<Value> {
+        Ok(u32::from(self.dll39.last_in.1).into())
+    }
+
+    fn dll39_sendmessage(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (id, host, port) = (int_arg(args, 0), string_arg(args, 1), int_arg(args, 2) as u16);
+        let data = self.dll39.buffers.get(int_arg(args, 3)).map(|b| b.data.clone()).unwrap_or_default();
+        self.dll39_net_call("sendmessage", |game| game.dll39_sockets.send(id, &host, port, &data).into())
+    }
+
+    fn dll39_receivemessage(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.dll39_receive(args, false)
+    }
+
+    fn dll39_peekmessage(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.dll39_receive(args, true)
+    }
+
+    fn dll39_write(&mut self, args: &[Value], bytes: &[u8]) -> gml::Result<Value> {
+        if let Some(buffer) = self.dll39.buffers.get_mut(int_arg(args, 1)) {
+            buffer.write(bytes);
+        }
+        Ok(1.into())
+    }
+
+    fn dll39_writebyte(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.dll39_write(args, &(real_arg(args, 0) as i64 as u8).to_le_bytes())
+    }
+
+    fn dll39_writeshort(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.dll39_write(args, &(real_arg(args, 0) as i64 as i16).to_le_bytes())
+    }
+
+    fn dll39_writeushort(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.dll39_write(args, &(real_arg(args, 0) as i64 as u16).to_le_bytes())
+    }
+
+    fn dll39_writeint(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.dll39_write(args, &(real_arg(args, 0) as i64 as i32).to_le_bytes())
+    }
+
+    fn dll39_writeuint(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.dll39_write(args, &(real_arg(args, 0) as i64 as u32).to_le_bytes())
+    }
+
+    fn dll39_writefloat(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.dll39_write(args, &(real_arg(args, 0) as f32).to_le_bytes())
+    }
+
+    fn dll39_writedouble(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.dll39_write(args, &real_arg(args, 0).to_le_bytes())
+    }
+
+    fn dll39_writechars(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.dll39_write(args, &bytes_arg(args, 0))
+    }
+
+    fn dll39_writestring(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let mut bytes = bytes_arg(args, 0);
+        bytes.push(0);
+        self.dll39_write(args, &bytes)
+    }
+
+    fn dll39_buffer(&mut self, args: &[Value], index: usize) -> Option<&mut Buffer> {
+        self.dll39.buffers.get_mut(int_arg(args, index))
+    }
+
+    fn dll39_readbyte(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.dll39_buffer(args, 0).map(|b| f64::from(u8::from_le_bytes(b.read_array()))).unwrap_or(0.0).into())
+    }
+
+    fn dll39_readshort(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.dll39_buffer(args, 0).map(|b| f64::from(i16::from_le_bytes(b.read_array()))).unwrap_or(0.0).into())
+    }
+
+    fn dll39_readushort(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.dll39_buffer(args, 0).map(|b| f64::from(u16::from_le_bytes(b.read_array()))).unwrap_or(0.0).into())
+    }
+
+    fn dll39_readint(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.dll39_buffer(args, 0).map(|b| i32::from_le_bytes(b.read_array())).unwrap_or(0).into())
+    }
+
+    fn dll39_readuint(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.dll39_buffer(args, 0).map(|b| u32::from_le_bytes(b.read_array())).unwrap_or(0).into())
+    }
+
+    fn dll39_readfloat(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.dll39_buffer(args, 0).map(|b| f64::from(f32::from_le_bytes(b.read_array()))).unwrap_or(0.0).into())
+    }
+
+    fn dll39_readdouble(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.dll39_buffer(args, 0).map(|b| f64::from_le_bytes(b.read_array())).unwrap_or(0.0).into())
+    }
+
+    fn dll39_readchars(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let len = int_arg(args, 0).max(0) as usize;
+        Ok(self.dll39_buffer(args, 1).map(|b| b.read(len).to_vec()).unwrap_or_default().into())
+    }
+
+    fn dll39_readstring(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.dll39_buffer(args, 0).map(|b| b.read_string()).unwrap_or_default().into())
+    }
+
+    fn dll39_getpos(&mut self, args: &[Value]) -> gml::Result<Value> {
+        // 0 is where the next write goes, which is always the end, and 1 is where the next read comes from
+        let read = int_arg(args, 0) != 0;
+        let pos = self.dll39_buffer(args, 1).map(|b| if read { b.read_pos } else { b.data.len() });
+        Ok(pos.unwrap_or(0).into())
+    }
+
+    fn dll39_setpos(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let pos = int_arg(args, 0).max(0) as usize;
+        if let Some(buffer) = self.dll39_buffer(args, 1) {
+            buffer.read_pos = pos.min(buffer.data.len());
+        }
+        Ok(1.into())
+    }
+
+    fn dll39_clearbuffer(&mut self, args: &[Value]) -> gml::Result<Value> {
+        if let Some(buffer) = self.dll39_buffer(args, 0) {
+            buffer.clear();
+        }
+        Ok(1.into())
+    }
+
+    fn dll39_buffsize(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.dll39_buffer(args, 0).map(|b| b.data.len()).unwrap_or(0).into())
+    }
+
+    fn dll39_bytesleft(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.dll39_buffer(args, 0).map(|b| b.data.len().saturating_sub(b.read_pos)).unwrap_or(0).into())
+    }
+
+    fn dll39_createbuffer(&mut self, _args: &[Value]) -> gml::Result<Value> {
+        Ok(self.dll39.buffers.put(Buffer::default()).into())
+    }
+
+    fn dll39_freebuffer(&mut self, args: &[Value]) -> gml::Result<Value> {
+        // the default buffer can't be freed
+        let id = int_arg(args, 0);
+        Ok((id != 0 && self.dll39.buffers.delete(id)).into())
+    }
+
+    fn dll39_bufferexists(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.dll39.buffers.get(int_arg(args, 0)).is_some().into())
+    }
+
+    fn dll39_copybuffer(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let data = self.dll39.buffers.get(int_arg(args, 1)).map(|b| b.data.clone()).unwrap_or_default();
+        if let Some(buffer) = self.dll39_buffer(args, 0) {
+            buffer.write(&data);
+        }
+        Ok(1.into())
+    }
+
+    fn dll39_copybuffer2(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (start, len) = (int_arg(args, 1).max(0) as usize, int_arg(args, 2).max(0) as usize);
+        let data = match self.dll39.buffers.get(int_arg(args, 3)) {
+            Some(source) => {
+                let start = start.min(source.data.len());
+                source.data[start..start.saturating_add(len).min(source.data.len())].to_vec()
+            },
+            None => Vec::new(),
+        };
+        if let Some(buffer) = self.dll39_buffer(args, 0) {
+            buffer.write(&data);
+        }
+        Ok(1.into())
+    }
+
+    fn dll39_adler32(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (mut a, mut b) = (1u32, 0u32);
+        for &byte in self.dll39_buffer(args, 0).map(|b| b.data.as_slice()).unwrap_or_default() {
+            a = (a + u32::from(byte)) % 65521;
+            b = (b + a) % 65521;
+        }
+        Ok(((b << 16) | a).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare_ip, Buffer, Message, Sockets, State};
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn buffer_reads() {
+        let mut buffer = Buffer::default();
+        buffer.write(&[1, 0xFE, 0xFF]);
+        buffer.write(b"hi\0");
+        assert_eq!(buffer.read_array::<1>(), [1]);
+        assert_eq!(i16::from_le_bytes(buffer.read_array()), -2);
+        assert_eq!(buffer.read_string(), b"hi");
+        // reading past the end gives zeroes
+        assert_eq!(buffer.read_array::<4>(), [0; 4]);
+    }
+
+    #[test]
+    fn ip_masks() {
+        assert!(compare_ip("192.168.1.20", "192.168.*.*"));
+        assert!(!compare_ip("10.0.0.1", "192.168.*.*"));
+        assert!(!compare_ip("192.168.1", "192.168.*.*"));
+    }
+
+    #[test]
+    fn tcp_loopback() {
+        let mut sockets = Sockets::default();
+        let listener = sockets.tcp_listen(0, false);
+        assert!(listener > 0);
+        let port = match &sockets.get(listener).unwrap().kind {
+            super::SocketKind::Listener(l) => l.local_addr().unwrap().port(),
+            _ => unreachable!(),
+        };
+        let client = sockets.tcp_connect("127.0.0.1", port, false);
+        assert!(client > 0);
+        let mut server = -1;
+        for _ in 0..100 {
+            server = sockets.tcp_accept(listener, false);
+            if server > 0 {
+                break
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(server > 0);
+
+        // two messages in one go, which have to come out separately
+        assert_eq!(sockets.send(client, "", 0, b"hello"), 5);
+        assert_eq!(sockets.send(client, "", 0, b"world!"), 6);
+        let mut state = State::default();
+        let mut received = Vec::new();
+        for _ in 0..100 {
+            let message = sockets.receive(server, 0, false);
+            if message.result > 0 {
+                received.push(String::from_utf8(message.data.clone()).unwrap());
+                assert_eq!(state.take_message(message, 0), received.last().unwrap().len() as i32);
+                if received.len() == 2 {
+                    break
+                }
+            } else {
+                assert_eq!(message, Message::none());
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+        assert_eq!(received, ["hello", "world!"]);
+        assert_eq!(state.buffers.get(0).unwrap().data, b"world!");
+
+        sockets.close(client);
+        let mut result = -1;
+        for _ in 0..100 {
+            result = sockets.receive(server, 0, false).result;
+            if result == 0 {
+                break
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(result, 0);
+        assert!(!sockets.is_connected(server));
+    }
+
+    #[test]
+    fn udp_loopback() {
+        let mut sockets = Sockets::default();
+        let receiver = sockets.udp_connect(0, false);
+        let sender = sockets.udp_connect(0, false);
+        let port = match &sockets.get(receiver).unwrap().kind {
+            super::SocketKind::Udp(s) => s.local_addr().unwrap().port(),
+            _ => unreachable!(),
+        };
+        assert_eq!(sockets.send(sender, "127.0.0.1", port, b"ping"), 4);
+        let mut message = Message::none();
+        for _ in 0..100 {
+            message = sockets.receive(receiver, 0, false);
+            if message.result > 0 {
+                break
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(message.data, b"ping");
+        let mut state = State::default();
+        state.take_message(message, 0);
+        assert_eq!(state.last_in.0, "127.0.0.1");
+    }
+}
@@ -0,0 +1,125 @@
+//! Built-in versions of DLL functions, and stubs for ones which don't need to do anything.
+//!
+//! When a game defines an external function, it's looked up here before the real DLL is loaded. Built-in versions
+//! work on every platform and keep their state in savestates, and they're always used if there is one.
+//! Anything else can be stubbed out per game, in an INI file next to the game named `<game>.externals.ini`:
+//!
+//! ```ini
+//! ; every function in a DLL
+//! [window_helper.dll]
+//! * = 1
+//!
+//! ; or just some of them, returning a number or a quoted string
+//! [strings.dll]
+//! string_reverse = ""
+//! string_count = 0
+//! ```
+
+use super::{dll39, sound, DefineInfo};
+use crate::{
+    game::Game,
+    gml::{self, Value},
+};
+use ini::{Ini, ParseOption};
+use std::{collections::HashMap, path::Path};
+
+pub type Function = fn(&mut Game, &[Value]) -> gml::Result<Value>;
+
+/// A built-in function, which replaces a function with the same name from any of the given DLLs.
+pub struct Builtin {
+    /// Lowercase file names of the DLLs.
+    pub dlls: &'static [&'static str],
+    pub name: &'static [u8],
+    /// How many arguments the function has to be defined with, if it matters.
+    pub arg_count: Option<usize>,
+    pub function: Function,
+}
+
+const TABLES: &[&[Builtin]] = &[sound::BUILTINS, dll39::BUILTINS];
+
+/// Finds the built-in version of a function, given the DLL's lowercase file name.
+pub fn find(dll_name: &str, info: &DefineInfo) -> Option<Function> {
+    let builtin = TABLES
+        .iter()
+        .flat_map(|table| table.iter())
+        .find(|b| b.name == info.fn_name.as_ref() && b.dlls.contains(&dll_name))?;
+    match builtin.arg_count {
+        Some(count) if count != info.arg_types.len() => {
+            eprintln!(
+                "Warning: {} from {} was defined with {} arguments instead of {}, so the built-in version isn't used",
+                info.fn_name,
+                dll_name,
+                info.arg_types.len(),
+                count
+            );
+            None
+        },
+        _ => Some(builtin.function),
+    }
+}
+
+/// What to return from DLL functions which aren't implemented, read from the game's externals file.
+#[derive(Default)]
+pub struct Stubs {
+    functions: HashMap<(String, Vec<u8>), Value>,
+    dlls: HashMap<String, Value>,
+}
+
+impl Stubs {
+    /// Reads stubs from an INI file. It's fine if the file doesn't exist.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let mut stubs = Self::default();
+        if !path.exists() {
+            return Ok(stubs)
+        }
+        // quotes are left in, since they're what tells strings apart from numbers
+        let ini = Ini::load_from_file_opt(path, ParseOption { enabled_quote: false, enabled_escape: true })
+            .map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+        for (section, properties) in ini.iter() {
+            let dll_name = match section {
+                Some(name) => name.to_ascii_lowercase(),
+                None => continue,
+            };
+            for (function, value) in properties.iter() {
+                let value = parse_value(value)
+                    .ok_or_else(|| format!("Invalid return value for {} in {}: {}", function, path.display(), value))?;
+                if function == "*" {
+                    stubs.dlls.insert(dll_name.clone(), value);
+                } else {
+                    stubs.functions.insert((dll_name.clone(), function.as_bytes().to_vec()), value);
+                }
+            }
+        }
+        Ok(stubs)
+    }
+
+    /// Finds what a function should return, given the DLL's lowercase file name.
+    pub fn get(&self, dll_name: &str, fn_name: &[u8]) -> Option<&Value> {
+        self.functions.get(&(dll_name.to_string(), fn_name.to_vec())).or_else(|| self.dlls.get(dll_name))
+    }
+}
+
+/// Parses a stub's return value, which is either a number or a string in double quotes.
+fn parse_value(value: &str) -> Option<Value> {
+    let value = value.trim();
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        Some(value[1..value.len() - 1].into())
+    } else {
+        value.parse::<f64>().ok().map(Value::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_value;
+    use crate::gml::Value;
+
+    #[test]
+    fn stub_values() {
+        assert!(matches!(parse_value("1"), Some(Value::Real(r)) if r.into_inner() == 1.0));
+        assert!(matches!(parse_value(" -0.5 "), Some(Value::Real(r)) if r.into_inner() == -0.5));
+        assert!(matches!(parse_value("\"\""), Some(Value::Str(s)) if s.as_ref().is_empty()));
+        assert!(matches!(parse_value("\"ok\""), Some(Value::Str(s)) if s.as_ref() == b"ok"));
+        assert!(parse_value("yes").is_none());
+    }
+}
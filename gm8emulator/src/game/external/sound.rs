@@ -0,0 +1,467 @@
+//! Built-in replacements for popular sound DLLs: GMFMODSimple and Super Sound.
+//!
+//! Sounds loaded through these play through the emulator's own mixer, so they work on any platform, stay
+//! deterministic for replays, and end up in audio dumps. Anything the mixer can't do, such as 3D sound or changing
+//! the playback frequency, is accepted and ignored. In normal play on Windows, the real DLLs are used instead if
+//! they can be loaded.
+
+use super::registry::{Builtin, Function};
+use crate::{
+    asset::sound::{Kind, Sound},
+    game::{audio::Source, string::RCStr, Game, GetAsset},
+    gml::{self, Value},
+    math::Real,
+};
+use gmio::audio::SAMPLE_RATE;
+use serde::{Deserialize, Serialize};
+use shared::types::ID;
+use std::{collections::HashMap, path::Path, rc::Rc};
+
+const FMOD: &[&str] = &["gmfmodsimple.dll"];
+const SUPER_SOUND: &[&str] = &["ssound.dll", "supersound.dll"];
+
+/// Every sound DLL, including SXMS-3, which plays tracker music and isn't emulated.
+pub const DLLS: &[&str] = &["gmfmodsimple.dll", "ssound.dll", "supersound.dll", "sxms-3.dll"];
+
+/// Wrapper scripts for these DLLs don't agree on how functions are defined, so the arguments are never checked.
+const fn builtin(dlls: &'static [&'static str], name: &'static [u8], function: Function) -> Builtin {
+    Builtin { dlls, name, arg_count: None, function }
+}
+
+pub const BUILTINS: &[Builtin] = &[
+    builtin(FMOD, b"FMODinit", Game::sound_dll_init),
+    builtin(SUPER_SOUND, b"SS_Init", Game::sound_dll_init),
+    builtin(FMOD, b"FMODfree", Game::sound_dll_stop_all),
+    builtin(FMOD, b"FMODAllStop", Game::sound_dll_stop_all),
+    builtin(SUPER_SOUND, b"SS_Unload", Game::sound_dll_stop_all),
+    builtin(FMOD, b"FMODUpdate", Game::sound_dll_nothing),
+    builtin(FMOD, b"FMODGetLastError", Game::sound_dll_nothing),
+    builtin(FMOD, b"FMODSoundAdd", Game::sound_dll_load),
+    builtin(SUPER_SOUND, b"SS_LoadSound", Game::sound_dll_load),
+    builtin(FMOD, b"FMODSoundFree", Game::sound_dll_free),
+    builtin(SUPER_SOUND, b"SS_FreeSound", Game::sound_dll_free),
+    builtin(FMOD, b"FMODSoundPlay", Game::fmod_sound_play),
+    builtin(FMOD, b"FMODSoundLoop", Game::fmod_sound_loop),
+    builtin(FMOD, b"FMODSoundGetLength", Game::sound_dll_get_length),
+    builtin(SUPER_SOUND, b"SS_GetSoundLength", Game::sound_dll_get_length),
+    builtin(FMOD, b"FMODSoundSetMaxVolume", Game::fmod_sound_set_max_volume),
+    builtin(FMOD, b"FMODSoundSetGroup", Game::fmod_sound_set_group),
+    builtin(FMOD, b"FMODGroupSetVolume", Game::fmod_group_set_volume),
+    builtin(FMOD, b"FMODGroupStop", Game::fmod_group_stop),
+    builtin(FMOD, b"FMODGroupSetPaused", Game::fmod_group_set_paused),
+    builtin(FMOD, b"FMODMasterSetVolume", Game::fmod_master_set_volume),
+    builtin(FMOD, b"FMODInstanceStop", Game::fmod_instance_stop),
+    builtin(FMOD, b"FMODInstanceIsPlaying", Game::fmod_instance_is_playing),
+    builtin(FMOD, b"FMODInstanceSetPaused", Game::fmod_instance_set_paused),
+    builtin(FMOD, b"FMODInstanceGetPaused", Game::fmod_instance_get_paused),
+    builtin(FMOD, b"FMODInstanceSetVolume", Game::fmod_instance_set_volume),
+    builtin(FMOD, b"FMODInstanceGetVolume", Game::fmod_instance_get_volume),
+    builtin(FMOD, b"FMODInstanceSetPan", Game::fmod_instance_set_pan),
+    builtin(FMOD, b"FMODInstanceGetPan", Game::fmod_instance_get_pan),
+    builtin(FMOD, b"FMODInstanceSetPosition", Game::fmod_instance_set_position),
+    builtin(FMOD, b"FMODInstanceGetPosition", Game::fmod_instance_get_position),
+    builtin(FMOD, b"FMODInstanceGetSound", Game::fmod_instance_get_sound),
+    builtin(SUPER_SOUND, b"SS_PlaySound", Game::ss_play_sound),
+    builtin(SUPER_SOUND, b"SS_LoopSound", Game::ss_loop_sound),
+    builtin(SUPER_SOUND, b"SS_StopSound", Game::ss_stop_sound),
+    builtin(SUPER_SOUND, b"SS_PauseSound", Game::ss_pause_sound),
+    builtin(SUPER_SOUND, b"SS_ResumeSound", Game::ss_resume_sound),
+    builtin(SUPER_SOUND, b"SS_IsSoundPlaying", Game::ss_is_sound_playing),
+    builtin(SUPER_SOUND, b"SS_IsSoundPaused", Game::ss_is_sound_paused),
+    builtin(SUPER_SOUND, b"SS_IsSoundLooping", Game::ss_is_sound_looping),
+    builtin(SUPER_SOUND, b"SS_SetSoundVol", Game::ss_set_sound_vol),
+    builtin(SUPER_SOUND, b"SS_GetSoundVol", Game::ss_get_sound_vol),
+    builtin(SUPER_SOUND, b"SS_SetSoundPan", Game::ss_set_sound_pan),
+    builtin(SUPER_SOUND, b"SS_GetSoundPan", Game::ss_get_sound_pan),
+    builtin(SUPER_SOUND, b"SS_SetSoundPosition", Game::ss_set_sound_position),
+    builtin(SUPER_SOUND, b"SS_GetSoundPosition", Game::ss_get_sound_position),
+];
+
+/// Sounds loaded by the DLLs, and GMFMODSimple's volume settings.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SoundDlls {
+    /// Loaded sounds, where the handle given to the game is the index plus one.
+    pub files: Vec<Option<Box<Sound>>>,
+    fmod_sounds: HashMap<ID, FmodSound>,
+    fmod_groups: HashMap<i32, Real>,
+    fmod_master_volume: Real,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct FmodSound {
+    group: i32,
+    max_volume: Real,
+}
+
+impl Default for SoundDlls {
+    fn default() -> Self {
+        Self {
+            files: Vec::new(),
+            fmod_sounds: HashMap::new(),
+            fmod_groups: HashMap::new(),
+            fmod_master_volume: Real::from(1.0),
+        }
+    }
+}
+
+impl SoundDlls {
+    /// Works a GMFMODSimple sound's settings into its volume.
+    fn update_fmod_volume(&mut self, id: ID) {
+        let (group, max_volume) = match self.fmod_sounds.get(&id) {
+            Some(fmod_sound) => (fmod_sound.group, fmod_sound.max_volume),
+            None => return,
+        };
+        let group_volume = self.fmod_groups.get(&group).copied().unwrap_or_else(|| Real::from(1.0));
+        if let Some(sound) = self.files.get_asset_mut(id) {
+            sound.volume = max_volume * group_volume * self.fmod_master_volume;
+        }
+    }
+
+    fn update_fmod_volumes(&mut self) {
+        let ids = self.fmod_sounds.keys().copied().collect::<Vec<_>>();
+        for id in ids {
+            self.update_fmod_volume(id);
+        }
+    }
+}
+
+/// Reads a number from an argument, which may have been passed as a string if the DLL was defined that way.
+fn real_arg(args: &[Value], index: usize) -> Real {
+    match args.get(index) {
+        Some(Value::Real(r)) => *r,
+        Some(Value::Str(s)) => Real::from(String::from_utf8_lossy(s.as_ref()).trim().parse::<f64>().unwrap_or(0.0)),
+        None => Real::from(0.0),
+    }
+}
+
+fn int_arg(args: &[Value], index: usize) -> i32 {
+    real_arg(args, index).round()
+}
+
+fn bool_arg(args: &[Value], index: usize) -> bool {
+    real_arg(args, index) >= Real::from(0.5)
+}
+
+/// Turns a sound handle into an index into `SoundDlls::files`.
+fn sound_arg(args: &[Value], index: usize) -> ID {
+    int_arg(args, index) - 1
+}
+
+fn instance_arg(args: &[Value], index: usize) -> u32 {
+    int_arg(args, index).max(0) as u32
+}
+
+fn samples_to_ms(samples: usize) -> Value {
+    (samples as f64 * 1000.0 / f64::from(SAMPLE_RATE)).into()
+}
+
+fn ms_to_samples(ms: Real) -> usize {
+    (ms.into_inner().max(0.0) * f64::from(SAMPLE_RATE) / 1000.0) as usize
+}
+
+impl Game {
+    fn sound_dll_nothing(&mut self, _args: &[Value]) -> gml::Result<Value> {
+        Ok(0.into())
+    }
+
+    fn sound_dll_init(&mut self, _args: &[Value]) -> gml::Result<Value> {
+        Ok(1.into())
+    }
+
+    fn sound_dll_stop_all(&mut self, _args: &[Value]) -> gml::Result<Value> {
+        for id in 0..self.sound_dlls.files.len() {
+            self.audio.stop_source(Source::File(id as ID));
+        }
+        Ok(1.into())
+    }
+
+    fn sound_dll_load(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let file_name = match args.first() {
+            Some(Value::Str(s)) => s.clone(),
+            _ => return Ok(0.into()),
+        };
+        let path = self.decode_str(file_name.as_ref()).into_owned();
+        let data = match std::fs::read(&path) {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("Warning: couldn't load sound {}: {}", path, e);
+                return Ok(0.into())
+            },
+        };
+        let extension = Path::new(&path).extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+        let sound = Sound {
+            name: file_name.clone(),
+            kind: Kind::Normal,
+            extension: RCStr::from(extension),
+            source: file_name,
+            data: Some(Rc::from(data)),
+            volume: Real::from(1.0),
+            pan: Real::from(0.0),
+            preload: true,
+        };
+        let id = self.sound_dlls.files.len() as ID;
+        self.sound_dlls.files.push(Some(Box::new(sound)));
+        self.sound_dlls.fmod_sounds.insert(id, FmodSound { group: 0, max_volume: Real::from(1.0) });
+        self.sound_dlls.update_fmod_volume(id);
+        Ok((id + 1).into())
+    }
+
+    fn sound_dll_free(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let id = sound_arg(args, 0);
+        self.audio.stop_source(Source::File(id));
+        if let Some(sound) = self.sound_dlls.files.get_mut(id as usize) {
+            *sound = None;
+        }
+        self.sound_dlls.fmod_sounds.remove(&id);
+        Ok(1.into())
+    }
+
+    fn sound_dll_get_length(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let id = sound_arg(args, 0);
+        match self.sound_dlls.files.get_asset(id) {
+            Some(sound) => Ok(samples_to_ms(self.audio.length(Source::File(id), sound))),
+            None => Ok(0.into()),
+        }
+    }
+
+    fn fmod_play(&mut self, args: &[Value], looping: bool) -> gml::Result<Value> {
+        let instance = match self.audio.play_file(&self.sound_dlls.files, sound_arg(args, 0), looping) {
+            Some(instance) => instance,
+            None => return Ok(0.into()),
+        };
+        if bool_arg(args, 1) {
+            self.audio.set_instance_paused(instance, true);
+        }
+        Ok(instance.into())
+    }
+
+    fn fmod_sound_play(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.fmod_play(args, false)
+    }
+
+    fn fmod_sound_loop(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.fmod_play(args, true)
+    }
+
+    fn fmod_sound_set_max_volume(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let id = sound_arg(args, 0);
+        if let Some(fmod_sound) = self.sound_dlls.fmod_sounds.get_mut(&id) {
+            fmod_sound.max_volume = real_arg(args, 1).max(Real::from(0.0)).min(Real::from(1.0));
+            self.sound_dlls.update_fmod_volume(id);
+        }
+        Ok(1.into())
+    }
+
+    fn fmod_sound_set_group(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let id = sound_arg(args, 0);
+        if let Some(fmod_sound) = self.sound_dlls.fmod_sounds.get_mut(&id) {
+            fmod_sound.group = int_arg(args, 1);
+            self.sound_dlls.update_fmod_volume(id);
+        }
+        Ok(1.into())
+    }
+
+    fn fmod_group_sounds(&self, group: i32) -> Vec<ID> {
+        self.sound_dlls.fmod_sounds.iter().filter(|(_, s)| s.group == group).map(|(id, _)| *id).collect()
+    }
+
+    fn fmod_group_set_volume(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.sound_dlls
+            .fmod_groups
+            .insert(int_arg(args, 0), real_arg(args, 1).max(Real::from(0.0)).min(Real::from(1.0)));
+        self.sound_dlls.update_fmod_volumes();
+        Ok(1.into())
+    }
+
+    fn fmod_group_stop(&mut self, args: &[Value]) -> gml::Result<Value> {
+        for id in self.fmod_group_sounds(int_arg(args, 0)) {
+            self.audio.stop_source(Source::File(id));
+        }
+        Ok(1.into())
+    }
+
+    fn fmod_group_set_paused(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let paused = bool_arg(args, 1);
+        for id in self.fmod_group_sounds(int_arg(args, 0)) {
+            for instance in self.audio.instances(Source::File(id)) {
+                self.audio.set_instance_paused(instance, paused);
+            }
+        }
+        Ok(1.into())
+    }
+
+    fn fmod_master_set_volume(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.sound_dlls.fmod_master_volume = real_arg(args, 0).max(Real::from(0.0)).min(Real::from(1.0));
+        self.sound_dlls.update_fmod_volumes();
+        Ok(1.into())
+    }
+
+    fn fmod_instance_stop(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.audio.stop_instance(instance_arg(args, 0));
+        Ok(1.into())
+    }
+
+    fn fmod_instance_is_playing(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.audio.is_instance_playing(instance_arg(args, 0)).into())
+    }
+
+    fn fmod_instance_set_paused(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.audio.set_instance_paused(instance_arg(args, 0), bool_arg(args, 1));
+        Ok(1.into())
+    }
+
+    fn fmod_instance_get_paused(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.audio.is_instance_paused(instance_arg(args, 0)).into())
+    }
+
+    fn fmod_instance_set_volume(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let volume = real_arg(args, 1).max(Real::from(0.0)).min(Real::from(1.0));
+        self.audio.set_instance_volume(instance_arg(args, 0), volume);
+        Ok(1.into())
+    }
+
+    fn fmod_instance_get_volume(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.audio.instance_volume(instance_arg(args, 0)).into())
+    }
+
+    fn fmod_instance_set_pan(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let pan = real_arg(args, 1).max(Real::from(-1.0)).min(Real::from(1.0));
+        self.audio.set_instance_pan(instance_arg(args, 0), pan);
+        Ok(1.into())
+    }
+
+    fn fmod_instance_get_pan(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.audio.instance_pan(instance_arg(args, 0)).into())
+    }
+
+    /// Length of the sound an instance is playing, in samples.
+    fn instance_length(&mut self, instance: u32) -> usize {
+        match self.audio.instance_source(instance) {
+            Some(Source::File(id)) => match self.sound_dlls.files.get_asset(id) {
+                Some(sound) => self.audio.length(Source::File(id), sound),
+                None => 0,
+            },
+            _ => 0,
+        }
+    }
+
+    fn fmod_instance_set_position(&mut self, args: &[Value]) -> gml::Result<Value> {
+        // positions are given as a fraction of the sound's length
+        let instance = instance_arg(args, 0);
+        let position = real_arg(args, 1).max(Real::from(0.0)).min(Real::from(1.0));
+        let length = self.instance_length(instance);
+        self.audio.set_instance_position(instance, (position.into_inner() * length as f64) as usize);
+        Ok(1.into())
+    }
+
+    fn fmod_instance_get_position(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let instance = instance_arg(args, 0);
+        match self.instance_length(instance) {
+            0 => Ok(0.into()),
+            length => Ok((self.audio.instance_position(instance) as f64 / length as f64).into()),
+        }
+    }
+
+    fn fmod_instance_get_sound(&mut self, args: &[Value]) -> gml::Result<Value> {
+        match self.audio.instance_source(instance_arg(args, 0)) {
+            Some(Source::File(id)) => Ok((id + 1).into()),
+            _ => Ok(0.into()),
+        }
+    }
+
+    /// Super Sound only plays a sound once at a time, so every handle has at most one instance.
+    fn ss_instance(&self, args: &[Value]) -> Option<u32> {
+        self.audio.instances(Source::File(sound_arg(args, 0))).first().copied()
+    }
+
+    fn ss_play(&mut self, args: &[Value], looping: bool) -> gml::Result<Value> {
+        let id = sound_arg(args, 0);
+        self.audio.stop_source(Source::File(id));
+        Ok(self.audio.play_file(&self.sound_dlls.files, id, looping).is_some().into())
+    }
+
+    fn ss_play_sound(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.ss_play(args, false)
+    }
+
+    fn ss_loop_sound(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.ss_play(args, true)
+    }
+
+    fn ss_stop_sound(&mut self, args: &[Value]) -> gml::Result<Value> {
+        self.audio.stop_source(Source::File(sound_arg(args, 0)));
+        Ok(1.into())
+    }
+
+    fn ss_pause_sound(&mut self, args: &[Value]) -> gml::Result<Value> {
+        if let Some(instance) = self.ss_instance(args) {
+            self.audio.set_instance_paused(instance, true);
+        }
+        Ok(1.into())
+    }
+
+    fn ss_resume_sound(&mut self, args: &[Value]) -> gml::Result<Value> {
+        if let Some(instance) = self.ss_instance(args) {
+            self.audio.set_instance_paused(instance, false);
+        }
+        Ok(1.into())
+    }
+
+    fn ss_is_sound_playing(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.ss_instance(args).map(|i| !self.audio.is_instance_paused(i)).unwrap_or(false).into())
+    }
+
+    fn ss_is_sound_paused(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.ss_instance(args).map(|i| self.audio.is_instance_paused(i)).unwrap_or(false).into())
+    }
+
+    fn ss_is_sound_looping(&mut self, args: &[Value]) -> gml::Result<Value> {
+        Ok(self.ss_instance(args).map(|i| self.audio.is_instance_looping(i)).unwrap_or(false).into())
+    }
+
+    // Super Sound's volume goes from 0 to 10000, and its pan from -10000 to 10000
+
+    fn ss_set_sound_vol(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let volume = real_arg(args, 1).max(Real::from(0.0)).min(Real::from(10000.0)) / Real::from(10000.0);
+        if let Some(sound) = self.sound_dlls.files.get_asset_mut(sound_arg(args, 0)) {
+            sound.volume = volume;
+        }
+        Ok(1.into())
+    }
+
+    fn ss_get_sound_vol(&mut self, args: &[Value]) -> gml::Result<Value> {
+        match self.sound_dlls.files.get_asset(sound_arg(args, 0)) {
+            Some(sound) => Ok((sound.volume * Real::from(10000.0)).round().into()),
+            None => Ok(0.into()),
+        }
+    }
+
+    fn ss_set_sound_pan(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let pan = real_arg(args, 1).max(Real::from(-10000.0)).min(Real::from(10000.0)) / Real::from(10000.0);
+        if let Some(sound) = self.sound_dlls.files.get_asset_mut(sound_arg(args, 0)) {
+            sound.pan = pan;
+        }
+        Ok(1.into())
+    }
+
+    fn ss_get_sound_pan(&mut self, args: &[Value]) -> gml::Result<Value> {
+        match self.sound_dlls.files.get_asset(sound_arg(args, 0)) {
+            Some(sound) => Ok((sound.pan * Real::from(10000.0)).round().into()),
+            None => Ok(0.into()),
+        }
+    }
+
+    fn ss_set_sound_position(&mut self, args: &[Value]) -> gml::Result<Value> {
+        if let Some(instance) = self.ss_instance(args) {
+            self.audio.set_instance_position(instance, ms_to_samples(real_arg(args, 1)));
+        }
+        Ok(1.into())
+    }
+
+    fn ss_get_sound_position(&mut self, args: &[Value]) -> gml::Result<Value> {
+        match self.ss_instance(args) {
+            Some(instance) => Ok(samples_to_ms(self.audio.instance_position(instance))),
+            None => Ok(0.into()),
+        }
+    }
+}
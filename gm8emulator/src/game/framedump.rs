@@ -0,0 +1,319 @@
+//! Recording every frame of a run, for encoding videos without having to screen-record.
+//!
+//! Frames can be saved as a numbered PNG sequence, with each frame's room speed written next to its file name in
+//! `frames.txt`, and/or as uncompressed AVI video. An AVI stream can only have one frame rate and size, so whenever
+//! the room speed or screen size changes the video continues in a new file, `name_1.avi`, `name_2.avi` and so on.
+//! Every frame's duration is exactly 1/room_speed seconds either way, which is also how much sound the mixer writes for
+//! it, so the WAV file written alongside a dump lines up with it.
+
+use crate::game::Game;
+use image::RgbImage;
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+/// Stores every frame it's given as images, video or both.
+pub struct FrameDump {
+    frames: Option<FrameDir>,
+    video: Option<VideoWriter>,
+}
+
+impl FrameDump {
+    pub fn new(frame_dir: Option<PathBuf>, video_path: Option<PathBuf>) -> io::Result<Self> {
+        Ok(Self { frames: frame_dir.map(FrameDir::new).transpose()?, video: video_path.map(VideoWriter::new) })
+    }
+
+    /// Adds a frame, given as RGBA rows from top to bottom, which will be shown for 1/room_speed seconds.
+    pub fn push(&mut self, pixels: &[u8], width: u32, height: u32, room_speed: u32) -> Result<(), Box<dyn Error>> {
+        if let Some(frames) = self.frames.as_mut() {
+            frames.push(pixels, width, height, room_speed)?;
+        }
+        if let Some(video) = self.video.as_mut() {
+            video.push(pixels, width, height, room_speed)?;
+        }
+        Ok(())
+    }
+}
+
+impl Game {
+    /// Records the frame which is currently on screen, if frames are being dumped.
+    pub fn dump_frame(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(frame_dump) = self.frame_dump.as_mut() {
+            let (width, height) = (self.unscaled_width, self.unscaled_height);
+            if width == 0 || height == 0 {
+                return Ok(())
+            }
+            let pixels = self.renderer.get_pixels(0, 0, width as _, height as _);
+            frame_dump.push(&pixels, width, height, self.room_speed)?;
+        }
+        Ok(())
+    }
+}
+
+/// A directory of numbered PNGs, with their timing in frames.txt.
+struct FrameDir {
+    dir: PathBuf,
+    timing: BufWriter<File>,
+    frame_count: usize,
+}
+
+impl FrameDir {
+    fn new(dir: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let mut timing = BufWriter::new(File::create(dir.join("frames.txt"))?);
+        writeln!(timing, "# file room_speed")?;
+        Ok(Self { dir, timing, frame_count: 0 })
+    }
+
+    fn push(&mut self, pixels: &[u8], width: u32, height: u32, room_speed: u32) -> Result<(), Box<dyn Error>> {
+        let name = format!("frame_{:06}.png", self.frame_count);
+        // the framebuffer's alpha isn't meaningful, so it's left out
+        let rgb = pixels.chunks_exact(4).flat_map(|p| &p[..3]).copied().collect::<Vec<_>>();
+        RgbImage::from_raw(width, height, rgb)
+            .ok_or("Frame has the wrong number of pixels")?
+            .save(self.dir.join(&name))?;
+        writeln!(self.timing, "{} {}", name, room_speed)?;
+        // keep the timing in step with the images, in case the game crashes
+        self.timing.flush()?;
+        self.frame_count += 1;
+        Ok(())
+    }
+}
+
+/// Uncompressed AVI video, split into a new file whenever the frame rate or size changes.
+struct VideoWriter {
+    path: PathBuf,
+    segment_count: usize,
+    segment: Option<AviFile>,
+}
+
+impl VideoWriter {
+    fn new(path: PathBuf) -> Self {
+        Self { path, segment_count: 0, segment: None }
+    }
+
+    fn segment_path(&self) -> PathBuf {
+        if self.segment_count == 0 {
+            self.path.clone()
+        } else {
+            let stem = self.path.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+            let extension = self.path.extension().map(|s| s.to_string_lossy()).unwrap_or_else(|| "avi".into());
+            self.path.with_file_name(format!("{}_{}.{}", stem, self.segment_count, extension))
+        }
+    }
+
+    fn push(&mut self, pixels: &[u8], width: u32, height: u32, room_speed: u32) -> io::Result<()> {
+        let fits = match &self.segment {
+            Some(avi) => avi.width == width && avi.height == height && avi.rate == room_speed && !avi.is_full(),
+            None => false,
+        };
+        if !fits {
+            if let Some(avi) = self.segment.take() {
+                avi.finish()?;
+            }
+            self.segment = Some(AviFile::create(&self.segment_path(), width, height, room_speed)?);
+            self.segment_count += 1;
+        }
+        self.segment.as_mut().unwrap().push(pixels)
+    }
+}
+
+impl Drop for VideoWriter {
+    fn drop(&mut self) {
+        if let Some(avi) = self.segment.take() {
+            if let Err(e) = avi.finish() {
+                eprintln!("Failed to finish writing video: {}", e);
+            }
+        }
+    }
+}
+
+/// Segments are kept below 1GB, which is as big as an AVI without OpenDML extensions is allowed to be.
+const MAX_AVI_SIZE: u64 = 1 << 30;
+
+// positions of the fields which are filled in once the file's finished
+const RIFF_SIZE_POS: u64 = 4;
+const AVIH_TOTAL_FRAMES_POS: u64 = 48;
+const STRH_LENGTH_POS: u64 = 140;
+const MOVI_SIZE_POS: u64 = 216;
+/// Offsets in the index are counted from the 'movi' tag.
+const MOVI_POS: u64 = 220;
+
+/// A single-stream AVI file with 24-bit uncompressed frames.
+struct AviFile {
+    file: BufWriter<File>,
+    width: u32,
+    height: u32,
+    rate: u32,
+    /// Where each frame's chunk starts, relative to `MOVI_POS`.
+    frame_offsets: Vec<u32>,
+    size: u64,
+}
+
+impl AviFile {
+    fn create(path: &Path, width: u32, height: u32, rate: u32) -> io::Result<Self> {
+        let mut avi =
+            Self { file: BufWriter::new(File::create(path)?), width, height, rate, frame_offsets: Vec::new(), size: 0 };
+        avi.write_header()?;
+        Ok(avi)
+    }
+
+    fn stride(&self) -> u32 {
+        // DIB rows are padded to 4 bytes
+        (self.width * 3 + 3) & !3
+    }
+
+    fn frame_size(&self) -> u32 {
+        self.stride() * self.height
+    }
+
+    fn is_full(&self) -> bool {
+        // leaving room for the frame's chunk header and index entry
+        self.size + u64::from(self.frame_size()) + 24 + 16 * (self.frame_offsets.len() as u64 + 1) > MAX_AVI_SIZE
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let (width, height, rate, frame_size) = (self.width, self.height, self.rate, self.frame_size());
+        let mut header = Vec::with_capacity(MOVI_POS as usize + 4);
+        header.extend_from_slice(b"RIFF\0\0\0\0AVI "); // file size is filled in later
+        header.extend_from_slice(b"LIST");
+        put_u32s(&mut header, &[192]);
+        header.extend_from_slice(b"hdrlavih");
+        put_u32s(&mut header, &[
+            56,
+            1_000_000 / rate,  // microseconds per frame
+            frame_size * rate, // max bytes per second
+            0,                 // padding granularity
+            0x10,              // AVIF_HASINDEX
+            0,                 // total frames, filled in later
+            0,                 // initial frames
+            1,                 // stream count
+            frame_size,        // suggested buffer size
+            width,
+            height,
+        ]);
+        put_u32s(&mut header, &[0; 4]); // reserved
+        header.extend_from_slice(b"LIST");
+        put_u32s(&mut header, &[116]);
+        header.extend_from_slice(b"strlstrh");
+        put_u32s(&mut header, &[56]);
+        header.extend_from_slice(b"vidsDIB ");
+        put_u32s(&mut header, &[
+            0,          // flags
+            0,          // priority and language
+            0,          // initial frames
+            1,          // scale
+            rate,       // rate, so each frame lasts scale/rate seconds
+            0,          // start
+            0,          // length in frames, filled in later
+            frame_size, // suggested buffer size
+            u32::MAX,   // default quality
+            0,          // sample size
+        ]);
+        // the frame rect, as 16-bit left, top, right and bottom
+        put_u32s(&mut header, &[0, (height << 16) | (width & 0xFFFF)]);
+        header.extend_from_slice(b"strf");
+        put_u32s(&mut header, &[40]);
+        // BITMAPINFOHEADER, where a positive height means the rows go from bottom to top
+        put_u32s(&mut header, &[
+            40,
+            width,
+            height,
+            (24 << 16) | 1, // planes and bits per pixel
+            0,              // BI_RGB
+            frame_size,
+        ]);
+        put_u32s(&mut header, &[0; 4]); // resolution and palette
+        header.extend_from_slice(b"LIST\0\0\0\0movi"); // movi size is filled in later
+        debug_assert_eq!(header.len() as u64, MOVI_POS + 4);
+
+        self.file.write_all(&header)?;
+        self.size = header.len() as u64;
+        Ok(())
+    }
+
+    fn push(&mut self, pixels: &[u8]) -> io::Result<()> {
+        let stride = self.stride() as usize;
+        let row_len = self.width as usize * 4;
+        let mut frame = vec![0; self.frame_size() as usize];
+        // RGBA from the top becomes BGR from the bottom
+        for (src, dst) in pixels.chunks_exact(row_len).rev().zip(frame.chunks_exact_mut(stride)) {
+            for (p, q) in src.chunks_exact(4).zip(dst.chunks_exact_mut(3)) {
+                q.copy_from_slice(&[p[2], p[1], p[0]]);
+            }
+        }
+        self.frame_offsets.push((self.size - MOVI_POS) as u32);
+        self.file.write_all(b"00db")?;
+        self.file.write_all(&(frame.len() as u32).to_le_bytes())?;
+        self.file.write_all(&frame)?;
+        self.size += 8 + frame.len() as u64;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        let movi_size = self.size - MOVI_POS;
+        let frame_size = self.frame_size();
+        self.file.write_all(b"idx1")?;
+        self.file.write_all(&(self.frame_offsets.len() as u32 * 16).to_le_bytes())?;
+        for offset in &self.frame_offsets {
+            self.file.write_all(b"00db")?;
+            self.file.write_all(&0x10u32.to_le_bytes())?; // AVIIF_KEYFRAME
+            self.file.write_all(&offset.to_le_bytes())?;
+            self.file.write_all(&frame_size.to_le_bytes())?;
+        }
+        self.size += 8 + self.frame_offsets.len() as u64 * 16;
+
+        let frame_count = self.frame_offsets.len() as u32;
+        for (pos, value) in &[
+            (RIFF_SIZE_POS, (self.size - 8) as u32),
+            (AVIH_TOTAL_FRAMES_POS, frame_count),
+            (STRH_LENGTH_POS, frame_count),
+            (MOVI_SIZE_POS, movi_size as u32),
+        ] {
+            self.file.seek(SeekFrom::Start(*pos))?;
+            self.file.write_all(&value.to_le_bytes())?;
+        }
+        self.file.flush()
+    }
+}
+
+fn put_u32s(out: &mut Vec<u8>, fields: &[u32]) {
+    for field in fields {
+        out.extend_from_slice(&field.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VideoWriter;
+    use std::{env, fs, process};
+
+    #[test]
+    fn video_segments() {
+        let dir = env::temp_dir().join(format!("gm8emulator-framedump-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let red = [255, 0, 0, 255].repeat(3 * 2);
+        let mut video = VideoWriter::new(dir.join("run.avi"));
+        video.push(&red, 3, 2, 30).unwrap();
+        video.push(&red, 3, 2, 30).unwrap();
+        video.push(&red, 3, 2, 50).unwrap();
+        drop(video);
+
+        let u32_at =
+            |data: &[u8], pos: usize| u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]);
+        let first = fs::read(dir.join("run.avi")).unwrap();
+        let second = fs::read(dir.join("run_1.avi")).unwrap();
+        fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(u32_at(&first, 4) as usize, first.len() - 8);
+        assert_eq!(u32_at(&first, 48), 2);
+        assert_eq!(u32_at(&first, 132), 30);
+        assert_eq!(u32_at(&second, 48), 1);
+        assert_eq!(u32_at(&second, 132), 50);
+        // rows are padded to 12 bytes, and the first frame starts right after the movi tag
+        assert_eq!(&first[224..236], b"00db\x18\0\0\0\0\0\xff\0");
+    }
+}
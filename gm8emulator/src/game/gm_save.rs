@@ -1,19 +1,66 @@
 use crate::{
-    game::{string::RCStr, Background, Game, View},
+    asset::room::Room,
+    game::{draw, string::RCStr, Background, Game, GetAsset, PersistentRoom, View},
+    gml::rand::Random,
     instance::DummyFieldHolder,
     instancelist::{InstanceList, TileList},
+    math::Real,
 };
 use serde::{Deserialize, Serialize};
-use shared::types::Colour;
-use std::collections::HashSet;
+use shared::types::{Colour, ID};
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Read, Write},
+};
+
+/// The magic number at the start of save files from before persistent rooms, rooms, captions, drawing settings and
+/// the RNG were saved. GM8 uses 0x21C, but its save files aren't compatible with these.
+const MAGIC_V1: [u8; 4] = [0x1D, 0x02, 0x00, 0x00];
+
+/// The magic number at the start of save files in the current format. If the format changes again, it gets the next
+/// one and this one's layout is kept around for reading, like `GMSaveV1`.
+const MAGIC_V2: [u8; 4] = [0x1E, 0x02, 0x00, 0x00];
 
 /// A save file for use with game_save() and game_load().
 /// The manual explicitly recommends against using save files between sessions, so this may be acceptable.
+/// Like GM8, this doesn't include data structures, particles, surfaces or anything else the manual calls advanced.
+/// It's the emulator's own format, so save files from GM8 can't be loaded, and GM8 can't load these.
 #[derive(Serialize, Deserialize)]
 pub struct GMSave {
     pub game_id: i32,
     room_id: i32,
     transition_kind: i32,
+    transition_steps: i32,
+    score: i32,
+    score_capt: RCStr,
+    score_capt_d: bool,
+    lives: i32,
+    lives_capt: RCStr,
+    lives_capt_d: bool,
+    health: f64,
+    health_capt: RCStr,
+    health_capt_d: bool,
+    cursor_sprite: i32,
+    cursor_sprite_frame: u32,
+    auto_draw: bool,
+    background_colour: Colour,
+    draw: GMDrawSave,
+    rand: Random,
+    globalvars: HashSet<usize>,
+    globals: DummyFieldHolder,
+    rooms: Vec<Option<Box<Room>>>,
+    persistent_rooms: HashMap<ID, PersistentRoom>,
+    room: GMRoomSave,
+    last_instance_id: i32,
+    last_tile_id: i32,
+}
+
+/// A save file from before persistent rooms, rooms, captions, drawing settings and the RNG were saved.
+#[derive(Serialize, Deserialize)]
+struct GMSaveV1 {
+    game_id: i32,
+    room_id: i32,
+    transition_kind: i32,
     score: i32,
     lives: i32,
     health: f64,
@@ -22,7 +69,6 @@ pub struct GMSave {
     auto_draw: bool,
     globalvars: HashSet<usize>,
     globals: DummyFieldHolder,
-    // TODO persistent stuff
     room: GMRoomSave,
     last_instance_id: i32,
     last_tile_id: i32,
@@ -38,7 +84,6 @@ struct GMRoomSave {
     bgcol: Colour,
     show_bgcol: bool,
     show_windowcol: bool,
-    // TODO room creation code
     backgrounds: Vec<Background>,
     views_enabled: bool,
     views: Vec<View>,
@@ -46,26 +91,90 @@ struct GMRoomSave {
     tiles: TileList,
 }
 
+#[derive(Serialize, Deserialize)]
+struct GMDrawSave {
+    font_id: i32,
+    colour: Colour,
+    alpha: Real,
+    halign: draw::Halign,
+    valign: draw::Valign,
+}
+
 impl GMSave {
+    /// Reads a save file in any version of the format.
+    /// Anything older versions didn't save is left as it is in the game, which is how they were loaded at the time.
+    pub fn read(mut reader: impl Read, game: &Game) -> Result<Self, String> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic).map_err(|e| e.to_string())?;
+        match magic {
+            MAGIC_V2 => bincode::deserialize_from(reader).map_err(|e| e.to_string()),
+            MAGIC_V1 => {
+                let old: GMSaveV1 = bincode::deserialize_from(reader).map_err(|e| e.to_string())?;
+                Ok(Self {
+                    game_id: old.game_id,
+                    room_id: old.room_id,
+                    transition_kind: old.transition_kind,
+                    score: old.score,
+                    lives: old.lives,
+                    health: old.health,
+                    cursor_sprite: old.cursor_sprite,
+                    cursor_sprite_frame: old.cursor_sprite_frame,
+                    auto_draw: old.auto_draw,
+                    globalvars: old.globalvars,
+                    globals: old.globals,
+                    room: old.room,
+                    last_instance_id: old.last_instance_id,
+                    last_tile_id: old.last_tile_id,
+                    ..Self::from_game(game)
+                })
+            },
+            _ => Err("tried to load wrong version of save file".into()),
+        }
+    }
+
+    /// Writes the save file in the current version of the format.
+    pub fn write(&self, mut writer: impl Write) -> Result<(), String> {
+        writer.write_all(&MAGIC_V2).map_err(|e| e.to_string())?;
+        bincode::serialize_into(writer, self).map_err(|e| e.to_string())
+    }
+
     pub fn from_game(game: &Game) -> Self {
         Self {
             game_id: game.game_id,
             room_id: game.room_id,
             transition_kind: game.transition_kind,
+            transition_steps: game.transition_steps,
             score: game.score,
+            score_capt: game.score_capt.clone(),
+            score_capt_d: game.score_capt_d,
             lives: game.lives,
+            lives_capt: game.lives_capt.clone(),
+            lives_capt_d: game.lives_capt_d,
             health: game.health.into(),
+            health_capt: game.health_capt.clone(),
+            health_capt_d: game.health_capt_d,
             cursor_sprite: game.cursor_sprite,
             cursor_sprite_frame: game.cursor_sprite_frame,
             auto_draw: game.auto_draw,
+            background_colour: game.background_colour,
+            draw: GMDrawSave {
+                font_id: game.draw_font_id,
+                colour: game.draw_colour,
+                alpha: game.draw_alpha,
+                halign: game.draw_halign,
+                valign: game.draw_valign,
+            },
+            rand: game.rand.clone(),
             globalvars: game.globalvars.clone(),
             globals: game.globals.clone(),
+            rooms: game.assets.rooms.clone(),
+            persistent_rooms: game.persistent_rooms.clone(),
             room: GMRoomSave {
                 caption: game.caption.clone(),
                 width: game.room_width,
                 height: game.room_height,
                 room_speed: game.room_speed,
-                persistent: false, // TODO
+                persistent: game.assets.rooms.get_asset(game.room_id).map(|r| r.persistent).unwrap_or_default(),
                 bgcol: game.room_colour,
                 show_bgcol: game.show_room_colour,
                 show_windowcol: true, // TODO
@@ -87,15 +196,35 @@ impl GMSave {
 
         game.room_id = self.room_id;
         game.transition_kind = self.transition_kind;
+        game.transition_steps = self.transition_steps;
         game.score = self.score;
+        game.score_capt = self.score_capt;
+        game.score_capt_d = self.score_capt_d;
         game.lives = self.lives;
+        game.lives_capt = self.lives_capt;
+        game.lives_capt_d = self.lives_capt_d;
         game.health = self.health.into();
+        game.health_capt = self.health_capt;
+        game.health_capt_d = self.health_capt_d;
         game.cursor_sprite = self.cursor_sprite;
         game.cursor_sprite_frame = self.cursor_sprite_frame;
         game.auto_draw = self.auto_draw;
+        game.background_colour = self.background_colour;
+        game.draw_font_id = self.draw.font_id;
+        game.draw_colour = self.draw.colour;
+        game.draw_alpha = self.draw.alpha;
+        game.draw_halign = self.draw.halign;
+        game.draw_valign = self.draw.valign;
+        game.rand = self.rand;
         game.globalvars = self.globalvars;
         game.globals = self.globals;
+        game.assets.rooms = self.rooms;
+        game.persistent_rooms = self.persistent_rooms;
+        if let Some(room) = game.assets.rooms.get_asset_mut(self.room_id) {
+            room.persistent = self.room.persistent;
+        }
         game.caption = self.room.caption;
+        game.caption_stale = true;
         game.room_width = self.room.width;
         game.room_height = self.room.height;
         game.room_speed = self.room.room_speed;
@@ -145,3 +274,92 @@ impl GMSave {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The layout save files had before they were versioned, as game_save() wrote it with the magic number 0x21D.
+    #[derive(Serialize)]
+    struct BaselineSave {
+        game_id: i32,
+        room_id: i32,
+        transition_kind: i32,
+        score: i32,
+        lives: i32,
+        health: f64,
+        cursor_sprite: i32,
+        cursor_sprite_frame: u32,
+        auto_draw: bool,
+        globalvars: HashSet<usize>,
+        globals: DummyFieldHolder,
+        room: GMRoomSave,
+        last_instance_id: i32,
+        last_tile_id: i32,
+    }
+
+    #[test]
+    fn read_baseline() {
+        let mut game = Game::for_tests();
+        game.score_capt = "Points: ".to_string().into();
+        game.globalvars.insert(3);
+
+        let room = GMSave::from_game(&game).room;
+        let old = BaselineSave {
+            game_id: game.game_id,
+            room_id: 4,
+            transition_kind: 2,
+            score: 1200,
+            lives: 3,
+            health: 50.0,
+            cursor_sprite: -1,
+            cursor_sprite_frame: 0,
+            auto_draw: false,
+            globalvars: HashSet::new(),
+            globals: DummyFieldHolder::new(),
+            room: GMRoomSave { caption: "Level 4".to_string().into(), width: 320, room_speed: 60, ..room },
+            last_instance_id: 100123,
+            last_tile_id: 10000045,
+        };
+        let mut file = MAGIC_V1.to_vec();
+        bincode::serialize_into(&mut file, &old).unwrap();
+
+        let save = GMSave::read(file.as_slice(), &game).unwrap();
+        assert_eq!((save.room_id, save.transition_kind, save.score, save.lives), (4, 2, 1200, 3));
+        assert_eq!(save.health, 50.0);
+        assert!(!save.auto_draw);
+        assert!(save.globalvars.is_empty());
+        assert_eq!(save.room.caption.as_ref(), b"Level 4");
+        assert_eq!((save.room.width, save.room.room_speed), (320, 60));
+        assert_eq!((save.last_instance_id, save.last_tile_id), (100123, 10000045));
+        // what it didn't save comes from the game
+        assert_eq!(save.score_capt.as_ref(), b"Points: ");
+
+        save.into_game(&mut game).unwrap();
+        assert_eq!((game.room_id, game.score, game.last_instance_id), (4, 1200, 100123));
+        assert_eq!(game.caption.as_ref(), b"Level 4");
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut game = Game::for_tests();
+        game.score = 77;
+        game.lives_capt = "Tries: ".to_string().into();
+        game.draw_alpha = Real::from(0.5);
+        let mut file = Vec::new();
+        GMSave::from_game(&game).write(&mut file).unwrap();
+        assert_eq!(file[..4], MAGIC_V2);
+
+        let mut other = Game::for_tests();
+        GMSave::read(file.as_slice(), &other).unwrap().into_game(&mut other).unwrap();
+        assert_eq!(other.score, 77);
+        assert_eq!(other.lives_capt.as_ref(), b"Tries: ");
+        assert_eq!(other.draw_alpha, Real::from(0.5));
+    }
+
+    #[test]
+    fn read_unknown_version() {
+        let game = Game::for_tests();
+        assert!(GMSave::read([0x1C, 0x02, 0x00, 0x00, 0x00].as_ref(), &game).is_err());
+    }
+}
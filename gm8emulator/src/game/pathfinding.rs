@@ -1,6 +1,18 @@
-use crate::{instance::Instance, math::Real};
+use crate::{asset::path::Point, instance::Instance, math::Real};
 use serde::{Deserialize, Serialize};
 
+pub enum Error {
+    NonexistentGrid(i32),
+}
+
+impl From<Error> for String {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::NonexistentGrid(id) => format!("motion planning grid with index {} does not exist", id),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct PotentialStepSettings {
     pub max_rotation: Real,
@@ -93,3 +105,268 @@ pub fn potential_step(
         res
     }
 }
+
+/// Takes a step straight towards a position without making any detours, returning whether it's been reached.
+pub fn linear_step(x: Real, y: Real, step_size: Real, instance: &Instance, coll: impl Fn() -> bool) -> bool {
+    let old_x = instance.x.get();
+    let old_y = instance.y.get();
+    if old_x == x && old_y == y {
+        return true
+    }
+    let distance = Real::from((x - old_x).into_inner().hypot((y - old_y).into()));
+    let (new_x, new_y) = if distance <= step_size {
+        (x, y)
+    } else {
+        (old_x + (x - old_x) * step_size / distance, old_y + (y - old_y) * step_size / distance)
+    };
+    instance.x.set(new_x);
+    instance.y.set(new_y);
+    instance.bbox_is_stale.set(true);
+    if coll() {
+        instance.x.set(old_x);
+        instance.y.set(old_y);
+        instance.bbox_is_stale.set(true);
+        return false
+    }
+    instance.direction.set((old_y - y).arctan2(x - old_x).to_degrees().rem_euclid(360.into()));
+    new_x == x && new_y == y
+}
+
+/// Takes steps towards a position until it's reached, collecting every position the instance moves to along the
+/// way. It gives up once the path would be longer than `factor` times the straight distance. The instance is put
+/// back where it was afterwards.
+pub fn step_path(
+    x: Real,
+    y: Real,
+    step_size: Real,
+    factor: Real,
+    instance: &Instance,
+    mut step: impl FnMut() -> bool,
+) -> (bool, Vec<Point>) {
+    let (old_x, old_y, old_direction) = (instance.x.get(), instance.y.get(), instance.direction.get());
+    let distance = (old_x - x).into_inner().hypot((old_y - y).into());
+    let max_steps = if step_size > 0.into() {
+        (distance * factor.into_inner() / step_size.into_inner()).ceil() as usize
+    } else {
+        0
+    };
+    let mut points = vec![Point { x: old_x, y: old_y, speed: 100.into() }];
+    let mut reached = old_x == x && old_y == y;
+    for _ in 0..max_steps {
+        if reached {
+            break
+        }
+        reached = step();
+        let (x, y) = (instance.x.get(), instance.y.get());
+        if points.last().map(|p| (p.x, p.y)) != Some((x, y)) {
+            points.push(Point { x, y, speed: 100.into() });
+        }
+    }
+    instance.x.set(old_x);
+    instance.y.set(old_y);
+    instance.direction.set(old_direction);
+    instance.bbox_is_stale.set(true);
+    (reached, points)
+}
+
+/// A grid for mp_grid_path, where each cell is either free or forbidden.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MpGrid {
+    pub left: i32,
+    pub top: i32,
+    pub hcells: i32,
+    pub vcells: i32,
+    pub cell_width: i32,
+    pub cell_height: i32,
+    forbidden: Vec<bool>,
+}
+
+impl MpGrid {
+    pub fn new(left: i32, top: i32, hcells: i32, vcells: i32, cell_width: i32, cell_height: i32) -> Self {
+        let (hcells, vcells) = (hcells.max(0), vcells.max(0));
+        Self {
+            left,
+            top,
+            hcells,
+            vcells,
+            cell_width: cell_width.max(1),
+            cell_height: cell_height.max(1),
+            forbidden: vec![false; (hcells * vcells) as usize],
+        }
+    }
+
+    fn index(&self, h: i32, v: i32) -> Option<usize> {
+        if h >= 0 && h < self.hcells && v >= 0 && v < self.vcells { Some((v * self.hcells + h) as usize) } else { None }
+    }
+
+    pub fn is_forbidden(&self, h: i32, v: i32) -> bool {
+        self.index(h, v).map(|i| self.forbidden[i]).unwrap_or(true)
+    }
+
+    pub fn set_cell(&mut self, h: i32, v: i32, forbidden: bool) {
+        if let Some(i) = self.index(h, v) {
+            self.forbidden[i] = forbidden;
+        }
+    }
+
+    pub fn set_all(&mut self, forbidden: bool) {
+        self.forbidden.iter_mut().for_each(|c| *c = forbidden);
+    }
+
+    /// The cell a position is in, which may be outside the grid.
+    pub fn cell_at(&self, x: Real, y: Real) -> (i32, i32) {
+        (
+            ((x - self.left.into()) / self.cell_width.into()).floor().round(),
+            ((y - self.top.into()) / self.cell_height.into()).floor().round(),
+        )
+    }
+
+    /// The rectangle a cell covers in the room, with inclusive bounds like bounding boxes have.
+    pub fn cell_rect(&self, h: i32, v: i32) -> (i32, i32, i32, i32) {
+        let (x, y) = (self.left + h * self.cell_width, self.top + v * self.cell_height);
+        (x, y, x + self.cell_width - 1, y + self.cell_height - 1)
+    }
+
+    /// All the cells which overlap a rectangle in the room, given with inclusive bounds.
+    pub fn cells_in_rect(&self, x1: i32, y1: i32, x2: i32, y2: i32) -> impl Iterator<Item = (i32, i32)> {
+        let (h1, v1) = self.cell_at(x1.min(x2).into(), y1.min(y2).into());
+        let (h2, v2) = self.cell_at(x1.max(x2).into(), y1.max(y2).into());
+        let (h1, h2) = (h1.max(0), h2.min(self.hcells - 1));
+        let (v1, v2) = (v1.max(0), v2.min(self.vcells - 1));
+        (v1..=v2).flat_map(move |v| (h1..=h2).map(move |h| (h, v)))
+    }
+
+    pub fn set_rect(&mut self, x1: i32, y1: i32, x2: i32, y2: i32, forbidden: bool) {
+        for (h, v) in self.cells_in_rect(x1, y1, x2, y2).collect::<Vec<_>>() {
+            self.set_cell(h, v, forbidden);
+        }
+    }
+
+    /// Whether a move to a neighbouring cell is allowed. Diagonal moves can't cut the corner of a forbidden cell.
+    fn can_move(&self, h: i32, v: i32, dh: i32, dv: i32) -> bool {
+        !self.is_forbidden(h + dh, v + dv)
+            && (dh == 0 || dv == 0 || (!self.is_forbidden(h + dh, v) && !self.is_forbidden(h, v + dv)))
+    }
+
+    /// Finds the shortest route between two positions, going through the centres of the cells in between.
+    /// Points in the middle of straight lines are left out, so there's only one wherever the path turns.
+    /// Returns None if either end is in a forbidden cell or outside the grid, or if there's no way through.
+    pub fn find_path(&self, start: (Real, Real), goal: (Real, Real), allow_diag: bool) -> Option<Vec<Point>> {
+        const ORTHOGONAL: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+        const DIAGONAL: [(i32, i32); 4] = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+        let (sh, sv) = self.cell_at(start.0, start.1);
+        let (gh, gv) = self.cell_at(goal.0, goal.1);
+        if self.is_forbidden(sh, sv) || self.is_forbidden(gh, gv) {
+            return None
+        }
+
+        // flood out from the start, counting steps, until the goal is reached
+        let mut steps = vec![0u32; self.forbidden.len()];
+        let mut queue = std::collections::VecDeque::new();
+        steps[self.index(sh, sv)?] = 1;
+        queue.push_back((sh, sv));
+        let directions = if allow_diag { &[ORTHOGONAL, DIAGONAL][..] } else { &[ORTHOGONAL][..] };
+        while let Some((h, v)) = queue.pop_front() {
+            if (h, v) == (gh, gv) {
+                break
+            }
+            let step = steps[self.index(h, v)?];
+            for &(dh, dv) in directions.iter().flatten() {
+                if self.can_move(h, v, dh, dv) {
+                    let next = self.index(h + dh, v + dv)?;
+                    if steps[next] == 0 {
+                        steps[next] = step + 1;
+                        queue.push_back((h + dh, v + dv));
+                    }
+                }
+            }
+        }
+        if steps[self.index(gh, gv)?] == 0 {
+            return None
+        }
+
+        // then walk back from the goal, going straight where possible so the path doesn't zigzag
+        let mut points = vec![Point { x: goal.0, y: goal.1, speed: 100.into() }];
+        let (mut h, mut v) = (gh, gv);
+        let mut step = steps[self.index(h, v)?];
+        while step > 2 {
+            let (dh, dv) = directions.iter().flatten().copied().find(|&(dh, dv)| {
+                self.can_move(h, v, dh, dv) && self.index(h + dh, v + dv).map(|i| steps[i]) == Some(step - 1)
+            })?;
+            h += dh;
+            v += dv;
+            step -= 1;
+            let (x, y, _, _) = self.cell_rect(h, v);
+            points.push(Point {
+                x: Real::from(x) + Real::from(self.cell_width) / 2.into(),
+                y: Real::from(y) + Real::from(self.cell_height) / 2.into(),
+                speed: 100.into(),
+            });
+        }
+        points.push(Point { x: start.0, y: start.1, speed: 100.into() });
+        points.reverse();
+
+        // finally, smooth it out by leaving out points in the middle of straight lines
+        let mut smoothed: Vec<Point> = Vec::with_capacity(points.len());
+        for point in points {
+            if let [.., a, b] = smoothed[..] {
+                let (dx1, dy1, dx2, dy2) = (b.x - a.x, b.y - a.y, point.x - b.x, point.y - b.y);
+                if dx1 * dy2 == dy1 * dx2 && dx1 * dx2 + dy1 * dy2 > 0.into() {
+                    smoothed.pop();
+                }
+            }
+            smoothed.push(point);
+        }
+        Some(smoothed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MpGrid;
+    use crate::math::Real;
+
+    fn cells(grid: &MpGrid, start: (i32, i32), goal: (i32, i32), allow_diag: bool) -> Option<Vec<(i32, i32)>> {
+        let centre = |(h, v): (i32, i32)| (Real::from(h * 10 + 5), Real::from(v * 10 + 5));
+        let points = grid.find_path(centre(start), centre(goal), allow_diag)?;
+        Some(points.iter().map(|p| grid.cell_at(p.x, p.y)).collect())
+    }
+
+    #[test]
+    fn grid_rectangles() {
+        let mut grid = MpGrid::new(0, 0, 4, 4, 10, 10);
+        grid.set_rect(15, 5, 25, 12, true);
+        let forbidden = (0..4).flat_map(|v| (0..4).map(move |h| (h, v))).filter(|&(h, v)| grid.is_forbidden(h, v));
+        assert_eq!(forbidden.collect::<Vec<_>>(), [(1, 0), (2, 0), (1, 1), (2, 1)]);
+        assert!(grid.is_forbidden(-1, 0));
+        grid.set_all(false);
+        assert!(!grid.is_forbidden(1, 0));
+    }
+
+    #[test]
+    fn grid_paths() {
+        // a wall down the middle with a gap at the bottom
+        let mut grid = MpGrid::new(0, 0, 5, 5, 10, 10);
+        for v in 0..4 {
+            grid.set_cell(2, v, true);
+        }
+        assert_eq!(cells(&grid, (0, 0), (4, 0), false).unwrap(), [(0, 0), (0, 4), (3, 4), (3, 0), (4, 0)]);
+        // diagonals can't cut the wall's corner
+        assert_eq!(cells(&grid, (0, 0), (4, 0), true).unwrap(), [(0, 0), (1, 1), (1, 4), (3, 4), (4, 3), (4, 0)]);
+        grid.set_cell(2, 4, true);
+        assert!(cells(&grid, (0, 0), (4, 0), true).is_none());
+        assert!(cells(&grid, (0, 0), (2, 0), true).is_none());
+        assert_eq!(cells(&grid, (0, 0), (0, 0), true).unwrap(), [(0, 0), (0, 0)]);
+    }
+
+    #[test]
+    fn neighbour_order() {
+        // walking back from the goal, steps are tried left, right, up, then down
+        let grid = MpGrid::new(0, 0, 3, 3, 10, 10);
+        assert_eq!(cells(&grid, (0, 0), (2, 2), false).unwrap(), [(0, 0), (0, 2), (2, 2)]);
+        assert_eq!(cells(&grid, (2, 2), (0, 0), false).unwrap(), [(2, 2), (2, 0), (0, 0)]);
+        assert_eq!(cells(&grid, (0, 2), (2, 0), false).unwrap(), [(0, 2), (0, 0), (2, 0)]);
+        // and orthogonal steps are tried before diagonal ones
+        assert_eq!(cells(&grid, (0, 0), (2, 1), true).unwrap(), [(0, 0), (1, 1), (2, 1)]);
+    }
+}
@@ -0,0 +1,136 @@
+//! A stand-in for the parts of the Windows registry GM8 games can touch with the registry_* functions.
+//!
+//! In normal play it's kept in a file next to the game and saved whenever something's written to it.
+//! When recording or replaying a TAS it starts out from the project's registry file, or empty if there isn't one,
+//! and is never saved to disk, so runs don't depend on what's on the machine. Its contents go in savestates instead.
+
+use crate::{game::string::RCStr, math::Real};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+/// The file in a TAS project's directory which recordings and replays start the registry from.
+/// It has the same format as the file kept next to the game, so one of those can be copied in.
+pub const PROJECT_FILE: &str = "project.registry";
+
+/// HKEY_CURRENT_USER, which is the default root and the one the non-_ext functions always use.
+pub const CURRENT_USER: usize = 0;
+
+/// The number of root keys registry_set_root can pick between:
+/// HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, HKEY_CLASSES_ROOT and HKEY_USERS.
+pub const ROOT_COUNT: usize = 4;
+
+/// The key the non-_ext registry functions use for a game's values.
+pub fn game_key(game_id: i32) -> String {
+    format!("Software\\Game Maker\\GMGames\\{}", game_id)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Entry {
+    String(RCStr),
+    Real(Real),
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Registry {
+    pub root: usize, // the root the _ext functions use
+    // Windows treats keys and value names case-insensitively, so they're stored lowercased
+    keys: HashMap<(usize, Vec<u8>), HashMap<Vec<u8>, Entry>>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl Registry {
+    /// Creates an empty registry which is never saved.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Loads the registry saved at the given path, or creates an empty one if there isn't one yet.
+    /// Either way, it'll be saved back to that path whenever it's written to.
+    pub fn load(path: PathBuf) -> Self {
+        Self { root: CURRENT_USER, keys: Self::read(&path), path: Some(path) }
+    }
+
+    /// Loads the registry saved at the given path, or creates an empty one if there isn't one.
+    /// Unlike with `load`, it's never saved.
+    pub fn load_read_only(path: &Path) -> Self {
+        Self { root: CURRENT_USER, keys: Self::read(path), path: None }
+    }
+
+    fn read(path: &Path) -> HashMap<(usize, Vec<u8>), HashMap<Vec<u8>, Entry>> {
+        if !path.exists() {
+            return HashMap::new()
+        }
+        match File::open(path).map_err(Into::into).and_then(|f| bincode::deserialize_from(BufReader::new(f))) {
+            Ok(keys) => keys,
+            Err(e) => {
+                eprintln!("Warning: couldn't read {} ({}), so the registry starts out empty", path.display(), e);
+                HashMap::new()
+            },
+        }
+    }
+
+    fn normalise(s: &[u8]) -> Vec<u8> {
+        let mut s = s.to_ascii_lowercase();
+        s.iter_mut().filter(|c| **c == b'/').for_each(|c| *c = b'\\');
+        let start = s.iter().position(|&c| c != b'\\').unwrap_or(s.len());
+        let end = s.iter().rposition(|&c| c != b'\\').map(|i| i + 1).unwrap_or(start);
+        s[start..end].to_vec()
+    }
+
+    /// Gets the value with the given name in the given key.
+    pub fn get(&self, root: usize, key: &[u8], name: &[u8]) -> Option<&Entry> {
+        self.keys.get(&(root, Self::normalise(key)))?.get(&name.to_ascii_lowercase())
+    }
+
+    /// Sets the value with the given name in the given key, creating the key if needed.
+    pub fn set(&mut self, root: usize, key: &[u8], name: &[u8], entry: Entry) -> bincode::Result<()> {
+        self.keys.entry((root, Self::normalise(key))).or_default().insert(name.to_ascii_lowercase(), entry);
+        self.save()
+    }
+
+    fn save(&self) -> bincode::Result<()> {
+        match &self.path {
+            Some(path) => bincode::serialize_into(BufWriter::new(File::create(path)?), &self.keys),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_case_insensitive() {
+        let mut registry = Registry::new();
+        registry.set(CURRENT_USER, b"Software\\Test\\", b"Score", Entry::Real(Real::from(10.0))).unwrap();
+        assert_eq!(registry.get(CURRENT_USER, b"software/test", b"SCORE"), Some(&Entry::Real(Real::from(10.0))));
+        assert_eq!(registry.get(CURRENT_USER, b"software", b"score"), None);
+    }
+
+    #[test]
+    fn corrupt_file() {
+        let path = std::env::temp_dir().join("gm8emulator-corrupt.registry");
+        std::fs::write(&path, b"\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x01").unwrap();
+        let mut registry = Registry::load_read_only(&path);
+        assert_eq!(registry.keys.len(), 0);
+        // read-only registries never write back over the file
+        registry.set(CURRENT_USER, b"key", b"name", Entry::Real(Real::from(1.0))).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x01");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn roots_are_separate() {
+        let mut registry = Registry::new();
+        registry.set(CURRENT_USER, b"key", b"name", Entry::String("hello".into())).unwrap();
+        assert_eq!(registry.get(1, b"key", b"name"), None);
+        assert_eq!(registry.get(CURRENT_USER, b"key", b"name"), Some(&Entry::String("hello".into())));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::gml::Value;
+use crate::{game::registry::Registry, gml::Value};
 use serde::{Deserialize, Serialize};
 use shared::input::{Key, MouseButton};
 
@@ -12,6 +12,9 @@ pub struct Replay {
     // RNG seed to use at the beginning of this replay.
     pub start_seed: i32,
 
+    // Registry to start the game with, so replaying doesn't depend on any registry file.
+    pub start_registry: Registry,
+
     // List of frames in this replay.
     frames: Vec<Frame>,
 }
@@ -37,6 +40,9 @@ pub enum Event {
     ShowMenu(Value),     // value returned from show_menu()
     ShowMessage,         // acknowledges that a show_message() does not need to be shown during replay
     ShowQuestion(Value), // value returned from show_question()
+    Network(Value),      // value returned from a 39dll function which used the network
+    // result, contents and sender of a message received by 39dll
+    NetworkReceive(i32, Vec<u8>, Option<(String, u16)>),
 }
 
 // An input event which takes place during a frame
@@ -51,8 +57,8 @@ pub enum Input {
 }
 
 impl Replay {
-    pub fn new(start_time: u128, start_seed: i32) -> Self {
-        Self { start_time, start_seed, frames: Vec::new() }
+    pub fn new(start_time: u128, start_seed: i32, start_registry: Registry) -> Self {
+        Self { start_time, start_seed, start_registry, frames: Vec::new() }
     }
 
     // Adds a new frame of input to the end of the replay.
@@ -110,3 +116,22 @@ impl Replay {
         ms
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::registry::{Entry, CURRENT_USER};
+
+    #[test]
+    fn keeps_start_registry() {
+        let mut registry = Registry::new();
+        registry.set(CURRENT_USER, b"Software\\Game", b"Best", Entry::Real(12.0.into())).unwrap();
+        let replay = Replay::new(0, 0, registry);
+
+        let replay: Replay = bincode::deserialize(&bincode::serialize(&replay).unwrap()).unwrap();
+        assert_eq!(
+            replay.start_registry.get(CURRENT_USER, b"Software\\Game", b"Best"),
+            Some(&Entry::Real(12.0.into()))
+        );
+    }
+}
@@ -1,16 +1,18 @@
 use crate::{
+    asset::Sound,
     game::{
-        background, draw,
-        external::{DefineInfo, External},
+        audio, background, draw,
+        external::{dll39, sound::SoundDlls, DefineInfo, External},
         includedfile::IncludedFile,
         model::Model,
         particle,
-        pathfinding::PotentialStepSettings,
+        pathfinding::{MpGrid, PotentialStepSettings},
+        registry::Registry,
         string::RCStr,
         surface::Surface,
         transition::UserTransition,
         view::View,
-        Assets, Game, Replay, Version,
+        Assets, Game, PersistentRoom, PlayType, Replay, Version,
     },
     gml::{ds, rand::Random, Compiler},
     handleman::HandleList,
@@ -62,6 +64,10 @@ pub struct SaveState {
     pub backgrounds: Vec<background::Background>,
 
     pub particles: particle::Manager,
+    pub audio: audio::Mixer,
+    pub sound_dlls: SoundDlls,
+    pub dll39: dll39::State,
+    pub registry: Registry,
 
     pub room_id: i32,
     pub room_width: i32,
@@ -109,6 +115,8 @@ pub struct SaveState {
     pub uninit_args_are_zero: bool,
 
     pub potential_step_settings: PotentialStepSettings,
+    pub mp_grids: HandleList<MpGrid>,
+    pub persistent_rooms: HashMap<ID, PersistentRoom>,
 
     pub transition_kind: i32,
     pub transition_steps: i32,
@@ -177,6 +185,10 @@ impl SaveState {
             views: game.views.clone(),
             backgrounds: game.backgrounds.clone(),
             particles: game.particles.clone(),
+            audio: game.audio.clone(),
+            sound_dlls: game.sound_dlls.clone(),
+            dll39: game.dll39.clone(),
+            registry: game.registry.clone(),
             room_id: game.room_id.clone(),
             room_width: game.room_width.clone(),
             room_height: game.room_height.clone(),
@@ -218,6 +230,8 @@ impl SaveState {
             uninit_fields_are_zero: game.uninit_fields_are_zero.clone(),
             uninit_args_are_zero: game.uninit_args_are_zero.clone(),
             potential_step_settings: game.potential_step_settings.clone(),
+            mp_grids: game.mp_grids.clone(),
+            persistent_rooms: game.persistent_rooms.clone(),
             transition_kind: game.transition_kind.clone(),
             transition_steps: game.transition_steps.clone(),
             cursor_sprite: game.cursor_sprite.clone(),
@@ -251,9 +265,16 @@ impl SaveState {
         }
     }
 
-    pub fn load_into(self, game: &mut Game) -> Replay {
+    pub fn load_into(mut self, game: &mut Game) -> Replay {
         game.window.resize(self.window_width, self.window_height);
 
+        restore_sound_data(&mut self.assets.sounds, &game.assets.sounds);
+        restore_sound_data(&mut self.sound_dlls.files, &game.sound_dlls.files);
+        for sound in self.sound_dlls.files.iter_mut().flatten().filter(|s| s.data.is_none()) {
+            // the game has unloaded it since the savestate was made, so it's read from its file again
+            sound.data = std::fs::read(game.decode_str(sound.source.as_ref()).as_ref()).ok().map(Rc::from);
+        }
+
         game.renderer.upload_dynamic_textures(&self.textures);
 
         game.renderer.draw_raw_frame(
@@ -280,15 +301,19 @@ impl SaveState {
         game.renderer.set_vsync(self.vsync);
 
         let mut externals = self.externals;
-        // we're always gonna be recording if we're loading savestates so disable sound
         game.externals = externals
             .drain(..)
             .map(|i| {
                 i.map(|i| {
-                    External::new(i, true, match game.gm_version {
-                        Version::GameMaker8_0 => game.encoding,
-                        Version::GameMaker8_1 => encoding_rs::UTF_8,
-                    })
+                    External::new(
+                        i,
+                        &game.external_stubs,
+                        match game.gm_version {
+                            Version::GameMaker8_0 => game.encoding,
+                            Version::GameMaker8_1 => encoding_rs::UTF_8,
+                        },
+                        game.play_type == PlayType::Normal,
+                    )
                     .unwrap()
                 })
             })
@@ -312,6 +337,10 @@ impl SaveState {
         game.views = self.views;
         game.backgrounds = self.backgrounds;
         game.particles = self.particles;
+        game.audio = self.audio;
+        game.sound_dlls = self.sound_dlls;
+        game.dll39 = self.dll39;
+        game.registry = self.registry;
         game.room_id = self.room_id;
         game.room_width = self.room_width;
         game.room_height = self.room_height;
@@ -352,6 +381,8 @@ impl SaveState {
         game.uninit_fields_are_zero = self.uninit_fields_are_zero;
         game.uninit_args_are_zero = self.uninit_args_are_zero;
         game.potential_step_settings = self.potential_step_settings;
+        game.mp_grids = self.mp_grids;
+        game.persistent_rooms = self.persistent_rooms;
         game.transition_kind = self.transition_kind;
         game.transition_steps = self.transition_steps;
         game.cursor_sprite = self.cursor_sprite;
@@ -384,3 +415,16 @@ impl SaveState {
         self.replay
     }
 }
+
+/// Sound data isn't stored in savestates, so this takes it from the sounds the game has loaded right now.
+fn restore_sound_data(sounds: &mut [Option<Box<Sound>>], current: &[Option<Box<Sound>>]) {
+    for (i, sound) in sounds.iter_mut().enumerate() {
+        if let Some(sound) = sound {
+            let same_source = |s: &Sound| s.source == sound.source;
+            sound.data = match current.get(i).and_then(|s| s.as_deref()).filter(|s| same_source(s)) {
+                Some(s) => s.data.clone(),
+                None => current.iter().flatten().find(|s| same_source(s)).and_then(|s| s.data.clone()),
+            };
+        }
+    }
+}
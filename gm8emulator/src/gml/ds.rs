@@ -26,6 +26,15 @@ pub struct Grid {
     height: usize, // if width is 0, this is inaccessible otherwise
 }
 
+/// A part of a grid, for the functions which work on more than one cell.
+#[derive(Clone, Copy)]
+pub enum Area {
+    /// A rectangle from (x1, y1) to (x2, y2), which may be given either way round.
+    Region(i32, i32, i32, i32),
+    /// The cells whose positions are within the given distance of a centre point.
+    Disk(Real, Real, Real),
+}
+
 #[derive(Debug)]
 pub enum Error {
     NonexistentStructure(i32),
@@ -138,6 +147,121 @@ impl Grid {
     pub fn height(&self) -> usize {
         self.height
     }
+
+    /// Returns the cells in an area, clipped to the grid, going down each column in turn from the left.
+    fn cells(&self, area: Area) -> Vec<(usize, usize)> {
+        match area {
+            Area::Region(x1, y1, x2, y2) => {
+                let (x1, x2) = (x1.min(x2).max(0), x1.max(x2).min(self.width() as i32 - 1));
+                let (y1, y2) = (y1.min(y2).max(0), y1.max(y2).min(self.height as i32 - 1));
+                (x1..=x2).flat_map(|x| (y1..=y2).map(move |y| (x as usize, y as usize))).collect()
+            },
+            Area::Disk(xm, ym, r) => {
+                let (x1, x2) = ((xm - r).floor().round().max(0), (xm + r).ceil().round().min(self.width() as i32 - 1));
+                let (y1, y2) = ((ym - r).floor().round().max(0), (ym + r).ceil().round().min(self.height as i32 - 1));
+                (x1..=x2)
+                    .flat_map(|x| (y1..=y2).map(move |y| (x, y)))
+                    .filter(|&(x, y)| {
+                        let (dx, dy) = (Real::from(x) - xm, Real::from(y) - ym);
+                        dx * dx + dy * dy <= r * r
+                    })
+                    .map(|(x, y)| (x as usize, y as usize))
+                    .collect()
+            },
+        }
+    }
+
+    /// Applies an operation with the given value, such as `Grid::add`, to every cell in an area.
+    pub fn apply(&mut self, area: Area, val: &Value, op: impl Fn(&mut Self, usize, usize, &Value)) {
+        for (x, y) in self.cells(area) {
+            op(self, x, y, val);
+        }
+    }
+
+    /// Copies the values in a region, along with their positions relative to its top left, for `Grid::paste`.
+    pub fn copy_region(&self, x1: i32, y1: i32, x2: i32, y2: i32) -> Vec<(i32, i32, Value)> {
+        let (left, top) = (x1.min(x2), y1.min(y2));
+        self.cells(Area::Region(x1, y1, x2, y2))
+            .into_iter()
+            .map(|(x, y)| (x as i32 - left, y as i32 - top, self.grid[x][y].clone()))
+            .collect()
+    }
+
+    /// Applies an operation with each copied value to the cell at its position plus (xpos, ypos).
+    /// Values which end up outside the grid are skipped.
+    pub fn paste(
+        &mut self,
+        values: Vec<(i32, i32, Value)>,
+        xpos: i32,
+        ypos: i32,
+        op: impl Fn(&mut Self, usize, usize, &Value),
+    ) {
+        for (x, y, val) in values {
+            let (x, y) = (x + xpos, y + ypos);
+            if x >= 0 && y >= 0 && (x as usize) < self.width() && (y as usize) < self.height {
+                op(self, x as usize, y as usize, &val);
+            }
+        }
+    }
+
+    /// Sets a cell to a copy of a value, like `Grid::set` but in the same form as `Grid::add`.
+    pub fn assign(&mut self, x: usize, y: usize, val: &Value) {
+        self.grid[x][y] = val.clone();
+    }
+
+    /// Adds a value to a cell. Reals are added and strings are concatenated, and nothing happens if the types differ.
+    pub fn add(&mut self, x: usize, y: usize, val: &Value) {
+        match (&mut self.grid[x][y], val) {
+            (Value::Real(cell), Value::Real(val)) => *cell += *val,
+            (cell @ Value::Str(_), Value::Str(_)) => {
+                let _ = cell.add_assign(val.clone());
+            },
+            _ => (),
+        }
+    }
+
+    /// Multiplies a cell by a value. Only reals can be multiplied, so nothing happens to strings.
+    pub fn multiply(&mut self, x: usize, y: usize, val: &Value) {
+        if let (Value::Real(cell), Value::Real(val)) = (&mut self.grid[x][y], val) {
+            *cell *= *val;
+        }
+    }
+
+    /// Reduces the reals in an area to one number with an aggregate such as `ds::sum`.
+    /// Strings are left out of sums, maximums and so on.
+    pub fn aggregate(&self, area: Area, reducer: fn(&[Real]) -> Real) -> Real {
+        let reals = self
+            .cells(area)
+            .into_iter()
+            .filter_map(|(x, y)| match self.grid[x][y] {
+                Value::Real(r) => Some(r),
+                Value::Str(_) => None,
+            })
+            .collect::<Vec<_>>();
+        reducer(&reals)
+    }
+
+    /// Returns the first cell in an area which holds the value, in the same order as `Grid::cells`.
+    pub fn find(&self, area: Area, val: &Value, precision: Real) -> Option<(usize, usize)> {
+        self.cells(area).into_iter().find(|&(x, y)| eq(&self.grid[x][y], val, precision))
+    }
+}
+
+pub fn sum(values: &[Real]) -> Real {
+    values.iter().fold(Real::from(0.0), |acc, &x| acc + x)
+}
+
+// Like the other aggregates, this gives 0 if there are no values.
+pub fn max(values: &[Real]) -> Real {
+    values.iter().copied().reduce(Real::max).unwrap_or(Real::from(0.0))
+}
+
+pub fn min(values: &[Real]) -> Real {
+    values.iter().copied().reduce(Real::min).unwrap_or(Real::from(0.0))
+}
+
+pub fn mean(values: &[Real]) -> Real {
+    if values.is_empty() { Real::from(0.0) } else { sum(values) / Real::from(values.len() as f64) }
 }
 
 pub fn eq(v1: &Value, v2: &Value, precision: Real) -> bool {
@@ -162,3 +286,75 @@ pub fn cmp(v1: &Value, v2: &Value, precision: Real) -> Ordering {
         (Value::Str(_), Value::Real(_)) => Ordering::Greater,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Area, Grid};
+    use crate::{gml::Value, math::Real};
+
+    fn disk(xm: f64, ym: f64, r: f64) -> Area {
+        Area::Disk(Real::from(xm), Real::from(ym), Real::from(r))
+    }
+
+    #[test]
+    fn grid_disk() {
+        let grid = Grid::new(5, 5);
+        // a cell is in the disk if its position is within the radius, so only the middle and its neighbours here
+        assert_eq!(grid.cells(disk(2.0, 2.0, 1.0)), [(1, 2), (2, 1), (2, 2), (2, 3), (3, 2)]);
+        assert_eq!(grid.cells(disk(0.0, 0.0, 1.5)), [(0, 0), (0, 1), (1, 0), (1, 1)]);
+        // cells exactly on the edge are included, but ones just outside aren't
+        let cells = grid.cells(disk(2.0, 2.0, 2.0));
+        assert_eq!(cells.len(), 13);
+        assert!(cells.contains(&(0, 2)) && cells.contains(&(4, 2)));
+        assert!(!cells.contains(&(0, 1)));
+        // a disk between cells doesn't have to contain any
+        assert!(grid.cells(disk(1.5, 1.5, 0.5)).is_empty());
+        assert_eq!(grid.cells(Area::Region(1, 1, 0, 9)).len(), 2 * 4);
+    }
+
+    #[test]
+    fn grid_find_order() {
+        // cells are searched down each column in turn, so the leftmost match wins, then the topmost
+        let mut grid = Grid::new(3, 3);
+        grid.set(2, 0, 1.0.into());
+        grid.set(1, 2, 1.0.into());
+        grid.set(1, 1, 1.0.into());
+        assert_eq!(grid.find(Area::Region(0, 0, 2, 2), &1.0.into(), Real::from(0.0)), Some((1, 1)));
+        assert_eq!(grid.find(Area::Region(2, 2, 0, 0), &1.0.into(), Real::from(0.0)), Some((1, 1)));
+        assert_eq!(grid.find(Area::Region(0, 2, 2, 2), &1.0.into(), Real::from(0.0)), Some((1, 2)));
+        assert_eq!(grid.find(disk(2.0, 0.0, 1.0), &1.0.into(), Real::from(0.0)), Some((2, 0)));
+        assert_eq!(grid.find(Area::Region(0, 0, 0, 2), &1.0.into(), Real::from(0.0)), None);
+    }
+
+    #[test]
+    fn grid_mixed_values() {
+        let mut grid = Grid::new(2, 1);
+        grid.set(1, 0, "a".into());
+        let val: Value = 2.0.into();
+        grid.add(0, 0, &val);
+        grid.add(1, 0, &val);
+        grid.add(1, 0, &"b".into());
+        grid.multiply(0, 0, &3.0.into());
+        grid.multiply(1, 0, &3.0.into());
+        assert!(matches!(grid.get(0, 0), Value::Real(r) if *r == Real::from(6.0)));
+        assert!(matches!(grid.get(1, 0), Value::Str(s) if s.as_ref() == b"ab"));
+
+        let area = Area::Region(0, 0, 1, 0);
+        assert_eq!(grid.aggregate(area, super::sum), Real::from(6.0));
+        assert_eq!(grid.aggregate(area, super::mean), Real::from(6.0));
+        assert_eq!(super::max(&[]), Real::from(0.0));
+        assert_eq!(grid.find(area, &"ab".into(), Real::from(0.0)), Some((1, 0)));
+    }
+
+    #[test]
+    fn grid_paste() {
+        // copying a region onto itself, shifted, uses the values from before the copy
+        let mut grid = Grid::new(3, 1);
+        (0..3).for_each(|x| grid.set(x, 0, (x as f64).into()));
+        let values = grid.copy_region(0, 0, 1, 0);
+        grid.paste(values, 1, 0, Grid::add);
+        let cells = (0..3).map(|x| grid.get(x, 0).clone()).collect::<Vec<_>>();
+        assert!(matches!(&cells[..], [Value::Real(a), Value::Real(b), Value::Real(c)]
+            if *a == Real::from(0.0) && *b == Real::from(1.0) && *c == Real::from(3.0)));
+    }
+}
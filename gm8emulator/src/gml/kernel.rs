@@ -5,8 +5,8 @@
 use crate::{
     action, asset,
     game::{
-        draw, external, gm_save::GMSave, model, particle, pathfinding, replay, string::RCStr, surface::Surface,
-        transition::UserTransition, view::View, Game, GetAsset, PlayType, SceneChange, Version,
+        draw, external, gm_save::GMSave, model, particle, pathfinding, registry, replay, string::RCStr,
+        surface::Surface, transition::UserTransition, view::View, Game, GetAsset, PlayType, SceneChange, Version,
     },
     gml::{
         self,
@@ -19,13 +19,14 @@ use crate::{
     tile::Tile,
 };
 use gmio::{
-    render::{BlendType, Fog, Light, Renderer, RendererOptions, Scaling},
+    render::{Backend, BlendType, Fog, Light, Renderer, RendererOptions, Scaling},
     window,
     window::Cursor,
 };
 use image::RgbaImage;
 use shared::{input::MouseButton, types::Colour};
 use std::{
+    cell::RefCell,
     io::{Read, Write},
     process::Command,
 };
@@ -2042,9 +2043,23 @@ impl Game {
         }
     }
 
-    pub fn surface_create_ext(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function surface_create_ext")
+    pub fn surface_create_ext(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (w, h) = expect_args!(args, [int, int])?;
+        let surf = Surface {
+            width: w as _,
+            height: h as _,
+            atlas_ref: match self.renderer.create_surface(w, h, false) {
+                Ok(atl_ref) => atl_ref,
+                Err(e) => return Err(gml::Error::FunctionError("surface_create_ext".into(), e)),
+            },
+        };
+        if let Some(id) = self.surfaces.iter().position(|x| x.is_none()) {
+            self.surfaces[id] = Some(surf);
+            Ok(id.into())
+        } else {
+            self.surfaces.push(Some(surf));
+            Ok((self.surfaces.len() - 1).into())
+        }
     }
 
     pub fn surface_free(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -2279,19 +2294,54 @@ impl Game {
         }
     }
 
-    pub fn surface_getpixel(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function surface_getpixel")
+    pub fn surface_getpixel(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (surf_id, x, y) = expect_args!(args, [int, int, int])?;
+        if Some(surf_id) == self.surface_target {
+            self.renderer.flush_queue();
+        }
+        match self.surfaces.get_asset(surf_id) {
+            Some(surf) if x >= 0 && y >= 0 && x < surf.width as i32 && y < surf.height as i32 => {
+                let data = self.renderer.dump_sprite_part(&surf.atlas_ref, x, y, 1, 1);
+                Ok(u32::from_le_bytes([data[0], data[1], data[2], 0]).into())
+            },
+            _ => Ok(0.into()),
+        }
     }
 
-    pub fn surface_copy(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function surface_copy")
+    pub fn surface_copy(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (dest_id, x, y, src_id) = expect_args!(args, [int, int, int, int])?;
+        if let Some(src) = self.surfaces.get_asset(src_id) {
+            let (w, h) = (src.width, src.height);
+            self.surface_copy_part(context, &[
+                dest_id.into(),
+                x.into(),
+                y.into(),
+                src_id.into(),
+                0.into(),
+                0.into(),
+                w.into(),
+                h.into(),
+            ])
+        } else {
+            Ok(Default::default())
+        }
     }
 
-    pub fn surface_copy_part(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 8
-        unimplemented!("Called unimplemented kernel function surface_copy_part")
+    pub fn surface_copy_part(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (dest_id, x, y, src_id, xs, ys, ws, hs) = expect_args!(args, [int, int, int, int, int, int, int, int])?;
+        if let (Some(dest), Some(src)) = (self.surfaces.get_asset(dest_id), self.surfaces.get_asset(src_id)) {
+            // only the part that's actually on the source gets copied
+            let (x, xs) = if xs < 0 { (x - xs, 0) } else { (x, xs) };
+            let (y, ys) = if ys < 0 { (y - ys, 0) } else { (y, ys) };
+            let ws = ws.min(src.width as i32 - xs);
+            let hs = hs.min(src.height as i32 - ys);
+            if ws > 0 && hs > 0 {
+                self.renderer
+                    .copy_surface_part(&dest.atlas_ref, x, y, &src.atlas_ref, xs, ys, ws, hs)
+                    .map_err(|e| gml::Error::FunctionError("surface_copy_part".into(), e))?;
+            }
+        }
+        Ok(Default::default())
     }
 
     pub fn action_path_old(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
@@ -2763,23 +2813,18 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn action_sound(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        //unimplemented!("Called unimplemented kernel function action_sound")
-        // TODO
+    pub fn action_sound(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, looping) = expect_args!(args, [int, bool])?;
+        self.audio.play(&self.assets.sounds, sound_id, looping);
         Ok(Default::default())
     }
 
-    pub fn action_end_sound(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function action_end_sound")
-        // TODO
-        Ok(Default::default())
+    pub fn action_end_sound(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        self.sound_stop(context, args)
     }
 
-    pub fn action_if_sound(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function action_if_sound")
+    pub fn action_if_sound(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        self.sound_isplaying(context, args)
     }
 
     pub fn action_another_room(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -4672,24 +4717,58 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn mp_linear_step(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function mp_linear_step")
+    pub fn mp_linear_step(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (x, y, step_size, checkall) = expect_args!(args, [real, real, real, bool])?;
+        Ok(pathfinding::linear_step(x, y, step_size, self.instance_list.get(context.this), || {
+            if checkall {
+                self.check_collision_any(context.this).is_some()
+            } else {
+                self.check_collision_solid(context.this).is_some()
+            }
+        })
+        .into())
     }
 
-    pub fn mp_linear_path(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function mp_linear_path")
+    pub fn mp_linear_path(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, x, y, step_size, checkall) = expect_args!(args, [int, real, real, real, bool])?;
+        let instance = self.instance_list.get(context.this);
+        let (reached, points) = pathfinding::step_path(x, y, step_size, 1.into(), instance, || {
+            pathfinding::linear_step(x, y, step_size, instance, || {
+                if checkall {
+                    self.check_collision_any(context.this).is_some()
+                } else {
+                    self.check_collision_solid(context.this).is_some()
+                }
+            })
+        });
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.points = points;
+            path.update();
+        }
+        Ok(reached.into())
     }
 
-    pub fn mp_linear_step_object(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function mp_linear_step_object")
+    pub fn mp_linear_step_object(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (x, y, step_size, obj) = expect_args!(args, [real, real, real, int])?;
+        Ok(pathfinding::linear_step(x, y, step_size, self.instance_list.get(context.this), || {
+            self.check_collision_object(context.this, context.other, obj)
+        })
+        .into())
     }
 
-    pub fn mp_linear_path_object(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function mp_linear_path_object")
+    pub fn mp_linear_path_object(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, x, y, step_size, obj) = expect_args!(args, [int, real, real, real, int])?;
+        let instance = self.instance_list.get(context.this);
+        let (reached, points) = pathfinding::step_path(x, y, step_size, 1.into(), instance, || {
+            pathfinding::linear_step(x, y, step_size, instance, || {
+                self.check_collision_object(context.this, context.other, obj)
+            })
+        });
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.points = points;
+            path.update();
+        }
+        Ok(reached.into())
     }
 
     pub fn mp_potential_settings(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -4722,9 +4801,24 @@ impl Game {
         .into())
     }
 
-    pub fn mp_potential_path(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 6
-        unimplemented!("Called unimplemented kernel function mp_potential_path")
+    pub fn mp_potential_path(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, x, y, step_size, factor, checkall) = expect_args!(args, [int, real, real, real, real, bool])?;
+        let instance = self.instance_list.get(context.this);
+        let (reached, points) = pathfinding::step_path(x, y, step_size, factor, instance, || {
+            pathfinding::potential_step(x, y, step_size, &self.potential_step_settings, instance, || {
+                if checkall {
+                    self.check_collision_any(context.this).is_some()
+                } else {
+                    self.check_collision_solid(context.this).is_some()
+                }
+            });
+            instance.x.get() == x && instance.y.get() == y
+        });
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.points = points;
+            path.update();
+        }
+        Ok(reached.into())
     }
 
     pub fn mp_potential_step_object(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -4735,68 +4829,201 @@ impl Game {
             step_size,
             &self.potential_step_settings,
             self.instance_list.get(context.this),
-            || match obj {
-                gml::SELF => false,
-                gml::OTHER => self.check_collision(context.this, context.other),
-                obj => self.find_instance_with(obj, |handle| self.check_collision(context.this, handle)).is_some(),
-            },
+            || self.check_collision_object(context.this, context.other, obj),
         )
         .into())
     }
 
-    pub fn mp_potential_path_object(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 6
-        unimplemented!("Called unimplemented kernel function mp_potential_path_object")
+    pub fn mp_potential_path_object(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, x, y, step_size, factor, obj) = expect_args!(args, [int, real, real, real, real, int])?;
+        let instance = self.instance_list.get(context.this);
+        let (reached, points) = pathfinding::step_path(x, y, step_size, factor, instance, || {
+            pathfinding::potential_step(x, y, step_size, &self.potential_step_settings, instance, || {
+                self.check_collision_object(context.this, context.other, obj)
+            });
+            instance.x.get() == x && instance.y.get() == y
+        });
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.points = points;
+            path.update();
+        }
+        Ok(reached.into())
     }
 
-    pub fn mp_grid_create(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 6
-        unimplemented!("Called unimplemented kernel function mp_grid_create")
+    pub fn mp_grid_create(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (left, top, hcells, vcells, cell_width, cell_height) = expect_args!(args, [int, int, int, int, int, int])?;
+        Ok(self.mp_grids.put(pathfinding::MpGrid::new(left, top, hcells, vcells, cell_width, cell_height)).into())
     }
 
-    pub fn mp_grid_destroy(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function mp_grid_destroy")
+    pub fn mp_grid_destroy(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        if self.mp_grids.delete(id) {
+            Ok(Default::default())
+        } else {
+            Err(gml::Error::FunctionError("mp_grid_destroy".into(), pathfinding::Error::NonexistentGrid(id).into()))
+        }
     }
 
-    pub fn mp_grid_clear_all(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function mp_grid_clear_all")
+    pub fn mp_grid_clear_all(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        match self.mp_grids.get_mut(id) {
+            Some(grid) => {
+                grid.set_all(false);
+                Ok(Default::default())
+            },
+            None => Err(gml::Error::FunctionError(
+                "mp_grid_clear_all".into(),
+                pathfinding::Error::NonexistentGrid(id).into(),
+            )),
+        }
     }
 
-    pub fn mp_grid_clear_cell(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function mp_grid_clear_cell")
+    pub fn mp_grid_clear_cell(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, h, v) = expect_args!(args, [int, int, int])?;
+        match self.mp_grids.get_mut(id) {
+            Some(grid) => {
+                grid.set_cell(h, v, false);
+                Ok(Default::default())
+            },
+            None => Err(gml::Error::FunctionError(
+                "mp_grid_clear_cell".into(),
+                pathfinding::Error::NonexistentGrid(id).into(),
+            )),
+        }
     }
 
-    pub fn mp_grid_clear_rectangle(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function mp_grid_clear_rectangle")
+    pub fn mp_grid_clear_rectangle(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, x1, y1, x2, y2) = expect_args!(args, [int, int, int, int, int])?;
+        match self.mp_grids.get_mut(id) {
+            Some(grid) => {
+                grid.set_rect(x1, y1, x2, y2, false);
+                Ok(Default::default())
+            },
+            None => Err(gml::Error::FunctionError(
+                "mp_grid_clear_rectangle".into(),
+                pathfinding::Error::NonexistentGrid(id).into(),
+            )),
+        }
     }
 
-    pub fn mp_grid_add_cell(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function mp_grid_add_cell")
+    pub fn mp_grid_add_cell(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, h, v) = expect_args!(args, [int, int, int])?;
+        match self.mp_grids.get_mut(id) {
+            Some(grid) => {
+                grid.set_cell(h, v, true);
+                Ok(Default::default())
+            },
+            None => Err(gml::Error::FunctionError(
+                "mp_grid_add_cell".into(),
+                pathfinding::Error::NonexistentGrid(id).into(),
+            )),
+        }
     }
 
-    pub fn mp_grid_add_rectangle(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function mp_grid_add_rectangle")
+    pub fn mp_grid_add_rectangle(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, x1, y1, x2, y2) = expect_args!(args, [int, int, int, int, int])?;
+        match self.mp_grids.get_mut(id) {
+            Some(grid) => {
+                grid.set_rect(x1, y1, x2, y2, true);
+                Ok(Default::default())
+            },
+            None => Err(gml::Error::FunctionError(
+                "mp_grid_add_rectangle".into(),
+                pathfinding::Error::NonexistentGrid(id).into(),
+            )),
+        }
     }
 
-    pub fn mp_grid_add_instances(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function mp_grid_add_instances")
+    pub fn mp_grid_add_instances(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, obj, precise) = expect_args!(args, [int, int, bool])?;
+        let grid = match self.mp_grids.get(id) {
+            Some(grid) => grid,
+            None => {
+                return Err(gml::Error::FunctionError(
+                    "mp_grid_add_instances".into(),
+                    pathfinding::Error::NonexistentGrid(id).into(),
+                ))
+            },
+        };
+        let handles = RefCell::new(Vec::new());
+        self.find_instance_with(obj, |handle| {
+            handles.borrow_mut().push(handle);
+            false
+        });
+        // check each cell the instance's bounding box touches, since a precise mask might not fill it
+        let mut forbidden = Vec::new();
+        for handle in handles.into_inner() {
+            let instance = self.instance_list.get(handle);
+            instance.update_bbox(self.get_instance_mask_sprite(handle));
+            let (left, top) = (instance.bbox_left.get(), instance.bbox_top.get());
+            let (right, bottom) = (instance.bbox_right.get(), instance.bbox_bottom.get());
+            forbidden.extend(grid.cells_in_rect(left, top, right, bottom).filter(|&(h, v)| {
+                let (x1, y1, x2, y2) = grid.cell_rect(h, v);
+                self.check_collision_rectangle(handle, x1, y1, x2, y2, precise)
+            }));
+        }
+        if let Some(grid) = self.mp_grids.get_mut(id) {
+            for (h, v) in forbidden {
+                grid.set_cell(h, v, true);
+            }
+        }
+        Ok(Default::default())
     }
 
-    pub fn mp_grid_path(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 7
-        unimplemented!("Called unimplemented kernel function mp_grid_path")
+    pub fn mp_grid_path(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, path_id, xstart, ystart, xgoal, ygoal, allow_diag) =
+            expect_args!(args, [int, int, real, real, real, real, bool])?;
+        let points = match self.mp_grids.get(id) {
+            Some(grid) => grid.find_path((xstart, ystart), (xgoal, ygoal), allow_diag),
+            None => {
+                return Err(gml::Error::FunctionError(
+                    "mp_grid_path".into(),
+                    pathfinding::Error::NonexistentGrid(id).into(),
+                ))
+            },
+        };
+        match points {
+            Some(points) => {
+                if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+                    // the path is always made of straight lines, and it ends at the goal
+                    path.points = points;
+                    path.curve = false;
+                    path.closed = false;
+                    path.update();
+                }
+                Ok(true.into())
+            },
+            None => Ok(false.into()),
+        }
     }
 
-    pub fn mp_grid_draw(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function mp_grid_draw")
+    pub fn mp_grid_draw(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        let grid = match self.mp_grids.get(id) {
+            Some(grid) => grid,
+            None => {
+                return Err(gml::Error::FunctionError(
+                    "mp_grid_draw".into(),
+                    pathfinding::Error::NonexistentGrid(id).into(),
+                ))
+            },
+        };
+        for v in 0..grid.vcells {
+            for h in 0..grid.hcells {
+                // c_red for forbidden cells and c_lime for free ones
+                let colour = if grid.is_forbidden(h, v) { 0x0000FF } else { 0x00FF00 };
+                let (x1, y1, x2, y2) = grid.cell_rect(h, v);
+                self.renderer.draw_rectangle(
+                    x1.into(),
+                    y1.into(),
+                    x2.into(),
+                    y2.into(),
+                    colour,
+                    self.draw_alpha.into(),
+                );
+            }
+        }
+        Ok(Default::default())
     }
 
     pub fn collision_point(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -5396,15 +5623,9 @@ impl Game {
 
     pub fn game_load(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         let fname = expect_args!(args, [string])?;
-        let mut file = std::fs::File::open(fname.as_ref())
-            .map_err(|e| gml::Error::FunctionError("game_load".into(), format!("{}", e)))?;
-        let mut magnum = [0u8; 4];
-        file.read(&mut magnum).map_err(|e| gml::Error::FunctionError("game_load".into(), format!("{}", e)))?;
-        if magnum != [0x1d, 0x02, 0x00, 0x00] {
-            return Err(gml::Error::FunctionError("game_load".into(), "tried to load wrong version of save file".into()))
-        }
-        let save: GMSave = bincode::deserialize_from(file)
+        let file = std::fs::File::open(fname.as_ref())
             .map_err(|e| gml::Error::FunctionError("game_load".into(), format!("{}", e)))?;
+        let save = GMSave::read(file, self).map_err(|e| gml::Error::FunctionError("game_load".into(), e))?;
         save.into_game(self).map_err(|e| gml::Error::FunctionError("game_load".into(), e))?;
         Ok(Default::default())
     }
@@ -5412,13 +5633,9 @@ impl Game {
     pub fn game_save(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
         let fname = expect_args!(args, [string])?;
         let save = GMSave::from_game(self);
-        let mut file = std::fs::File::create(fname.as_ref())
-            .map_err(|e| gml::Error::FunctionError("game_save".into(), format!("{}", e)))?;
-        // write magic number (0x21c in GM8)
-        file.write(&[0x1d, 0x02, 0x00, 0x00])
-            .map_err(|e| gml::Error::FunctionError("game_save".into(), format!("{}", e)))?;
-        bincode::serialize_into(file, &save)
+        let file = std::fs::File::create(fname.as_ref())
             .map_err(|e| gml::Error::FunctionError("game_save".into(), format!("{}", e)))?;
+        save.write(file).map_err(|e| gml::Error::FunctionError("game_save".into(), e))?;
         Ok(Default::default())
     }
 
@@ -6113,59 +6330,91 @@ impl Game {
         Ok(env.as_ref().into())
     }
 
-    pub fn registry_write_string(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function registry_write_string")
+    pub fn registry_write_string(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (name, value) = expect_args!(args, [bytes, bytes])?;
+        let key = registry::game_key(self.game_id);
+        self.registry
+            .set(registry::CURRENT_USER, key.as_ref(), name.as_ref(), registry::Entry::String(value))
+            .map_err(|e| gml::Error::FunctionError("registry_write_string".into(), e.to_string()))?;
+        Ok(Default::default())
     }
 
-    pub fn registry_write_real(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function registry_write_real")
+    pub fn registry_write_real(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (name, value) = expect_args!(args, [bytes, real])?;
+        let key = registry::game_key(self.game_id);
+        self.registry
+            .set(registry::CURRENT_USER, key.as_ref(), name.as_ref(), registry::Entry::Real(value))
+            .map_err(|e| gml::Error::FunctionError("registry_write_real".into(), e.to_string()))?;
+        Ok(Default::default())
     }
 
-    pub fn registry_read_string(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function registry_read_string")
+    pub fn registry_read_string(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let name = expect_args!(args, [bytes])?;
+        let key = registry::game_key(self.game_id);
+        match self.registry.get(registry::CURRENT_USER, key.as_ref(), name.as_ref()) {
+            Some(registry::Entry::String(s)) => Ok(s.clone().into()),
+            _ => Ok("".into()),
+        }
     }
 
-    pub fn registry_read_real(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function registry_read_real")
+    pub fn registry_read_real(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let name = expect_args!(args, [bytes])?;
+        let key = registry::game_key(self.game_id);
+        match self.registry.get(registry::CURRENT_USER, key.as_ref(), name.as_ref()) {
+            Some(registry::Entry::Real(x)) => Ok((*x).into()),
+            _ => Ok(0.into()),
+        }
     }
 
-    pub fn registry_exists(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function registry_exists")
+    pub fn registry_exists(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let name = expect_args!(args, [bytes])?;
+        let key = registry::game_key(self.game_id);
+        Ok(self.registry.get(registry::CURRENT_USER, key.as_ref(), name.as_ref()).is_some().into())
     }
 
-    pub fn registry_write_string_ext(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function registry_write_string_ext")
+    pub fn registry_write_string_ext(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (key, name, value) = expect_args!(args, [bytes, bytes, bytes])?;
+        self.registry
+            .set(self.registry.root, key.as_ref(), name.as_ref(), registry::Entry::String(value))
+            .map_err(|e| gml::Error::FunctionError("registry_write_string_ext".into(), e.to_string()))?;
+        Ok(Default::default())
     }
 
-    pub fn registry_write_real_ext(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function registry_write_real_ext")
+    pub fn registry_write_real_ext(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (key, name, value) = expect_args!(args, [bytes, bytes, real])?;
+        self.registry
+            .set(self.registry.root, key.as_ref(), name.as_ref(), registry::Entry::Real(value))
+            .map_err(|e| gml::Error::FunctionError("registry_write_real_ext".into(), e.to_string()))?;
+        Ok(Default::default())
     }
 
-    pub fn registry_read_string_ext(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function registry_read_string_ext")
+    pub fn registry_read_string_ext(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (key, name) = expect_args!(args, [bytes, bytes])?;
+        match self.registry.get(self.registry.root, key.as_ref(), name.as_ref()) {
+            Some(registry::Entry::String(s)) => Ok(s.clone().into()),
+            _ => Ok("".into()),
+        }
     }
 
-    pub fn registry_read_real_ext(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function registry_read_real_ext")
+    pub fn registry_read_real_ext(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (key, name) = expect_args!(args, [bytes, bytes])?;
+        match self.registry.get(self.registry.root, key.as_ref(), name.as_ref()) {
+            Some(registry::Entry::Real(x)) => Ok((*x).into()),
+            _ => Ok(0.into()),
+        }
     }
 
-    pub fn registry_exists_ext(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function registry_exists_ext")
+    pub fn registry_exists_ext(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (key, name) = expect_args!(args, [bytes, bytes])?;
+        Ok(self.registry.get(self.registry.root, key.as_ref(), name.as_ref()).is_some().into())
     }
 
-    pub fn registry_set_root(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function registry_set_root")
+    pub fn registry_set_root(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let root = expect_args!(args, [int])?;
+        if root >= 0 && (root as usize) < registry::ROOT_COUNT {
+            self.registry.root = root as usize;
+        }
+        Ok(Default::default())
     }
 
     pub fn ini_open(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -6439,7 +6688,14 @@ impl Game {
     }
 
     pub fn show_message(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
-        let _text = expect_args!(args, [string])?;
+        let text = expect_args!(args, [string])?;
+
+        // there's nobody to click OK, so just log it
+        if self.window.is_headless() {
+            println!("show_message: {}", text);
+            return Ok(Default::default())
+        }
+
         let width = 300;
         let height = 200;
 
@@ -6452,7 +6708,7 @@ impl Game {
 
         let wb = window::WindowBuilder::new().with_size(width, height);
         let mut window = wb.build().map_err(|e| gml::Error::FunctionError("show_message".into(), e))?;
-        let mut renderer = Renderer::new((), &options, &window, clear_colour)
+        let mut renderer = Renderer::new(Backend::OpenGL, &options, &window, clear_colour)
             .map_err(|e| gml::Error::FunctionError("show_message".into(), e))?;
         window.set_visible(true);
         renderer.set_vsync(false);
@@ -7177,11 +7433,12 @@ impl Game {
             self.externals.push(Some(
                 external::External::new(
                     external::DefineInfo { dll_name, fn_name, call_conv, res_type, arg_types },
-                    self.play_type == PlayType::Record,
+                    &self.external_stubs,
                     match self.gm_version {
                         Version::GameMaker8_0 => self.encoding,
                         Version::GameMaker8_1 => encoding_rs::UTF_8,
                     },
+                    self.play_type == PlayType::Normal,
                 )
                 .map_err(|e| gml::Error::FunctionError("external_define".into(), e))?,
             ));
@@ -7195,6 +7452,14 @@ impl Game {
         if let Some(id) = args.get(0) {
             let id = id.round();
             if let Some(external) = self.externals.get_asset(id) {
+                if let Some(function) = external.builtin() {
+                    let res_type = external.info.res_type;
+                    let args = match external.convert_args(&args[1..]) {
+                        Some(args) => args.collect::<Vec<_>>(),
+                        None => return Ok(Default::default()),
+                    };
+                    return function(self, &args).map(|result| external::convert_result(result, res_type))
+                }
                 return external.call(&args[1..])
             }
         }
@@ -7858,9 +8123,46 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn sprite_set_alpha_from_sprite(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function sprite_set_alpha_from_sprite")
+    pub fn sprite_set_alpha_from_sprite(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (dst_id, src_id) = expect_args!(args, [int, int])?;
+        let src_images = match self.assets.sprites.get_asset(src_id) {
+            Some(src) if !src.frames.is_empty() => src
+                .frames
+                .iter()
+                .map(|f| {
+                    RgbaImage::from_vec(f.width, f.height, self.renderer.dump_sprite(&f.atlas_ref).into_vec()).unwrap()
+                })
+                .collect::<Vec<_>>(),
+            _ => return Ok(Default::default()),
+        };
+        if let Some(sprite) = self.assets.sprites.get_asset_mut(dst_id) {
+            let mut images = Vec::with_capacity(sprite.frames.len());
+            for (i, frame) in sprite.frames.iter_mut().enumerate() {
+                let mut image = RgbaImage::from_vec(
+                    frame.width,
+                    frame.height,
+                    self.renderer.dump_sprite(&frame.atlas_ref).into_vec(),
+                )
+                .unwrap();
+                asset::sprite::set_alpha_from(&mut image, &src_images[i % src_images.len()]);
+                self.renderer.delete_sprite(frame.atlas_ref);
+                frame.atlas_ref = self
+                    .renderer
+                    .upload_sprite(
+                        image.clone().into_raw().into_boxed_slice(),
+                        frame.width as _,
+                        frame.height as _,
+                        sprite.origin_x,
+                        sprite.origin_y,
+                    )
+                    .map_err(|e| gml::Error::FunctionError("sprite_set_alpha_from_sprite".into(), e))?;
+                images.push(image);
+            }
+            if !images.is_empty() {
+                sprite.update_colliders(&images);
+            }
+        }
+        Ok(Default::default())
     }
 
     pub fn sprite_create_from_screen(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -7907,6 +8209,7 @@ impl Game {
             origin_x,
             origin_y,
             per_frame_colliders: false,
+            mask: Default::default(),
         })));
         Ok(sprite_id.into())
     }
@@ -7935,11 +8238,7 @@ impl Game {
             }
             images.push(image);
             let sprite = self.assets.sprites.get_asset_mut(sprite_id).unwrap();
-            sprite.colliders = asset::sprite::make_colliders_precise(&images, 0, sprite.per_frame_colliders);
-            sprite.bbox_left = sprite.colliders.iter().map(|c| c.bbox_left).min().unwrap();
-            sprite.bbox_top = sprite.colliders.iter().map(|c| c.bbox_top).min().unwrap();
-            sprite.bbox_right = sprite.colliders.iter().map(|c| c.bbox_right).max().unwrap();
-            sprite.bbox_bottom = sprite.colliders.iter().map(|c| c.bbox_bottom).max().unwrap();
+            sprite.update_colliders(&images);
             // upload frame
             let image = images.pop().unwrap();
             sprite.frames.push(asset::sprite::Frame {
@@ -8007,6 +8306,7 @@ impl Game {
                 origin_x,
                 origin_y,
                 per_frame_colliders: false,
+                mask: Default::default(),
             })));
             Ok(sprite_id.into())
         } else {
@@ -8042,11 +8342,7 @@ impl Game {
                 }
                 images.push(image);
                 let sprite = self.assets.sprites.get_asset_mut(sprite_id).unwrap();
-                sprite.colliders = asset::sprite::make_colliders_precise(&images, 0, sprite.per_frame_colliders);
-                sprite.bbox_left = sprite.colliders.iter().map(|c| c.bbox_left).min().unwrap();
-                sprite.bbox_top = sprite.colliders.iter().map(|c| c.bbox_top).min().unwrap();
-                sprite.bbox_right = sprite.colliders.iter().map(|c| c.bbox_right).max().unwrap();
-                sprite.bbox_bottom = sprite.colliders.iter().map(|c| c.bbox_bottom).max().unwrap();
+                sprite.update_colliders(&images);
                 // upload frame
                 let image = images.pop().unwrap();
                 sprite.frames.push(asset::sprite::Frame {
@@ -8121,6 +8417,7 @@ impl Game {
             origin_x,
             origin_y,
             per_frame_colliders: false,
+            mask: Default::default(),
         })));
         Ok(sprite_id.into())
     }
@@ -8173,6 +8470,7 @@ impl Game {
                 origin_x,
                 origin_y,
                 per_frame_colliders: false,
+                mask: Default::default(),
             });
             Ok(Default::default())
         } else {
@@ -8180,20 +8478,117 @@ impl Game {
         }
     }
 
-    pub fn sprite_add_sprite(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function sprite_add_sprite")
-    }
-
-    pub fn sprite_replace_sprite(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function sprite_replace_sprite")
+    pub fn sprite_add_sprite(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let fname = expect_args!(args, [string])?;
+        let file = match std::fs::read(fname.as_ref())
+            .map_err(|e| e.to_string())
+            .and_then(|f| asset::sprite::SpriteFile::read(&f))
+        {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("Warning: sprite_add_sprite on {} failed: {}", fname, e);
+                return Ok((-1).into())
+            },
+        };
+        let (width, height) = file.frames[0].dimensions();
+        let frames = file
+            .frames
+            .iter()
+            .map(|i| {
+                Ok(asset::sprite::Frame {
+                    width,
+                    height,
+                    atlas_ref: self
+                        .renderer
+                        .upload_sprite(
+                            i.clone().into_raw().into_boxed_slice(),
+                            width as _,
+                            height as _,
+                            file.origin_x,
+                            file.origin_y,
+                        )
+                        .map_err(|e| gml::Error::FunctionError("sprite_add_sprite".into(), e))?,
+                })
+            })
+            .collect::<gml::Result<_>>()?;
+        let sprite_id = self.assets.sprites.len();
+        let mut sprite = asset::Sprite {
+            name: format!("__newsprite{}", sprite_id).into(),
+            frames,
+            colliders: Vec::new(),
+            width,
+            height,
+            origin_x: file.origin_x,
+            origin_y: file.origin_y,
+            per_frame_colliders: file.per_frame_colliders,
+            bbox_left: 0,
+            bbox_right: 0,
+            bbox_top: 0,
+            bbox_bottom: 0,
+            mask: file.mask,
+        };
+        sprite.update_colliders(&file.frames);
+        self.assets.sprites.push(Some(Box::new(sprite)));
+        Ok(sprite_id.into())
     }
 
-    pub fn sprite_delete(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
-        let sprite_id = expect_args!(args, [int])?;
-        if let Some(sprite) = self.assets.sprites.get_asset(sprite_id) {
-            for frame in &sprite.frames {
+    pub fn sprite_replace_sprite(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (sprite_id, fname) = expect_args!(args, [int, string])?;
+        if let Some(sprite) = self.assets.sprites.get_asset_mut(sprite_id) {
+            let file = match std::fs::read(fname.as_ref())
+                .map_err(|e| e.to_string())
+                .and_then(|f| asset::sprite::SpriteFile::read(&f))
+            {
+                Ok(file) => file,
+                Err(e) => {
+                    eprintln!("Warning: sprite_replace_sprite on {} failed: {}", fname, e);
+                    return Ok((-1).into())
+                },
+            };
+            for frame in &sprite.frames {
+                self.renderer.delete_sprite(frame.atlas_ref);
+            }
+            let (width, height) = file.frames[0].dimensions();
+            let renderer = &mut self.renderer;
+            sprite.frames = file
+                .frames
+                .iter()
+                .map(|i| {
+                    Ok(asset::sprite::Frame {
+                        width,
+                        height,
+                        atlas_ref: renderer
+                            .upload_sprite(
+                                i.clone().into_raw().into_boxed_slice(),
+                                width as _,
+                                height as _,
+                                file.origin_x,
+                                file.origin_y,
+                            )
+                            .map_err(|e| gml::Error::FunctionError("sprite_replace_sprite".into(), e))?,
+                    })
+                })
+                .collect::<gml::Result<_>>()?;
+            sprite.width = width;
+            sprite.height = height;
+            sprite.origin_x = file.origin_x;
+            sprite.origin_y = file.origin_y;
+            sprite.per_frame_colliders = file.per_frame_colliders;
+            sprite.mask = file.mask;
+            sprite.update_colliders(&file.frames);
+            Ok(Default::default())
+        } else {
+            Err(gml::Error::FunctionError(
+                "sprite_replace_sprite".into(),
+                "Trying to replace non-existing sprite.".into(),
+            ))
+        }
+    }
+
+    pub fn sprite_delete(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let sprite_id = expect_args!(args, [int])?;
+        if let Some(sprite) = self.assets.sprites.get_asset(sprite_id) {
+            for frame in &sprite.frames {
                 self.renderer.delete_sprite(frame.atlas_ref);
             }
         } else {
@@ -8203,19 +8598,95 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn sprite_duplicate(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function sprite_duplicate")
+    pub fn sprite_duplicate(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let sprite_id = expect_args!(args, [int])?;
+        if let Some(sprite) = self.assets.sprites.get_asset(sprite_id) {
+            let new_id = self.assets.sprites.len();
+            let mut sprite = sprite.as_ref().clone();
+            sprite.name = format!("__newsprite{}", new_id).into();
+            for frame in sprite.frames.iter_mut() {
+                frame.atlas_ref = self
+                    .renderer
+                    .duplicate_sprite(&frame.atlas_ref)
+                    .map_err(|e| gml::Error::FunctionError("sprite_duplicate".into(), e))?;
+            }
+            self.assets.sprites.push(Some(Box::new(sprite)));
+            Ok(new_id.into())
+        } else {
+            Err(gml::Error::NonexistentAsset(asset::Type::Sprite, sprite_id))
+        }
     }
 
-    pub fn sprite_assign(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function sprite_assign")
+    pub fn sprite_assign(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (dst_id, src_id) = expect_args!(args, [int, int])?;
+        if let Some(src) = self.assets.sprites.get_asset(src_id) {
+            if dst_id >= 0 && self.assets.sprites.len() > dst_id as usize {
+                let mut sprite = src.as_ref().clone();
+                for frame in sprite.frames.iter_mut() {
+                    frame.atlas_ref = self
+                        .renderer
+                        .duplicate_sprite(&frame.atlas_ref)
+                        .map_err(|e| gml::Error::FunctionError("sprite_assign".into(), e))?;
+                }
+                if let Some(old_sprite) = self.assets.sprites.get_asset(dst_id) {
+                    sprite.name = old_sprite.name.clone();
+                    for frame in &old_sprite.frames {
+                        self.renderer.delete_sprite(frame.atlas_ref);
+                    }
+                }
+                self.assets.sprites[dst_id as usize] = Some(Box::new(sprite));
+                Ok(Default::default())
+            } else {
+                Err(gml::Error::FunctionError("sprite_assign".into(), "Destination sprite has an invalid index".into()))
+            }
+        } else {
+            Err(gml::Error::FunctionError("sprite_assign".into(), "Source sprite does not exist".into()))
+        }
     }
 
-    pub fn sprite_merge(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function sprite_merge")
+    pub fn sprite_merge(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (dst_id, src_id) = expect_args!(args, [int, int])?;
+        let src_images = match self.assets.sprites.get_asset(src_id) {
+            Some(src) => src
+                .frames
+                .iter()
+                .map(|f| {
+                    RgbaImage::from_vec(f.width, f.height, self.renderer.dump_sprite(&f.atlas_ref).into_vec()).unwrap()
+                })
+                .collect::<Vec<_>>(),
+            None => return Ok(Default::default()),
+        };
+        if let Some(sprite) = self.assets.sprites.get_asset_mut(dst_id) {
+            let mut images = Vec::with_capacity(sprite.frames.len() + src_images.len());
+            for f in sprite.frames.iter() {
+                images.push(
+                    RgbaImage::from_vec(f.width, f.height, self.renderer.dump_sprite(&f.atlas_ref).into_vec()).unwrap(),
+                );
+            }
+            // the merged frames are stretched to fit
+            for mut image in src_images {
+                asset::sprite::scale(&mut image, sprite.width, sprite.height);
+                sprite.frames.push(asset::sprite::Frame {
+                    width: sprite.width,
+                    height: sprite.height,
+                    atlas_ref: self
+                        .renderer
+                        .upload_sprite(
+                            image.clone().into_raw().into_boxed_slice(),
+                            sprite.width as _,
+                            sprite.height as _,
+                            sprite.origin_x,
+                            sprite.origin_y,
+                        )
+                        .map_err(|e| gml::Error::FunctionError("sprite_merge".into(), e))?,
+                });
+                images.push(image);
+            }
+            if !images.is_empty() {
+                sprite.update_colliders(&images);
+            }
+        }
+        Ok(Default::default())
     }
 
     pub fn sprite_save(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -8236,34 +8707,38 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn sprite_save_strip(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function sprite_save_strip")
+    pub fn sprite_save_strip(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (sprite_id, fname) = expect_args!(args, [int, string])?;
+        if let Some(sprite) = self.assets.sprites.get_asset(sprite_id) {
+            let images = sprite
+                .frames
+                .iter()
+                .map(|f| {
+                    RgbaImage::from_vec(f.width, f.height, self.renderer.dump_sprite(&f.atlas_ref).into_vec()).unwrap()
+                })
+                .collect::<Vec<_>>();
+            if let Err(e) = file::save_image(fname.as_ref(), asset::sprite::strip(&images)) {
+                return Err(gml::Error::FunctionError("sprite_save_strip".into(), e.to_string()))
+            }
+        }
+        Ok(Default::default())
     }
 
     pub fn sprite_collision_mask(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
-        let (sprite_id, sepmasks, bboxmode, bbleft, bbtop, bbright, bbbottom, kind, tolerance) =
+        let (sprite_id, sepmasks, bbox_mode, bbleft, bbtop, bbright, bbbottom, kind, tolerance) =
             expect_args!(args, [int, bool, int, int, int, int, int, int, int])?;
-        let tolerance = tolerance.min(255).max(0) as u8;
-        let sepmasks = sepmasks;
         if let Some(sprite) = self.assets.sprites.get_asset_mut(sprite_id) {
-            // formulate requested bounding box
-            let bbox = match bboxmode {
-                0 => None, // automatic
-                1 => Some(asset::sprite::BoundingBox {
-                    // full image
-                    left: 0,
-                    right: sprite.width - 1,
-                    top: 0,
-                    bottom: sprite.height - 1,
-                }),
-                _ => Some(asset::sprite::BoundingBox {
-                    // user defined
+            sprite.per_frame_colliders = sepmasks;
+            sprite.mask = asset::sprite::MaskSettings {
+                bbox_mode,
+                bbox: asset::sprite::BoundingBox {
                     left: bbleft.max(0) as u32,
-                    right: (bbright as u32).min(sprite.width),
+                    right: bbright as u32,
                     top: bbtop.max(0) as u32,
-                    bottom: (bbbottom as u32).min(sprite.height),
-                }),
+                    bottom: bbbottom as u32,
+                },
+                kind,
+                tolerance: tolerance.min(255).max(0) as u8,
             };
 
             // download frames from gpu
@@ -8273,31 +8748,7 @@ impl Game {
                 .iter()
                 .map(|f| RgbaImage::from_vec(f.width, f.height, renderer.dump_sprite(&f.atlas_ref).to_vec()).unwrap())
                 .collect::<Vec<RgbaImage>>();
-
-            // make colliders
-            sprite.colliders = match kind {
-                0 => asset::sprite::make_colliders_precise(&frames, tolerance, sepmasks), // precise
-                _ => asset::sprite::make_colliders_shaped(&frames, tolerance, sepmasks, bbox, match kind {
-                    1 => Some(asset::sprite::ColliderShape::Rectangle),
-                    2 => Some(asset::sprite::ColliderShape::Ellipse),
-                    3 => Some(asset::sprite::ColliderShape::Diamond),
-                    _ => None,
-                }),
-            };
-
-            // set bbox variables manually if needed (even if using precise collision)
-            if let Some(bbox) = bbox {
-                for c in &mut sprite.colliders {
-                    c.bbox_left = bbox.left;
-                    c.bbox_top = bbox.top;
-                    c.bbox_right = bbox.right;
-                    c.bbox_bottom = bbox.bottom;
-                }
-            }
-            sprite.bbox_left = sprite.colliders.iter().map(|c| c.bbox_left).min().unwrap();
-            sprite.bbox_top = sprite.colliders.iter().map(|c| c.bbox_top).min().unwrap();
-            sprite.bbox_right = sprite.colliders.iter().map(|c| c.bbox_right).max().unwrap();
-            sprite.bbox_bottom = sprite.colliders.iter().map(|c| c.bbox_bottom).max().unwrap();
+            sprite.update_colliders(&frames);
         }
         Ok(Default::default())
     }
@@ -8450,9 +8901,22 @@ impl Game {
         Ok(background_id.into())
     }
 
-    pub fn background_create_gradient(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function background_create_gradient")
+    pub fn background_create_gradient(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (w, h, col1, col2, kind) = expect_args!(args, [int, int, int, int, int])?;
+        let (w, h) = (w.max(1), h.max(1));
+        let image = asset::background::make_gradient(w as _, h as _, (col1 as u32).into(), (col2 as u32).into(), kind);
+        let background_id = self.assets.backgrounds.len();
+        self.assets.backgrounds.push(Some(Box::new(asset::Background {
+            name: format!("__newbackground{}", background_id).into(),
+            width: w as _,
+            height: h as _,
+            atlas_ref: Some(
+                self.renderer
+                    .upload_sprite(image.into_raw().into_boxed_slice(), w, h, 0, 0)
+                    .map_err(|e| gml::Error::FunctionError("background_create_gradient".into(), e))?,
+            ),
+        })));
+        Ok(background_id.into())
     }
 
     pub fn background_add(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -8513,14 +8977,71 @@ impl Game {
         }
     }
 
-    pub fn background_add_background(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function background_add_background")
+    pub fn background_add_background(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let fname = expect_args!(args, [string])?;
+        let image = match std::fs::read(fname.as_ref())
+            .map_err(|e| e.to_string())
+            .and_then(|f| asset::background::read_gmbck(&f))
+        {
+            Ok(image) => image,
+            Err(e) => {
+                eprintln!("Warning: background_add_background on {} failed: {}", fname, e);
+                return Ok((-1).into())
+            },
+        };
+        let (width, height) = image.dimensions();
+        let atlas_ref = if width > 0 && height > 0 {
+            Some(
+                self.renderer
+                    .upload_sprite(image.into_raw().into_boxed_slice(), width as _, height as _, 0, 0)
+                    .map_err(|e| gml::Error::FunctionError("background_add_background".into(), e))?,
+            )
+        } else {
+            None
+        };
+        let background_id = self.assets.backgrounds.len();
+        self.assets.backgrounds.push(Some(Box::new(asset::Background {
+            name: format!("__newbackground{}", background_id).into(),
+            width,
+            height,
+            atlas_ref,
+        })));
+        Ok(background_id.into())
     }
 
-    pub fn background_replace_background(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function background_replace_background")
+    pub fn background_replace_background(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (background_id, fname) = expect_args!(args, [int, string])?;
+        if let Some(background) = self.assets.backgrounds.get_asset_mut(background_id) {
+            let image = match std::fs::read(fname.as_ref())
+                .map_err(|e| e.to_string())
+                .and_then(|f| asset::background::read_gmbck(&f))
+            {
+                Ok(image) => image,
+                Err(e) => {
+                    eprintln!("Warning: background_replace_background on {} failed: {}", fname, e);
+                    return Ok((-1).into())
+                },
+            };
+            if let Some(atlas_ref) = background.atlas_ref.take() {
+                self.renderer.delete_sprite(atlas_ref);
+            }
+            let (width, height) = image.dimensions();
+            if width > 0 && height > 0 {
+                background.atlas_ref = Some(
+                    self.renderer
+                        .upload_sprite(image.into_raw().into_boxed_slice(), width as _, height as _, 0, 0)
+                        .map_err(|e| gml::Error::FunctionError("background_replace_background".into(), e))?,
+                );
+            }
+            background.width = width;
+            background.height = height;
+            Ok(Default::default())
+        } else {
+            Err(gml::Error::FunctionError(
+                "background_replace_background".into(),
+                "Trying to replace non-existing background.".into(),
+            ))
+        }
     }
 
     pub fn background_delete(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -8539,9 +9060,23 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn background_duplicate(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function background_duplicate")
+    pub fn background_duplicate(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let background_id = expect_args!(args, [int])?;
+        if let Some(background) = self.assets.backgrounds.get_asset(background_id) {
+            let new_id = self.assets.backgrounds.len();
+            let mut background = background.as_ref().clone();
+            background.name = format!("__newbackground{}", new_id).into();
+            if let Some(atlas_ref) = background.atlas_ref.as_mut() {
+                *atlas_ref = self
+                    .renderer
+                    .duplicate_sprite(atlas_ref)
+                    .map_err(|e| gml::Error::FunctionError("background_duplicate".into(), e))?;
+            }
+            self.assets.backgrounds.push(Some(Box::new(background)));
+            Ok(new_id.into())
+        } else {
+            Err(gml::Error::NonexistentAsset(asset::Type::Background, background_id))
+        }
     }
 
     pub fn background_assign(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -8604,28 +9139,24 @@ impl Game {
         self.sound_get_name(context, args)
     }
 
-    pub fn sound_exists(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // TODO: uncomment this when there are sounds
-        //let sound = expect_args!(args, [int])?;
-        //Ok(self.assets.sounds.get_asset(sound).is_some().into())
-        todo!()
+    pub fn sound_exists(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let sound = expect_args!(args, [int])?;
+        Ok(self.assets.sounds.get_asset(sound).is_some().into())
     }
 
-    pub fn sound_get_name(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // TODO: uncomment this when there are sounds
-        //let asset_id = expect_args!(args, [int])?;
-        //Ok(self.assets.sounds.get_asset(asset_id).map(|x| x.name.clone().into()).unwrap_or("<undefined>".into()))
-        todo!()
+    pub fn sound_get_name(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let asset_id = expect_args!(args, [int])?;
+        Ok(self.assets.sounds.get_asset(asset_id).map(|x| x.name.clone().into()).unwrap_or("<undefined>".into()))
     }
 
-    pub fn sound_get_kind(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function sound_get_kind")
+    pub fn sound_get_kind(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let asset_id = expect_args!(args, [int])?;
+        Ok(self.assets.sounds.get_asset(asset_id).map(|x| i32::from(x.kind)).unwrap_or(-1).into())
     }
 
-    pub fn sound_get_preload(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function sound_get_preload")
+    pub fn sound_get_preload(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let asset_id = expect_args!(args, [int])?;
+        Ok(self.assets.sounds.get_asset(asset_id).map(|x| x.preload).unwrap_or(false).into())
     }
 
     pub fn sound_discard(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
@@ -8670,44 +9201,77 @@ impl Game {
         Ok(self.assets.fonts.get_asset(asset_id).map(|x| x.name.clone().into()).unwrap_or("<undefined>".into()))
     }
 
-    pub fn font_get_fontname(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function font_get_fontname")
+    pub fn font_get_fontname(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let font_id = expect_args!(args, [int])?;
+        if let Some(font) = self.assets.fonts.get_asset(font_id) {
+            Ok(font.sys_name.clone().into())
+        } else {
+            Ok("".into())
+        }
     }
 
-    pub fn font_get_size(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function font_get_size")
+    pub fn font_get_size(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let font_id = expect_args!(args, [int])?;
+        if let Some(font) = self.assets.fonts.get_asset(font_id) { Ok(font.size.into()) } else { Ok((-1).into()) }
     }
 
-    pub fn font_get_bold(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function font_get_bold")
+    pub fn font_get_bold(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let font_id = expect_args!(args, [int])?;
+        if let Some(font) = self.assets.fonts.get_asset(font_id) { Ok(font.bold.into()) } else { Ok((-1).into()) }
     }
 
-    pub fn font_get_italic(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function font_get_italic")
+    pub fn font_get_italic(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let font_id = expect_args!(args, [int])?;
+        if let Some(font) = self.assets.fonts.get_asset(font_id) { Ok(font.italic.into()) } else { Ok((-1).into()) }
     }
 
-    pub fn font_get_first(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function font_get_first")
+    pub fn font_get_first(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let font_id = expect_args!(args, [int])?;
+        if let Some(font) = self.assets.fonts.get_asset(font_id) {
+            Ok(u32::from(font.first).into())
+        } else {
+            Ok((-1).into())
+        }
     }
 
-    pub fn font_get_last(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function font_get_last")
+    pub fn font_get_last(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let font_id = expect_args!(args, [int])?;
+        if let Some(font) = self.assets.fonts.get_asset(font_id) {
+            Ok(u32::from(font.last).into())
+        } else {
+            Ok((-1).into())
+        }
     }
 
-    pub fn font_add(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 6
-        unimplemented!("Called unimplemented kernel function font_add")
+    pub fn font_add(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (sys_name, size, bold, italic, first, last) = expect_args!(args, [bytes, int, bool, bool, int, int])?;
+        let first = first.clamp(0, 255) as u8;
+        let last = last.clamp(first.into(), 255) as u8;
+        let font = self
+            .load_font(sys_name, size.max(1) as u32, bold, italic, first, last)
+            .map_err(|e| gml::Error::FunctionError("font_add".into(), e))?;
+        let font_id = self.assets.fonts.len();
+        self.assets.fonts.push(Some(Box::new(asset::Font { name: format!("__newfont{}", font_id).into(), ..font })));
+        Ok(font_id.into())
     }
 
-    pub fn font_replace(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 7
-        unimplemented!("Called unimplemented kernel function font_replace")
+    pub fn font_replace(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (font_id, sys_name, size, bold, italic, first, last) =
+            expect_args!(args, [int, bytes, int, bool, bool, int, int])?;
+        if self.assets.fonts.get_asset(font_id).is_none() {
+            return Err(gml::Error::NonexistentAsset(asset::Type::Font, font_id))
+        }
+        let first = first.clamp(0, 255) as u8;
+        let last = last.clamp(first.into(), 255) as u8;
+        let new_font = self
+            .load_font(sys_name, size.max(1) as u32, bold, italic, first, last)
+            .map_err(|e| gml::Error::FunctionError("font_replace".into(), e))?;
+        let font = self.assets.fonts.get_asset_mut(font_id).unwrap();
+        if font.own_graphics {
+            font.delete_graphics(&mut self.renderer);
+        }
+        **font = asset::Font { name: font.name.clone(), ..new_font };
+        Ok(Default::default())
     }
 
     pub fn font_add_sprite(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -8741,10 +9305,7 @@ impl Game {
         if let Some(font) = self.assets.fonts.get_asset_mut(font_id) {
             if let Some(sprite) = self.assets.sprites.get_asset(sprite_id) {
                 if font.own_graphics {
-                    // font_add isn't in yet but atm for ttfs all characters are on the same texture
-                    if let Some(c) = font.get_char(font.first) {
-                        self.renderer.delete_sprite(c.atlas_ref);
-                    }
+                    font.delete_graphics(&mut self.renderer);
                 }
                 let chars = asset::font::create_chars_from_sprite(sprite, prop, sep, &self.renderer);
                 font.sys_name = "".into();
@@ -8764,9 +9325,17 @@ impl Game {
         }
     }
 
-    pub fn font_delete(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function font_delete")
+    pub fn font_delete(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let font_id = expect_args!(args, [int])?;
+        if let Some(font) = self.assets.fonts.get_asset(font_id) {
+            if font.own_graphics {
+                font.delete_graphics(&mut self.renderer);
+            }
+            self.assets.fonts[font_id as usize] = None;
+            Ok(Default::default())
+        } else {
+            Err(gml::Error::NonexistentAsset(asset::Type::Font, font_id))
+        }
     }
 
     pub fn script_name(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -8984,19 +9553,46 @@ impl Game {
         Ok(path_id.into())
     }
 
-    pub fn path_duplicate(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function path_duplicate")
+    pub fn path_duplicate(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let path_id = expect_args!(args, [int])?;
+        if let Some(path) = self.assets.paths.get_asset(path_id) {
+            let new_id = self.assets.paths.len();
+            let mut path = path.as_ref().clone();
+            path.name = format!("__newpath{}", new_id).into();
+            self.assets.paths.push(Some(Box::new(path)));
+            Ok(new_id.into())
+        } else {
+            Err(gml::Error::NonexistentAsset(asset::Type::Path, path_id))
+        }
     }
 
-    pub fn path_assign(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function path_assign")
+    pub fn path_assign(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (dst_id, src_id) = expect_args!(args, [int, int])?;
+        if let Some(src) = self.assets.paths.get_asset(src_id) {
+            if dst_id >= 0 && self.assets.paths.len() > dst_id as usize {
+                let mut path = src.clone();
+                if let Some(dst) = self.assets.paths.get_asset(dst_id) {
+                    path.name = dst.name.clone();
+                }
+                self.assets.paths[dst_id as usize] = Some(path);
+                Ok(Default::default())
+            } else {
+                Err(gml::Error::FunctionError("path_assign".into(), "Destination path has an invalid index".into()))
+            }
+        } else {
+            Err(gml::Error::FunctionError("path_assign".into(), "Source path does not exist".into()))
+        }
     }
 
-    pub fn path_append(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function path_append")
+    pub fn path_append(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, src_id) = expect_args!(args, [int, int])?;
+        if let Some(src_points) = self.assets.paths.get_asset(src_id).map(|x| x.points.clone()) {
+            if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+                path.points.extend(src_points);
+                path.update();
+            }
+        }
+        Ok(Default::default())
     }
 
     pub fn path_delete(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -9016,9 +9612,14 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn path_insert_point(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function path_insert_point")
+    pub fn path_insert_point(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, n, x, y, speed) = expect_args!(args, [int, int, real, real, real])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            let n = (n.max(0) as usize).min(path.points.len());
+            path.points.insert(n, asset::path::Point { x, y, speed });
+            path.update();
+        }
+        Ok(Default::default())
     }
 
     pub fn path_change_point(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -9036,44 +9637,73 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn path_delete_point(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function path_delete_point")
+    pub fn path_delete_point(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, n) = expect_args!(args, [int, int])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            if n >= 0 && (n as usize) < path.points.len() {
+                path.points.remove(n as usize);
+                path.update();
+            }
+        }
+        Ok(Default::default())
     }
 
-    pub fn path_clear_points(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function path_clear_points")
+    pub fn path_clear_points(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let path_id = expect_args!(args, [int])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.points.clear();
+            path.update();
+        }
+        Ok(Default::default())
     }
 
-    pub fn path_reverse(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function path_reverse")
+    pub fn path_reverse(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let path_id = expect_args!(args, [int])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.points.reverse();
+            path.update();
+        }
+        Ok(Default::default())
     }
 
-    pub fn path_mirror(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function path_mirror")
+    pub fn path_mirror(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let path_id = expect_args!(args, [int])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.mirror();
+        }
+        Ok(Default::default())
     }
 
-    pub fn path_flip(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function path_flip")
+    pub fn path_flip(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let path_id = expect_args!(args, [int])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.flip();
+        }
+        Ok(Default::default())
     }
 
-    pub fn path_rotate(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function path_rotate")
+    pub fn path_rotate(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, angle) = expect_args!(args, [int, real])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.rotate(angle);
+        }
+        Ok(Default::default())
     }
 
-    pub fn path_scale(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function path_scale")
+    pub fn path_scale(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, xscale, yscale) = expect_args!(args, [int, real, real])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.scale(xscale, yscale);
+        }
+        Ok(Default::default())
     }
 
-    pub fn path_shift(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function path_shift")
+    pub fn path_shift(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, xshift, yshift) = expect_args!(args, [int, real, real])?;
+        if let Some(path) = self.assets.paths.get_asset_mut(path_id) {
+            path.shift(xshift, yshift);
+        }
+        Ok(Default::default())
     }
 
     pub fn timeline_name(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -9090,29 +9720,55 @@ impl Game {
         Ok(self.assets.timelines.get_asset(asset_id).map(|x| x.name.clone().into()).unwrap_or("<undefined>".into()))
     }
 
-    pub fn timeline_add(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function timeline_add")
+    pub fn timeline_add(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        let timeline_id = self.assets.timelines.len();
+        let name = format!("__newtimeline{}", timeline_id);
+        self.compiler.register_constant(name.as_bytes().into(), timeline_id as f64);
+        self.assets.timelines.push(Some(Box::new(asset::Timeline { name: name.into(), moments: Default::default() })));
+        Ok(timeline_id.into())
     }
 
-    pub fn timeline_delete(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function timeline_delete")
+    pub fn timeline_delete(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let timeline_id = expect_args!(args, [int])?;
+        if self.assets.timelines.get_asset(timeline_id).is_some() {
+            self.assets.timelines[timeline_id as usize] = None;
+        }
+        Ok(Default::default())
     }
 
-    pub fn timeline_clear(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function timeline_clear")
+    pub fn timeline_clear(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let timeline_id = expect_args!(args, [int])?;
+        if let Some(timeline) = self.assets.timelines.get_asset(timeline_id) {
+            timeline.moments.borrow_mut().clear();
+        }
+        Ok(Default::default())
     }
 
-    pub fn timeline_moment_clear(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function timeline_moment_clear")
+    pub fn timeline_moment_clear(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (timeline_id, step) = expect_args!(args, [int, int])?;
+        if let Some(timeline) = self.assets.timelines.get_asset(timeline_id) {
+            timeline.moments.borrow_mut().remove(&step);
+        }
+        Ok(Default::default())
     }
 
-    pub fn timeline_moment_add(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function timeline_moment_add")
+    pub fn timeline_moment_add(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (timeline_id, step, code) = expect_args!(args, [int, int, bytes])?;
+        if let Some(timeline) = self.assets.timelines.get_asset(timeline_id) {
+            let instrs = match self.compiler.compile(code.as_ref()) {
+                Ok(instrs) => instrs,
+                Err(e) => return Err(gml::Error::FunctionError("timeline_moment_add".into(), e.message)),
+            };
+            let mut moments = timeline.moments.borrow_mut();
+            match moments.get(&step) {
+                Some(tree) => tree.borrow_mut().push_code(instrs),
+                None => {
+                    moments.insert(step, action::Tree::new_from_code(instrs));
+                },
+            }
+        }
+        Ok(Default::default())
     }
 
     pub fn object_name(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -9282,9 +9938,25 @@ impl Game {
         Ok(id.into())
     }
 
-    pub fn object_delete(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function object_delete")
+    pub fn object_delete(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let object_id = expect_args!(args, [int])?;
+        if let Some(object) = self.assets.objects.get_asset(object_id) {
+            // Remove object and all its children from its parents
+            let children = object.children.borrow();
+            let mut parent_index = object.parent_index;
+            while let Some(parent) = self.assets.objects.get_asset(parent_index) {
+                parent.children.borrow_mut().retain(|c| !children.contains(c));
+                parent_index = parent.parent_index;
+            }
+            drop(children);
+            self.assets.objects[object_id as usize] = None;
+            // Its children are left without a parent, rather than pointing at an object that doesn't exist
+            for child in self.assets.objects.iter_mut().flatten().filter(|o| o.parent_index == object_id) {
+                child.parent_index = -1;
+            }
+            self.refresh_event_holders();
+        }
+        Ok(Default::default())
     }
 
     pub fn object_event_clear(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -9355,14 +10027,23 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn room_set_persistent(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function room_set_persistent")
+    pub fn room_set_persistent(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (room_id, persistent) = expect_args!(args, [int, bool])?;
+        if let Some(room) = self.assets.rooms.get_asset_mut(room_id) {
+            room.persistent = persistent;
+        }
+        Ok(Default::default())
     }
 
-    pub fn room_set_code(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function room_set_code")
+    pub fn room_set_code(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (room_id, code) = expect_args!(args, [int, bytes])?;
+        if let Some(room) = self.assets.rooms.get_asset_mut(room_id) {
+            match self.compiler.compile(code.as_ref()) {
+                Ok(instrs) => room.creation_code = Ok(instrs),
+                Err(e) => return Err(gml::Error::FunctionError("room_set_code".into(), e.message)),
+            }
+        }
+        Ok(Default::default())
     }
 
     pub fn room_set_background_color(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -9448,44 +10129,121 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn room_add(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function room_add")
+    pub fn room_add(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        let room_id = self.assets.rooms.len();
+        let name = format!("__newroom{}", room_id);
+        self.compiler.register_constant(name.as_bytes().into(), room_id as f64);
+        self.assets.rooms.push(Some(Box::new(asset::Room::new(name.into()))));
+        Ok(room_id.into())
     }
 
-    pub fn room_duplicate(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function room_duplicate")
+    pub fn room_duplicate(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let room_id = expect_args!(args, [int])?;
+        if let Some(room) = self.assets.rooms.get_asset(room_id) {
+            let new_id = self.assets.rooms.len();
+            let name = format!("__newroom{}", new_id);
+            self.compiler.register_constant(name.as_bytes().into(), new_id as f64);
+            let mut room = room.as_ref().clone();
+            room.name = name.into();
+            self.assets.rooms.push(Some(Box::new(room)));
+            Ok(new_id.into())
+        } else {
+            Err(gml::Error::NonexistentAsset(asset::Type::Room, room_id))
+        }
     }
 
-    pub fn room_assign(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function room_assign")
+    pub fn room_assign(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (dst_id, src_id) = expect_args!(args, [int, int])?;
+        if let Some(src) = self.assets.rooms.get_asset(src_id) {
+            if dst_id >= 0 && self.assets.rooms.len() > dst_id as usize {
+                let mut room = src.clone();
+                if let Some(dst) = self.assets.rooms.get_asset(dst_id) {
+                    room.name = dst.name.clone();
+                }
+                self.assets.rooms[dst_id as usize] = Some(room);
+                // the destination starts over as the source next time it's entered, even if it was left persistent
+                self.persistent_rooms.remove(&dst_id);
+                Ok(Default::default())
+            } else {
+                Err(gml::Error::FunctionError("room_assign".into(), "Destination room has an invalid index".into()))
+            }
+        } else {
+            Err(gml::Error::FunctionError("room_assign".into(), "Source room does not exist".into()))
+        }
     }
 
-    pub fn room_instance_add(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function room_instance_add")
+    pub fn room_instance_add(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (room_id, x, y, object_id) = expect_args!(args, [int, int, int, int])?;
+        if let Some(room) = self.assets.rooms.get_asset_mut(room_id) {
+            self.last_instance_id += 1;
+            room.instances.push(asset::room::Instance {
+                x,
+                y,
+                object: object_id,
+                id: self.last_instance_id,
+                creation: Ok(Vec::new().into()),
+            });
+            Ok(self.last_instance_id.into())
+        } else {
+            Ok(Default::default())
+        }
     }
 
-    pub fn room_instance_clear(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function room_instance_clear")
+    pub fn room_instance_clear(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let room_id = expect_args!(args, [int])?;
+        if let Some(room) = self.assets.rooms.get_asset_mut(room_id) {
+            room.instances.clear();
+        }
+        if let Some(state) = self.persistent_rooms.get_mut(&room_id) {
+            state.instances.clear();
+        }
+        Ok(Default::default())
     }
 
-    pub fn room_tile_add(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 9
-        unimplemented!("Called unimplemented kernel function room_tile_add")
+    pub fn room_tile_add(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [any, any, any, any, any, any, any, any, any])?;
+        let mut ext_args = args.to_vec();
+        ext_args.extend_from_slice(&[1.into(), 1.into(), 1.into()]);
+        self.room_tile_add_ext(context, &ext_args)
     }
 
-    pub fn room_tile_add_ext(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 12
-        unimplemented!("Called unimplemented kernel function room_tile_add_ext")
+    pub fn room_tile_add_ext(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (room_id, background_index, tile_x, tile_y, width, height, x, y, depth, xscale, yscale, alpha) =
+            expect_args!(args, [int, int, int, int, int, int, real, real, real, real, real, real])?;
+        if let Some(room) = self.assets.rooms.get_asset_mut(room_id) {
+            self.last_tile_id += 1;
+            room.tiles.push(Tile {
+                x: x.into(),
+                y: y.into(),
+                background_index: background_index.into(),
+                tile_x: tile_x.into(),
+                tile_y: tile_y.into(),
+                width: width.into(),
+                height: height.into(),
+                depth: depth.into(),
+                id: self.last_tile_id.into(),
+                alpha: alpha.into(),
+                blend: 0xffffff.into(),
+                xscale: xscale.into(),
+                yscale: yscale.into(),
+                visible: true.into(),
+            });
+            Ok(self.last_tile_id.into())
+        } else {
+            Ok(Default::default())
+        }
     }
 
-    pub fn room_tile_clear(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function room_tile_clear")
+    pub fn room_tile_clear(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let room_id = expect_args!(args, [int])?;
+        if let Some(room) = self.assets.rooms.get_asset_mut(room_id) {
+            room.tiles.clear();
+        }
+        if let Some(state) = self.persistent_rooms.get_mut(&room_id) {
+            state.tiles.clear();
+        }
+        Ok(Default::default())
     }
 
     pub fn part_type_create(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -11323,59 +12081,184 @@ impl Game {
         }
     }
 
-    pub fn ds_grid_add(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function ds_grid_add")
+    pub fn ds_grid_add(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, x, y, val) = expect_args!(args, [int, int, int, any])?;
+        match self.grids.get_mut(id) {
+            Some(grid) => {
+                if x >= 0 && y >= 0 && (x as usize) < grid.width() && (y as usize) < grid.height() {
+                    grid.add(x as usize, y as usize, &val);
+                }
+                Ok(Default::default())
+            },
+            None => Err(gml::Error::FunctionError("ds_grid_add".into(), ds::Error::NonexistentStructure(id).into())),
+        }
     }
 
-    pub fn ds_grid_multiply(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function ds_grid_multiply")
+    pub fn ds_grid_multiply(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, x, y, val) = expect_args!(args, [int, int, int, any])?;
+        match self.grids.get_mut(id) {
+            Some(grid) => {
+                if x >= 0 && y >= 0 && (x as usize) < grid.width() && (y as usize) < grid.height() {
+                    grid.multiply(x as usize, y as usize, &val);
+                }
+                Ok(Default::default())
+            },
+            None => {
+                Err(gml::Error::FunctionError("ds_grid_multiply".into(), ds::Error::NonexistentStructure(id).into()))
+            },
+        }
     }
 
-    pub fn ds_grid_set_region(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 6
-        unimplemented!("Called unimplemented kernel function ds_grid_set_region")
+    pub fn ds_grid_set_region(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, x1, y1, x2, y2, val) = expect_args!(args, [int, int, int, int, int, any])?;
+        match self.grids.get_mut(id) {
+            Some(grid) => {
+                grid.apply(ds::Area::Region(x1, y1, x2, y2), &val, ds::Grid::assign);
+                Ok(Default::default())
+            },
+            None => {
+                Err(gml::Error::FunctionError("ds_grid_set_region".into(), ds::Error::NonexistentStructure(id).into()))
+            },
+        }
     }
 
-    pub fn ds_grid_add_region(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 6
-        unimplemented!("Called unimplemented kernel function ds_grid_add_region")
+    pub fn ds_grid_add_region(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, x1, y1, x2, y2, val) = expect_args!(args, [int, int, int, int, int, any])?;
+        match self.grids.get_mut(id) {
+            Some(grid) => {
+                grid.apply(ds::Area::Region(x1, y1, x2, y2), &val, ds::Grid::add);
+                Ok(Default::default())
+            },
+            None => {
+                Err(gml::Error::FunctionError("ds_grid_add_region".into(), ds::Error::NonexistentStructure(id).into()))
+            },
+        }
     }
 
-    pub fn ds_grid_multiply_region(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 6
-        unimplemented!("Called unimplemented kernel function ds_grid_multiply_region")
+    pub fn ds_grid_multiply_region(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, x1, y1, x2, y2, val) = expect_args!(args, [int, int, int, int, int, any])?;
+        match self.grids.get_mut(id) {
+            Some(grid) => {
+                grid.apply(ds::Area::Region(x1, y1, x2, y2), &val, ds::Grid::multiply);
+                Ok(Default::default())
+            },
+            None => Err(gml::Error::FunctionError(
+                "ds_grid_multiply_region".into(),
+                ds::Error::NonexistentStructure(id).into(),
+            )),
+        }
     }
 
-    pub fn ds_grid_set_disk(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function ds_grid_set_disk")
+    pub fn ds_grid_set_disk(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, xm, ym, r, val) = expect_args!(args, [int, real, real, real, any])?;
+        match self.grids.get_mut(id) {
+            Some(grid) => {
+                grid.apply(ds::Area::Disk(xm, ym, r), &val, ds::Grid::assign);
+                Ok(Default::default())
+            },
+            None => {
+                Err(gml::Error::FunctionError("ds_grid_set_disk".into(), ds::Error::NonexistentStructure(id).into()))
+            },
+        }
     }
 
-    pub fn ds_grid_add_disk(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function ds_grid_add_disk")
+    pub fn ds_grid_add_disk(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, xm, ym, r, val) = expect_args!(args, [int, real, real, real, any])?;
+        match self.grids.get_mut(id) {
+            Some(grid) => {
+                grid.apply(ds::Area::Disk(xm, ym, r), &val, ds::Grid::add);
+                Ok(Default::default())
+            },
+            None => {
+                Err(gml::Error::FunctionError("ds_grid_add_disk".into(), ds::Error::NonexistentStructure(id).into()))
+            },
+        }
     }
 
-    pub fn ds_grid_multiply_disk(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function ds_grid_multiply_disk")
+    pub fn ds_grid_multiply_disk(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, xm, ym, r, val) = expect_args!(args, [int, real, real, real, any])?;
+        match self.grids.get_mut(id) {
+            Some(grid) => {
+                grid.apply(ds::Area::Disk(xm, ym, r), &val, ds::Grid::multiply);
+                Ok(Default::default())
+            },
+            None => Err(gml::Error::FunctionError(
+                "ds_grid_multiply_disk".into(),
+                ds::Error::NonexistentStructure(id).into(),
+            )),
+        }
     }
 
-    pub fn ds_grid_set_grid_region(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 8
-        unimplemented!("Called unimplemented kernel function ds_grid_set_grid_region")
+    pub fn ds_grid_set_grid_region(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, src_id, x1, y1, x2, y2, xpos, ypos) = expect_args!(args, [int, int, int, int, int, int, int, int])?;
+        // the source is read first, since it can be the same grid
+        let values = match self.grids.get(src_id) {
+            Some(src) => src.copy_region(x1, y1, x2, y2),
+            None => {
+                return Err(gml::Error::FunctionError(
+                    "ds_grid_set_grid_region".into(),
+                    ds::Error::NonexistentStructure(src_id).into(),
+                ))
+            },
+        };
+        match self.grids.get_mut(id) {
+            Some(grid) => {
+                grid.paste(values, xpos, ypos, ds::Grid::assign);
+                Ok(Default::default())
+            },
+            None => Err(gml::Error::FunctionError(
+                "ds_grid_set_grid_region".into(),
+                ds::Error::NonexistentStructure(id).into(),
+            )),
+        }
     }
 
-    pub fn ds_grid_add_grid_region(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 8
-        unimplemented!("Called unimplemented kernel function ds_grid_add_grid_region")
+    pub fn ds_grid_add_grid_region(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, src_id, x1, y1, x2, y2, xpos, ypos) = expect_args!(args, [int, int, int, int, int, int, int, int])?;
+        // the source is read first, since it can be the same grid
+        let values = match self.grids.get(src_id) {
+            Some(src) => src.copy_region(x1, y1, x2, y2),
+            None => {
+                return Err(gml::Error::FunctionError(
+                    "ds_grid_add_grid_region".into(),
+                    ds::Error::NonexistentStructure(src_id).into(),
+                ))
+            },
+        };
+        match self.grids.get_mut(id) {
+            Some(grid) => {
+                grid.paste(values, xpos, ypos, ds::Grid::add);
+                Ok(Default::default())
+            },
+            None => Err(gml::Error::FunctionError(
+                "ds_grid_add_grid_region".into(),
+                ds::Error::NonexistentStructure(id).into(),
+            )),
+        }
     }
 
-    pub fn ds_grid_multiply_grid_region(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 8
-        unimplemented!("Called unimplemented kernel function ds_grid_multiply_grid_region")
+    pub fn ds_grid_multiply_grid_region(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, src_id, x1, y1, x2, y2, xpos, ypos) = expect_args!(args, [int, int, int, int, int, int, int, int])?;
+        // the source is read first, since it can be the same grid
+        let values = match self.grids.get(src_id) {
+            Some(src) => src.copy_region(x1, y1, x2, y2),
+            None => {
+                return Err(gml::Error::FunctionError(
+                    "ds_grid_multiply_grid_region".into(),
+                    ds::Error::NonexistentStructure(src_id).into(),
+                ))
+            },
+        };
+        match self.grids.get_mut(id) {
+            Some(grid) => {
+                grid.paste(values, xpos, ypos, ds::Grid::multiply);
+                Ok(Default::default())
+            },
+            None => Err(gml::Error::FunctionError(
+                "ds_grid_multiply_grid_region".into(),
+                ds::Error::NonexistentStructure(id).into(),
+            )),
+        }
     }
 
     pub fn ds_grid_get(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -11392,79 +12275,184 @@ impl Game {
         }
     }
 
-    pub fn ds_grid_get_sum(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function ds_grid_get_sum")
+    pub fn ds_grid_get_sum(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, x1, y1, x2, y2) = expect_args!(args, [int, int, int, int, int])?;
+        match self.grids.get(id) {
+            Some(grid) => Ok(grid.aggregate(ds::Area::Region(x1, y1, x2, y2), ds::sum).into()),
+            None => {
+                Err(gml::Error::FunctionError("ds_grid_get_sum".into(), ds::Error::NonexistentStructure(id).into()))
+            },
+        }
     }
 
-    pub fn ds_grid_get_max(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function ds_grid_get_max")
+    pub fn ds_grid_get_max(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, x1, y1, x2, y2) = expect_args!(args, [int, int, int, int, int])?;
+        match self.grids.get(id) {
+            Some(grid) => Ok(grid.aggregate(ds::Area::Region(x1, y1, x2, y2), ds::max).into()),
+            None => {
+                Err(gml::Error::FunctionError("ds_grid_get_max".into(), ds::Error::NonexistentStructure(id).into()))
+            },
+        }
     }
 
-    pub fn ds_grid_get_min(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function ds_grid_get_min")
+    pub fn ds_grid_get_min(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, x1, y1, x2, y2) = expect_args!(args, [int, int, int, int, int])?;
+        match self.grids.get(id) {
+            Some(grid) => Ok(grid.aggregate(ds::Area::Region(x1, y1, x2, y2), ds::min).into()),
+            None => {
+                Err(gml::Error::FunctionError("ds_grid_get_min".into(), ds::Error::NonexistentStructure(id).into()))
+            },
+        }
     }
 
-    pub fn ds_grid_get_mean(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function ds_grid_get_mean")
+    pub fn ds_grid_get_mean(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, x1, y1, x2, y2) = expect_args!(args, [int, int, int, int, int])?;
+        match self.grids.get(id) {
+            Some(grid) => Ok(grid.aggregate(ds::Area::Region(x1, y1, x2, y2), ds::mean).into()),
+            None => {
+                Err(gml::Error::FunctionError("ds_grid_get_mean".into(), ds::Error::NonexistentStructure(id).into()))
+            },
+        }
     }
 
-    pub fn ds_grid_get_disk_sum(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function ds_grid_get_disk_sum")
+    pub fn ds_grid_get_disk_sum(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, xm, ym, r) = expect_args!(args, [int, real, real, real])?;
+        match self.grids.get(id) {
+            Some(grid) => Ok(grid.aggregate(ds::Area::Disk(xm, ym, r), ds::sum).into()),
+            None => Err(gml::Error::FunctionError(
+                "ds_grid_get_disk_sum".into(),
+                ds::Error::NonexistentStructure(id).into(),
+            )),
+        }
     }
 
-    pub fn ds_grid_get_disk_max(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function ds_grid_get_disk_max")
+    pub fn ds_grid_get_disk_max(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, xm, ym, r) = expect_args!(args, [int, real, real, real])?;
+        match self.grids.get(id) {
+            Some(grid) => Ok(grid.aggregate(ds::Area::Disk(xm, ym, r), ds::max).into()),
+            None => Err(gml::Error::FunctionError(
+                "ds_grid_get_disk_max".into(),
+                ds::Error::NonexistentStructure(id).into(),
+            )),
+        }
     }
 
-    pub fn ds_grid_get_disk_min(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function ds_grid_get_disk_min")
+    pub fn ds_grid_get_disk_min(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, xm, ym, r) = expect_args!(args, [int, real, real, real])?;
+        match self.grids.get(id) {
+            Some(grid) => Ok(grid.aggregate(ds::Area::Disk(xm, ym, r), ds::min).into()),
+            None => Err(gml::Error::FunctionError(
+                "ds_grid_get_disk_min".into(),
+                ds::Error::NonexistentStructure(id).into(),
+            )),
+        }
     }
 
-    pub fn ds_grid_get_disk_mean(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function ds_grid_get_disk_mean")
+    pub fn ds_grid_get_disk_mean(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, xm, ym, r) = expect_args!(args, [int, real, real, real])?;
+        match self.grids.get(id) {
+            Some(grid) => Ok(grid.aggregate(ds::Area::Disk(xm, ym, r), ds::mean).into()),
+            None => Err(gml::Error::FunctionError(
+                "ds_grid_get_disk_mean".into(),
+                ds::Error::NonexistentStructure(id).into(),
+            )),
+        }
     }
 
-    pub fn ds_grid_value_exists(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 6
-        unimplemented!("Called unimplemented kernel function ds_grid_value_exists")
+    pub fn ds_grid_value_exists(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, x1, y1, x2, y2, val) = expect_args!(args, [int, int, int, int, int, any])?;
+        match self.grids.get(id) {
+            Some(grid) => Ok(grid.find(ds::Area::Region(x1, y1, x2, y2), &val, self.ds_precision).is_some().into()),
+            None => Err(gml::Error::FunctionError(
+                "ds_grid_value_exists".into(),
+                ds::Error::NonexistentStructure(id).into(),
+            )),
+        }
     }
 
-    pub fn ds_grid_value_x(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 6
-        unimplemented!("Called unimplemented kernel function ds_grid_value_x")
+    pub fn ds_grid_value_x(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, x1, y1, x2, y2, val) = expect_args!(args, [int, int, int, int, int, any])?;
+        match self.grids.get(id) {
+            Some(grid) => Ok(grid
+                .find(ds::Area::Region(x1, y1, x2, y2), &val, self.ds_precision)
+                .map_or(-1, |(x, _)| x as i32)
+                .into()),
+            None => {
+                Err(gml::Error::FunctionError("ds_grid_value_x".into(), ds::Error::NonexistentStructure(id).into()))
+            },
+        }
     }
 
-    pub fn ds_grid_value_y(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 6
-        unimplemented!("Called unimplemented kernel function ds_grid_value_y")
+    pub fn ds_grid_value_y(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, x1, y1, x2, y2, val) = expect_args!(args, [int, int, int, int, int, any])?;
+        match self.grids.get(id) {
+            Some(grid) => Ok(grid
+                .find(ds::Area::Region(x1, y1, x2, y2), &val, self.ds_precision)
+                .map_or(-1, |(_, y)| y as i32)
+                .into()),
+            None => {
+                Err(gml::Error::FunctionError("ds_grid_value_y".into(), ds::Error::NonexistentStructure(id).into()))
+            },
+        }
     }
 
-    pub fn ds_grid_value_disk_exists(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function ds_grid_value_disk_exists")
+    pub fn ds_grid_value_disk_exists(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, xm, ym, r, val) = expect_args!(args, [int, real, real, real, any])?;
+        match self.grids.get(id) {
+            Some(grid) => Ok(grid.find(ds::Area::Disk(xm, ym, r), &val, self.ds_precision).is_some().into()),
+            None => Err(gml::Error::FunctionError(
+                "ds_grid_value_disk_exists".into(),
+                ds::Error::NonexistentStructure(id).into(),
+            )),
+        }
     }
 
-    pub fn ds_grid_value_disk_x(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function ds_grid_value_disk_x")
+    pub fn ds_grid_value_disk_x(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, xm, ym, r, val) = expect_args!(args, [int, real, real, real, any])?;
+        match self.grids.get(id) {
+            Some(grid) => {
+                Ok(grid.find(ds::Area::Disk(xm, ym, r), &val, self.ds_precision).map_or(-1, |(x, _)| x as i32).into())
+            },
+            None => Err(gml::Error::FunctionError(
+                "ds_grid_value_disk_x".into(),
+                ds::Error::NonexistentStructure(id).into(),
+            )),
+        }
     }
 
-    pub fn ds_grid_value_disk_y(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function ds_grid_value_disk_y")
+    pub fn ds_grid_value_disk_y(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, xm, ym, r, val) = expect_args!(args, [int, real, real, real, any])?;
+        match self.grids.get(id) {
+            Some(grid) => {
+                Ok(grid.find(ds::Area::Disk(xm, ym, r), &val, self.ds_precision).map_or(-1, |(_, y)| y as i32).into())
+            },
+            None => Err(gml::Error::FunctionError(
+                "ds_grid_value_disk_y".into(),
+                ds::Error::NonexistentStructure(id).into(),
+            )),
+        }
     }
 
-    pub fn ds_grid_shuffle(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function ds_grid_shuffle")
+    pub fn ds_grid_shuffle(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        match self.grids.get_mut(id) {
+            Some(grid) => {
+                let (width, height) = (grid.width(), grid.height());
+                let count = width * height;
+                for _ in 1..count {
+                    let id1 = self.rand.next_int(count as u32 - 1) as usize;
+                    let id2 = self.rand.next_int(count as u32 - 1) as usize;
+                    let val1 = grid.get(id1 % width, id1 / width).clone();
+                    let val2 = grid.get(id2 % width, id2 / width).clone();
+                    grid.set(id1 % width, id1 / width, val2);
+                    grid.set(id2 % width, id2 / width, val1);
+                }
+                Ok(Default::default())
+            },
+            None => {
+                Err(gml::Error::FunctionError("ds_grid_shuffle".into(), ds::Error::NonexistentStructure(id).into()))
+            },
+        }
     }
 
     pub fn ds_grid_write(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -11521,57 +12509,55 @@ impl Game {
         }
     }
 
-    pub fn sound_play(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function sound_play")
-        // TODO
+    pub fn sound_play(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let sound_id = expect_args!(args, [int])?;
+        self.audio.play(&self.assets.sounds, sound_id, false);
         Ok(Default::default())
     }
 
-    pub fn sound_loop(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function sound_loop")
-        // TODO
+    pub fn sound_loop(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let sound_id = expect_args!(args, [int])?;
+        self.audio.play(&self.assets.sounds, sound_id, true);
         Ok(Default::default())
     }
 
-    pub fn sound_stop(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function sound_stop")
-        // TODO
+    pub fn sound_stop(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let sound_id = expect_args!(args, [int])?;
+        self.audio.stop(sound_id);
         Ok(Default::default())
     }
 
-    pub fn sound_stop_all(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        //unimplemented!("Called unimplemented kernel function sound_stop_all")
-        // TODO
+    pub fn sound_stop_all(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        self.audio.stop_all();
         Ok(Default::default())
     }
 
-    pub fn sound_isplaying(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function sound_isplaying")
-        // TODO
-        Ok(Default::default())
+    pub fn sound_isplaying(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let sound_id = expect_args!(args, [int])?;
+        Ok(self.audio.is_playing(sound_id).into())
     }
 
-    pub fn sound_volume(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        //unimplemented!("Called unimplemented kernel function sound_volume")
-        // TODO
+    pub fn sound_volume(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, volume) = expect_args!(args, [int, real])?;
+        if let Some(sound) = self.assets.sounds.get_asset_mut(sound_id) {
+            sound.volume = volume.max(Real::from(0.0)).min(Real::from(1.0));
+        }
         Ok(Default::default())
     }
 
-    pub fn sound_fade(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        //unimplemented!("Called unimplemented kernel function sound_fade")
+    pub fn sound_fade(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, volume, time) = expect_args!(args, [int, real, int])?;
+        self.audio.fade(sound_id, volume.max(Real::from(0.0)).min(Real::from(1.0)), time.max(0) as u32);
         Ok(Default::default())
     }
 
-    pub fn sound_pan(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function sound_pan")
+    pub fn sound_pan(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, pan) = expect_args!(args, [int, real])?;
+        if let Some(sound) = self.assets.sounds.get_asset_mut(sound_id) {
+            sound.pan = pan.max(Real::from(-1.0)).min(Real::from(1.0));
+        }
+        Ok(Default::default())
     }
 
     pub fn sound_background_tempo(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
@@ -11580,10 +12566,9 @@ impl Game {
         Ok(Default::default())
     }
 
-    pub fn sound_global_volume(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function sound_global_volume")
-        // TODO
+    pub fn sound_global_volume(&mut self, _context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let volume = expect_args!(args, [real])?;
+        self.audio.set_global_volume(volume.max(Real::from(0.0)).min(Real::from(1.0)));
         Ok(Default::default())
     }
 
@@ -159,6 +159,7 @@ pub enum Error {
     FunctionError(String, String),
     ReplayError(String),
     BadDirectoryError(String),
+    CompileError(String),
 }
 
 impl std::error::Error for Error {}
@@ -203,6 +204,7 @@ impl Display for Error {
             Self::FunctionError(fname, s) => write!(f, "{}: {}", fname, s),
             Self::ReplayError(s) => write!(f, "{}", s),
             Self::BadDirectoryError(s) => write!(f, "cannot encode working directory {} with current encoding", s),
+            Self::CompileError(s) => write!(f, "compile error: {}", s),
         }
     }
 }
@@ -1002,7 +1004,9 @@ impl Game {
             InstanceVariable::RoomHeight => Ok(self.room_height.into()),
             InstanceVariable::RoomCaption => Ok(self.caption.clone().into()),
             InstanceVariable::RoomSpeed => Ok(self.room_speed.into()),
-            InstanceVariable::RoomPersistent => todo!("room_persistent getter"),
+            InstanceVariable::RoomPersistent => {
+                Ok(self.assets.rooms.get_asset(self.room_id).map(|r| r.persistent).unwrap_or_default().into())
+            },
             InstanceVariable::BackgroundColor => Ok(self.room_colour.as_decimal().into()),
             InstanceVariable::BackgroundShowcolor => Ok(self.show_room_colour.into()),
             InstanceVariable::BackgroundVisible => {
@@ -1333,7 +1337,11 @@ impl Game {
                 }
                 self.room_speed = speed as _
             },
-            InstanceVariable::RoomPersistent => todo!("room_persistent setter"),
+            InstanceVariable::RoomPersistent => {
+                if let Some(room) = self.assets.rooms.get_asset_mut(self.room_id) {
+                    room.persistent = value.is_truthy();
+                }
+            },
             InstanceVariable::BackgroundColor => self.room_colour = (value.round() as u32).into(),
             InstanceVariable::BackgroundShowcolor => self.show_room_colour = value.is_truthy(),
             InstanceVariable::BackgroundVisible => match self.backgrounds.get_mut(array_index as usize) {
@@ -1574,3 +1582,88 @@ impl Game {
         }
     }
 }
+
+/// The run tier of the GML conformance corpus: snippets are compiled and run on an empty game, and what they
+/// return is checked against what GM8 gives. The parse tier is in gml-parser's tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    fn run(source: &str) -> Value {
+        Game::for_tests().run_for_tests(source).unwrap()
+    }
+
+    fn real(source: &str) -> f64 {
+        match run(source) {
+            Value::Real(r) => r.into(),
+            v => panic!("{:?} returned {}, not a real", source, v),
+        }
+    }
+
+    fn string(source: &str) -> String {
+        match run(source) {
+            Value::Str(s) => String::from_utf8_lossy(s.as_ref()).into_owned(),
+            v => panic!("{:?} returned {}, not a string", source, v),
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(real("return 1 + 2 * 3"), 7.0);
+        assert_eq!(real("return 7 / 2"), 3.5);
+        assert_eq!(real("return 7 div 2"), 3.0);
+        assert_eq!(real("return -7 div 2"), -4.0);
+        assert_eq!(real("return 7 mod 3"), 1.0);
+        assert_eq!(real("return -7 mod 3"), -1.0);
+        assert_eq!(real("return $FF"), 255.0);
+        // Bitwise operators all share a precedence level.
+        assert_eq!(real("return 5 | 2 & 3 ^ 1"), 2.0);
+        assert_eq!(real("return 1 << 4 >> 2"), 4.0);
+    }
+
+    #[test]
+    fn comparison() {
+        assert_eq!(real("a = 1 return a = 1"), 1.0);
+        assert_eq!(real("a = 1 b = a = 2 return b"), 0.0);
+        assert_eq!(real("return 1 <> 2"), 1.0);
+        assert_eq!(real("return 0.1 + 0.2 == 0.3"), 1.0);
+        assert_eq!(real("return \"a\" < \"b\""), 1.0);
+    }
+
+    #[test]
+    fn truthiness() {
+        assert_eq!(real("if 0.5 return 1 return 0"), 1.0);
+        assert_eq!(real("if 0.4 return 1 return 0"), 0.0);
+        assert_eq!(real("if -1 return 1 return 0"), 0.0);
+        assert_eq!(real("return not 0.5"), 0.0);
+        assert_eq!(real("return 1 xor 1"), 0.0);
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(string("return \"ab\" + 'cd'"), "abcd");
+        assert_eq!(string("return string(1.5)"), "1.50");
+        assert_eq!(string("return string(3)"), "3");
+        assert_eq!(real("return string_length(\"hello\")"), 5.0);
+    }
+
+    #[test]
+    fn control_flow() {
+        assert_eq!(real("x = 0 repeat 3.6 x += 1 return x"), 4.0);
+        assert_eq!(real("x = 0 for (i = 0; i < 10; i += 1) { if i == 5 break; x += i } return x"), 10.0);
+        assert_eq!(real("x = 0 do x += 1 until x >= 3 return x"), 3.0);
+        assert_eq!(real("x = 0 switch 2 { case 1: x += 1 case 2: x += 2 case 3: x += 3 } return x"), 5.0);
+        assert_eq!(real("x = 0 switch 4 { case 1: x = 1 break; default: x = 9 } return x"), 9.0);
+        assert_eq!(real("with (noone) return 1 return 2"), 2.0);
+    }
+
+    #[test]
+    fn variables() {
+        assert_eq!(real("var a; a = 3; return a"), 3.0);
+        assert_eq!(real("globalvar g; g = 4; return global.g"), 4.0);
+        assert_eq!(real("a[2] = 5 return a[2] + a"), 5.0);
+        assert_eq!(real("a[1, 1] = 6 return a[1, 1]"), 6.0);
+        assert!(Game::for_tests().run_for_tests("return undefined_var").is_err());
+    }
+}
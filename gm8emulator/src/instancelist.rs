@@ -402,10 +402,35 @@ impl InstanceList {
             let chunks = &self.chunks;
             self.draw_order.retain(|idx| chunks.get(*idx).is_some());
             self.insert_order.retain(|idx| chunks.get(*idx).is_some());
-            for instances in self.object_id_map.values_mut() {
+            for instances in self.object_id_map.values_mut().chain(self.inactive_id_map.values_mut()) {
                 instances.retain(|idx| chunks.get(*idx).is_some());
             }
             self.object_id_map.retain(|_, list| !list.is_empty());
+            self.inactive_id_map.retain(|_, list| !list.is_empty());
+        }
+    }
+
+    /// Clones every instance which matches the predicate and hasn't been deleted, in insertion order.
+    pub fn clone_with(&self, f: impl Fn(&Instance) -> bool) -> Vec<Instance> {
+        self.insert_order
+            .iter()
+            .map(|&idx| self.get(idx))
+            .filter(|instance| instance.state.get() != InstanceState::Deleted && f(instance))
+            .cloned()
+            .collect()
+    }
+
+    /// Moves everything that was in the list before the last `newer` instances were inserted to the back,
+    /// keeping their order, so the newer ones come first in every ordering.
+    pub fn move_older_to_back(&mut self, newer: usize) {
+        let older = self.insert_order.len().saturating_sub(newer);
+        self.insert_order.rotate_left(older);
+        let moved = &self.insert_order[self.insert_order.len() - older..];
+        self.draw_order.retain(|idx| !moved.contains(idx));
+        self.draw_order.extend_from_slice(moved);
+        let positions: HashMap<usize, usize> = self.insert_order.iter().enumerate().map(|(i, &idx)| (idx, i)).collect();
+        for instances in self.object_id_map.values_mut().chain(self.inactive_id_map.values_mut()) {
+            instances.sort_by_key(|idx| positions.get(idx).copied());
         }
     }
 }
@@ -607,3 +632,70 @@ fn defrag_map(map: &HashMap<i32, Vec<usize>>, all_insts: &[usize]) -> HashMap<i3
 }
 
 // TODO: Maybe preallocating order/draw_order would increase perf - test this!
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(id: ID, object_index: ID) -> Instance {
+        let instance = Instance::new_dummy(None);
+        instance.id.set(id);
+        instance.object_index.set(object_index);
+        instance
+    }
+
+    fn ids(list: &InstanceList, mut iter: impl FnMut(&InstanceList) -> Option<usize>) -> Vec<ID> {
+        let mut ids = Vec::new();
+        while let Some(idx) = iter(list) {
+            ids.push(list.get(idx).id.get());
+        }
+        ids
+    }
+
+    #[test]
+    fn clone_with() {
+        let mut list = InstanceList::new();
+        for id in 1..=4 {
+            list.insert(instance(id, 0));
+        }
+        list.get(list.get_by_instid(2).unwrap()).persistent.set(true);
+        list.mark_deleted(list.get_by_instid(3).unwrap());
+
+        let clones = list.clone_with(|instance| !instance.persistent.get());
+        assert_eq!(clones.iter().map(|instance| instance.id.get()).collect::<Vec<_>>(), [1, 4]);
+        // the list itself is left alone
+        assert_eq!(list.count_all(), 4);
+    }
+
+    #[test]
+    fn move_older_to_back() {
+        let mut list = InstanceList::new();
+        list.insert(instance(1, 0));
+        list.insert(instance(2, 1));
+        list.insert(instance(3, 0));
+        list.insert(instance(4, 1));
+        list.insert(instance(5, 0));
+        list.move_older_to_back(2);
+
+        let mut iter = list.iter_by_insertion();
+        assert_eq!(ids(&list, |list| iter.next(list)), [4, 5, 1, 2, 3]);
+        let mut iter = list.iter_by_drawing();
+        assert_eq!(ids(&list, |list| iter.next(list)), [4, 5, 1, 2, 3]);
+        let mut iter = list.iter_by_object(0);
+        assert_eq!(ids(&list, |list| iter.next(list)), [5, 1, 3]);
+        let mut iter = list.iter_by_object(1);
+        assert_eq!(ids(&list, |list| iter.next(list)), [4, 2]);
+        assert_eq!(list.instance_at(0), 4);
+    }
+
+    #[test]
+    fn move_older_to_back_all_newer() {
+        let mut list = InstanceList::new();
+        list.insert(instance(1, 0));
+        list.insert(instance(2, 0));
+        list.move_older_to_back(5);
+
+        let mut iter = list.iter_by_insertion();
+        assert_eq!(ids(&list, |list| iter.next(list)), [1, 2]);
+    }
+}
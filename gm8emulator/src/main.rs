@@ -13,6 +13,7 @@ mod math;
 mod tile;
 mod util;
 
+use gmio::{audio::AudioOutput, render::Backend};
 use std::{
     env, fs,
     io::{BufReader, Write},
@@ -47,6 +48,14 @@ fn xmain() -> i32 {
     opts.optflag("t", "singlethread", "parse gamedata synchronously");
     opts.optflag("v", "verbose", "enables verbose logging");
     opts.optflag("r", "realtime", "disables clock spoofing");
+    opts.optflag("c", "console", "reads GML from stdin and runs it between frames (not while recording or replaying)");
+    opts.optflag("", "strict-gml", "refuses to start if any game code fails to compile");
+    opts.optflag("", "no-code-cache", "always recompiles game code instead of using the cache");
+    opts.optflag("", "headless", "runs without a window, rendering offscreen");
+    opts.optflag("", "software", "renders on the CPU for identical output on every machine (needs --headless)");
+    opts.optopt("", "dump-frames", "saves every frame to DIR as PNGs, with timing in frames.txt", "DIR");
+    opts.optopt("", "dump-video", "saves every frame as uncompressed AVI video", "FILE");
+    opts.optopt("", "audio-file", "writes game audio to a WAV file, which dumps do by default", "FILE");
     opts.optopt("p", "port", "port to open for external game control (default 15560)", "PORT");
     opts.optopt("n", "project-name", "name of TAS project to create or load", "NAME");
     opts.optopt("f", "replay-file", "path to savestate file to replay", "FILE");
@@ -76,6 +85,19 @@ fn xmain() -> i32 {
     let multithread = !matches.opt_present("t");
     let spoof_time = !matches.opt_present("r");
     let verbose = matches.opt_present("v");
+    let console = matches.opt_present("c");
+    let strict_gml = matches.opt_present("strict-gml");
+    let use_code_cache = !matches.opt_present("no-code-cache");
+    let headless = matches.opt_present("headless");
+    let software = matches.opt_present("software");
+    let dump_frames = matches.opt_str("dump-frames").map(PathBuf::from);
+    let dump_video = matches.opt_str("dump-video").map(PathBuf::from);
+    // sound goes with the frames it was dumped alongside, unless it's wanted somewhere else
+    let audio_file = matches.opt_str("audio-file").map(PathBuf::from).or_else(|| match (&dump_video, &dump_frames) {
+        (Some(video), _) => Some(video.with_extension("wav")),
+        (None, Some(dir)) => Some(dir.join("audio.wav")),
+        (None, None) => None,
+    });
     let port = match matches.opt_str("p").map(|x| x.parse::<u16>()).transpose() {
         Ok(p) => p,
         Err(e) => {
@@ -114,6 +136,18 @@ fn xmain() -> i32 {
                 path
             })
     });
+    if headless && project_path.is_some() {
+        eprintln!("--headless can't be used with -n, as recording needs a window");
+        return EXIT_FAILURE
+    }
+    if software && !headless {
+        eprintln!("--software can only be used with --headless, as it can't draw to a window");
+        return EXIT_FAILURE
+    }
+    if (dump_frames.is_some() || dump_video.is_some()) && project_path.is_some() {
+        eprintln!("--dump-frames and --dump-video can't be used with -n, use -f to replay the recording instead");
+        return EXIT_FAILURE
+    }
     let can_clear_temp_dir = temp_dir.is_none();
     let replay = matches.opt_str("f").map(|filename| {
         let mut filepath = PathBuf::from(&filename);
@@ -166,6 +200,15 @@ fn xmain() -> i32 {
         println!("loading '{}'...", input);
     }
 
+    // this has to be done before reading, as that modifies the exe data
+    let code_cache = if use_code_cache {
+        let mut cache_dir = env::current_dir().expect("std::env::current_dir() failed");
+        cache_dir.push("cache");
+        game::CodeCache::new(cache_dir, &file)
+    } else {
+        None
+    };
+
     #[rustfmt::skip]
     let assets = gm8exe::reader::from_exe(
         &mut file,                              // mut exe: AsRef<[u8]>
@@ -212,17 +255,78 @@ fn xmain() -> i32 {
         game::PlayType::Normal
     };
 
-    let mut components =
-        match game::Game::launch(assets, absolute_path, time_nanos, game_args, temp_dir, encoding, play_type) {
-            Ok(g) => g,
+    // TASes start from the project's registry file, if it has one, and never write to it,
+    // so they don't depend on what was saved by earlier runs
+    let registry = if let Some(path) = &project_path {
+        game::registry::Registry::load_read_only(&path.join(game::registry::PROJECT_FILE))
+    } else if let Some(replay) = &replay {
+        // replays carry the registry they were recorded with
+        replay.start_registry.clone()
+    } else {
+        game::registry::Registry::load(absolute_path.with_extension("registry"))
+    };
+    let start_registry = registry.clone();
+
+    let mut components = match game::Game::launch(
+        assets,
+        absolute_path,
+        time_nanos,
+        game_args,
+        temp_dir,
+        encoding,
+        play_type,
+        registry,
+        strict_gml,
+        code_cache,
+        headless,
+        if software { Backend::Software } else { Backend::OpenGL },
+    ) {
+        Ok(g) => g,
+        Err(e) => {
+            eprintln!("Failed to launch game: {}", e);
+            return EXIT_FAILURE
+        },
+    };
+
+    if console {
+        // console input isn't recorded, so it would make TASes desync
+        if components.play_type == game::PlayType::Normal {
+            components.console = Some(game::console::Console::new());
+        } else {
+            eprintln!("Warning: the console can't be used while recording or replaying, ignoring --console");
+        }
+    }
+
+    if dump_frames.is_some() || dump_video.is_some() {
+        match game::framedump::FrameDump::new(dump_frames, dump_video) {
+            Ok(frame_dump) => components.frame_dump = Some(frame_dump),
             Err(e) => {
-                eprintln!("Failed to launch game: {}", e);
+                eprintln!("Failed to set up frame dumping: {}", e);
                 return EXIT_FAILURE
             },
-        };
+        }
+    }
+
+    components.audio_output = if let Some(path) = audio_file {
+        match AudioOutput::wav_file(&path) {
+            Ok(output) => output,
+            Err(e) => {
+                eprintln!("Failed to create audio file {}: {}", path.display(), e);
+                return EXIT_FAILURE
+            },
+        }
+    } else if headless || project_path.is_some() {
+        // headless runs shouldn't need a sound card, and recording rewinds all the time
+        AudioOutput::null()
+    } else {
+        AudioOutput::device().unwrap_or_else(|e| {
+            eprintln!("Warning: {}, so there won't be any sound", e);
+            AudioOutput::null()
+        })
+    };
 
     if let Err(err) = if let Some(path) = project_path {
-        components.record(path, port)
+        components.record(path, port, start_registry)
     } else {
         // cache temp_dir and included files because the other functions take ownership
         let temp_dir: Option<PathBuf> = if can_clear_temp_dir {
@@ -70,6 +70,19 @@ fn main() -> Result<(), Box<dyn Error>> {
             "GLX_ARB_create_context",
             "GLX_ARB_create_context_profile",
             "GLX_EXT_swap_control",
+            "GLX_MESA_swap_control",
+            "GLX_SGI_swap_control",
+            "GLX_SGI_video_sync",
+        ])
+        .write_bindings(StructGenerator, &mut file)?;
+    }
+
+    // EGL Bindings, used for offscreen rendering
+    if cfg!(target_os = "linux") {
+        let mut file = File::create(&Path::new(&out).join("egl_bindings.rs"))?;
+        Registry::new(Api::Egl, (1, 5), Profile::Core, Fallbacks::All, [
+            "EGL_EXT_platform_base",
+            "EGL_MESA_platform_surfaceless",
         ])
         .write_bindings(StructGenerator, &mut file)?;
     }
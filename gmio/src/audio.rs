@@ -0,0 +1,126 @@
+//! Sound output.
+//!
+//! Mixing is up to the user, this only takes 16-bit stereo samples at `SAMPLE_RATE` and sends them somewhere:
+//! to the sound card, to a WAV file, or nowhere at all.
+
+mod alsa;
+mod winmm;
+
+use cfg_if::cfg_if;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+cfg_if! {
+    if #[cfg(target_os = "windows")] {
+        use winmm as platform;
+    } else if #[cfg(target_os = "linux")] {
+        use alsa as platform;
+    }
+}
+
+/// Samples per second of every output.
+pub const SAMPLE_RATE: u32 = 44100;
+
+/// One sample for each of the left and right channels.
+pub type Frame = [i16; 2];
+
+pub struct AudioOutput(Box<dyn OutputTrait>);
+
+pub trait OutputTrait {
+    /// Queues some samples to be played after the ones which were already written.
+    fn write(&mut self, frames: &[Frame]) -> io::Result<()>;
+}
+
+impl AudioOutput {
+    /// Plays sound through the default audio device.
+    pub fn device() -> Result<Self, String> {
+        Ok(Self(Box::new(platform::OutputImpl::new()?)))
+    }
+
+    /// Throws all sound away.
+    pub fn null() -> Self {
+        Self(Box::new(NullOutput))
+    }
+
+    /// Writes all sound to a WAV file, which is finished when the output is dropped.
+    pub fn wav_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self(Box::new(WavOutput::create(path.as_ref())?)))
+    }
+
+    pub fn write(&mut self, frames: &[Frame]) -> io::Result<()> {
+        self.0.write(frames)
+    }
+}
+
+impl Default for AudioOutput {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+struct NullOutput;
+
+impl OutputTrait for NullOutput {
+    fn write(&mut self, _frames: &[Frame]) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Position of the RIFF chunk's size, and then the data chunk's, which are filled in when the file is finished.
+const RIFF_SIZE_POS: u64 = 4;
+const DATA_SIZE_POS: u64 = 40;
+
+struct WavOutput {
+    file: BufWriter<File>,
+    data_size: u32,
+}
+
+impl WavOutput {
+    fn create(path: &Path) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let block_align = 2 * 2;
+        file.write_all(b"RIFF\0\0\0\0WAVEfmt ")?;
+        file.write_all(&16u32.to_le_bytes())?;
+        file.write_all(&1u16.to_le_bytes())?; // PCM
+        file.write_all(&2u16.to_le_bytes())?; // channels
+        file.write_all(&SAMPLE_RATE.to_le_bytes())?;
+        file.write_all(&(SAMPLE_RATE * block_align).to_le_bytes())?;
+        file.write_all(&(block_align as u16).to_le_bytes())?;
+        file.write_all(&16u16.to_le_bytes())?; // bits per sample
+        file.write_all(b"data\0\0\0\0")?;
+        Ok(Self { file, data_size: 0 })
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(RIFF_SIZE_POS))?;
+        self.file.write_all(&(DATA_SIZE_POS as u32 - 4 + self.data_size).to_le_bytes())?;
+        self.file.seek(SeekFrom::Start(DATA_SIZE_POS))?;
+        self.file.write_all(&self.data_size.to_le_bytes())?;
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.flush()
+    }
+}
+
+impl OutputTrait for WavOutput {
+    fn write(&mut self, frames: &[Frame]) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(frames.len() * 4);
+        for [left, right] in frames {
+            bytes.extend_from_slice(&left.to_le_bytes());
+            bytes.extend_from_slice(&right.to_le_bytes());
+        }
+        self.file.write_all(&bytes)?;
+        self.data_size += bytes.len() as u32;
+        Ok(())
+    }
+}
+
+impl Drop for WavOutput {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            eprintln!("Failed to finish writing WAV file: {}", e);
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Linux sound output through ALSA.
+//! libasound is loaded at runtime, so it isn't needed to build or to run without sound.
+
+#![cfg(target_os = "linux")]
+
+use super::{Frame, OutputTrait, SAMPLE_RATE};
+use std::{
+    ffi::CStr,
+    io, mem,
+    os::raw::{c_char, c_int, c_long, c_uint, c_ulong, c_void},
+    ptr,
+};
+
+// from pcm.h
+const SND_PCM_STREAM_PLAYBACK: c_int = 0;
+const SND_PCM_NONBLOCK: c_int = 1;
+const SND_PCM_FORMAT_S16_LE: c_int = 2;
+const SND_PCM_ACCESS_RW_INTERLEAVED: c_int = 3;
+
+/// How far ahead of the game the device is kept, so that it has something to play while the next frame runs.
+const LATENCY_US: c_uint = 100_000;
+const LEAD_FRAMES: usize = (SAMPLE_RATE / 20) as usize;
+
+#[allow(non_camel_case_types)]
+type snd_pcm_t = c_void;
+
+type PcmOpen = unsafe extern "C" fn(*mut *mut snd_pcm_t, *const c_char, c_int, c_int) -> c_int;
+type PcmSetParams = unsafe extern "C" fn(*mut snd_pcm_t, c_int, c_int, c_uint, c_uint, c_int, c_uint) -> c_int;
+type PcmWritei = unsafe extern "C" fn(*mut snd_pcm_t, *const c_void, c_ulong) -> c_long;
+type PcmRecover = unsafe extern "C" fn(*mut snd_pcm_t, c_int, c_int) -> c_int;
+type PcmClose = unsafe extern "C" fn(*mut snd_pcm_t) -> c_int;
+type StrError = unsafe extern "C" fn(c_int) -> *const c_char;
+
+struct Alsa {
+    pcm_open: PcmOpen,
+    pcm_set_params: PcmSetParams,
+    pcm_writei: PcmWritei,
+    pcm_recover: PcmRecover,
+    pcm_close: PcmClose,
+    strerror: StrError,
+}
+
+unsafe fn open_libasound() -> Result<*mut c_void, String> {
+    for name in &[&b"libasound.so.2\0"[..], &b"libasound.so\0"[..]] {
+        let handle = libc::dlopen(name.as_ptr().cast(), libc::RTLD_NOW | libc::RTLD_LOCAL);
+        if !handle.is_null() {
+            return Ok(handle)
+        }
+    }
+    Err(format!("Could not load libasound: {}", CStr::from_ptr(libc::dlerror()).to_string_lossy()))
+}
+
+impl Alsa {
+    unsafe fn load() -> Result<Self, String> {
+        let lib = open_libasound()?;
+        let load = |name: &[u8]| {
+            let addr = libc::dlsym(lib, name.as_ptr().cast());
+            if addr.is_null() {
+                Err(format!("libasound doesn't export {}", String::from_utf8_lossy(&name[..name.len() - 1])))
+            } else {
+                Ok(addr)
+            }
+        };
+        Ok(Self {
+            pcm_open: mem::transmute::<*mut c_void, PcmOpen>(load(b"snd_pcm_open\0")?),
+            pcm_set_params: mem::transmute::<*mut c_void, PcmSetParams>(load(b"snd_pcm_set_params\0")?),
+            pcm_writei: mem::transmute::<*mut c_void, PcmWritei>(load(b"snd_pcm_writei\0")?),
+            pcm_recover: mem::transmute::<*mut c_void, PcmRecover>(load(b"snd_pcm_recover\0")?),
+            pcm_close: mem::transmute::<*mut c_void, PcmClose>(load(b"snd_pcm_close\0")?),
+            strerror: mem::transmute::<*mut c_void, StrError>(load(b"snd_strerror\0")?),
+        })
+    }
+
+    unsafe fn error_string(&self, err: c_int) -> String {
+        CStr::from_ptr((self.strerror)(err)).to_string_lossy().into_owned()
+    }
+}
+
+pub struct OutputImpl {
+    alsa: Alsa,
+    pcm: *mut snd_pcm_t,
+}
+
+impl OutputImpl {
+    pub fn new() -> Result<Self, String> {
+        unsafe {
+            let alsa = Alsa::load()?;
+            let mut pcm = ptr::null_mut();
+            // non-blocking, since the game shouldn't ever wait for the sound card
+            let err =
+                (alsa.pcm_open)(&mut pcm, b"default\0".as_ptr().cast(), SND_PCM_STREAM_PLAYBACK, SND_PCM_NONBLOCK);
+            if err < 0 {
+                return Err(format!("Could not open the audio device: {}", alsa.error_string(err)))
+            }
+            let err = (alsa.pcm_set_params)(
+                pcm,
+                SND_PCM_FORMAT_S16_LE,
+                SND_PCM_ACCESS_RW_INTERLEAVED,
+                2,
+                SAMPLE_RATE,
+                1,
+                LATENCY_US,
+            );
+            if err < 0 {
+                (alsa.pcm_close)(pcm);
+                return Err(format!("Could not set up the audio device: {}", alsa.error_string(err)))
+            }
+            let mut output = Self { alsa, pcm };
+            output.write_silence();
+            Ok(output)
+        }
+    }
+
+    fn write_silence(&mut self) {
+        let silence = [[0; 2]; LEAD_FRAMES];
+        unsafe {
+            (self.alsa.pcm_writei)(self.pcm, silence.as_ptr().cast(), silence.len() as c_ulong);
+        }
+    }
+}
+
+impl OutputTrait for OutputImpl {
+    fn write(&mut self, mut frames: &[Frame]) -> io::Result<()> {
+        while !frames.is_empty() {
+            let written = unsafe { (self.alsa.pcm_writei)(self.pcm, frames.as_ptr().cast(), frames.len() as c_ulong) };
+            if written >= 0 {
+                frames = &frames[written as usize..];
+            } else if written == -(libc::EAGAIN as c_long) {
+                // the buffer's full, so the device is behind and this can be skipped
+                break
+            } else {
+                // most likely an underrun, which needs the device restarting
+                if unsafe { (self.alsa.pcm_recover)(self.pcm, written as c_int, 1) } < 0 {
+                    break
+                }
+                self.write_silence();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for OutputImpl {
+    fn drop(&mut self) {
+        unsafe {
+            (self.alsa.pcm_close)(self.pcm);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Windows sound output through the waveOut API.
+
+#![cfg(target_os = "windows")]
+
+use super::{Frame, OutputTrait, SAMPLE_RATE};
+use std::{collections::VecDeque, io, mem, ptr};
+use winapi::{
+    shared::mmreg::{WAVEFORMATEX, WAVE_FORMAT_PCM},
+    um::{
+        mmeapi::{waveOutClose, waveOutOpen, waveOutPrepareHeader, waveOutReset, waveOutUnprepareHeader, waveOutWrite},
+        mmsystem::{CALLBACK_NULL, HWAVEOUT, MMSYSERR_NOERROR, WAVEHDR, WAVE_MAPPER, WHDR_DONE},
+    },
+};
+
+/// How many buffers can be queued before new sound gets skipped, so that the device can't fall too far behind.
+const MAX_QUEUED: usize = 16;
+const LEAD_FRAMES: usize = (SAMPLE_RATE / 20) as usize;
+
+/// A buffer which has been given to the device. It's boxed so the header doesn't move while the device has it.
+struct Block {
+    header: WAVEHDR,
+    frames: Vec<Frame>,
+}
+
+pub struct OutputImpl {
+    handle: HWAVEOUT,
+    queue: VecDeque<Box<Block>>,
+}
+
+impl OutputImpl {
+    pub fn new() -> Result<Self, String> {
+        unsafe {
+            let format = WAVEFORMATEX {
+                wFormatTag: WAVE_FORMAT_PCM,
+                nChannels: 2,
+                nSamplesPerSec: SAMPLE_RATE,
+                nAvgBytesPerSec: SAMPLE_RATE * mem::size_of::<Frame>() as u32,
+                nBlockAlign: mem::size_of::<Frame>() as u16,
+                wBitsPerSample: 16,
+                cbSize: 0,
+            };
+            let mut handle = ptr::null_mut();
+            let result = waveOutOpen(&mut handle, WAVE_MAPPER, &format, 0, 0, CALLBACK_NULL);
+            if result != MMSYSERR_NOERROR {
+                return Err(format!("Could not open the audio device (error {})", result))
+            }
+            let mut output = Self { handle, queue: VecDeque::new() };
+            output.queue_frames(vec![[0; 2]; LEAD_FRAMES]);
+            Ok(output)
+        }
+    }
+
+    /// Frees any buffers the device has finished playing.
+    fn reclaim(&mut self) {
+        while let Some(block) = self.queue.front_mut() {
+            // the device sets the flag from another thread
+            if unsafe { ptr::read_volatile(&block.header.dwFlags) } & WHDR_DONE == 0 {
+                break
+            }
+            unsafe {
+                waveOutUnprepareHeader(self.handle, &mut block.header, mem::size_of::<WAVEHDR>() as u32);
+            }
+            self.queue.pop_front();
+        }
+    }
+
+    fn queue_frames(&mut self, frames: Vec<Frame>) {
+        let mut block = Box::new(Block { header: unsafe { mem::zeroed() }, frames });
+        block.header.lpData = block.frames.as_mut_ptr().cast();
+        block.header.dwBufferLength = (block.frames.len() * mem::size_of::<Frame>()) as u32;
+        unsafe {
+            let header_size = mem::size_of::<WAVEHDR>() as u32;
+            if waveOutPrepareHeader(self.handle, &mut block.header, header_size) != MMSYSERR_NOERROR {
+                return
+            }
+            if waveOutWrite(self.handle, &mut block.header, header_size) != MMSYSERR_NOERROR {
+                waveOutUnprepareHeader(self.handle, &mut block.header, header_size);
+                return
+            }
+        }
+        self.queue.push_back(block);
+    }
+}
+
+impl OutputTrait for OutputImpl {
+    fn write(&mut self, frames: &[Frame]) -> io::Result<()> {
+        self.reclaim();
+        if self.queue.is_empty() {
+            // the device ran dry, so get ahead again
+            self.queue_frames(vec![[0; 2]; LEAD_FRAMES]);
+        }
+        if self.queue.len() < MAX_QUEUED && !frames.is_empty() {
+            self.queue_frames(frames.to_vec());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for OutputImpl {
+    fn drop(&mut self) {
+        unsafe {
+            // this marks every buffer as done, so they can all be freed
+            waveOutReset(self.handle);
+            self.reclaim();
+            waveOutClose(self.handle);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 //! Game rendering functionality
 
 mod opengl;
+mod software;
 
 use crate::{atlas::AtlasBuilder, window::Window};
 use serde::{Deserialize, Serialize};
@@ -10,6 +11,16 @@ use std::any::Any;
 // Re-export for more logical module pathing
 pub use crate::atlas::AtlasRef;
 
+/// Which renderer implementation to use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// Hardware rendering through OpenGL.
+    OpenGL,
+    /// Rendering on the CPU, which gives the same pixels on every machine.
+    /// This can't show anything, so it only works with headless windows.
+    Software,
+}
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Scaling {
     /// Fixed scale, with a multiplier. The multiplier always be strictly positive.
@@ -334,6 +345,18 @@ pub trait RendererTrait {
     fn create_surface(&mut self, w: i32, h: i32, has_zbuffer: bool) -> Result<AtlasRef, String>;
     fn set_target(&mut self, atlas_ref: &AtlasRef);
     fn reset_target(&mut self);
+    /// Copies raw pixels between surfaces, ignoring the blend mode, 3D mode and depth buffers like GM8 does.
+    fn copy_surface_part(
+        &mut self,
+        dest: &AtlasRef,
+        dest_x: i32,
+        dest_y: i32,
+        src: &AtlasRef,
+        part_x: i32,
+        part_y: i32,
+        part_w: i32,
+        part_h: i32,
+    ) -> Result<(), String>;
 
     fn get_texture_id(&mut self, atl_ref: &AtlasRef) -> i32;
     fn get_texture_from_id(&self, id: i32) -> Option<&AtlasRef>;
@@ -517,10 +540,16 @@ impl Default for RendererOptions {
 }
 
 impl Renderer {
-    pub fn new(backend: (), options: &RendererOptions, window: &Window, clear_colour: Colour) -> Result<Self, String> {
-        Ok(Self(Box::new(match backend {
-            () => opengl::RendererImpl::new(options, window, clear_colour)?,
-        })))
+    pub fn new(
+        backend: Backend,
+        options: &RendererOptions,
+        window: &Window,
+        clear_colour: Colour,
+    ) -> Result<Self, String> {
+        Ok(Self(match backend {
+            Backend::OpenGL => Box::new(opengl::RendererImpl::new(options, window, clear_colour)?),
+            Backend::Software => Box::new(software::RendererImpl::new(options, window, clear_colour)?),
+        }))
     }
 
     pub fn max_texture_size(&self) -> u32 {
@@ -893,6 +922,20 @@ impl Renderer {
         self.0.reset_target()
     }
 
+    pub fn copy_surface_part(
+        &mut self,
+        dest: &AtlasRef,
+        dest_x: i32,
+        dest_y: i32,
+        src: &AtlasRef,
+        part_x: i32,
+        part_y: i32,
+        part_w: i32,
+        part_h: i32,
+    ) -> Result<(), String> {
+        self.0.copy_surface_part(dest, dest_x, dest_y, src, part_x, part_y, part_w, part_h)
+    }
+
     pub fn get_texture_id(&mut self, atl_ref: &AtlasRef) -> i32 {
         self.0.get_texture_id(atl_ref)
     }
@@ -1067,3 +1110,89 @@ fn mat4mult(m1: [f32; 16], m2: [f32; 16]) -> [f32; 16] {
         (m1[12] * m2[3]) + (m1[13] * m2[7]) + (m1[14] * m2[11]) + (m1[15] * m2[15]),
     ]
 }
+
+fn make_view_matrix(x: f64, y: f64, z: f64, w: f64, h: f64, angle: f64) -> [f32; 16] {
+    // Note: sin is negated because it's the same as negating the angle, which is how GM8 does view angles
+    let angle = angle.to_radians();
+    // libm rather than std so the software renderer gets the same matrix on every machine
+    let sin_angle = -libm::sin(angle) as f32;
+    let cos_angle = libm::cos(angle) as f32;
+
+    #[rustfmt::skip]
+    let view_matrix: [f32; 16] = {
+        // source rectangle's center coordinates aka -(x + w/2) and -(y + h/2)
+        let scx = -((x as f32) + (w as f32 / 2.0));
+        let scy = -((y as f32) + (h as f32 / 2.0));
+        let scz = -z as f32;
+        mat4mult(
+            // Place camera at (scx, scy, scz)
+            [
+                1.0, 0.0, 0.0, 0.0,
+                0.0, 1.0, 0.0, 0.0,
+                0.0, 0.0, 1.0, 0.0,
+                scx, scy, scz, 1.0,
+            ],
+            // Rotate to view_angle
+            [
+                cos_angle,  sin_angle, 0.0, 0.0,
+                -sin_angle, cos_angle, 0.0, 0.0,
+                0.0,        0.0,       1.0, 0.0,
+                0.0,        0.0,       0.0, 1.0,
+            ]
+        )
+    };
+
+    view_matrix
+}
+
+fn split_colour(rgb: i32, alpha: f64) -> [f32; 4] {
+    [
+        ((rgb & 0xFF) as f32) / 255.0,
+        (((rgb >> 8) & 0xFF) as f32) / 255.0,
+        (((rgb >> 16) & 0xFF) as f32) / 255.0,
+        alpha.max(0.0).min(1.0) as f32,
+    ]
+}
+
+/// A builder to be used for building basic shapes.
+struct ShapeBuilder {
+    primitive: PrimitiveBuilder,
+    outline: bool,
+    depth: f32,
+    alpha: f64,
+}
+
+impl ShapeBuilder {
+    fn new(outline: bool, atlas_ref: AtlasRef, alpha: f64, depth: f32) -> Self {
+        Self {
+            primitive: PrimitiveBuilder::new(
+                atlas_ref,
+                if outline { PrimitiveType::LineStrip } else { PrimitiveType::TriFan },
+            ),
+            outline,
+            depth,
+            alpha,
+        }
+    }
+
+    /// Shortcut for basic shapes.
+    fn push_point(&mut self, x: f64, y: f64, colour: i32) -> &mut Self {
+        self.primitive.push_vertex([x as f32, y as f32, self.depth], [0.0, 0.0], split_colour(colour, self.alpha), [
+            0.0, 0.0, 0.0,
+        ]);
+        self
+    }
+
+    /// Should only be called once. This is only used for basic shapes, so it's fine for it to be *possible* to
+    /// call it multiple times, as that makes things easier elsewhere.
+    fn build(&mut self) -> &PrimitiveBuilder {
+        if self.outline {
+            let vertices = self.primitive.get_vertices();
+            if vertices.len() > 2 {
+                let vertex = vertices[0];
+                self.primitive.push_vertex_raw(vertex);
+            }
+        }
+        &self.primitive
+    }
+}
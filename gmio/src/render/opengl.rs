@@ -1,10 +1,12 @@
+mod egl;
+mod glx;
 mod wgl;
 
 use crate::{
     atlas::{AtlasBuilder, AtlasRef},
     render::{
-        mat4mult, BlendType, Fog, Light, PrimitiveBuilder, PrimitiveShape, PrimitiveType, RendererOptions,
-        RendererTrait, SavedTexture, Scaling, Vertex, VertexBuffer,
+        make_view_matrix, mat4mult, split_colour, BlendType, Fog, Light, PrimitiveBuilder, PrimitiveShape,
+        PrimitiveType, RendererOptions, RendererTrait, SavedTexture, Scaling, ShapeBuilder, Vertex, VertexBuffer,
     },
     window::Window,
 };
@@ -28,6 +30,67 @@ cfg_if! {
     } else {
         // TODO: This won't work when Wayland but that's okay just make a function for it.
         use crate::window::xorg as w_imp;
+        use glx as imp;
+        use crate::window::headless as offscreen_w_imp;
+        use egl as offscreen_imp;
+    }
+}
+
+/// The GL context, which either belongs to a real window or renders offscreen for a headless one.
+enum Context {
+    Window(imp::PlatformImpl),
+    #[cfg(target_os = "linux")]
+    Offscreen(offscreen_imp::PlatformImpl),
+}
+
+macro_rules! with_context {
+    ($context: expr, $imp: ident => $ex: expr) => {
+        match $context {
+            Context::Window($imp) => $ex,
+            #[cfg(target_os = "linux")]
+            Context::Offscreen($imp) => $ex,
+        }
+    };
+}
+
+impl Context {
+    /// Creates a context for the window and loads GL function pointers with it.
+    unsafe fn new(window: &Window) -> Result<(Self, gl::Gl), String> {
+        #[cfg(target_os = "linux")]
+        if let Some(window_impl) = window.as_any().downcast_ref::<offscreen_w_imp::WindowImpl>() {
+            let imp = offscreen_imp::PlatformImpl::new(window_impl)?;
+            let gl = gl::Gl::load_with(offscreen_imp::PlatformImpl::get_function_loader()?);
+            offscreen_imp::PlatformImpl::clean_function_loader();
+            return Ok((Self::Offscreen(imp), gl))
+        }
+
+        let window_impl: &w_imp::WindowImpl = match window.as_any().downcast_ref() {
+            Some(x) => x,
+            None if window.is_headless() => return Err("Offscreen rendering isn't supported on this platform".into()),
+            None => return Err("Wrong backend provided to OpenGLRenderer::new()".into()),
+        };
+        let imp = imp::PlatformImpl::new(window_impl)?;
+
+        // gl function pointers
+        let gl = gl::Gl::load_with(imp::PlatformImpl::get_function_loader()?);
+        imp::PlatformImpl::clean_function_loader();
+        Ok((Self::Window(imp), gl))
+    }
+
+    unsafe fn swap_buffers(&self) {
+        with_context!(self, imp => imp.swap_buffers())
+    }
+
+    unsafe fn set_swap_interval(&self, n: u32) -> bool {
+        with_context!(self, imp => imp.set_swap_interval(n))
+    }
+
+    unsafe fn get_swap_interval(&self) -> u32 {
+        with_context!(self, imp => imp.get_swap_interval())
+    }
+
+    unsafe fn wait_vsync(&self) {
+        with_context!(self, imp => imp.wait_vsync())
     }
 }
 
@@ -38,7 +101,7 @@ macro_rules! shader_file {
 }
 
 pub struct RendererImpl {
-    imp: imp::PlatformImpl,
+    imp: Context,
     gl: gl::Gl,
     //program: GLuint,
     //vao: GLuint,
@@ -109,48 +172,6 @@ unsafe fn shader_info_log(gl: &gl::Gl, name: &str, id: GLuint) -> String {
     )
 }
 
-fn make_view_matrix(x: f64, y: f64, z: f64, w: f64, h: f64, angle: f64) -> [f32; 16] {
-    // Note: sin is negated because it's the same as negating the angle, which is how GM8 does view angles
-    let angle = angle.to_radians();
-    let sin_angle = -angle.sin() as f32;
-    let cos_angle = angle.cos() as f32;
-
-    #[rustfmt::skip]
-    let view_matrix: [f32; 16] = {
-        // source rectangle's center coordinates aka -(x + w/2) and -(y + h/2)
-        let scx = -((x as f32) + (w as f32 / 2.0));
-        let scy = -((y as f32) + (h as f32 / 2.0));
-        let scz = -z as f32;
-        mat4mult(
-            // Place camera at (scx, scy, scz)
-            [
-                1.0, 0.0, 0.0, 0.0,
-                0.0, 1.0, 0.0, 0.0,
-                0.0, 0.0, 1.0, 0.0,
-                scx, scy, scz, 1.0,
-            ],
-            // Rotate to view_angle
-            [
-                cos_angle,  sin_angle, 0.0, 0.0,
-                -sin_angle, cos_angle, 0.0, 0.0,
-                0.0,        0.0,       1.0, 0.0,
-                0.0,        0.0,       0.0, 1.0,
-            ]
-        )
-    };
-
-    view_matrix
-}
-
-fn split_colour(rgb: i32, alpha: f64) -> [f32; 4] {
-    [
-        ((rgb & 0xFF) as f32) / 255.0,
-        (((rgb >> 8) & 0xFF) as f32) / 255.0,
-        (((rgb >> 16) & 0xFF) as f32) / 255.0,
-        alpha.max(0.0).min(1.0) as f32,
-    ]
-}
-
 // TODO: probably put this in render.rs instead
 impl VertexBuffer {
     pub fn swap_colour(&mut self, old: (i32, f64), new: (i32, f64)) {
@@ -226,62 +247,10 @@ impl From<PrimitiveShape> for GLenum {
     }
 }
 
-/// A builder to be used for building basic shapes.
-struct ShapeBuilder {
-    primitive: PrimitiveBuilder,
-    outline: bool,
-    depth: f32,
-    alpha: f64,
-}
-
-impl ShapeBuilder {
-    fn new(outline: bool, atlas_ref: AtlasRef, alpha: f64, depth: f32) -> Self {
-        Self {
-            primitive: PrimitiveBuilder::new(
-                atlas_ref,
-                if outline { PrimitiveType::LineStrip } else { PrimitiveType::TriFan },
-            ),
-            outline,
-            depth,
-            alpha,
-        }
-    }
-
-    /// Shortcut for basic shapes.
-    fn push_point(&mut self, x: f64, y: f64, colour: i32) -> &mut Self {
-        self.primitive.push_vertex([x as f32, y as f32, self.depth], [0.0, 0.0], split_colour(colour, self.alpha), [
-            0.0, 0.0, 0.0,
-        ]);
-        self
-    }
-
-    /// Should only be called once. This is only used for basic shapes, so it's fine for it to be *possible* to
-    /// call it multiple times, as that makes things easier elsewhere.
-    fn build(&mut self) -> &PrimitiveBuilder {
-        if self.outline {
-            let vertices = self.primitive.get_vertices();
-            if vertices.len() > 2 {
-                let vertex = vertices[0];
-                self.primitive.push_vertex_raw(vertex);
-            }
-        }
-        &self.primitive
-    }
-}
-
 impl RendererImpl {
     pub fn new(options: &RendererOptions, window: &Window, clear_colour: Colour) -> Result<Self, String> {
-        let window_impl: &w_imp::WindowImpl = match window.as_any().downcast_ref() {
-            Some(x) => x,
-            None => return Err("Wrong backend provided to OpenGLRenderer::new()".into()),
-        };
-
         unsafe {
-            let imp = imp::PlatformImpl::new(window_impl)?;
-
-            // gl function pointers
-            let gl = gl::Gl::load_with(imp::PlatformImpl::get_function_loader()?);
-            imp::PlatformImpl::clean_function_loader();
+            let (imp, gl) = Context::new(window)?;
 
             // debug print
             let ver_str = CStr::from_ptr(gl.GetString(gl::VERSION).cast()).to_str().unwrap();
@@ -1054,6 +1023,114 @@ impl RendererTrait for RendererImpl {
         }
     }
 
+    fn copy_surface_part(
+        &mut self,
+        dest: &AtlasRef,
+        dest_x: i32,
+        dest_y: i32,
+        src: &AtlasRef,
+        part_x: i32,
+        part_y: i32,
+        part_w: i32,
+        part_h: i32,
+    ) -> Result<(), String> {
+        self.flush_queue();
+        if let (Some(Some(src_fbo)), Some(Some(dest_fbo))) =
+            (self.fbo_ids.get(src.atlas_id as usize).copied(), self.fbo_ids.get(dest.atlas_id as usize).copied())
+        {
+            unsafe {
+                let mut prev_read_fbo = 0;
+                let mut prev_draw_fbo = 0;
+                let mut prev_tex2d = 0;
+                self.gl.GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut prev_read_fbo);
+                self.gl.GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut prev_draw_fbo);
+                self.gl.GetIntegerv(gl::TEXTURE_BINDING_2D, &mut prev_tex2d);
+
+                // The scissor box applies to blits too, so it has to be off while copying
+                self.gl.Disable(gl::SCISSOR_TEST);
+                let (mut src_fbo, mut src_x, mut src_y) = (src_fbo, src.x + part_x, src.y + part_y);
+
+                // Blitting between overlapping parts of the same framebuffer is undefined,
+                // so copies within one surface go through a temporary texture
+                let mut temp = None;
+                if src.atlas_id == dest.atlas_id {
+                    let mut tex_id: GLuint = 0;
+                    let mut fbo: GLuint = 0;
+                    self.gl.GenTextures(1, &mut tex_id);
+                    self.gl.BindTexture(gl::TEXTURE_2D, tex_id);
+                    self.gl.TexImage2D(
+                        gl::TEXTURE_2D,
+                        0,
+                        gl::RGBA as _,
+                        part_w,
+                        part_h,
+                        0,
+                        gl::RGBA,
+                        gl::UNSIGNED_BYTE,
+                        ptr::null(),
+                    );
+                    self.gl.GenFramebuffers(1, &mut fbo);
+                    self.gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, fbo);
+                    self.gl.FramebufferTexture2D(
+                        gl::DRAW_FRAMEBUFFER,
+                        gl::COLOR_ATTACHMENT0,
+                        gl::TEXTURE_2D,
+                        tex_id,
+                        0,
+                    );
+                    self.gl.BindFramebuffer(gl::READ_FRAMEBUFFER, src_fbo);
+                    self.gl.BlitFramebuffer(
+                        src_x,
+                        src_y,
+                        src_x + part_w,
+                        src_y + part_h,
+                        0,
+                        0,
+                        part_w,
+                        part_h,
+                        gl::COLOR_BUFFER_BIT,
+                        gl::NEAREST,
+                    );
+                    temp = Some((tex_id, fbo));
+                    src_fbo = fbo;
+                    src_x = 0;
+                    src_y = 0;
+                }
+
+                self.gl.BindFramebuffer(gl::READ_FRAMEBUFFER, src_fbo);
+                self.gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, dest_fbo);
+                let (dest_x, dest_y) = (dest.x + dest_x, dest.y + dest_y);
+                self.gl.BlitFramebuffer(
+                    src_x,
+                    src_y,
+                    src_x + part_w,
+                    src_y + part_h,
+                    dest_x,
+                    dest_y,
+                    dest_x + part_w,
+                    dest_y + part_h,
+                    gl::COLOR_BUFFER_BIT,
+                    gl::NEAREST,
+                );
+
+                // cleanup
+                self.gl.BindFramebuffer(gl::READ_FRAMEBUFFER, prev_read_fbo as _);
+                self.gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, prev_draw_fbo as _);
+                self.gl.BindTexture(gl::TEXTURE_2D, prev_tex2d as _);
+                if let Some((tex_id, fbo)) = temp {
+                    self.gl.DeleteFramebuffers(1, &fbo);
+                    self.gl.DeleteTextures(1, &tex_id);
+                }
+                self.gl.Enable(gl::SCISSOR_TEST);
+                match self.gl.GetError() {
+                    0 => (),
+                    err => return Err(format!("Failed to copy surface! (OpenGL code {})", err)),
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn reset_target(&mut self) {
         self.flush_queue();
         unsafe {
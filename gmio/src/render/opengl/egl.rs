@@ -0,0 +1,219 @@
+//! Offscreen OpenGL contexts for headless windows.
+//! These need no display server, and work with Mesa's software rasterizer if there's no GPU.
+
+#![cfg(target_os = "linux")]
+
+use crate::window::headless::WindowImpl;
+use std::{
+    cell::Cell,
+    ffi::CStr,
+    mem,
+    ops::Drop,
+    os::raw::{c_char, c_void},
+    ptr,
+};
+
+pub mod egl {
+    #![allow(clippy::all, non_camel_case_types)]
+
+    // platform types which the generated bindings need, from eglplatform.h
+    pub type khronos_utime_nanoseconds_t = u64;
+    pub type khronos_uint64_t = u64;
+    pub type khronos_ssize_t = isize;
+    pub type EGLNativeDisplayType = *const std::os::raw::c_void;
+    pub type EGLNativePixmapType = std::os::raw::c_ulong;
+    pub type EGLNativeWindowType = std::os::raw::c_ulong;
+    pub type EGLint = i32;
+    pub type NativeDisplayType = EGLNativeDisplayType;
+    pub type NativePixmapType = EGLNativePixmapType;
+    pub type NativeWindowType = EGLNativeWindowType;
+
+    include!(concat!(env!("OUT_DIR"), "/egl_bindings.rs"));
+}
+
+use egl::types::{EGLConfig, EGLContext, EGLDisplay, EGLSurface, EGLint};
+
+pub struct PlatformImpl {
+    context: EGLContext,
+    display: EGLDisplay,
+    surface: EGLSurface,
+    egl: egl::Egl,
+    swap_interval: Cell<u32>,
+}
+
+type GetProcAddress = unsafe extern "C" fn(*const c_char) -> *const c_void;
+
+/// Configuration for choosing a framebuffer, equivalent to the one in wgl.rs.
+#[rustfmt::skip]
+static CONFIG_ATTRIBS: &[u32] = &[
+    egl::SURFACE_TYPE,    egl::PBUFFER_BIT,
+    egl::RENDERABLE_TYPE, egl::OPENGL_BIT,
+    egl::RED_SIZE,        8,
+    egl::GREEN_SIZE,      8,
+    egl::BLUE_SIZE,       8,
+    egl::DEPTH_SIZE,      24,
+    egl::STENCIL_SIZE,    8,
+    egl::NONE, // END
+];
+
+/// Flags for eglCreateContext
+#[rustfmt::skip]
+static CONTEXT_ATTRIBS: &[u32] = &[
+    egl::CONTEXT_MAJOR_VERSION,       3,
+    egl::CONTEXT_MINOR_VERSION,       3,
+    egl::CONTEXT_OPENGL_PROFILE_MASK, egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+    egl::NONE, // END
+];
+
+/// The surface is never shown, but GL needs a default framebuffer for `present` to draw to.
+/// Everything that gets read back comes from the renderer's own framebuffer, so this can be tiny.
+#[rustfmt::skip]
+static PBUFFER_ATTRIBS: &[u32] = &[
+    egl::WIDTH,  1,
+    egl::HEIGHT, 1,
+    egl::NONE, // END
+];
+
+unsafe fn open_libegl() -> Result<*mut c_void, String> {
+    for name in &[&b"libEGL.so.1\0"[..], &b"libEGL.so\0"[..]] {
+        let handle = libc::dlopen(name.as_ptr().cast(), libc::RTLD_NOW | libc::RTLD_GLOBAL);
+        if !handle.is_null() {
+            return Ok(handle)
+        }
+    }
+    Err(format!("Could not load libEGL: {}", CStr::from_ptr(libc::dlerror()).to_string_lossy()))
+}
+
+/// Loads an OpenGL or EGL function pointer.
+unsafe fn load_function(name: *const c_char, libegl: *mut c_void, get_proc_address: GetProcAddress) -> *const c_void {
+    match get_proc_address(name) {
+        // core EGL functions aren't always available through eglGetProcAddress
+        addr if addr.is_null() => libc::dlsym(libegl, name),
+        addr => addr,
+    }
+}
+
+unsafe fn get_display(egl: &egl::Egl) -> EGLDisplay {
+    // querying client extensions fails if they aren't supported
+    let client_extensions = egl.QueryString(egl::NO_DISPLAY, egl::EXTENSIONS as EGLint);
+    let surfaceless = !client_extensions.is_null()
+        && CStr::from_ptr(client_extensions)
+            .to_string_lossy()
+            .split_whitespace()
+            .any(|ext| ext == "EGL_MESA_platform_surfaceless");
+    if surfaceless && egl.GetPlatformDisplayEXT.is_loaded() {
+        egl.GetPlatformDisplayEXT(egl::PLATFORM_SURFACELESS_MESA, egl::DEFAULT_DISPLAY as *mut _, ptr::null())
+    } else {
+        // this will try to connect to a display server, but it'll still work if there isn't one
+        egl.GetDisplay(egl::DEFAULT_DISPLAY)
+    }
+}
+
+impl PlatformImpl {
+    pub unsafe fn new(_window: &WindowImpl) -> Result<Self, String> {
+        // load egl function pointers
+        let egl = egl::Egl::load_with(Self::get_function_loader()?);
+
+        let display = get_display(&egl);
+        if display == egl::NO_DISPLAY {
+            return Err("Could not get an EGL display".into())
+        }
+        let (mut major, mut minor) = (0, 0);
+        if egl.Initialize(display, &mut major, &mut minor) == 0 {
+            return Err(format!("Could not initialize EGL (error {:#X})", egl.GetError()))
+        }
+        if (major, minor) < (1, 5) {
+            return Err(format!("EGL version 1.5 or later is required, but found version {}.{}", major, minor))
+        }
+        if egl.BindAPI(egl::OPENGL_API) == 0 {
+            return Err("EGL doesn't support desktop OpenGL".into())
+        }
+
+        let mut config: EGLConfig = ptr::null();
+        let mut config_count = 0;
+        egl.ChooseConfig(display, CONFIG_ATTRIBS.as_ptr().cast(), &mut config, 1, &mut config_count);
+        if config_count == 0 {
+            return Err("No suitable EGL framebuffer configs".into())
+        }
+
+        let context = egl.CreateContext(display, config, egl::NO_CONTEXT, CONTEXT_ATTRIBS.as_ptr().cast());
+        if context == egl::NO_CONTEXT {
+            return Err(format!("Could not create an EGL context (error {:#X})", egl.GetError()))
+        }
+        let surface = egl.CreatePbufferSurface(display, config, PBUFFER_ATTRIBS.as_ptr().cast());
+        if surface == egl::NO_SURFACE {
+            egl.DestroyContext(display, context);
+            return Err(format!("Could not create an EGL surface (error {:#X})", egl.GetError()))
+        }
+        if egl.MakeCurrent(display, surface, surface, context) == 0 {
+            egl.DestroySurface(display, surface);
+            egl.DestroyContext(display, context);
+            return Err(format!("Could not make the EGL context current (error {:#X})", egl.GetError()))
+        }
+
+        Ok(Self { context, display, surface, egl, swap_interval: Cell::new(0) })
+    }
+
+    pub unsafe fn get_function_loader() -> Result<Box<dyn FnMut(&'static str) -> *const std::os::raw::c_void>, String> {
+        static mut LIBEGL: *mut c_void = ptr::null_mut();
+
+        let libegl = match LIBEGL {
+            x if x.is_null() => open_libegl()?,
+            x => x,
+        };
+        LIBEGL = libegl;
+
+        let get_proc_address = libc::dlsym(libegl, b"eglGetProcAddress\0".as_ptr().cast());
+        if get_proc_address.is_null() {
+            return Err("libEGL doesn't export eglGetProcAddress".into())
+        }
+        let get_proc_address = mem::transmute::<*mut c_void, GetProcAddress>(get_proc_address);
+
+        // same as glx.rs, the loader owns its name buffer
+        let mut name_buf = Vec::new();
+        Ok(Box::new(move |s: &'static str| {
+            name_buf.clear();
+            name_buf.extend_from_slice(s.as_bytes());
+            name_buf.push(0);
+            load_function(name_buf.as_ptr() as *const c_char, libegl, get_proc_address)
+        }))
+    }
+
+    pub unsafe fn clean_function_loader() {
+        // nothing to clean up, the buffer is dropped with the loader
+    }
+
+    pub unsafe fn swap_buffers(&self) {
+        self.egl.SwapBuffers(self.display, self.surface);
+    }
+
+    // nothing is ever displayed, so the swap interval is only remembered for get_vsync
+    pub unsafe fn set_swap_interval(&self, n: u32) -> bool {
+        self.swap_interval.set(n);
+        true
+    }
+
+    pub unsafe fn get_swap_interval(&self) -> u32 {
+        self.swap_interval.get()
+    }
+
+    pub unsafe fn wait_vsync(&self) {
+        // there's no display to wait for, and headless runs should go as fast as possible
+    }
+}
+
+impl Drop for PlatformImpl {
+    fn drop(&mut self) {
+        unsafe {
+            // unset if we're the current context
+            if self.egl.GetCurrentContext() == self.context {
+                self.egl.MakeCurrent(self.display, egl::NO_SURFACE, egl::NO_SURFACE, egl::NO_CONTEXT);
+            }
+
+            self.egl.DestroySurface(self.display, self.surface);
+            self.egl.DestroyContext(self.display, self.context);
+
+            // not terminating the display, since another renderer could still be using it
+        }
+    }
+}
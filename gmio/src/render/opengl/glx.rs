@@ -0,0 +1,299 @@
+//! Linux-specific OpenGL loading.
+
+#![cfg(target_os = "linux")]
+
+use crate::window::xorg::WindowImpl;
+use std::{
+    cell::Cell,
+    ffi::CStr,
+    mem,
+    ops::Drop,
+    os::raw::{c_char, c_int, c_uint, c_void},
+    ptr, slice,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+use x11::xlib;
+
+pub mod glx {
+    #![allow(clippy::all)]
+
+    include!(concat!(env!("OUT_DIR"), "/glx_bindings.rs"));
+}
+
+pub struct PlatformImpl {
+    context: glx::types::GLXContext,
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    glx: glx::Glx,
+    swap_control: SwapControl,
+    swap_interval: Cell<u32>,
+    video_sync: bool,
+    created: Instant,
+}
+
+/// Which extension we're using to set the swap interval, best first.
+/// Mesa's software rasterizer only has some of these depending on the version.
+enum SwapControl {
+    Ext,
+    Mesa,
+    Sgi,
+    Unsupported,
+}
+
+type GetProcAddress = unsafe extern "C" fn(*const u8) -> *const c_void;
+
+/// Configuration for choosing a framebuffer, equivalent to the one in wgl.rs.
+#[rustfmt::skip]
+static FB_CONFIG_ATTRIBS: &[u32] = &[
+    glx::X_RENDERABLE,  1,
+    glx::DRAWABLE_TYPE, glx::WINDOW_BIT,
+    glx::RENDER_TYPE,   glx::RGBA_BIT,
+    glx::DOUBLEBUFFER,  1,
+    glx::RED_SIZE,      8,
+    glx::GREEN_SIZE,    8,
+    glx::BLUE_SIZE,     8,
+    glx::DEPTH_SIZE,    24,
+    glx::STENCIL_SIZE,  8,
+    0, // END
+];
+
+/// Flags for glXCreateContextAttribsARB
+#[rustfmt::skip]
+static GLX_CCTX_ATTR_ARB: &[u32] = &[
+    glx::CONTEXT_MAJOR_VERSION_ARB, 3,
+    glx::CONTEXT_MINOR_VERSION_ARB, 3,
+    glx::CONTEXT_FLAGS_ARB,         glx::CONTEXT_FORWARD_COMPATIBLE_BIT_ARB,
+    glx::CONTEXT_PROFILE_MASK_ARB,  glx::CONTEXT_CORE_PROFILE_BIT_ARB,
+    0, // END
+];
+
+/// Set by `trap_x_error` if any X request fails while it's the error handler.
+static X_ERROR: AtomicBool = AtomicBool::new(false);
+unsafe extern "C" fn trap_x_error(_: *mut xlib::Display, _: *mut xlib::XErrorEvent) -> c_int {
+    X_ERROR.store(true, Ordering::SeqCst);
+    0
+}
+
+unsafe fn open_libgl() -> Result<*mut c_void, String> {
+    for name in &[&b"libGL.so.1\0"[..], &b"libGL.so\0"[..]] {
+        let handle = libc::dlopen(name.as_ptr().cast(), libc::RTLD_NOW | libc::RTLD_GLOBAL);
+        if !handle.is_null() {
+            return Ok(handle)
+        }
+    }
+    Err(format!("Could not load libGL: {}", CStr::from_ptr(libc::dlerror()).to_string_lossy()))
+}
+
+/// Loads an OpenGL or GLX function pointer.
+unsafe fn load_function(name: *const c_char, libgl: *mut c_void, get_proc_address: GetProcAddress) -> *const c_void {
+    match get_proc_address(name.cast()) {
+        // extensions are only available through glXGetProcAddress, but core functions should always be exported
+        addr if addr.is_null() => libc::dlsym(libgl, name),
+        addr => addr,
+    }
+}
+
+/// Makes a GL 3.3 core context, returning null on failure.
+/// Failing to create a context is an X error, which would normally exit the process, so it's trapped here.
+unsafe fn create_context_attribs(
+    glx: &glx::Glx,
+    display: *mut xlib::Display,
+    config: glx::types::GLXFBConfig,
+) -> glx::types::GLXContext {
+    X_ERROR.store(false, Ordering::SeqCst);
+    let old_handler = xlib::XSetErrorHandler(Some(trap_x_error));
+    let context = glx.CreateContextAttribsARB(
+        display.cast(),
+        config,
+        ptr::null(),
+        xlib::True,
+        GLX_CCTX_ATTR_ARB as *const _ as *const c_int,
+    );
+    xlib::XSync(display, xlib::False);
+    xlib::XSetErrorHandler(old_handler);
+    if X_ERROR.load(Ordering::SeqCst) {
+        if !context.is_null() {
+            glx.DestroyContext(display.cast(), context);
+        }
+        ptr::null()
+    } else {
+        context
+    }
+}
+
+impl PlatformImpl {
+    pub unsafe fn new(window: &WindowImpl) -> Result<Self, String> {
+        let display = window.display;
+        let screen = window.screen_id;
+
+        // load glx function pointers
+        let glx = glx::Glx::load_with(Self::get_function_loader()?);
+
+        let (mut major, mut minor) = (0, 0);
+        if glx.QueryVersion(display.cast(), &mut major, &mut minor) == 0 {
+            return Err("The X server doesn't support GLX".into())
+        }
+        if (major, minor) < (1, 3) {
+            return Err(format!("GLX version 1.3 or later is required, but found version {}.{}", major, minor))
+        }
+        let extensions = CStr::from_ptr(glx.QueryExtensionsString(display.cast(), screen)).to_string_lossy();
+        let has_extension = |name: &str| extensions.split_whitespace().any(|ext| ext == name);
+
+        // the window's already been created, so we need a config that matches its visual
+        let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+        xlib::XGetWindowAttributes(display, window.window_id, &mut attributes);
+        let visual_id = xlib::XVisualIDFromVisual(attributes.visual);
+        let mut config_count = 0;
+        let configs = glx.ChooseFBConfig(
+            display.cast(),
+            screen,
+            FB_CONFIG_ATTRIBS as *const _ as *const c_int,
+            &mut config_count,
+        );
+        if configs.is_null() {
+            return Err("No suitable GLX framebuffer configs".into())
+        }
+        let config = slice::from_raw_parts(configs, config_count as usize).iter().copied().find(|&config| {
+            let mut id = 0;
+            glx.GetFBConfigAttrib(display.cast(), config, glx::VISUAL_ID as c_int, &mut id);
+            id as xlib::VisualID == visual_id
+        });
+        xlib::XFree(configs.cast());
+        let config = config.ok_or("No GLX framebuffer config matches the window's visual")?;
+
+        // try for a core context, but the basic one may still be new enough
+        let mut context = ptr::null();
+        if has_extension("GLX_ARB_create_context_profile") && glx.CreateContextAttribsARB.is_loaded() {
+            context = create_context_attribs(&glx, display, config);
+        }
+        if context.is_null() {
+            context = glx.CreateNewContext(display.cast(), config, glx::RGBA_TYPE as c_int, ptr::null(), xlib::True);
+        }
+        if context.is_null() {
+            return Err("Could not create a GLX context".into())
+        }
+        if glx.MakeCurrent(display.cast(), window.window_id, context) == 0 {
+            glx.DestroyContext(display.cast(), context);
+            return Err("Could not make the GLX context current".into())
+        }
+
+        let swap_control = if has_extension("GLX_EXT_swap_control") && glx.SwapIntervalEXT.is_loaded() {
+            SwapControl::Ext
+        } else if has_extension("GLX_MESA_swap_control") && glx.SwapIntervalMESA.is_loaded() {
+            SwapControl::Mesa
+        } else if has_extension("GLX_SGI_swap_control") && glx.SwapIntervalSGI.is_loaded() {
+            SwapControl::Sgi
+        } else {
+            SwapControl::Unsupported
+        };
+        let video_sync = has_extension("GLX_SGI_video_sync") && glx.WaitVideoSyncSGI.is_loaded();
+
+        Ok(Self {
+            context,
+            display,
+            window: window.window_id,
+            glx,
+            swap_control,
+            // GLX_SGI_swap_control can't be queried, but the spec says it starts at 1
+            swap_interval: Cell::new(1),
+            video_sync,
+            created: Instant::now(),
+        })
+    }
+
+    pub unsafe fn get_function_loader() -> Result<Box<dyn FnMut(&'static str) -> *const std::os::raw::c_void>, String> {
+        static mut LIBGL: *mut c_void = ptr::null_mut();
+
+        let libgl = match LIBGL {
+            x if x.is_null() => open_libgl()?,
+            x => x,
+        };
+        LIBGL = libgl;
+
+        let get_proc_address = libc::dlsym(libgl, b"glXGetProcAddressARB\0".as_ptr().cast());
+        if get_proc_address.is_null() {
+            return Err("libGL doesn't export glXGetProcAddressARB".into())
+        }
+        let get_proc_address = mem::transmute::<*mut c_void, GetProcAddress>(get_proc_address);
+
+        // unlike wgl.rs the loader owns its name buffer, so there's only one alloc per loader
+        let mut name_buf = Vec::new();
+        Ok(Box::new(move |s: &'static str| {
+            name_buf.clear();
+            name_buf.extend_from_slice(s.as_bytes());
+            name_buf.push(0);
+            load_function(name_buf.as_ptr() as *const c_char, libgl, get_proc_address)
+        }))
+    }
+
+    pub unsafe fn clean_function_loader() {
+        // nothing to clean up, the buffer is dropped with the loader
+    }
+
+    pub unsafe fn swap_buffers(&self) {
+        self.glx.SwapBuffers(self.display.cast(), self.window);
+    }
+
+    pub unsafe fn set_swap_interval(&self, n: u32) -> bool {
+        match self.swap_control {
+            SwapControl::Ext => {
+                self.glx.SwapIntervalEXT(self.display.cast(), self.window, n as c_int);
+                true
+            },
+            SwapControl::Mesa => self.glx.SwapIntervalMESA(n as c_uint) == 0,
+            // this one can't turn vsync off
+            SwapControl::Sgi if n > 0 => {
+                let success = self.glx.SwapIntervalSGI(n as c_int) == 0;
+                if success {
+                    self.swap_interval.set(n);
+                }
+                success
+            },
+            _ => false,
+        }
+    }
+
+    pub unsafe fn get_swap_interval(&self) -> u32 {
+        match self.swap_control {
+            SwapControl::Ext => {
+                let mut value = 0;
+                self.glx.QueryDrawable(self.display.cast(), self.window, glx::SWAP_INTERVAL_EXT as c_int, &mut value);
+                value
+            },
+            SwapControl::Mesa => self.glx.GetSwapIntervalMESA() as u32,
+            SwapControl::Sgi => self.swap_interval.get(),
+            SwapControl::Unsupported => 0,
+        }
+    }
+
+    pub unsafe fn wait_vsync(&self) {
+        if self.video_sync {
+            let mut count = 0;
+            if self.glx.GetVideoSyncSGI(&mut count) == 0 {
+                // waits until the retrace counter goes up by one
+                self.glx.WaitVideoSyncSGI(2, ((count + 1) % 2) as c_int, &mut count);
+                return
+            }
+        }
+
+        // software renderers have no idea where the retrace is, so pretend it's 60Hz
+        let frame = Duration::from_nanos(1_000_000_000 / 60);
+        let into_frame = self.created.elapsed().as_nanos() % frame.as_nanos();
+        thread::sleep(frame - Duration::from_nanos(into_frame as u64));
+    }
+}
+
+impl Drop for PlatformImpl {
+    fn drop(&mut self) {
+        unsafe {
+            // unset if we're the current context
+            if self.glx.GetCurrentContext() == self.context {
+                self.glx.MakeCurrent(self.display.cast(), 0, ptr::null());
+            }
+
+            self.glx.DestroyContext(self.display.cast(), self.context);
+        }
+    }
+}
@@ -0,0 +1,1762 @@
+//! A renderer that draws everything on the CPU.
+//!
+//! It follows what the OpenGL renderer and its shaders do, but all the maths is done in a fixed order with plain
+//! f32 operations, trigonometry comes from libm rather than the platform's maths library, and rasterization uses
+//! fixed-point coordinates, so the output is the same on every machine.
+
+use crate::{
+    atlas::{AtlasBuilder, AtlasRef},
+    render::{
+        BlendType, Fog, Light, PrimitiveBuilder, PrimitiveShape, PrimitiveType, RendererOptions, RendererTrait,
+        SavedTexture, Scaling, ShapeBuilder, Vertex, VertexBuffer, make_view_matrix, mat4mult, split_colour,
+    },
+    window::Window,
+};
+use shared::types::Colour;
+use std::{any::Any, collections::HashMap, f64::consts::PI, mem};
+
+/// Largest texture size, which is a constant so that atlases are packed the same way everywhere.
+const MAX_TEXTURE_SIZE: u32 = 8192;
+
+/// Fractional bits in the fixed-point coordinates used for rasterizing triangles.
+const SUBPIXEL_BITS: u32 = 8;
+const SUBPIXEL_ONE: i64 = 1 << SUBPIXEL_BITS;
+
+/// An RGBA texture with an optional depth buffer, stored from the top row down.
+#[derive(Clone, Default)]
+struct Texture {
+    width: i32,
+    height: i32,
+    pixels: Vec<u8>,
+    zbuf: Option<Vec<f32>>,
+}
+
+impl Texture {
+    fn new(width: i32, height: i32, has_zbuffer: bool) -> Self {
+        let len = (width.max(0) * height.max(0)) as usize;
+        Self { width, height, pixels: vec![0; len * 4], zbuf: if has_zbuffer { Some(vec![1.0; len]) } else { None } }
+    }
+
+    /// Gets a texel as floats, wrapping around like GL_REPEAT.
+    fn fetch(&self, x: i32, y: i32) -> [f32; 4] {
+        if self.width <= 0 || self.height <= 0 {
+            return [0.0; 4]
+        }
+        let index = (y.rem_euclid(self.height) * self.width + x.rem_euclid(self.width)) as usize * 4;
+        let texel = &self.pixels[index..index + 4];
+        [
+            f32::from(texel[0]) / 255.0,
+            f32::from(texel[1]) / 255.0,
+            f32::from(texel[2]) / 255.0,
+            f32::from(texel[3]) / 255.0,
+        ]
+    }
+
+    /// Samples between the four nearest texels, like GL_LINEAR.
+    fn fetch_linear(&self, x: f32, y: f32) -> [f32; 4] {
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (left, top) = (x.floor(), y.floor());
+        let (fx, fy) = (x - left, y - top);
+        let (left, top) = (left as i32, top as i32);
+        let top_row = mix(self.fetch(left, top), self.fetch(left + 1, top), fx);
+        let bottom_row = mix(self.fetch(left, top + 1), self.fetch(left + 1, top + 1), fx);
+        mix(top_row, bottom_row, fy)
+    }
+
+    /// Copies a rectangle of pixels out, leaving anything outside the texture as zeroes.
+    fn read(&self, x: i32, y: i32, w: i32, h: i32) -> Box<[u8]> {
+        let mut data = vec![0u8; (w.max(0) * h.max(0) * 4) as usize];
+        let left = x.max(0);
+        let right = (x + w).min(self.width);
+        if left < right {
+            for row in y.max(0)..(y + h).min(self.height) {
+                let src = ((row * self.width + left) * 4) as usize;
+                let dst = (((row - y) * w + left - x) * 4) as usize;
+                let len = ((right - left) * 4) as usize;
+                data[dst..dst + len].copy_from_slice(&self.pixels[src..src + len]);
+            }
+        }
+        data.into_boxed_slice()
+    }
+
+    fn write(&mut self, x: i32, y: i32, w: i32, h: i32, data: &[u8]) {
+        let left = x.max(0);
+        let right = (x + w).min(self.width);
+        if left < right {
+            for row in y.max(0)..(y + h).min(self.height) {
+                let src = (((row - y) * w + left - x) * 4) as usize;
+                let dst = ((row * self.width + left) * 4) as usize;
+                let len = ((right - left) * 4) as usize;
+                self.pixels[dst..dst + len].copy_from_slice(&data[src..src + len]);
+            }
+        }
+    }
+}
+
+/// The part of a texture inside the scissor box, as (left, top, right, bottom) with exclusive right and bottom.
+fn draw_bounds(scissor: (i32, i32, i32, i32), target: &Texture) -> (i32, i32, i32, i32) {
+    let (x, y, w, h) = scissor;
+    (x.max(0), y.max(0), (x + w).min(target.width), (y + h).min(target.height))
+}
+
+fn mix(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    [a[0] * (1.0 - t) + b[0] * t, a[1] * (1.0 - t) + b[1] * t, a[2] * (1.0 - t) + b[2] * t, a[3] * (1.0 - t) + b[3] * t]
+}
+
+/// Converts a colour channel to a byte, rounding to nearest like GL does for normalized formats.
+fn to_byte(c: f32) -> u8 {
+    (c.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
+
+/// Multiplies a row vector by one of our matrices, the same as `matrix * vector` in GLSL.
+fn transform(v: [f32; 4], m: &[f32; 16]) -> [f32; 4] {
+    let mut out = [0.0; 4];
+    for (j, out) in out.iter_mut().enumerate() {
+        *out = v[0] * m[j] + v[1] * m[4 + j] + v[2] * m[8 + j] + v[3] * m[12 + j];
+    }
+    out
+}
+
+/// Integer division rounding up, for a positive divisor.
+fn div_ceil(a: i64, b: i64) -> i64 {
+    -(-a).div_euclid(b)
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let len = dot(v, v).sqrt();
+    // GLSL gives NaN for zero vectors, which doesn't light anything either
+    if len == 0.0 { v } else { [v[0] / len, v[1] / len, v[2] / len] }
+}
+
+/// A vertex after the vertex shader, in clip space.
+#[derive(Clone, Copy)]
+struct ClipVertex {
+    pos: [f32; 4],
+    tex_coord: [f32; 2],
+    blend: [f32; 4],
+}
+
+impl ClipVertex {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let l = |a: f32, b: f32| a + (b - a) * t;
+        Self {
+            pos: [
+                l(self.pos[0], other.pos[0]),
+                l(self.pos[1], other.pos[1]),
+                l(self.pos[2], other.pos[2]),
+                l(self.pos[3], other.pos[3]),
+            ],
+            tex_coord: [l(self.tex_coord[0], other.tex_coord[0]), l(self.tex_coord[1], other.tex_coord[1])],
+            blend: [
+                l(self.blend[0], other.blend[0]),
+                l(self.blend[1], other.blend[1]),
+                l(self.blend[2], other.blend[2]),
+                l(self.blend[3], other.blend[3]),
+            ],
+        }
+    }
+
+    /// Signed distance from each of the six clip planes, positive when inside.
+    fn clip_distance(&self, plane: usize) -> f32 {
+        let axis = plane / 2;
+        match plane % 2 {
+            0 => self.pos[3] + self.pos[axis],
+            _ => self.pos[3] - self.pos[axis],
+        }
+    }
+
+    fn is_inside(&self) -> bool {
+        (0..6).all(|plane| self.clip_distance(plane) >= 0.0)
+    }
+}
+
+/// Interpolates between an inside vertex and an outside vertex on a clip plane.
+/// Always going from the inside one means edges shared by two triangles get clipped identically.
+fn clip_edge(inside: &ClipVertex, outside: &ClipVertex, plane: usize) -> ClipVertex {
+    let d_in = inside.clip_distance(plane);
+    let d_out = outside.clip_distance(plane);
+    inside.lerp(outside, d_in / (d_in - d_out))
+}
+
+/// Clips a convex polygon against the view volume.
+fn clip_polygon(mut polygon: Vec<ClipVertex>) -> Vec<ClipVertex> {
+    for plane in 0..6 {
+        if polygon.is_empty() {
+            break
+        }
+        let mut clipped = Vec::with_capacity(polygon.len() + 1);
+        for (i, a) in polygon.iter().enumerate() {
+            let b = &polygon[(i + 1) % polygon.len()];
+            let a_inside = a.clip_distance(plane) >= 0.0;
+            let b_inside = b.clip_distance(plane) >= 0.0;
+            if a_inside {
+                clipped.push(*a);
+            }
+            if a_inside && !b_inside {
+                clipped.push(clip_edge(a, b, plane));
+            } else if !a_inside && b_inside {
+                clipped.push(clip_edge(b, a, plane));
+            }
+        }
+        polygon = clipped;
+    }
+    polygon
+}
+
+/// Clips a line against the view volume, or returns None if none of it is visible.
+fn clip_line(mut a: ClipVertex, mut b: ClipVertex) -> Option<(ClipVertex, ClipVertex)> {
+    for plane in 0..6 {
+        match (a.clip_distance(plane) >= 0.0, b.clip_distance(plane) >= 0.0) {
+            (true, true) => (),
+            (true, false) => b = clip_edge(&a, &b, plane),
+            (false, true) => a = clip_edge(&b, &a, plane),
+            (false, false) => return None,
+        }
+    }
+    Some((a, b))
+}
+
+/// A vertex in window coordinates, ready to be rasterized.
+#[derive(Clone, Copy)]
+struct ScreenVertex {
+    x: f32,
+    y: f32,
+    z: f32,
+    inv_w: f32,
+    fog_z: f32,
+    tex_coord: [f32; 2],
+    blend: [f32; 4],
+}
+
+/// Values which are the same across a whole primitive, taken from its first vertex.
+struct Flat {
+    blend: [f32; 4],
+    // GL interpolates this, but it's always the same on every vertex, and interpolating it could drift off
+    atlas_xywh: [f32; 4],
+}
+
+/// Everything the fragment stage needs for a single pixel.
+struct Fragment {
+    tex_coord: [f32; 2],
+    blend: [f32; 4],
+    fog_z: f32,
+}
+
+/// Interpolates a value over a triangle, making sure it doesn't drift if it's the same at every corner.
+fn interpolate(weights: [f32; 3], values: [f32; 3]) -> f32 {
+    if values[0] == values[1] && values[1] == values[2] {
+        values[0]
+    } else {
+        weights[0] * values[0] + weights[1] * values[1] + weights[2] * values[2]
+    }
+}
+
+/// Interpolates every attribute of a fragment with perspective-correct weights.
+fn interpolate_fragment(weights: [f32; 3], v: &[ScreenVertex; 3]) -> Fragment {
+    let attr = |f: fn(&ScreenVertex) -> f32| interpolate(weights, [f(&v[0]), f(&v[1]), f(&v[2])]);
+    Fragment {
+        tex_coord: [attr(|v| v.tex_coord[0]), attr(|v| v.tex_coord[1])],
+        blend: [attr(|v| v.blend[0]), attr(|v| v.blend[1]), attr(|v| v.blend[2]), attr(|v| v.blend[3])],
+        fog_z: attr(|v| v.fog_z),
+    }
+}
+
+/// One edge of a triangle in fixed-point coordinates.
+struct Edge {
+    x: i64,
+    y: i64,
+    dx: i64,
+    dy: i64,
+    /// Subtracted from the edge function so that pixel centres exactly on an edge only get drawn once.
+    bias: i64,
+}
+
+impl Edge {
+    fn new(a: (i64, i64), b: (i64, i64)) -> Self {
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        // top-left rule, the same one D3D uses
+        let top_left = dy < 0 || (dy == 0 && dx > 0);
+        Self { x: a.0, y: a.1, dx, dy, bias: if top_left { 0 } else { 1 } }
+    }
+
+    /// Positive on the inside of a counter-clockwise triangle.
+    fn at(&self, x: i64, y: i64) -> i64 {
+        self.dx * (y - self.y) - self.dy * (x - self.x)
+    }
+}
+
+fn blend_factor(factor: BlendType, src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+    match factor {
+        BlendType::Zero => [0.0; 4],
+        BlendType::One => [1.0; 4],
+        BlendType::SrcColour => src,
+        BlendType::InvSrcColour => [1.0 - src[0], 1.0 - src[1], 1.0 - src[2], 1.0 - src[3]],
+        BlendType::SrcAlpha => [src[3]; 4],
+        BlendType::InvSrcAlpha => [1.0 - src[3]; 4],
+        BlendType::DestAlpha => [dst[3]; 4],
+        BlendType::InvDestAlpha => [1.0 - dst[3]; 4],
+        BlendType::DestColour => dst,
+        BlendType::InvDestColour => [1.0 - dst[0], 1.0 - dst[1], 1.0 - dst[2], 1.0 - dst[3]],
+        BlendType::SrcAlphaSaturate => {
+            let f = src[3].min(1.0 - dst[3]);
+            [f, f, f, 1.0]
+        },
+    }
+}
+
+pub struct RendererImpl {
+    textures: Vec<Option<Texture>>,
+    framebuffer: Texture,
+    target: Option<u32>,
+    sprites: HashMap<i32, AtlasRef>,
+    sprite_count: i32,
+    stock_atlas_count: u32,
+    current_atlas: u32,
+    zbuf_24: bool,
+    white_pixel: AtlasRef,
+    vertex_queue: Vec<Vertex>,
+    queue_type: PrimitiveShape,
+    viewport: (i32, i32, i32, i32),
+    scissor: (i32, i32, i32, i32),
+    blend_mode: (BlendType, BlendType),
+    vsync: bool,
+    interpolate_pixels: bool,
+    texture_repeat: bool,
+    circle_precision: i32,
+    using_3d: bool,
+    depth_test: bool,
+    write_depth: bool,
+    culling: bool,
+    perspective: bool,
+    depth: f32,
+    fog: Option<Fog>,
+    lighting: bool,
+    gouraud: bool,
+    normalize_normals: bool,
+    ambient_colour: i32,
+    lights: [(bool, Light); 8], // (enabled, light)
+    primitive_2d: PrimitiveBuilder,
+    primitive_3d: PrimitiveBuilder,
+
+    model_matrix: [f32; 16],
+    view_matrix: [f32; 16],
+    proj_matrix: [f32; 16],
+    viewproj_matrix: [f32; 16],
+}
+
+impl RendererImpl {
+    pub fn new(options: &RendererOptions, window: &Window, clear_colour: Colour) -> Result<Self, String> {
+        if !window.is_headless() {
+            return Err("The software renderer can only draw offscreen, so it needs a headless window".into())
+        }
+
+        #[rustfmt::skip]
+        let identity_matrix: [f32; 16] = [
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        ];
+
+        let (width, height) = (options.size.0 as i32, options.size.1 as i32);
+        let mut renderer = Self {
+            textures: vec![],
+            framebuffer: Texture::new(width, height, true),
+            target: None,
+            sprites: HashMap::new(),
+            sprite_count: 0,
+            stock_atlas_count: 0,
+            current_atlas: 0,
+            zbuf_24: options.zbuf_24,
+            white_pixel: Default::default(),
+            vertex_queue: Vec::with_capacity(1536),
+            queue_type: PrimitiveShape::Triangle,
+            viewport: (0, 0, width, height),
+            scissor: (0, 0, width, height),
+            blend_mode: (BlendType::SrcAlpha, BlendType::InvSrcAlpha),
+            vsync: options.vsync,
+            interpolate_pixels: options.interpolate_pixels,
+            texture_repeat: false,
+            circle_precision: 24,
+            using_3d: false,
+            depth_test: false,
+            write_depth: true,
+            culling: false,
+            perspective: false,
+            depth: 0.0,
+            fog: None,
+            lighting: false,
+            gouraud: true,
+            normalize_normals: options.normalize_normals,
+            ambient_colour: 0,
+            lights: [(false, Light::Directional { direction: [0.0; 3], colour: 0 }); 8],
+            primitive_2d: PrimitiveBuilder::new(Default::default(), PrimitiveType::PointList),
+            primitive_3d: PrimitiveBuilder::new(Default::default(), PrimitiveType::PointList),
+
+            model_matrix: identity_matrix,
+            view_matrix: identity_matrix,
+            proj_matrix: identity_matrix,
+            viewproj_matrix: identity_matrix,
+        };
+
+        // Start first frame
+        renderer.setup_frame(clear_colour);
+
+        Ok(renderer)
+    }
+
+    fn setup_frame(&mut self, clear_colour: Colour) {
+        let (width, height) = (self.framebuffer.width, self.framebuffer.height);
+        self.set_view(0, 0, width, height, 0.0, 0, 0, width, height);
+        self.clear_view(clear_colour, 1.0);
+    }
+
+    fn setup_queue(&mut self, atlas_id: u32, queue_type: PrimitiveShape) {
+        if atlas_id != self.current_atlas || self.queue_type != queue_type {
+            self.flush_queue();
+            self.current_atlas = atlas_id;
+            self.queue_type = queue_type;
+        }
+    }
+
+    fn push_primitive(&mut self, builder: &PrimitiveBuilder) {
+        self.setup_queue(builder.get_atlas_id(), builder.get_shape());
+        self.vertex_queue.extend_from_slice(builder.get_vertices());
+    }
+
+    fn update_matrix(&mut self) {
+        // same as in the OpenGL renderer, the half-pixel offset is baked in here
+        let offset_x = 1.0 / f64::from(self.viewport.2);
+        let offset_y = 1.0 / f64::from(self.viewport.3);
+        #[rustfmt::skip]
+        let viewproj = mat4mult(
+            mat4mult(self.view_matrix, self.proj_matrix),
+            [
+                1.0,             0.0,             0.0, 0.0,
+                0.0,             -1.0,            0.0, 0.0,
+                0.0,             0.0,             1.0, 0.0,
+                offset_x as f32, offset_y as f32, 0.0, 1.0,
+            ],
+        );
+        self.viewproj_matrix = viewproj;
+    }
+
+    fn target_mut(&mut self) -> Option<&mut Texture> {
+        match self.target {
+            Some(id) => self.textures[id as usize].as_mut(),
+            None => Some(&mut self.framebuffer),
+        }
+    }
+
+    fn quantize_depth(&self, z: f32) -> f32 {
+        // GL stores depth as 16 or 24 bit integers, so do that too
+        let max = if self.zbuf_24 { 16777215.0 } else { 65535.0 };
+        ((f64::from(z.clamp(0.0, 1.0)) * max).round() / max) as f32
+    }
+
+    /// Runs a vertex through the same steps as the vertex shader.
+    fn vertex_shader(&self, vertex: &Vertex) -> (ClipVertex, [f32; 4]) {
+        let pos = vertex.pos;
+        let world_pos = transform([pos[0], pos[1], pos[2], 1.0], &self.model_matrix);
+        let mut blend = vertex.blend;
+        let mut blend_flat = [1.0; 4];
+        if self.lighting {
+            let mut light_col = [0.0f32; 3];
+            let normal = transform([vertex.normal[0], vertex.normal[1], vertex.normal[2], 0.0], &self.model_matrix);
+            let mut normal = [-normal[0], -normal[1], -normal[2]];
+            if self.normalize_normals {
+                normal = normalize(normal);
+            }
+            for (_, light) in self.lights.iter().filter(|(enabled, _)| *enabled) {
+                let (ray, colour) = match *light {
+                    Light::Directional { direction, colour } => (direction, split_colour(colour, 1.0)),
+                    Light::Point { position, range, colour } => {
+                        let ray = [world_pos[0] - position[0], world_pos[1] - position[1], world_pos[2] - position[2]];
+                        let dist = dot(ray, ray).sqrt();
+                        let mut colour = split_colour(colour, 1.0);
+                        if dist < range {
+                            let attenuation = 1.0 + (4.0 / range) * dist;
+                            for c in colour.iter_mut() {
+                                *c /= attenuation;
+                            }
+                        } else {
+                            colour = [0.0; 4];
+                        }
+                        (ray, colour)
+                    },
+                };
+                let intensity = dot(normalize(ray), normal).clamp(0.0, 1.0);
+                for (total, c) in light_col.iter_mut().zip(colour.iter()) {
+                    *total += c * intensity;
+                }
+            }
+            let ambient = split_colour(self.ambient_colour, 1.0);
+            let lit = if self.gouraud { &mut blend } else { &mut blend_flat };
+            for i in 0..3 {
+                lit[i] = lit[i] * light_col[i] + ambient[i];
+            }
+        }
+        (
+            ClipVertex { pos: transform(world_pos, &self.viewproj_matrix), tex_coord: vertex.tex_coord, blend },
+            blend_flat,
+        )
+    }
+
+    fn to_screen(&self, v: &ClipVertex) -> Option<ScreenVertex> {
+        let w = v.pos[3];
+        if w <= 0.0 {
+            return None
+        }
+        let (vx, vy, vw, vh) = self.viewport;
+        // snap to the subpixel grid, so lines and points land on the same pixels as triangles would
+        let snap = |x: f32| (x * SUBPIXEL_ONE as f32).round() / SUBPIXEL_ONE as f32;
+        Some(ScreenVertex {
+            x: snap(vx as f32 + (v.pos[0] / w + 1.0) * (vw as f32 * 0.5)),
+            y: snap(vy as f32 + (v.pos[1] / w + 1.0) * (vh as f32 * 0.5)),
+            z: (v.pos[2] / w) * 0.5 + 0.5,
+            inv_w: 1.0 / w,
+            fog_z: v.pos[2],
+            tex_coord: v.tex_coord,
+            blend: v.blend,
+        })
+    }
+
+    /// Gets a fragment's colour the same way as the fragment shader, or None if it's discarded.
+    fn fragment_shader(&self, source: &Texture, frag: &Fragment, flat: &Flat) -> Option<[f32; 4]> {
+        let [atlas_x, atlas_y, atlas_w, atlas_h] = flat.atlas_xywh;
+        let [u, v] = frag.tex_coord;
+        let tex_col = if self.texture_repeat {
+            let sprite_x = (u - u.floor()) * atlas_w;
+            let sprite_y = (v - v.floor()) * atlas_h;
+            if self.interpolate_pixels {
+                // mix the four texels around the coordinate, wrapping within the sprite
+                let (floor_x, floor_y) = ((sprite_x - 0.5).floor(), (sprite_y - 0.5).floor());
+                let (w, h) = (atlas_w as i32, atlas_h as i32);
+                let left = atlas_x as i32 + (floor_x as i32).rem_euclid(w);
+                let right = atlas_x as i32 + (floor_x as i32 + 1).rem_euclid(w);
+                let top = atlas_y as i32 + (floor_y as i32).rem_euclid(h);
+                let bottom = atlas_y as i32 + (floor_y as i32 + 1).rem_euclid(h);
+                let (fx, fy) = (sprite_x + 0.5 - (sprite_x + 0.5).floor(), sprite_y + 0.5 - (sprite_y + 0.5).floor());
+                let top_row = mix(source.fetch(left, top), source.fetch(right, top), fx);
+                let bottom_row = mix(source.fetch(left, bottom), source.fetch(right, bottom), fx);
+                mix(top_row, bottom_row, fy)
+            } else {
+                let sprite_x = sprite_x.max(0.5).min(atlas_w - 0.5);
+                let sprite_y = sprite_y.max(0.5).min(atlas_h - 0.5);
+                source.fetch((atlas_x + sprite_x).floor() as i32, (atlas_y + sprite_y).floor() as i32)
+            }
+        } else {
+            // clamp to center of edge pixels
+            let sprite_x = (u * atlas_w).max(0.5).min(atlas_w - 0.5);
+            let sprite_y = (v * atlas_h).max(0.5).min(atlas_h - 0.5);
+            if self.interpolate_pixels {
+                source.fetch_linear(atlas_x + sprite_x, atlas_y + sprite_y)
+            } else {
+                source.fetch((atlas_x + sprite_x).floor() as i32, (atlas_y + sprite_y).floor() as i32)
+            }
+        };
+        let mut colour = [0.0; 4];
+        for i in 0..4 {
+            colour[i] = tex_col[i] * frag.blend[i] * flat.blend[i];
+        }
+        if let Some(fog) = self.fog.as_ref() {
+            let fog_colour = split_colour(fog.colour, 1.0);
+            let f = ((fog.end - frag.fog_z) / (fog.end - fog.begin)).clamp(0.0, 1.0);
+            for i in 0..3 {
+                colour[i] = (1.0 - f) * fog_colour[i] + f * colour[i];
+            }
+        }
+        // alpha test, which is tied to the depth test
+        if self.depth_test && colour[3] <= 0.0 {
+            return None
+        }
+        Some(colour)
+    }
+
+    /// Depth tests and blends a fragment onto the target.
+    fn write_fragment(&self, target: &mut Texture, x: i32, y: i32, depth: f32, colour: [f32; 4]) {
+        let index = (y * target.width + x) as usize;
+        if self.depth_test {
+            if let Some(zbuf) = target.zbuf.as_mut() {
+                let depth = self.quantize_depth(depth);
+                if depth > zbuf[index] {
+                    return
+                }
+                if self.write_depth {
+                    zbuf[index] = depth;
+                }
+            }
+        }
+        let pixel = &mut target.pixels[index * 4..index * 4 + 4];
+        let src = [
+            colour[0].clamp(0.0, 1.0),
+            colour[1].clamp(0.0, 1.0),
+            colour[2].clamp(0.0, 1.0),
+            colour[3].clamp(0.0, 1.0),
+        ];
+        let dst = [
+            f32::from(pixel[0]) / 255.0,
+            f32::from(pixel[1]) / 255.0,
+            f32::from(pixel[2]) / 255.0,
+            f32::from(pixel[3]) / 255.0,
+        ];
+        let src_factor = blend_factor(self.blend_mode.0, src, dst);
+        let dst_factor = blend_factor(self.blend_mode.1, src, dst);
+        for i in 0..4 {
+            pixel[i] = to_byte(src[i] * src_factor[i] + dst[i] * dst_factor[i]);
+        }
+    }
+
+    fn rasterize_triangle(&self, target: &mut Texture, source: &Texture, mut v: [ScreenVertex; 3], flat: &Flat) {
+        let fixed =
+            |v: &ScreenVertex| ((v.x * SUBPIXEL_ONE as f32).round() as i64, (v.y * SUBPIXEL_ONE as f32).round() as i64);
+        let mut p = [fixed(&v[0]), fixed(&v[1]), fixed(&v[2])];
+        let mut area = (p[1].0 - p[0].0) * (p[2].1 - p[0].1) - (p[2].0 - p[0].0) * (p[1].1 - p[0].1);
+        if area == 0 || (self.culling && area < 0) {
+            return
+        }
+        if area < 0 {
+            v.swap(1, 2);
+            p.swap(1, 2);
+            area = -area;
+        }
+
+        let (left, top, right, bottom) = draw_bounds(self.scissor, target);
+        let half = SUBPIXEL_ONE / 2;
+        let min_x = ((p[0].0.min(p[1].0).min(p[2].0) - half) >> SUBPIXEL_BITS).max(i64::from(left));
+        let min_y = ((p[0].1.min(p[1].1).min(p[2].1) - half) >> SUBPIXEL_BITS).max(i64::from(top));
+        let max_x = ((p[0].0.max(p[1].0).max(p[2].0) - half) >> SUBPIXEL_BITS).min(i64::from(right) - 1);
+        let max_y = ((p[0].1.max(p[1].1).max(p[2].1) - half) >> SUBPIXEL_BITS).min(i64::from(bottom) - 1);
+        if min_x > max_x || min_y > max_y {
+            return
+        }
+
+        // each edge is opposite the vertex it weights
+        let edges = [Edge::new(p[1], p[2]), Edge::new(p[2], p[0]), Edge::new(p[0], p[1])];
+        let area = area as f64;
+        for y in min_y..=max_y {
+            let sample_y = (y << SUBPIXEL_BITS) + half;
+            let sample_x = (min_x << SUBPIXEL_BITS) + half;
+            let mut e =
+                [edges[0].at(sample_x, sample_y), edges[1].at(sample_x, sample_y), edges[2].at(sample_x, sample_y)];
+            for x in min_x..=max_x {
+                if e.iter().zip(edges.iter()).all(|(e, edge)| e - edge.bias >= 0) {
+                    let l = [(e[0] as f64 / area) as f32, (e[1] as f64 / area) as f32, (e[2] as f64 / area) as f32];
+                    let depth = interpolate(l, [v[0].z, v[1].z, v[2].z]);
+                    let q = [l[0] * v[0].inv_w, l[1] * v[1].inv_w, l[2] * v[2].inv_w];
+                    let sum = q[0] + q[1] + q[2];
+                    let frag = interpolate_fragment([q[0] / sum, q[1] / sum, q[2] / sum], &v);
+                    if let Some(colour) = self.fragment_shader(source, &frag, flat) {
+                        self.write_fragment(target, x as i32, y as i32, depth, colour);
+                    }
+                }
+                for (e, edge) in e.iter_mut().zip(edges.iter()) {
+                    *e -= edge.dy << SUBPIXEL_BITS;
+                }
+            }
+        }
+    }
+
+    /// Draws a line, filling the pixels whose centres it passes along its major axis.
+    /// Like in GL, the last pixel isn't drawn so that line strips don't overlap.
+    fn rasterize_line(&self, target: &mut Texture, source: &Texture, a: ScreenVertex, b: ScreenVertex, flat: &Flat) {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        if dx == 0.0 && dy == 0.0 {
+            return
+        }
+        let x_major = dx.abs() >= dy.abs();
+        // pixels are picked in fixed-point, since float error would break the ties between two pixels
+        let fixed = |v: f32| (v * SUBPIXEL_ONE as f32).round() as i64;
+        let (major, minor) = if x_major { ((a.x, b.x), (a.y, b.y)) } else { ((a.y, b.y), (a.x, b.x)) };
+        let (major_a, major_b, minor_a, minor_b) = (fixed(major.0), fixed(major.1), fixed(minor.0), fixed(minor.1));
+        let (major_delta, minor_delta) = (major_b - major_a, minor_b - minor_a);
+        let (start, delta) = (major.0, major.1 - major.0);
+        let half = SUBPIXEL_ONE / 2;
+        let (first, last) = if major_delta > 0 {
+            (div_ceil(major_a - half, SUBPIXEL_ONE), div_ceil(major_b - half, SUBPIXEL_ONE) - 1)
+        } else {
+            ((major_b - half).div_euclid(SUBPIXEL_ONE) + 1, (major_a - half).div_euclid(SUBPIXEL_ONE))
+        };
+        let (left, top, right, bottom) = draw_bounds(self.scissor, target);
+        for i in first..=last {
+            let t = ((i as f32 + 0.5 - start) / delta).clamp(0.0, 1.0);
+            // the minor axis at this pixel's centre is minor_num / major_delta, and the pixel is ceil of that minus one
+            let centre = i * SUBPIXEL_ONE + half;
+            let minor_num = minor_a * major_delta + (centre - major_a) * minor_delta;
+            let j = if major_delta > 0 {
+                div_ceil(minor_num, major_delta * SUBPIXEL_ONE)
+            } else {
+                div_ceil(-minor_num, -major_delta * SUBPIXEL_ONE)
+            } - 1;
+            let (x, y) = if x_major { (i as i32, j as i32) } else { (j as i32, i as i32) };
+            if x < left || x >= right || y < top || y >= bottom {
+                continue
+            }
+            let depth = interpolate([1.0 - t, t, 0.0], [a.z, b.z, b.z]);
+            let q = [(1.0 - t) * a.inv_w, t * b.inv_w];
+            let sum = q[0] + q[1];
+            let frag = interpolate_fragment([q[0] / sum, q[1] / sum, 0.0], &[a, b, b]);
+            if let Some(colour) = self.fragment_shader(source, &frag, flat) {
+                self.write_fragment(target, x, y, depth, colour);
+            }
+        }
+    }
+
+    fn rasterize_point(&self, target: &mut Texture, source: &Texture, v: ScreenVertex, flat: &Flat) {
+        // the pixel whose centre is in the point's square, counting its top and left edges
+        let (x, y) = (v.x.ceil() as i32 - 1, v.y.ceil() as i32 - 1);
+        let (left, top, right, bottom) = draw_bounds(self.scissor, target);
+        if x < left || x >= right || y < top || y >= bottom {
+            return
+        }
+        let frag = Fragment { tex_coord: v.tex_coord, blend: v.blend, fog_z: v.fog_z };
+        if let Some(colour) = self.fragment_shader(source, &frag, flat) {
+            self.write_fragment(target, x, y, v.z, colour);
+        }
+    }
+
+    fn rasterize(&self, target: &mut Texture, source: &Texture, shape: PrimitiveShape, buffer: &[Vertex]) {
+        let vertices_per_primitive = match shape {
+            PrimitiveShape::Point => 1,
+            PrimitiveShape::Line => 2,
+            PrimitiveShape::Triangle => 3,
+        };
+        for primitive in buffer.chunks_exact(vertices_per_primitive) {
+            let shaded: Vec<(ClipVertex, [f32; 4])> = primitive.iter().map(|v| self.vertex_shader(v)).collect();
+            // flat values come from the first vertex, like D3D
+            let flat = Flat { blend: shaded[0].1, atlas_xywh: primitive[0].atlas_xywh };
+            match shape {
+                PrimitiveShape::Point => {
+                    if let Some(v) = Some(shaded[0].0).filter(ClipVertex::is_inside).and_then(|v| self.to_screen(&v)) {
+                        self.rasterize_point(target, source, v, &flat);
+                    }
+                },
+                PrimitiveShape::Line => {
+                    if let Some((a, b)) = clip_line(shaded[0].0, shaded[1].0) {
+                        if let (Some(a), Some(b)) = (self.to_screen(&a), self.to_screen(&b)) {
+                            self.rasterize_line(target, source, a, b, &flat);
+                        }
+                    }
+                },
+                PrimitiveShape::Triangle => {
+                    let mut polygon: Vec<ClipVertex> = shaded.iter().map(|(v, _)| *v).collect();
+                    if !polygon.iter().all(ClipVertex::is_inside) {
+                        polygon = clip_polygon(polygon);
+                    }
+                    let screen: Option<Vec<ScreenVertex>> = polygon.iter().map(|v| self.to_screen(v)).collect();
+                    if let Some(screen) = screen {
+                        for i in 2..screen.len() {
+                            self.rasterize_triangle(target, source, [screen[0], screen[i - 1], screen[i]], &flat);
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    fn draw_buffer(&mut self, atlas_id: u32, shape: PrimitiveShape, buffer: &[Vertex]) {
+        if buffer.is_empty() {
+            return
+        }
+
+        // move the target out of self so that it can be drawn to while reading everything else
+        let mut target = match self.target {
+            Some(id) => match self.textures[id as usize].take() {
+                Some(target) => target,
+                None => return,
+            },
+            None => mem::take(&mut self.framebuffer),
+        };
+
+        // drawing a surface onto itself is undefined in GL, so just draw what was there before
+        let feedback;
+        let source = match self.textures.get(atlas_id as usize) {
+            Some(Some(source)) => Some(source),
+            _ if self.target == Some(atlas_id) => {
+                feedback = target.clone();
+                Some(&feedback)
+            },
+            _ => None,
+        };
+        if let Some(source) = source {
+            self.rasterize(&mut target, source, shape, buffer);
+        }
+
+        match self.target {
+            Some(id) => self.textures[id as usize] = Some(target),
+            None => self.framebuffer = target,
+        }
+    }
+
+    fn clear(&mut self, colour: Option<[u8; 4]>, depth: bool) {
+        let scissor = self.scissor;
+        let depth = depth && self.write_depth;
+        if let Some(target) = self.target_mut() {
+            let (left, top, right, bottom) = draw_bounds(scissor, target);
+            for y in top..bottom {
+                for x in left..right {
+                    let index = (y * target.width + x) as usize;
+                    if let Some(colour) = colour {
+                        target.pixels[index * 4..index * 4 + 4].copy_from_slice(&colour);
+                    }
+                    if let Some(zbuf) = target.zbuf.as_mut().filter(|_| depth) {
+                        zbuf[index] = 1.0;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl RendererTrait for RendererImpl {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn max_texture_size(&self) -> u32 {
+        MAX_TEXTURE_SIZE
+    }
+
+    fn push_atlases(&mut self, mut atl: AtlasBuilder) -> Result<(), String> {
+        assert!(self.textures.is_empty(), "atlases should be initialized only once");
+        self.white_pixel =
+            atl.texture(1, 1, 0, 0, Box::new([0xFF, 0xFF, 0xFF, 0xFF])).ok_or("Couldn't pack white_pixel")?;
+        // update primitive buffers with white pixel
+        self.reset_primitive_2d(PrimitiveType::PointList, None);
+        self.reset_primitive_3d(PrimitiveType::PointList, None);
+
+        let (packers, sprites) = atl.into_inner();
+
+        let mut textures: Vec<Texture> = packers
+            .iter()
+            .map(|packer| {
+                let (width, height) = packer.size();
+                Texture::new(width, height, false)
+            })
+            .collect();
+
+        // copy in the sprites, which are BGRA
+        for (atl_ref, pixels) in &sprites {
+            let texture = &mut textures[atl_ref.atlas_id as usize];
+            for row in 0..atl_ref.h {
+                for col in 0..atl_ref.w {
+                    let src = ((row * atl_ref.w + col) * 4) as usize;
+                    let dst = (((atl_ref.y + row) * texture.width + atl_ref.x + col) * 4) as usize;
+                    let bgra = &pixels[src..src + 4];
+                    texture.pixels[dst..dst + 4].copy_from_slice(&[bgra[2], bgra[1], bgra[0], bgra[3]]);
+                }
+            }
+            self.sprite_count += 1;
+        }
+
+        self.stock_atlas_count = textures.len() as u32;
+        self.textures = textures.into_iter().map(Some).collect();
+
+        Ok(())
+    }
+
+    fn upload_sprite(
+        &mut self,
+        data: Box<[u8]>,
+        width: i32,
+        height: i32,
+        origin_x: i32,
+        origin_y: i32,
+    ) -> Result<AtlasRef, String> {
+        let atlas_ref = AtlasRef {
+            origin_x: origin_x as f32 / width as f32,
+            origin_y: origin_y as f32 / height as f32,
+            ..self.create_surface(width, height, false)?
+        };
+        let texture = self.textures[atlas_ref.atlas_id as usize].as_mut().unwrap();
+        if data.len() != texture.pixels.len() {
+            return Err(format!("Sprite data is {} bytes, but it should be {}", data.len(), texture.pixels.len()))
+        }
+        texture.pixels.copy_from_slice(&data);
+        Ok(atlas_ref)
+    }
+
+    fn duplicate_sprite(&mut self, atlas_ref: &AtlasRef) -> Result<AtlasRef, String> {
+        let pixels = self.dump_sprite(atlas_ref);
+        let new_sprite = self.create_surface(atlas_ref.w, atlas_ref.h, false)?;
+        self.textures[new_sprite.atlas_id as usize].as_mut().unwrap().pixels.copy_from_slice(&pixels);
+        Ok(new_sprite)
+    }
+
+    fn delete_sprite(&mut self, atlas_ref: AtlasRef) {
+        // this only deletes sprites created with upload_sprite
+        self.flush_queue();
+        self.sprites.remove(&atlas_ref.sprite_id);
+        if atlas_ref.atlas_id >= self.stock_atlas_count {
+            self.textures[atlas_ref.atlas_id as usize] = None;
+        }
+    }
+
+    fn set_vsync(&self, _vsync: bool) {
+        // nothing is ever displayed, so there's nothing to sync to
+    }
+
+    fn get_vsync(&self) -> bool {
+        self.vsync
+    }
+
+    fn wait_vsync(&self) {}
+
+    fn create_sprite_colour(&mut self, width: i32, height: i32, col: Colour) -> Result<AtlasRef, String> {
+        let atlas_ref = self.create_surface(width, height, false)?;
+        let colour = [to_byte(col.r as f32), to_byte(col.g as f32), to_byte(col.b as f32), 255];
+        for pixel in self.textures[atlas_ref.atlas_id as usize].as_mut().unwrap().pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&colour);
+        }
+        Ok(atlas_ref)
+    }
+
+    fn create_surface(&mut self, width: i32, height: i32, has_zbuffer: bool) -> Result<AtlasRef, String> {
+        if width < 0 || height < 0 || width as u32 > MAX_TEXTURE_SIZE || height as u32 > MAX_TEXTURE_SIZE {
+            return Err(format!("Can't make a {}x{} texture", width, height))
+        }
+        let texture = Texture::new(width, height, has_zbuffer);
+        let atlas_id = if let Some(id) = self.textures.iter().position(|x| x.is_none()) {
+            self.textures[id] = Some(texture);
+            id as u32
+        } else {
+            self.textures.push(Some(texture));
+            self.textures.len() as u32 - 1
+        };
+        let sprite_id = self.sprite_count;
+        self.sprite_count += 1;
+        Ok(AtlasRef { atlas_id, sprite_id, x: 0, y: 0, w: width, h: height, origin_x: 0.0, origin_y: 0.0 })
+    }
+
+    fn set_target(&mut self, atlas_ref: &AtlasRef) {
+        self.flush_queue();
+        if let Some(Some(_)) = self.textures.get(atlas_ref.atlas_id as usize) {
+            self.target = Some(atlas_ref.atlas_id);
+            // set viewport here since set_view doesn't
+            self.viewport = (atlas_ref.x, atlas_ref.y, atlas_ref.w, atlas_ref.h);
+            self.scissor = self.viewport;
+            self.set_view(
+                atlas_ref.x,
+                atlas_ref.y,
+                atlas_ref.w,
+                atlas_ref.h,
+                0.0,
+                atlas_ref.x,
+                atlas_ref.y,
+                atlas_ref.w,
+                atlas_ref.h,
+            );
+        }
+    }
+
+    fn copy_surface_part(
+        &mut self,
+        dest: &AtlasRef,
+        dest_x: i32,
+        dest_y: i32,
+        src: &AtlasRef,
+        part_x: i32,
+        part_y: i32,
+        part_w: i32,
+        part_h: i32,
+    ) -> Result<(), String> {
+        self.flush_queue();
+        if let Some(Some(_)) = self.textures.get(src.atlas_id as usize) {
+            // the part is read out before anything's written, so copies within one surface can overlap
+            let pixels = self.dump_sprite_part(src, part_x, part_y, part_w, part_h);
+            if let Some(Some(texture)) = self.textures.get_mut(dest.atlas_id as usize) {
+                texture.write(dest.x + dest_x, dest.y + dest_y, part_w, part_h, &pixels);
+            }
+        }
+        Ok(())
+    }
+
+    fn reset_target(&mut self) {
+        self.flush_queue();
+        self.target = None;
+        let (fb_width, fb_height) = (self.framebuffer.width, self.framebuffer.height);
+        self.set_view(0, 0, fb_width, fb_height, 0.0, 0, 0, fb_width, fb_height);
+    }
+
+    fn resize_framebuffer(&mut self, width: u32, height: u32) {
+        self.flush_queue();
+        let mut framebuffer = Texture::new(width as i32, height as i32, true);
+        let old = &self.framebuffer;
+        let copy_width = framebuffer.width.min(old.width) as usize;
+        for row in 0..framebuffer.height.min(old.height) as usize {
+            let (src, dst) = (row * old.width as usize, row * framebuffer.width as usize);
+            framebuffer.pixels[dst * 4..(dst + copy_width) * 4]
+                .copy_from_slice(&old.pixels[src * 4..(src + copy_width) * 4]);
+            if let (Some(new_zbuf), Some(old_zbuf)) = (framebuffer.zbuf.as_mut(), old.zbuf.as_ref()) {
+                new_zbuf[dst..dst + copy_width].copy_from_slice(&old_zbuf[src..src + copy_width]);
+            }
+        }
+        self.framebuffer = framebuffer;
+    }
+
+    fn get_texture_id(&mut self, atl_ref: &AtlasRef) -> i32 {
+        self.sprites.entry(atl_ref.sprite_id).or_insert(*atl_ref);
+        atl_ref.sprite_id
+    }
+
+    fn get_texture_from_id(&self, id: i32) -> Option<&AtlasRef> {
+        if id >= 0 { self.sprites.get(&id) } else { None }
+    }
+
+    fn get_sprite_count(&self) -> i32 {
+        self.sprite_count
+    }
+
+    fn set_sprite_count(&mut self, sprite_count: i32) {
+        self.sprite_count = sprite_count;
+    }
+
+    fn dump_sprite(&self, atlas_ref: &AtlasRef) -> Box<[u8]> {
+        self.textures[atlas_ref.atlas_id as usize].as_ref().expect("Trying to dump nonexistent sprite").read(
+            atlas_ref.x,
+            atlas_ref.y,
+            atlas_ref.w,
+            atlas_ref.h,
+        )
+    }
+
+    fn get_pixels(&self, x: i32, y: i32, w: i32, h: i32) -> Box<[u8]> {
+        self.framebuffer.read(x, y, w, h)
+    }
+
+    fn dump_zbuffer(&self) -> Box<[f32]> {
+        self.framebuffer.zbuf.clone().unwrap_or_default().into_boxed_slice()
+    }
+
+    fn draw_raw_frame(
+        &mut self,
+        rgba: Box<[u8]>,
+        zbuf: Box<[f32]>,
+        fb_w: i32,
+        fb_h: i32,
+        window_w: u32,
+        window_h: u32,
+        scaling: Scaling,
+    ) {
+        self.resize_framebuffer(fb_w as _, fb_h as _);
+        if rgba.len() == self.framebuffer.pixels.len() {
+            self.framebuffer.pixels.copy_from_slice(&rgba);
+        }
+        if let Some(fb_zbuf) = self.framebuffer.zbuf.as_mut().filter(|z| z.len() == zbuf.len()) {
+            fb_zbuf.copy_from_slice(&zbuf);
+        }
+        self.vertex_queue.clear();
+        self.present(window_w as _, window_h as _, scaling);
+        self.set_view(0, 0, fb_w, fb_h, 0.0, 0, 0, fb_w, fb_h);
+    }
+
+    fn dump_dynamic_textures(&self) -> Vec<Option<SavedTexture>> {
+        self.textures
+            .iter()
+            .skip(self.stock_atlas_count as usize)
+            .map(|texture| {
+                texture.as_ref().map(|t| SavedTexture {
+                    width: t.width,
+                    height: t.height,
+                    pixels: t.pixels.clone().into_boxed_slice(),
+                    zbuf: t.zbuf.clone().map(Vec::into_boxed_slice),
+                })
+            })
+            .collect()
+    }
+
+    fn upload_dynamic_textures(&mut self, textures: &[Option<SavedTexture>]) {
+        self.textures.truncate(self.stock_atlas_count as usize);
+        self.textures.extend(textures.iter().map(|texture| {
+            texture.as_ref().map(|t| Texture {
+                width: t.width,
+                height: t.height,
+                pixels: t.pixels.to_vec(),
+                zbuf: t.zbuf.as_ref().map(|z| z.to_vec()),
+            })
+        }));
+    }
+
+    fn draw_sprite_general(
+        &mut self,
+        texture: &AtlasRef,
+        part_x: f64,
+        part_y: f64,
+        part_w: f64,
+        part_h: f64,
+        x: f64,
+        y: f64,
+        xscale: f64,
+        yscale: f64,
+        angle: f64,
+        col1: i32,
+        col2: i32,
+        col3: i32,
+        col4: i32,
+        alpha: f64,
+        use_origin: bool,
+    ) {
+        let atlas_ref = *texture;
+
+        if !matches!(self.textures.get(atlas_ref.atlas_id as usize), Some(Some(_))) {
+            return // fail silently when drawing deleted sprite fonts
+        }
+        self.set_texture_repeat(false);
+
+        // get angle
+        let angle = -angle.to_radians();
+        let angle_sin = libm::sin(angle);
+        let angle_cos = libm::cos(angle);
+
+        // get real width of drawn sprite
+        let width: f64 = xscale * part_w;
+        let height: f64 = yscale * part_h;
+        // calculate pre-rotation corner offsets from sprite origin
+        // incl. subtraction 0.5 from left and top (GM does this in an attempt to combat the DX half-pixel offset)
+        let (left, top): (f64, f64) = if use_origin {
+            (-width * f64::from(atlas_ref.origin_x) - 0.5, -height * f64::from(atlas_ref.origin_y) - 0.5)
+        } else {
+            (-0.5, -0.5)
+        };
+        let right: f64 = left + width;
+        let bottom: f64 = top + height;
+
+        // get texture corners
+        let tex_left = part_x / f64::from(atlas_ref.w);
+        let tex_top = part_y / f64::from(atlas_ref.h);
+        let tex_right = tex_left + part_w / f64::from(atlas_ref.w);
+        let tex_bottom = tex_top + part_h / f64::from(atlas_ref.h);
+
+        let (tex_left, tex_top, tex_right, tex_bottom) =
+            (tex_left as f32, tex_top as f32, tex_right as f32, tex_bottom as f32);
+
+        let normal = [0.0, 0.0, 0.0];
+        let depth = self.depth;
+
+        // rotate around draw origin
+        let rotate = |xoff, yoff| {
+            [(x + xoff * angle_cos - yoff * angle_sin) as f32, (y + yoff * angle_cos + xoff * angle_sin) as f32, depth]
+        };
+
+        // push the vertices
+        self.push_primitive(
+            PrimitiveBuilder::new(atlas_ref, PrimitiveType::TriFan)
+                .push_vertex(rotate(left, top), [tex_left, tex_top], split_colour(col1, alpha), normal)
+                .push_vertex(rotate(right, top), [tex_right, tex_top], split_colour(col2, alpha), normal)
+                .push_vertex(rotate(right, bottom), [tex_right, tex_bottom], split_colour(col3, alpha), normal)
+                .push_vertex(rotate(left, bottom), [tex_left, tex_bottom], split_colour(col4, alpha), normal),
+        );
+    }
+
+    fn draw_rectangle(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, colour: i32, alpha: f64) {
+        let x2 = if x2 == x2.floor() { x2 + 0.01 } else { x2 };
+        let y2 = if y2 == y2.floor() { y2 + 0.01 } else { y2 };
+        self.push_primitive(
+            ShapeBuilder::new(false, self.white_pixel, alpha, self.depth)
+                .push_point(x1, y1, colour)
+                .push_point(x2, y1, colour)
+                .push_point(x2, y2, colour)
+                .push_point(x1, y2, colour)
+                .build(),
+        );
+    }
+
+    fn draw_rectangle_outline(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, colour: i32, alpha: f64) {
+        let x2 = if x2 == x2.floor() { x2 + 0.01 } else { x2 };
+        let y2 = if y2 == y2.floor() { y2 + 0.01 } else { y2 };
+        self.push_primitive(
+            ShapeBuilder::new(true, self.white_pixel, alpha, self.depth)
+                .push_point(x1, y1, colour)
+                .push_point(x2, y1, colour)
+                .push_point(x2, y2, colour)
+                .push_point(x1, y2, colour)
+                .build(),
+        );
+    }
+
+    fn draw_rectangle_gradient(
+        &mut self,
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+        c1: i32,
+        c2: i32,
+        c3: i32,
+        c4: i32,
+        alpha: f64,
+        outline: bool,
+    ) {
+        let x2 = if x2 == x2.floor() { x2 + 0.01 } else { x2 };
+        let y2 = if y2 == y2.floor() { y2 + 0.01 } else { y2 };
+        self.push_primitive(
+            ShapeBuilder::new(outline, self.white_pixel, alpha, self.depth)
+                .push_point(x1, y1, c1)
+                .push_point(x2, y1, c2)
+                .push_point(x2, y2, c3)
+                .push_point(x1, y2, c4)
+                .build(),
+        );
+    }
+
+    fn draw_point(&mut self, x: f64, y: f64, colour: i32, alpha: f64) {
+        self.setup_queue(self.white_pixel.atlas_id, PrimitiveShape::Point);
+        self.vertex_queue.push(Vertex {
+            pos: [x as f32, y as f32, self.depth],
+            tex_coord: [0.0, 0.0],
+            blend: split_colour(colour, alpha),
+            atlas_xywh: self.white_pixel.into(),
+            normal: [0.0, 0.0, 0.0],
+        });
+    }
+
+    fn draw_line(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, width: Option<f64>, c1: i32, c2: i32, alpha: f64) {
+        if let Some(width) = width {
+            let length = libm::hypot(x2 - x1, y2 - y1);
+            // on the off chance that they're in different points but the length is still somehow 0, check length
+            if length != 0.0 {
+                // calculate corners
+                let width_x = (y2 - y1) * (width / 2.0) / length;
+                let width_y = (x2 - x1) * (width / 2.0) / length;
+                // actually push the rectangle
+                self.push_primitive(
+                    ShapeBuilder::new(false, self.white_pixel, alpha, self.depth)
+                        .push_point(x1 - width_x, y1 + width_y, c1)
+                        .push_point(x1 + width_x, y1 - width_y, c1)
+                        .push_point(x2 + width_x, y2 - width_y, c2)
+                        .push_point(x2 - width_x, y2 + width_y, c2)
+                        .build(),
+                );
+            }
+        } else {
+            self.push_primitive(
+                ShapeBuilder::new(true, self.white_pixel, alpha, self.depth)
+                    .push_point(x1, y1, c1)
+                    .push_point(x2, y2, c2)
+                    .build(),
+            );
+        }
+    }
+
+    fn draw_triangle(
+        &mut self,
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+        x3: f64,
+        y3: f64,
+        c1: i32,
+        c2: i32,
+        c3: i32,
+        alpha: f64,
+        outline: bool,
+    ) {
+        self.push_primitive(
+            ShapeBuilder::new(outline, self.white_pixel, alpha, self.depth)
+                .push_point(x1, y1, c1)
+                .push_point(x2, y2, c2)
+                .push_point(x3, y3, c3)
+                .build(),
+        );
+    }
+
+    fn draw_ellipse(&mut self, x: f64, y: f64, rad_x: f64, rad_y: f64, c1: i32, c2: i32, alpha: f64, outline: bool) {
+        let mut builder = ShapeBuilder::new(outline, self.white_pixel, alpha, self.depth);
+        if !outline {
+            builder.push_point(x, y, c1);
+        }
+        for i in 0..=self.circle_precision {
+            let angle = f64::from(i) * 2.0 * PI / f64::from(self.circle_precision);
+            builder.push_point(x + rad_x * libm::cos(angle), y + rad_y * libm::sin(angle), c2);
+        }
+        self.push_primitive(builder.build());
+    }
+
+    fn draw_roundrect(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, c1: i32, c2: i32, alpha: f64, outline: bool) {
+        let x2 = if x2 == x2.floor() { x2 + 0.01 } else { x2 };
+        let y2 = if y2 == y2.floor() { y2 + 0.01 } else { y2 };
+        let xcenter = (x1 + x2) / 2.0;
+        let ycenter = (y1 + y2) / 2.0;
+        let width = (x2 - x1).abs();
+        let height = (y2 - y1).abs();
+        let rad_x = width.min(10.0) / 2.0;
+        let rad_y = height.min(10.0) / 2.0;
+        let rect_half_w = (width / 2.0 - rad_x).max(0.0);
+        let rect_half_h = (height / 2.0 - rad_y).max(0.0);
+        let mut builder = ShapeBuilder::new(outline, self.white_pixel, alpha, self.depth);
+        if !outline {
+            builder.push_point(xcenter, ycenter, c1);
+        }
+        let quarter_circle = self.circle_precision / 4;
+        for quad in 0..4 {
+            let circle_x = xcenter + if quad == 0 || quad == 3 { rect_half_w } else { -rect_half_w };
+            let circle_y = ycenter + if quad < 2 { rect_half_h } else { -rect_half_h };
+            for i in quarter_circle * quad..=quarter_circle * (quad + 1) {
+                let angle = f64::from(i) * 2.0 * PI / f64::from(self.circle_precision);
+                builder.push_point(circle_x + rad_x * libm::cos(angle), circle_y + rad_y * libm::sin(angle), c2);
+            }
+        }
+        self.push_primitive(builder.push_point(xcenter + rect_half_w + rad_x, ycenter + rect_half_h, c2).build());
+    }
+
+    fn set_circle_precision(&mut self, prec: i32) {
+        self.circle_precision = (prec.clamp(4, 64) >> 2) << 2;
+    }
+
+    fn get_circle_precision(&self) -> i32 {
+        self.circle_precision
+    }
+
+    fn reset_primitive_2d(&mut self, ptype: PrimitiveType, atlas_ref: Option<AtlasRef>) {
+        self.primitive_2d = PrimitiveBuilder::new(atlas_ref.unwrap_or(self.white_pixel), ptype);
+    }
+
+    fn vertex_2d(&mut self, x: f64, y: f64, xtex: f64, ytex: f64, col: i32, alpha: f64) {
+        self.primitive_2d.push_vertex(
+            [x as f32, y as f32, self.depth],
+            [xtex as f32, ytex as f32],
+            split_colour(col, alpha),
+            [0.0, 0.0, 0.0],
+        );
+    }
+
+    fn draw_primitive_2d(&mut self) {
+        // I would use push_primitive but that causes borrowing issues.
+        self.setup_queue(self.primitive_2d.get_atlas_id(), self.primitive_2d.get_shape());
+        self.vertex_queue.extend_from_slice(self.primitive_2d.get_vertices());
+    }
+
+    fn get_primitive_2d(&self) -> PrimitiveBuilder {
+        self.primitive_2d.clone()
+    }
+
+    fn set_primitive_2d(&mut self, prim: PrimitiveBuilder) {
+        self.primitive_2d = prim;
+    }
+
+    fn reset_primitive_3d(&mut self, ptype: PrimitiveType, atlas_ref: Option<AtlasRef>) {
+        self.primitive_3d = PrimitiveBuilder::new(atlas_ref.unwrap_or(self.white_pixel), ptype);
+    }
+
+    fn vertex_3d(
+        &mut self,
+        x: f64,
+        y: f64,
+        z: f64,
+        nx: f64,
+        ny: f64,
+        nz: f64,
+        xtex: f64,
+        ytex: f64,
+        col: i32,
+        alpha: f64,
+    ) {
+        self.primitive_3d.push_vertex(
+            [x as f32, y as f32, z as f32],
+            [xtex as f32, ytex as f32],
+            split_colour(col, alpha),
+            [nx as f32, ny as f32, nz as f32],
+        );
+    }
+
+    fn draw_primitive_3d(&mut self) {
+        // See draw_primitive_2d.
+        self.setup_queue(self.primitive_3d.get_atlas_id(), self.primitive_3d.get_shape());
+        self.vertex_queue.extend_from_slice(self.primitive_3d.get_vertices());
+    }
+
+    fn get_primitive_3d(&self) -> PrimitiveBuilder {
+        self.primitive_3d.clone()
+    }
+
+    fn set_primitive_3d(&mut self, prim: PrimitiveBuilder) {
+        self.primitive_3d = prim;
+    }
+
+    fn extend_buffers(&self, buf: &mut VertexBuffer) {
+        let verts = self.primitive_3d.get_vertices();
+        match self.primitive_3d.get_shape() {
+            PrimitiveShape::Point => buf.points.extend_from_slice(verts),
+            PrimitiveShape::Line => buf.lines.extend_from_slice(&verts[..verts.len() / 2 * 2]),
+            PrimitiveShape::Triangle => buf.tris.extend_from_slice(&verts[..verts.len() / 3 * 3]),
+        }
+    }
+
+    fn draw_buffers(&mut self, atlas_ref: Option<AtlasRef>, buf: &VertexBuffer) {
+        self.flush_queue();
+        let atlas_id = atlas_ref.unwrap_or(self.white_pixel).atlas_id;
+        self.draw_buffer(atlas_id, PrimitiveShape::Point, &buf.points);
+        self.draw_buffer(atlas_id, PrimitiveShape::Line, &buf.lines);
+        self.draw_buffer(atlas_id, PrimitiveShape::Triangle, &buf.tris);
+    }
+
+    fn get_blend_mode(&self) -> (BlendType, BlendType) {
+        self.blend_mode
+    }
+
+    fn set_blend_mode(&mut self, src: BlendType, dst: BlendType) {
+        self.flush_queue();
+        self.blend_mode = (src, dst);
+    }
+
+    fn get_pixel_interpolation(&self) -> bool {
+        self.interpolate_pixels
+    }
+
+    fn set_pixel_interpolation(&mut self, lerping: bool) {
+        if self.interpolate_pixels != lerping {
+            self.flush_queue();
+            self.interpolate_pixels = lerping;
+        }
+    }
+
+    fn get_texture_repeat(&self) -> bool {
+        self.texture_repeat
+    }
+
+    fn set_texture_repeat(&mut self, repeat: bool) {
+        if self.texture_repeat != repeat {
+            self.flush_queue();
+            self.texture_repeat = repeat;
+        }
+    }
+
+    /// Does anything that's queued to be done.
+    fn flush_queue(&mut self) {
+        // move the queue out of self to satisfy the borrow checker
+        let mut queue = mem::take(&mut self.vertex_queue);
+        self.draw_buffer(self.current_atlas, self.queue_type, &queue);
+        // clear it and put it back so we can reuse the memory
+        queue.clear();
+        self.vertex_queue = queue;
+    }
+
+    fn set_view_matrix(&mut self, view: [f32; 16]) {
+        self.flush_queue();
+        self.view_matrix = view;
+        self.update_matrix();
+    }
+
+    fn set_viewproj_matrix(&mut self, view: [f32; 16], proj: [f32; 16]) {
+        self.flush_queue();
+        self.view_matrix = view;
+        self.proj_matrix = proj;
+        self.update_matrix();
+    }
+
+    fn get_model_matrix(&self) -> [f32; 16] {
+        self.model_matrix
+    }
+
+    fn set_model_matrix(&mut self, model: [f32; 16]) {
+        self.flush_queue();
+        self.model_matrix = model;
+        self.update_matrix();
+    }
+
+    fn mult_model_matrix(&mut self, model: [f32; 16]) {
+        self.flush_queue();
+        self.model_matrix = mat4mult(self.model_matrix, model);
+        self.update_matrix();
+    }
+
+    fn set_projection_ortho(&mut self, x: f64, y: f64, w: f64, h: f64, angle: f64) {
+        // Draw anything that was meant to be drawn with the old view first
+        self.flush_queue();
+
+        #[rustfmt::skip]
+        let proj_matrix: [f32; 16] = {
+            // Squish to screen, flip vertically, and constrain z to range 1 - 32000
+            [
+                2.0 / w as f32, 0.0,             0.0,            0.0,
+                0.0,            -2.0 / h as f32, 0.0,            0.0,
+                0.0,            0.0,             1.0 / 31999.0,  0.0,
+                0.0,            0.0,             -1.0 / 31999.0, 1.0,
+            ]
+        };
+
+        self.set_viewproj_matrix(make_view_matrix(x, y, -16000.0, w, h, angle), proj_matrix);
+    }
+
+    fn set_projection_perspective(&mut self, x: f64, y: f64, w: f64, h: f64, angle: f64) {
+        self.flush_queue();
+
+        #[rustfmt::skip]
+        let proj_matrix: [f32; 16] = {
+            // Squish to screen, flip vertically, and constrain z to range 1 - 32000
+            [
+                2.0, 0.0,                  0.0,                0.0,
+                0.0, 2.0 * (w / h) as f32, 0.0,                0.0,
+                0.0, 0.0,                  32000.0 / 31999.0,  1.0,
+                0.0, 0.0,                  -32000.0 / 31999.0, 0.0,
+            ]
+        };
+
+        self.set_viewproj_matrix(make_view_matrix(x, y, -w, w, h, angle), proj_matrix);
+    }
+
+    fn set_view(
+        &mut self,
+        src_x: i32,
+        src_y: i32,
+        src_w: i32,
+        src_h: i32,
+        src_angle: f64,
+        port_x: i32,
+        port_y: i32,
+        port_w: i32,
+        port_h: i32,
+    ) {
+        // DX8's viewport function doesn't do anything if a surface is set as the draw target, so emulate that
+        if self.target.is_none() && port_x >= 0 && port_y >= 0 && port_w >= 0 && port_h >= 0 {
+            self.flush_queue();
+            self.viewport = (port_x, port_y, port_w, port_h);
+            self.scissor = self.viewport;
+        }
+        if self.using_3d && self.perspective {
+            self.set_projection_perspective(src_x.into(), src_y.into(), src_w.into(), src_h.into(), src_angle);
+        } else {
+            self.set_projection_ortho(src_x.into(), src_y.into(), src_w.into(), src_h.into(), src_angle);
+        }
+    }
+
+    fn present(&mut self, _window_width: u32, _window_height: u32, _scaling: Scaling) {
+        // there's no screen, so presenting just means finishing the frame
+        self.flush_queue();
+    }
+
+    fn finish(&mut self, window_width: u32, window_height: u32, clear_colour: Colour) {
+        // Present screen
+        self.present(window_width, window_height, Scaling::Fixed(1.0));
+
+        // Start next frame
+        self.setup_frame(clear_colour)
+    }
+
+    fn clear_view(&mut self, colour: Colour, alpha: f64) {
+        self.flush_queue();
+        let colour =
+            [to_byte(colour.r as f32), to_byte(colour.g as f32), to_byte(colour.b as f32), to_byte(alpha as f32)];
+        self.clear(Some(colour), true);
+    }
+
+    fn clear_zbuf(&mut self) {
+        if self.using_3d {
+            self.flush_queue();
+            self.clear(None, true);
+        }
+    }
+
+    fn get_3d(&self) -> bool {
+        self.using_3d
+    }
+
+    fn set_3d(&mut self, use_3d: bool) {
+        self.using_3d = use_3d;
+        self.set_depth_test(use_3d);
+        self.set_perspective(use_3d);
+    }
+
+    fn get_depth(&self) -> f32 {
+        self.depth
+    }
+
+    fn set_depth(&mut self, depth: f32) {
+        self.depth = if self.using_3d { depth.clamp(-16000.0, 16000.0) } else { 0.0 };
+    }
+
+    fn get_depth_test(&self) -> bool {
+        self.depth_test
+    }
+
+    fn set_depth_test(&mut self, depth_test: bool) {
+        let depth_test = depth_test && self.using_3d;
+        if self.depth_test != depth_test {
+            self.flush_queue();
+            self.depth_test = depth_test;
+        }
+    }
+
+    fn get_write_depth(&self) -> bool {
+        self.write_depth
+    }
+
+    fn set_write_depth(&mut self, write_depth: bool) {
+        if self.write_depth != write_depth {
+            self.flush_queue();
+            self.write_depth = write_depth;
+        }
+    }
+
+    fn get_culling(&self) -> bool {
+        self.culling
+    }
+
+    fn set_culling(&mut self, culling: bool) {
+        if self.culling != culling {
+            self.flush_queue();
+            self.culling = culling;
+        }
+    }
+
+    fn get_perspective(&self) -> bool {
+        self.perspective
+    }
+
+    fn set_perspective(&mut self, perspective: bool) {
+        // don't need to flush_queue for this because this only affects set_view
+        self.perspective = perspective;
+    }
+
+    fn get_fog(&self) -> Option<Fog> {
+        self.fog.clone()
+    }
+
+    fn set_fog(&mut self, fog: Option<Fog>) {
+        if fog != self.fog {
+            self.flush_queue();
+            self.fog = fog;
+        }
+    }
+
+    fn get_gouraud(&self) -> bool {
+        self.gouraud
+    }
+
+    fn set_gouraud(&mut self, gouraud: bool) {
+        if self.gouraud != gouraud {
+            self.flush_queue();
+            self.gouraud = gouraud;
+        }
+    }
+
+    fn get_lighting_enabled(&self) -> bool {
+        self.lighting
+    }
+
+    fn set_lighting_enabled(&mut self, enabled: bool) {
+        if self.lighting != enabled {
+            self.flush_queue();
+            self.lighting = enabled;
+        }
+    }
+
+    fn get_ambient_colour(&self) -> i32 {
+        self.ambient_colour
+    }
+
+    fn set_ambient_colour(&mut self, colour: i32) {
+        if self.ambient_colour != colour {
+            self.flush_queue();
+            self.ambient_colour = colour;
+        }
+    }
+
+    fn get_lights(&self) -> [(bool, Light); 8] {
+        self.lights
+    }
+
+    fn set_light_enabled(&mut self, id: usize, enabled: bool) {
+        if self.lights[id].0 != enabled {
+            self.flush_queue();
+            self.lights[id].0 = enabled;
+        }
+    }
+
+    fn set_light(&mut self, id: usize, light: Light) {
+        if self.lights[id].1 != light {
+            self.flush_queue();
+            self.lights[id].1 = light;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        atlas::AtlasRef,
+        render::{Backend, BlendType, Renderer, RendererOptions},
+        window::{Window, WindowBuilder},
+    };
+    use shared::types::Colour;
+
+    fn renderer() -> Renderer {
+        let window = Window::new(&WindowBuilder::new().with_size(8, 8).with_headless(true)).unwrap();
+        Renderer::new(Backend::Software, &RendererOptions::default(), &window, Colour::new(0.0, 0.0, 0.0)).unwrap()
+    }
+
+    fn surface(renderer: &mut Renderer, w: i32, h: i32, has_zbuffer: bool, colour: Colour, alpha: f64) -> AtlasRef {
+        let surf = renderer.create_surface(w, h, has_zbuffer).unwrap();
+        renderer.set_target(&surf);
+        renderer.clear_view(colour, alpha);
+        renderer.reset_target();
+        surf
+    }
+
+    #[test]
+    fn surface_copy_is_raw() {
+        let mut renderer = renderer();
+        let src = surface(&mut renderer, 2, 2, false, Colour::new(1.0, 0.0, 0.0), 0.5);
+        let dest = surface(&mut renderer, 3, 3, true, Colour::new(0.0, 0.0, 1.0), 1.0);
+
+        // none of these affect copies, which keep the source alpha as it is
+        renderer.set_blend_mode(BlendType::One, BlendType::One);
+        renderer.set_3d(true);
+        renderer.set_depth(-1000.0);
+        renderer.set_target(&dest);
+        renderer.copy_surface_part(&dest, 1, 1, &src, 0, 0, 2, 2).unwrap();
+        renderer.reset_target();
+
+        let pixels = renderer.dump_sprite(&dest);
+        for (i, pixel) in pixels.chunks_exact(4).enumerate() {
+            if i % 3 > 0 && i / 3 > 0 {
+                assert_eq!(pixel, [255, 0, 0, 128]);
+            } else {
+                assert_eq!(pixel, [0, 0, 255, 255]);
+            }
+        }
+    }
+
+    #[test]
+    fn surface_copy_overlapping() {
+        let mut renderer = renderer();
+        let red = surface(&mut renderer, 1, 1, false, Colour::new(1.0, 0.0, 0.0), 1.0);
+        let blue = surface(&mut renderer, 1, 1, false, Colour::new(0.0, 0.0, 1.0), 1.0);
+        let row = surface(&mut renderer, 4, 1, false, Colour::new(0.0, 1.0, 0.0), 1.0);
+        renderer.copy_surface_part(&row, 0, 0, &red, 0, 0, 1, 1).unwrap();
+        renderer.copy_surface_part(&row, 1, 0, &blue, 0, 0, 1, 1).unwrap();
+
+        // copying in place one pixel at a time would smear the red across
+        renderer.copy_surface_part(&row, 1, 0, &row, 0, 0, 3, 1).unwrap();
+        assert_eq!(&*renderer.dump_sprite(&row), &[
+            255, 0, 0, 255, //
+            255, 0, 0, 255, //
+            0, 0, 255, 255, //
+            0, 255, 0, 255, //
+        ]);
+    }
+
+    /// FNV-1a, so pixel dumps can be pinned without checking them in.
+    fn hash(pixels: &[u8]) -> u64 {
+        pixels.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3))
+    }
+
+    #[test]
+    fn rotation_is_deterministic() {
+        let mut renderer = renderer();
+        let sprite = surface(&mut renderer, 5, 3, false, Colour::new(1.0, 0.5, 0.0), 1.0);
+        let dest = surface(&mut renderer, 32, 32, false, Colour::new(0.0, 0.0, 0.0), 1.0);
+        renderer.set_target(&dest);
+        renderer.set_projection_ortho(0.0, 0.0, 32.0, 32.0, 7.0);
+        renderer.draw_sprite(&sprite, 10.0, 10.0, 2.5, 1.5, 33.0, 0xffffff, 1.0);
+        renderer.set_circle_precision(24);
+        renderer.draw_ellipse(20.0, 21.0, 9.5, 6.25, 0x00ff00, 0x0000ff, 1.0, false);
+        renderer.reset_target();
+        assert_eq!(hash(&renderer.dump_sprite(&dest)), 0x1b72_8379_0a35_1259);
+    }
+}
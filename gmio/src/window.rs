@@ -1,5 +1,6 @@
 //! Windowing magic.
 
+pub mod headless;
 pub mod win32;
 pub mod xorg;
 
@@ -109,7 +110,16 @@ impl Window {
 
     /// Creates a new Window, invisible by default.
     pub fn new(builder: &WindowBuilder) -> Result<Self, String> {
-        Ok(Self(Box::new(platform::WindowImpl::new(builder)?)))
+        if builder.headless {
+            Ok(Self(Box::new(headless::WindowImpl::new(builder))))
+        } else {
+            Ok(Self(Box::new(platform::WindowImpl::new(builder)?)))
+        }
+    }
+
+    /// Returns whether this is a headless window, meaning there's no real window or user.
+    pub fn is_headless(&self) -> bool {
+        self.as_any().is::<headless::WindowImpl>()
     }
 
     /// Returns whether the window requested to be closed.
@@ -223,6 +233,7 @@ impl Window {
 
 pub struct WindowBuilder {
     cursor: Cursor,
+    headless: bool,
     size: (u32, u32),
     style: Style,
     title: String,
@@ -230,7 +241,13 @@ pub struct WindowBuilder {
 
 impl Default for WindowBuilder {
     fn default() -> Self {
-        Self { cursor: Cursor::default(), size: (640, 480), style: Style::Regular, title: String::new() }
+        Self {
+            cursor: Cursor::default(),
+            headless: false,
+            size: (640, 480),
+            style: Style::Regular,
+            title: String::new(),
+        }
     }
 }
 
@@ -243,6 +260,11 @@ impl WindowBuilder {
         Self { cursor, ..self }
     }
 
+    /// Makes a headless window, which never opens and has no events. It can still be rendered to offscreen.
+    pub fn with_headless(self, headless: bool) -> Self {
+        Self { headless, ..self }
+    }
+
     pub fn with_size(self, width: u32, height: u32) -> Self {
         Self { size: (width, height), ..self }
     }
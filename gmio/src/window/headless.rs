@@ -0,0 +1,133 @@
+//! A window that doesn't exist, for running games without a display.
+
+use super::{Cursor, Event, Style, WindowBuilder, WindowTrait};
+use std::{any::Any, slice};
+
+/// Size of the display this window pretends to be on.
+pub const DISPLAY_WIDTH: i32 = 1024;
+pub const DISPLAY_HEIGHT: i32 = 768;
+
+pub struct WindowImpl {
+    close_requested: bool,
+    cursor: Cursor,
+    inner_size: (u32, u32),
+    pos: (i32, i32),
+    title: String,
+    visible: bool,
+}
+
+impl WindowImpl {
+    pub fn new(builder: &WindowBuilder) -> Self {
+        let mut window = Self {
+            close_requested: false,
+            cursor: builder.cursor,
+            inner_size: builder.size,
+            pos: (0, 0),
+            title: builder.title.clone(),
+            visible: false,
+        };
+        if let Style::BorderlessFullscreen = builder.style {
+            window.inner_size = (DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32);
+        }
+        window.center();
+        window
+    }
+}
+
+impl WindowTrait for WindowImpl {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn close_requested(&self) -> bool {
+        self.close_requested
+    }
+
+    fn set_close_requested(&mut self, value: bool) {
+        self.close_requested = value
+    }
+
+    fn get_inner_size(&self) -> (u32, u32) {
+        self.inner_size
+    }
+
+    fn process_events<'a>(&'a mut self) -> slice::Iter<'a, Event> {
+        // nobody's there to make any
+        [].iter()
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.inner_size = (width, height);
+        self.center();
+    }
+
+    fn center(&mut self) {
+        let (width, height) = self.inner_size;
+        self.pos = ((DISPLAY_WIDTH - width as i32) / 2, (DISPLAY_HEIGHT - height as i32) / 2);
+    }
+
+    fn get_pos(&self) -> (i32, i32) {
+        self.pos
+    }
+
+    fn set_pos(&mut self, x: i32, y: i32) {
+        self.pos = (x, y);
+    }
+
+    fn get_cursor(&self) -> Cursor {
+        self.cursor
+    }
+
+    fn set_cursor(&mut self, cursor: Cursor) {
+        self.cursor = cursor;
+    }
+
+    fn set_style(&mut self, _style: Style) {}
+
+    fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.title = title.to_owned();
+    }
+
+    fn get_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    fn show_context_menu(&mut self, _options: &[(String, usize)]) {}
+
+    fn window_handle(&self) -> usize {
+        0
+    }
+
+    fn display_width(&self) -> i32 {
+        DISPLAY_WIDTH
+    }
+
+    fn display_height(&self) -> i32 {
+        DISPLAY_HEIGHT
+    }
+
+    fn display_frequency(&self) -> i32 {
+        60
+    }
+
+    fn display_colour(&self) -> i32 {
+        32
+    }
+
+    // the host's disks would make runs behave differently on different machines
+    fn disk_free(&self, _drive: Option<char>) -> Option<u64> {
+        None
+    }
+
+    fn disk_size(&self, _drive: Option<char>) -> Option<u64> {
+        None
+    }
+}
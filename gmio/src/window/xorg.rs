@@ -1,12 +1,55 @@
 #![cfg(target_os = "linux")]
 
 use super::{Cursor, Event, Style, WindowBuilder, WindowTrait};
-use std::{any::Any, ffi, ptr, slice};
+use shared::input::{Key, MouseButton, VK_F1, VK_NUMPAD0};
+use std::{
+    any::Any,
+    ffi::{self, CString},
+    mem,
+    ops::Drop,
+    os::raw::{c_char, c_int, c_long, c_uint, c_ulong},
+    path::PathBuf,
+    ptr, slice,
+};
 use x11::{
+    keysym::*,
     xlib,
     xlib::{XDisplayHeight, XDisplayWidth},
 };
 
+// cursorfont.h, which the x11 crate doesn't have
+const XC_BOTTOM_LEFT_CORNER: c_uint = 12;
+const XC_BOTTOM_RIGHT_CORNER: c_uint = 14;
+const XC_CROSSHAIR: c_uint = 34;
+const XC_FLEUR: c_uint = 52;
+const XC_HAND2: c_uint = 60;
+const XC_LEFT_PTR: c_uint = 68;
+const XC_SB_H_DOUBLE_ARROW: c_uint = 108;
+const XC_SB_UP_ARROW: c_uint = 114;
+const XC_SB_V_DOUBLE_ARROW: c_uint = 116;
+const XC_WATCH: c_uint = 150;
+const XC_XTERM: c_uint = 152;
+
+// _MOTIF_WM_HINTS, which is what window managers look at for decorations
+const MWM_HINTS_FUNCTIONS: c_ulong = 1 << 0;
+const MWM_HINTS_DECORATIONS: c_ulong = 1 << 1;
+const MWM_FUNC_ALL: c_ulong = 1 << 0;
+const MWM_FUNC_MOVE: c_ulong = 1 << 2;
+const MWM_FUNC_MINIMIZE: c_ulong = 1 << 3;
+const MWM_FUNC_CLOSE: c_ulong = 1 << 5;
+
+const EVENT_MASK: c_long = xlib::KeyPressMask
+    | xlib::KeyReleaseMask
+    | xlib::ButtonPressMask
+    | xlib::ButtonReleaseMask
+    | xlib::PointerMotionMask
+    | xlib::EnterWindowMask
+    | xlib::LeaveWindowMask
+    | xlib::StructureNotifyMask;
+
+/// Height of one option in a context menu, in pixels.
+const MENU_ITEM_HEIGHT: c_int = 20;
+
 pub struct WindowImpl {
     pub display: *mut xlib::Display,
     pub window_id: u64,
@@ -15,6 +58,161 @@ pub struct WindowImpl {
     pub inner_size: (u32, u32),
     pub visible: bool,
     pub events: Vec<Event>,
+
+    cursor: Cursor,
+    cursor_handle: xlib::Cursor,
+    style: Style,
+    title: String,
+
+    /// stored as to not re-emit stale mouse coordinates tracked outside the window
+    mouse_cache: Option<(c_int, c_int)>,
+
+    /// whether the mouse is inside the window, yielding motion events
+    mouse_tracked: bool,
+
+    wm_delete_window: xlib::Atom,
+    context_menu: Option<ContextMenu>,
+}
+
+/// X has no native menus, so context menus are popup windows we draw ourselves.
+struct ContextMenu {
+    window: xlib::Window,
+    gc: xlib::GC,
+    font: *mut xlib::XFontStruct,
+    options: Vec<(CString, usize)>,
+    width: c_int,
+    hovered: Option<usize>,
+}
+
+#[allow(non_upper_case_globals)]
+fn key_from_keysym(keysym: c_uint) -> Option<Key> {
+    Some(match keysym {
+        // these line up with the virtual key codes
+        XK_a..=XK_z => return Key::from_winapi((keysym - XK_a) as u8 + b'A'),
+        XK_A..=XK_Z | XK_0..=XK_9 => return Key::from_winapi(keysym as u8),
+        XK_KP_0..=XK_KP_9 => return Key::from_winapi((keysym - XK_KP_0) as u8 + VK_NUMPAD0),
+        XK_F1..=XK_F12 => return Key::from_winapi((keysym - XK_F1) as u8 + VK_F1),
+
+        XK_BackSpace => Key::Backspace,
+        XK_Tab | XK_ISO_Left_Tab => Key::Tab,
+        XK_Return | XK_KP_Enter => Key::Enter,
+        XK_Escape => Key::Escape,
+        XK_space => Key::Space,
+        XK_Pause => Key::Pause,
+        XK_Print => Key::PrintScreen,
+
+        // windows only reports the generic versions of these in WM_KEYDOWN, so do the same
+        XK_Shift_L | XK_Shift_R => Key::Shift,
+        XK_Control_L | XK_Control_R => Key::Control,
+        XK_Alt_L | XK_Alt_R | XK_Meta_L | XK_Meta_R => Key::Alt,
+        XK_Super_L => Key::LeftWin,
+        XK_Super_R => Key::RightWin,
+        XK_Caps_Lock => Key::CapsLock,
+        XK_Num_Lock => Key::NumLock,
+        XK_Scroll_Lock => Key::ScrollLock,
+
+        XK_Left | XK_KP_Left => Key::Left,
+        XK_Right | XK_KP_Right => Key::Right,
+        XK_Up | XK_KP_Up => Key::Up,
+        XK_Down | XK_KP_Down => Key::Down,
+        XK_Home | XK_KP_Home => Key::Home,
+        XK_End | XK_KP_End => Key::End,
+        XK_Prior | XK_KP_Prior => Key::PageUp,
+        XK_Next | XK_KP_Next => Key::PageDown,
+        XK_Insert | XK_KP_Insert => Key::Insert,
+        XK_Delete | XK_KP_Delete => Key::Delete,
+
+        XK_KP_Add => Key::Add,
+        XK_KP_Subtract => Key::Subtract,
+        XK_KP_Multiply => Key::Multiply,
+        XK_KP_Divide => Key::Divide,
+        XK_KP_Decimal => Key::Decimal,
+
+        // these are the US layout positions of the OEM keys
+        XK_semicolon => Key::OEM1,
+        XK_slash => Key::OEM2,
+        XK_grave => Key::OEM3,
+        XK_bracketleft => Key::OEM4,
+        XK_backslash => Key::OEM5,
+        XK_bracketright => Key::OEM6,
+        XK_apostrophe => Key::OEM7,
+        XK_less => Key::OEM102,
+        XK_comma => Key::Comma,
+        XK_minus => Key::Minus,
+        XK_period => Key::Period,
+        XK_equal => Key::Plus,
+
+        _ => return None,
+    })
+}
+
+/// Finds the keysym for a key event, ignoring modifiers except for num lock on the keypad,
+/// which is how windows picks virtual key codes.
+unsafe fn lookup_keysym(event: &mut xlib::XKeyEvent) -> c_uint {
+    let keysym = xlib::XLookupKeysym(event, 0) as c_uint;
+    if (XK_KP_Space..=XK_KP_Equal).contains(&keysym) && event.state & xlib::Mod2Mask != 0 {
+        let numlocked = xlib::XLookupKeysym(event, 1) as c_uint;
+        if numlocked != 0 {
+            return numlocked
+        }
+    }
+    keysym
+}
+
+unsafe fn load_cursor(display: *mut xlib::Display, window: xlib::Window, cursor: Cursor) -> xlib::Cursor {
+    let shape = match cursor {
+        Cursor::Arrow => XC_LEFT_PTR,
+        Cursor::AppStart => XC_WATCH,
+        Cursor::Beam => XC_XTERM,
+        Cursor::Cross => XC_CROSSHAIR,
+        Cursor::Hand => XC_HAND2,
+        Cursor::Hourglass => XC_WATCH,
+        Cursor::Invisible => {
+            // an empty 1x1 bitmap
+            let data = [0 as c_char; 1];
+            let pixmap = xlib::XCreateBitmapFromData(display, window, data.as_ptr(), 1, 1);
+            let mut colour: xlib::XColor = mem::zeroed();
+            let handle = xlib::XCreatePixmapCursor(display, pixmap, pixmap, &mut colour, &mut colour, 0, 0);
+            xlib::XFreePixmap(display, pixmap);
+            return handle
+        },
+        Cursor::SizeNESW => XC_BOTTOM_LEFT_CORNER,
+        Cursor::SizeNS => XC_SB_V_DOUBLE_ARROW,
+        Cursor::SizeNWSE => XC_BOTTOM_RIGHT_CORNER,
+        Cursor::SizeWE => XC_SB_H_DOUBLE_ARROW,
+        Cursor::SizeAll => XC_FLEUR,
+        Cursor::Up => XC_SB_UP_ARROW,
+    };
+    xlib::XCreateFontCursor(display, shape)
+}
+
+unsafe fn intern_atom(display: *mut xlib::Display, name: &str) -> xlib::Atom {
+    let name = CString::new(name).unwrap();
+    xlib::XInternAtom(display, name.as_ptr(), xlib::False)
+}
+
+fn disk_path(drive: Option<char>) -> PathBuf {
+    // there are no drive letters here, so any drive is the root filesystem
+    match drive {
+        Some(_) => "/".into(),
+        None => std::env::current_dir().unwrap(),
+    }
+}
+
+/// Returns the free and total space on a drive in bytes.
+#[allow(clippy::unnecessary_cast)] // these are only u64 on 64-bit
+fn disk_space(drive: Option<char>) -> Option<(u64, u64)> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = CString::new(disk_path(drive).as_os_str().as_bytes()).ok()?;
+    unsafe {
+        let mut stat: libc::statvfs = mem::zeroed();
+        if libc::statvfs(path.as_ptr(), &mut stat) == 0 {
+            let block_size = stat.f_frsize as u64;
+            Some((stat.f_bavail as u64 * block_size, stat.f_blocks as u64 * block_size))
+        } else {
+            None
+        }
+    }
 }
 
 impl WindowImpl {
@@ -28,36 +226,235 @@ impl WindowImpl {
             let screen_id = xlib::XDefaultScreen(display);
             let root = xlib::XRootWindow(display, screen_id);
 
-            let mut attributes: xlib::XSetWindowAttributes = std::mem::MaybeUninit::uninit().assume_init();
-            attributes.background_pixel = xlib::XWhitePixel(display, screen_id);
+            let mut attributes: xlib::XSetWindowAttributes = mem::zeroed();
+            attributes.background_pixel = xlib::XBlackPixel(display, screen_id);
+            attributes.event_mask = EVENT_MASK;
+
+            let size = match builder.style {
+                Style::BorderlessFullscreen => {
+                    (XDisplayWidth(display, screen_id) as u32, XDisplayHeight(display, screen_id) as u32)
+                },
+                _ => builder.size,
+            };
+            let x = (XDisplayWidth(display, screen_id) - size.0 as c_int) / 2;
+            let y = (XDisplayHeight(display, screen_id) - size.1 as c_int) / 2;
 
             let window_id = xlib::XCreateWindow(
                 display,
                 root,
-                100,
-                100,
-                builder.size.0,
-                builder.size.1,
-                0,
+                x,
+                y,
+                size.0,
+                size.1,
                 0,
+                xlib::CopyFromParent,
                 xlib::InputOutput as _,
                 ptr::null_mut(),
-                xlib::CWBackPixel,
+                xlib::CWBackPixel | xlib::CWEventMask,
                 &mut attributes,
             );
 
             let title = ffi::CString::new(builder.title.clone()).unwrap();
             xlib::XStoreName(display, window_id, title.as_ptr() as *mut _);
 
-            Ok(Self {
+            // ask the window manager to tell us about the X button instead of killing the connection
+            let mut wm_delete_window = intern_atom(display, "WM_DELETE_WINDOW");
+            xlib::XSetWMProtocols(display, window_id, &mut wm_delete_window, 1);
+
+            let cursor_handle = load_cursor(display, window_id, builder.cursor);
+            xlib::XDefineCursor(display, window_id, cursor_handle);
+
+            let mut window = Self {
                 display,
                 window_id,
                 screen_id,
                 close_requested: false,
-                inner_size: builder.size,
+                inner_size: size,
                 visible: false,
                 events: Vec::with_capacity(8),
-            })
+
+                cursor: builder.cursor,
+                cursor_handle,
+                style: builder.style,
+                title: builder.title.clone(),
+                mouse_cache: None,
+                mouse_tracked: false,
+                wm_delete_window,
+                context_menu: None,
+            };
+            window.set_style(builder.style);
+            Ok(window)
+        }
+    }
+
+    /// Tells the window manager which decorations to use, and whether the window can be resized.
+    unsafe fn apply_style(&mut self) {
+        let (functions, decorations) = match self.style {
+            Style::Regular => (MWM_FUNC_MOVE | MWM_FUNC_MINIMIZE | MWM_FUNC_CLOSE, 1),
+            Style::Resizable => (MWM_FUNC_ALL, 1),
+            Style::Undecorated => (MWM_FUNC_MOVE, 1),
+            Style::Borderless | Style::BorderlessFullscreen => (MWM_FUNC_MOVE | MWM_FUNC_MINIMIZE | MWM_FUNC_CLOSE, 0),
+        };
+        let hints: [c_ulong; 5] = [MWM_HINTS_FUNCTIONS | MWM_HINTS_DECORATIONS, functions, decorations, 0, 0];
+        let motif_hints = intern_atom(self.display, "_MOTIF_WM_HINTS");
+        xlib::XChangeProperty(
+            self.display,
+            self.window_id,
+            motif_hints,
+            motif_hints,
+            32,
+            xlib::PropModeReplace,
+            hints.as_ptr().cast(),
+            hints.len() as c_int,
+        );
+        self.apply_size_hints();
+
+        // fullscreen is a window manager state, which has to be requested differently once the window is mapped
+        let net_wm_state = intern_atom(self.display, "_NET_WM_STATE");
+        let fullscreen = intern_atom(self.display, "_NET_WM_STATE_FULLSCREEN");
+        let enable = matches!(self.style, Style::BorderlessFullscreen);
+        if self.visible {
+            let mut event: xlib::XEvent = mem::zeroed();
+            event.client_message.type_ = xlib::ClientMessage;
+            event.client_message.window = self.window_id;
+            event.client_message.message_type = net_wm_state;
+            event.client_message.format = 32;
+            event.client_message.data.set_long(0, if enable { 1 } else { 0 }); // _NET_WM_STATE_ADD / REMOVE
+            event.client_message.data.set_long(1, fullscreen as c_long);
+            xlib::XSendEvent(
+                self.display,
+                xlib::XRootWindow(self.display, self.screen_id),
+                xlib::False,
+                xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask,
+                &mut event,
+            );
+        } else if enable {
+            xlib::XChangeProperty(
+                self.display,
+                self.window_id,
+                net_wm_state,
+                xlib::XA_ATOM,
+                32,
+                xlib::PropModeReplace,
+                (&fullscreen as *const xlib::Atom).cast(),
+                1,
+            );
+        } else {
+            xlib::XDeleteProperty(self.display, self.window_id, net_wm_state);
+        }
+    }
+
+    /// Pins the window to its current size, unless it's resizable.
+    unsafe fn apply_size_hints(&mut self) {
+        let mut hints: xlib::XSizeHints = mem::zeroed();
+        if !matches!(self.style, Style::Resizable) {
+            let (width, height) = self.inner_size;
+            hints.flags = xlib::PMinSize | xlib::PMaxSize;
+            hints.min_width = width as c_int;
+            hints.max_width = width as c_int;
+            hints.min_height = height as c_int;
+            hints.max_height = height as c_int;
+        }
+        xlib::XSetWMNormalHints(self.display, self.window_id, &mut hints);
+    }
+
+    fn push_key_event(&mut self, event: &mut xlib::XKeyEvent, down: bool) {
+        if let Some(key) = key_from_keysym(unsafe { lookup_keysym(event) }) {
+            self.events.push(if down { Event::KeyboardDown(key) } else { Event::KeyboardUp(key) });
+        }
+    }
+
+    /// Checks if a KeyRelease is immediately followed by a matching KeyPress, which is how X reports key repeat.
+    /// Windows only sends repeated WM_KEYDOWNs, so the release is skipped.
+    unsafe fn is_key_repeat(&mut self, release: &xlib::XKeyEvent) -> bool {
+        if xlib::XPending(self.display) == 0 {
+            return false
+        }
+        let mut next: xlib::XEvent = mem::zeroed();
+        xlib::XPeekEvent(self.display, &mut next);
+        next.get_type() == xlib::KeyPress && next.key.time == release.time && next.key.keycode == release.keycode
+    }
+
+    fn close_context_menu(&mut self) {
+        if let Some(menu) = self.context_menu.take() {
+            unsafe {
+                xlib::XUngrabPointer(self.display, xlib::CurrentTime);
+                if !menu.font.is_null() {
+                    xlib::XFreeFont(self.display, menu.font);
+                }
+                xlib::XFreeGC(self.display, menu.gc);
+                xlib::XDestroyWindow(self.display, menu.window);
+            }
+        }
+    }
+
+    fn draw_context_menu(&self) {
+        if let Some(menu) = &self.context_menu {
+            unsafe {
+                let black = xlib::XBlackPixel(self.display, self.screen_id);
+                let white = xlib::XWhitePixel(self.display, self.screen_id);
+                let ascent = if menu.font.is_null() { 12 } else { (*menu.font).ascent };
+                for (i, (description, _)) in menu.options.iter().enumerate() {
+                    let top = i as c_int * MENU_ITEM_HEIGHT;
+                    let highlighted = menu.hovered == Some(i);
+                    xlib::XSetForeground(self.display, menu.gc, if highlighted { black } else { white });
+                    xlib::XFillRectangle(
+                        self.display,
+                        menu.window,
+                        menu.gc,
+                        0,
+                        top,
+                        menu.width as c_uint,
+                        MENU_ITEM_HEIGHT as c_uint,
+                    );
+                    xlib::XSetForeground(self.display, menu.gc, if highlighted { white } else { black });
+                    xlib::XDrawString(
+                        self.display,
+                        menu.window,
+                        menu.gc,
+                        8,
+                        top + (MENU_ITEM_HEIGHT + ascent) / 2 - 1,
+                        description.as_ptr(),
+                        description.as_bytes().len() as c_int,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Handles an event for the context menu window, or for anywhere else while the menu has the pointer grabbed.
+    fn process_context_menu_event(&mut self, event: &xlib::XEvent) {
+        let (width, count) = match &self.context_menu {
+            Some(menu) => (menu.width, menu.options.len() as c_int),
+            None => return,
+        };
+        let hovered_at = |x: c_int, y: c_int| {
+            if x >= 0 && x < width && y >= 0 && y < count * MENU_ITEM_HEIGHT {
+                Some((y / MENU_ITEM_HEIGHT) as usize)
+            } else {
+                None
+            }
+        };
+        unsafe {
+            match event.get_type() {
+                xlib::Expose => self.draw_context_menu(),
+                xlib::MotionNotify => {
+                    let hovered = hovered_at(event.motion.x, event.motion.y);
+                    if let Some(menu) = self.context_menu.as_mut().filter(|m| m.hovered != hovered) {
+                        menu.hovered = hovered;
+                        self.draw_context_menu();
+                    }
+                },
+                xlib::ButtonRelease => {
+                    if let Some(i) = hovered_at(event.button.x, event.button.y) {
+                        let id = self.context_menu.as_ref().unwrap().options[i].1;
+                        self.events.push(Event::MenuOption(id));
+                        self.close_context_menu();
+                    }
+                },
+                xlib::ButtonPress if hovered_at(event.button.x, event.button.y).is_none() => self.close_context_menu(),
+                _ => (),
+            }
         }
     }
 }
@@ -80,34 +477,170 @@ impl WindowTrait for WindowImpl {
     }
 
     fn process_events<'a>(&'a mut self) -> slice::Iter<'a, Event> {
-        // TODO
+        self.events.clear();
+        unsafe {
+            while xlib::XPending(self.display) > 0 {
+                let mut event: xlib::XEvent = mem::zeroed();
+                xlib::XNextEvent(self.display, &mut event);
+
+                // while the menu is open it has the pointer grabbed, so all mouse input goes to it
+                if self.context_menu.as_ref().map(|m| m.window) == Some(event.any.window) {
+                    self.process_context_menu_event(&event);
+                    continue
+                }
+                if event.any.window != self.window_id {
+                    continue
+                }
+
+                match event.get_type() {
+                    xlib::ClientMessage => {
+                        if event.client_message.data.get_long(0) as xlib::Atom == self.wm_delete_window {
+                            self.close_requested = true;
+                        }
+                    },
+
+                    // keyboard events
+                    xlib::KeyPress => self.push_key_event(&mut event.key, true),
+                    xlib::KeyRelease => {
+                        if !self.is_key_repeat(&event.key) {
+                            self.push_key_event(&mut event.key, false);
+                        }
+                    },
+
+                    // mouse events
+                    xlib::ButtonPress => match event.button.button {
+                        xlib::Button1 => self.events.push(Event::MouseButtonDown(MouseButton::Left)),
+                        xlib::Button2 => self.events.push(Event::MouseButtonDown(MouseButton::Middle)),
+                        xlib::Button3 => self.events.push(Event::MouseButtonDown(MouseButton::Right)),
+                        xlib::Button4 => self.events.push(Event::MouseWheelUp),
+                        xlib::Button5 => self.events.push(Event::MouseWheelDown),
+                        _ => (),
+                    },
+                    xlib::ButtonRelease => match event.button.button {
+                        xlib::Button1 => self.events.push(Event::MouseButtonUp(MouseButton::Left)),
+                        xlib::Button2 => self.events.push(Event::MouseButtonUp(MouseButton::Middle)),
+                        xlib::Button3 => self.events.push(Event::MouseButtonUp(MouseButton::Right)),
+                        _ => (),
+                    },
+
+                    // mouse movements
+                    xlib::MotionNotify => {
+                        let (x, y) = (event.motion.x, event.motion.y);
+                        self.mouse_tracked = true;
+                        self.mouse_cache = Some((x, y));
+                        self.events.push(Event::MouseMove(x, y));
+                    },
+                    xlib::EnterNotify => self.mouse_tracked = true,
+                    xlib::LeaveNotify => self.mouse_tracked = false,
+
+                    // window resizing
+                    xlib::ConfigureNotify => {
+                        let size = (event.configure.width as u32, event.configure.height as u32);
+                        if size != self.inner_size {
+                            match self.events.last_mut() {
+                                Some(Event::Resize(w, h)) => {
+                                    *w = size.0;
+                                    *h = size.1;
+                                },
+                                _ => self.events.push(Event::Resize(size.0, size.1)),
+                            }
+                            self.inner_size = size;
+                        }
+                    },
+
+                    _ => (),
+                }
+            }
+
+            // if mouse out of bounds, calculate mouse pos, emit if changed
+            if !self.mouse_tracked {
+                let (mut root, mut child) = (0, 0);
+                let (mut root_x, mut root_y, mut x, mut y) = (0, 0, 0, 0);
+                let mut mask = 0;
+                let on_screen = xlib::XQueryPointer(
+                    self.display,
+                    self.window_id,
+                    &mut root,
+                    &mut child,
+                    &mut root_x,
+                    &mut root_y,
+                    &mut x,
+                    &mut y,
+                    &mut mask,
+                );
+                if on_screen != 0 && self.mouse_cache != Some((x, y)) {
+                    self.mouse_cache = Some((x, y));
+                    self.events.push(Event::MouseMove(x, y));
+                }
+            }
+        }
         self.events.iter()
     }
 
     fn resize(&mut self, width: u32, height: u32) {
+        if let Style::BorderlessFullscreen = self.style {
+            return
+        }
+
         self.inner_size = (width, height);
         unsafe {
+            self.apply_size_hints();
             xlib::XResizeWindow(self.display, self.window_id, width, height);
         }
+
+        // GM8 centers the window on the primary display when it's resized
+        self.center();
+    }
+
+    fn center(&mut self) {
+        let (width, height) = self.inner_size;
+        let x = (self.display_width() - width as c_int) / 2;
+        let y = (self.display_height() - height as c_int) / 2;
+        self.set_pos(x, y);
+    }
+
+    fn get_pos(&self) -> (i32, i32) {
+        unsafe {
+            let (mut x, mut y, mut child) = (0, 0, 0);
+            let root = xlib::XRootWindow(self.display, self.screen_id);
+            xlib::XTranslateCoordinates(self.display, self.window_id, root, 0, 0, &mut x, &mut y, &mut child);
+            (x, y)
+        }
+    }
+
+    fn set_pos(&mut self, x: i32, y: i32) {
+        unsafe {
+            xlib::XMoveWindow(self.display, self.window_id, x, y);
+        }
     }
 
     fn get_cursor(&self) -> Cursor {
-        todo!()
+        self.cursor
     }
 
-    fn set_cursor(&mut self, _cursor: Cursor) {
-        todo!()
+    fn set_cursor(&mut self, cursor: Cursor) {
+        self.cursor = cursor;
+        unsafe {
+            let old_handle = self.cursor_handle;
+            self.cursor_handle = load_cursor(self.display, self.window_id, cursor);
+            xlib::XDefineCursor(self.display, self.window_id, self.cursor_handle);
+            xlib::XFreeCursor(self.display, old_handle);
+        }
     }
 
-    fn set_style(&mut self, _style: Style) {
-        todo!()
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+        unsafe {
+            self.apply_style();
+        }
     }
 
     fn get_title(&self) -> &str {
-        todo!()
+        &self.title
     }
 
     fn set_title(&mut self, title: &str) {
+        self.title = title.to_owned();
         unsafe {
             let title = ffi::CString::new(title).unwrap();
             xlib::XStoreName(self.display, self.window_id, title.as_ptr() as *mut _);
@@ -131,15 +664,140 @@ impl WindowTrait for WindowImpl {
         }
     }
 
+    fn show_context_menu(&mut self, options: &[(String, usize)]) {
+        self.close_context_menu();
+        if options.is_empty() {
+            return
+        }
+        unsafe {
+            let font_name = CString::new("fixed").unwrap();
+            let font = xlib::XLoadQueryFont(self.display, font_name.as_ptr());
+            let options = options
+                .iter()
+                .map(|(description, id)| (CString::new(description.replace('\0', "")).unwrap(), *id))
+                .collect::<Vec<_>>();
+            let text_width = options
+                .iter()
+                .map(|(description, _)| {
+                    let len = description.as_bytes().len() as c_int;
+                    if font.is_null() { len * 8 } else { xlib::XTextWidth(font, description.as_ptr(), len) }
+                })
+                .max()
+                .unwrap_or(0);
+            let width = text_width + 16;
+            let height = options.len() as c_int * MENU_ITEM_HEIGHT;
+
+            // open it at the mouse, same as TrackPopupMenu
+            let root = xlib::XRootWindow(self.display, self.screen_id);
+            let (mut root_return, mut child) = (0, 0);
+            let (mut x, mut y, mut win_x, mut win_y) = (0, 0, 0, 0);
+            let mut mask = 0;
+            xlib::XQueryPointer(
+                self.display,
+                root,
+                &mut root_return,
+                &mut child,
+                &mut x,
+                &mut y,
+                &mut win_x,
+                &mut win_y,
+                &mut mask,
+            );
+
+            let mut attributes: xlib::XSetWindowAttributes = mem::zeroed();
+            attributes.override_redirect = xlib::True;
+            attributes.background_pixel = xlib::XWhitePixel(self.display, self.screen_id);
+            attributes.border_pixel = xlib::XBlackPixel(self.display, self.screen_id);
+            attributes.event_mask =
+                xlib::ExposureMask | xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::PointerMotionMask;
+            let window = xlib::XCreateWindow(
+                self.display,
+                root,
+                x,
+                y,
+                width as c_uint,
+                height as c_uint,
+                1,
+                xlib::CopyFromParent,
+                xlib::InputOutput as _,
+                ptr::null_mut(),
+                xlib::CWOverrideRedirect | xlib::CWBackPixel | xlib::CWBorderPixel | xlib::CWEventMask,
+                &mut attributes,
+            );
+            let gc = xlib::XCreateGC(self.display, window, 0, ptr::null_mut());
+            if !font.is_null() {
+                xlib::XSetFont(self.display, gc, (*font).fid);
+            }
+            xlib::XMapRaised(self.display, window);
+
+            // grab the pointer so that clicking anywhere else closes the menu
+            xlib::XGrabPointer(
+                self.display,
+                window,
+                xlib::False,
+                (xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::PointerMotionMask) as c_uint,
+                xlib::GrabModeAsync,
+                xlib::GrabModeAsync,
+                0,
+                0,
+                xlib::CurrentTime,
+            );
+
+            self.context_menu = Some(ContextMenu { window, gc, font, options, width, hovered: None });
+        }
+    }
+
     fn window_handle(&self) -> usize {
-        todo!()
+        self.window_id as usize
     }
 
     fn display_width(&self) -> i32 {
-        unsafe { XDisplayWidth(self.display, self.window_id) }
+        unsafe { XDisplayWidth(self.display, self.screen_id) }
     }
 
     fn display_height(&self) -> i32 {
-        unsafe { XDisplayHeight(self.display, self.window_id) }
+        unsafe { XDisplayHeight(self.display, self.screen_id) }
+    }
+
+    fn display_frequency(&self) -> i32 {
+        // the core protocol has no idea what the refresh rate is, and XRandR isn't always around
+        60
+    }
+
+    fn display_colour(&self) -> i32 {
+        unsafe {
+            // windows reports bits per pixel, which isn't the same as depth (24-bit colour is 32bpp)
+            let depth = xlib::XDefaultDepth(self.display, self.screen_id);
+            let mut count = 0;
+            let formats = xlib::XListPixmapFormats(self.display, &mut count);
+            if formats.is_null() {
+                return depth
+            }
+            let bits_per_pixel = slice::from_raw_parts(formats, count as usize)
+                .iter()
+                .find(|f| f.depth == depth)
+                .map_or(depth, |f| f.bits_per_pixel);
+            xlib::XFree(formats.cast());
+            bits_per_pixel
+        }
+    }
+
+    fn disk_free(&self, drive: Option<char>) -> Option<u64> {
+        disk_space(drive).map(|(free, _)| free)
+    }
+
+    fn disk_size(&self, drive: Option<char>) -> Option<u64> {
+        disk_space(drive).map(|(_, size)| size)
+    }
+}
+
+impl Drop for WindowImpl {
+    fn drop(&mut self) {
+        self.close_context_menu();
+        unsafe {
+            xlib::XFreeCursor(self.display, self.cursor_handle);
+            xlib::XDestroyWindow(self.display, self.window_id);
+            xlib::XCloseDisplay(self.display);
+        }
     }
 }
@@ -747,7 +747,7 @@ impl<'a> AST<'a> {
         Ok(Expr::Function(Box::new(FunctionExpr { name: function_name, params })))
     }
 
-    fn get_op_precedence(op: &Operator) -> Option<u8> {
+    pub(crate) fn get_op_precedence(op: &Operator) -> Option<u8> {
         match op {
             Operator::Add => Some(4),
             Operator::Subtract => Some(4),
@@ -0,0 +1,512 @@
+//! Turns an AST back into GML source code.
+//!
+//! The output always parses back into the same AST it was printed from, but it isn't the original source:
+//! comments, Pascal-style keywords and redundant parentheses are all lost in parsing.
+
+use crate::{
+    ast::{AST, Expr},
+    lexer::Lexer,
+    token::{Operator, Token},
+};
+
+use std::collections::HashSet;
+
+/// Options for printing source code.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PrintOptions {
+    /// If set, each statement is put on its own line and indented, and ends in a semicolon.
+    /// Otherwise everything is printed on one line, which is better for showing short snippets.
+    pub normalise: bool,
+}
+
+/// Prints a list of statements, such as an `AST`, as GML source code.
+pub fn print(statements: &[Expr], options: PrintOptions) -> Vec<u8> {
+    let mut printer = Printer { out: Vec::new(), options, indent: 0 };
+    printer.statements(statements, false);
+    printer.out
+}
+
+/// Prints a single expression as GML source code.
+pub fn print_expr(expr: &Expr, options: PrintOptions) -> Vec<u8> {
+    let mut printer = Printer { out: Vec::new(), options, indent: 0 };
+    printer.expr(expr);
+    printer.out
+}
+
+impl<'a> AST<'a> {
+    /// Prints this AST as GML source code. See `printer::print`.
+    pub fn print(&self, options: PrintOptions) -> Vec<u8> {
+        print(self, options)
+    }
+}
+
+struct Printer {
+    out: Vec<u8>,
+    options: PrintOptions,
+    indent: usize,
+}
+
+impl Printer {
+    fn write(&mut self, s: impl AsRef<[u8]>) {
+        self.out.extend_from_slice(s.as_ref());
+    }
+
+    fn newline(&mut self, indent: usize) {
+        self.out.push(b'\n');
+        self.out.resize(self.out.len() + indent * 4, b' ');
+    }
+
+    /// Prints a list of statements. In normalised mode, each goes on its own line at the current indent.
+    /// Statements following a `case` label inside a switch get one extra level of indentation.
+    fn statements(&mut self, statements: &[Expr], switch_body: bool) {
+        let mut in_case = false;
+        let mut prev_label = false;
+        for (i, statement) in statements.iter().enumerate() {
+            let is_label = matches!(statement, Expr::Case(_) | Expr::Default);
+            if self.options.normalise {
+                let indent = if switch_body && in_case && !is_label { self.indent + 1 } else { self.indent };
+                if i != 0 || self.indent != 0 {
+                    self.newline(indent);
+                }
+                let outer = std::mem::replace(&mut self.indent, indent);
+                self.statement(statement);
+                self.indent = outer;
+            } else {
+                if i != 0 {
+                    self.write(if prev_label { " " } else { "; " });
+                }
+                self.statement(statement);
+            }
+            in_case |= is_label;
+            prev_label = is_label;
+        }
+        if self.options.normalise && self.indent == 0 && !statements.is_empty() {
+            self.write("\n");
+        }
+    }
+
+    /// Prints a braced group of statements.
+    fn block(&mut self, statements: &[Expr], switch_body: bool) {
+        if statements.is_empty() {
+            self.write("{}");
+            return
+        }
+        self.write("{");
+        if self.options.normalise {
+            let outer = self.indent;
+            self.indent += 1;
+            self.statements(statements, switch_body);
+            self.indent = outer;
+            self.newline(outer);
+        } else {
+            self.statements(statements, switch_body);
+        }
+        self.write("}");
+    }
+
+    /// Prints the body of a control statement, then whatever separates it from a following keyword.
+    /// Bodies never get braces added, as that would change the AST, except when `braced` is set:
+    /// that's needed to stop an inner `if` from stealing the `else` of an outer one, which GML can't
+    /// express otherwise, so it won't come up in anything that was parsed.
+    fn body(&mut self, body: &Expr, braced: bool, keyword: Option<&str>) {
+        let block = match body {
+            Expr::Group(statements) => Some(&statements[..]),
+            _ if braced => Some(std::slice::from_ref(body)),
+            _ => None,
+        };
+        match block {
+            Some(statements) => {
+                self.write(" ");
+                self.block(statements, false);
+                if keyword.is_some() {
+                    self.write(" ");
+                }
+            },
+            None if self.options.normalise => {
+                self.indent += 1;
+                self.newline(self.indent);
+                self.statement(body);
+                self.indent -= 1;
+                if keyword.is_some() {
+                    self.newline(self.indent);
+                }
+            },
+            None => {
+                self.write(" ");
+                self.statement(body);
+                if keyword.is_some() {
+                    self.write(" ");
+                }
+            },
+        }
+        if let Some(keyword) = keyword {
+            self.write(keyword);
+        }
+    }
+
+    fn condition(&mut self, cond: &Expr) {
+        self.write("(");
+        self.expr(cond);
+        self.write(")");
+    }
+
+    fn terminator(&mut self) {
+        if self.options.normalise {
+            self.write(";");
+        }
+    }
+
+    fn statement(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Group(statements) => self.block(statements, false),
+            Expr::If(if_ex) => {
+                self.write("if ");
+                self.condition(&if_ex.cond);
+                match &if_ex.else_body {
+                    Some(else_body) => {
+                        self.body(&if_ex.body, ends_with_open_if(&if_ex.body), Some("else"));
+                        if let Expr::If(_) = else_body {
+                            self.write(" ");
+                            self.statement(else_body);
+                        } else {
+                            self.body(else_body, false, None);
+                        }
+                    },
+                    None => self.body(&if_ex.body, false, None),
+                }
+            },
+            Expr::While(while_ex) => {
+                self.write("while ");
+                self.condition(&while_ex.cond);
+                self.body(&while_ex.body, false, None);
+            },
+            Expr::Repeat(repeat) => {
+                self.write("repeat ");
+                self.condition(&repeat.count);
+                self.body(&repeat.body, false, None);
+            },
+            Expr::With(with) => {
+                self.write("with ");
+                self.condition(&with.target);
+                self.body(&with.body, false, None);
+            },
+            Expr::DoUntil(dountil) => {
+                self.write("do");
+                self.body(&dountil.body, false, Some("until "));
+                self.condition(&dountil.cond);
+                self.terminator();
+            },
+            Expr::For(for_ex) => {
+                // The start and step are statements, but they're always printed inline.
+                let options = self.options;
+                self.options.normalise = false;
+                self.write("for (");
+                self.statement(&for_ex.start);
+                self.write("; ");
+                self.expr(&for_ex.cond);
+                self.write("; ");
+                self.statement(&for_ex.step);
+                self.write(")");
+                self.options = options;
+                self.body(&for_ex.body, false, None);
+            },
+            Expr::Switch(switch) => {
+                self.write("switch ");
+                self.condition(&switch.input);
+                self.write(" ");
+                match &switch.body {
+                    Expr::Group(statements) => self.block(statements, true),
+                    body => self.statement(body),
+                }
+            },
+            Expr::Case(value) => {
+                self.write("case ");
+                self.expr(value);
+                self.write(":");
+            },
+            Expr::Default => self.write("default:"),
+            Expr::Var(var) => {
+                self.write("var");
+                self.names(&var.vars);
+                self.terminator();
+            },
+            Expr::GlobalVar(var) => {
+                self.write("globalvar");
+                self.names(&var.vars);
+                self.terminator();
+            },
+            Expr::Continue => {
+                self.write("continue");
+                self.terminator();
+            },
+            Expr::Break => {
+                self.write("break");
+                self.terminator();
+            },
+            Expr::Exit => {
+                self.write("exit");
+                self.terminator();
+            },
+            Expr::Return(value) => {
+                self.write("return ");
+                self.expr(value);
+                self.terminator();
+            },
+            Expr::Binary(binary)
+                if AST::get_op_precedence(&binary.op).is_none()
+                    && binary.op != Operator::Deref
+                    && binary.op != Operator::Index =>
+            {
+                // Assignment - `=` is only an assignment at the start of a statement.
+                self.expr(&binary.left);
+                self.write(" ");
+                self.write(binary.op.to_string());
+                self.write(" ");
+                self.expr(&binary.right);
+                self.terminator();
+            },
+            _ => {
+                self.expr(expr);
+                self.terminator();
+            },
+        }
+    }
+
+    fn names(&mut self, names: &[&[u8]]) {
+        for (i, name) in names.iter().enumerate() {
+            self.write(if i == 0 { " " } else { ", " });
+            self.write(name);
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::LiteralIdentifier(id) => self.write(id),
+            Expr::LiteralReal(r) => self.write(real_literal(*r)),
+            Expr::LiteralString(s) => {
+                // GML strings have no escapes, so the only choice is which quote to use.
+                let quote = if s.contains(&b'"') { b'\'' } else { b'"' };
+                self.out.push(quote);
+                self.write(s);
+                self.out.push(quote);
+            },
+            Expr::Unary(unary) => {
+                self.write(unary.op.to_string());
+                // Unary operators bind tighter than any binary operator except `.` and `[]`.
+                self.operand(&unary.child, precedence(&unary.child).is_some());
+            },
+            Expr::Binary(binary) if binary.op == Operator::Deref || binary.op == Operator::Index => {
+                match &binary.left {
+                    Expr::LiteralIdentifier(_) | Expr::Function(_) => self.expr(&binary.left),
+                    Expr::Binary(left) if left.op == Operator::Deref || left.op == Operator::Index => {
+                        self.expr(&binary.left)
+                    },
+                    left => {
+                        self.write("(");
+                        self.expr(left);
+                        self.write(")");
+                    },
+                }
+                if binary.op == Operator::Deref {
+                    self.write(".");
+                    self.expr(&binary.right);
+                } else {
+                    self.write("[");
+                    match &binary.right {
+                        Expr::Group(dimensions) => self.list(dimensions),
+                        dimension => self.expr(dimension),
+                    }
+                    self.write("]");
+                }
+            },
+            Expr::Binary(binary) => {
+                // All binary operators are left-associative, so the right side needs parentheses
+                // even when it's on the same precedence level.
+                let prec = AST::get_op_precedence(&binary.op);
+                let wrap_left = matches!((precedence(&binary.left), prec), (Some(l), Some(p)) if l < p);
+                let wrap_right = matches!((precedence(&binary.right), prec), (Some(r), Some(p)) if r <= p);
+                self.operand(&binary.left, wrap_left);
+                self.write(" ");
+                self.write(if binary.op == Operator::Assign { "==".into() } else { binary.op.to_string() });
+                self.write(" ");
+                self.operand(&binary.right, wrap_right);
+            },
+            Expr::Function(call) => {
+                self.write(call.name);
+                self.write("(");
+                self.list(&call.params);
+                self.write(")");
+            },
+            _ => {
+                let options = self.options;
+                self.options.normalise = false;
+                self.statement(expr);
+                self.options = options;
+            },
+        }
+    }
+
+    fn operand(&mut self, expr: &Expr, parenthesise: bool) {
+        if parenthesise {
+            self.write("(");
+            self.expr(expr);
+            self.write(")");
+        } else {
+            self.expr(expr);
+        }
+    }
+
+    fn list(&mut self, exprs: &[Expr]) {
+        for (i, expr) in exprs.iter().enumerate() {
+            if i != 0 {
+                self.write(", ");
+            }
+            self.expr(expr);
+        }
+    }
+}
+
+/// Gets the precedence of the operator at the root of an expression, if it's a binary operator
+/// which could be split up by a neighbouring one.
+fn precedence(expr: &Expr) -> Option<u8> {
+    match expr {
+        Expr::Binary(binary) => AST::get_op_precedence(&binary.op),
+        _ => None,
+    }
+}
+
+/// Checks if a statement ends in an `if` with no `else`, which would take the `else` of an enclosing `if`.
+fn ends_with_open_if(expr: &Expr) -> bool {
+    match expr {
+        Expr::If(if_ex) => match &if_ex.else_body {
+            Some(else_body) => ends_with_open_if(else_body),
+            None => true,
+        },
+        Expr::While(while_ex) => ends_with_open_if(&while_ex.body),
+        Expr::Repeat(repeat) => ends_with_open_if(&repeat.body),
+        Expr::With(with) => ends_with_open_if(&with.body),
+        Expr::For(for_ex) => ends_with_open_if(&for_ex.body),
+        _ => false,
+    }
+}
+
+/// Writes out a real so that the lexer will read back exactly the same value.
+///
+/// The lexer builds reals digit by digit, which doesn't always land on the closest f64 to the decimal,
+/// so the shortest representation isn't always right. If it isn't, this works out which digits the lexer
+/// would need to see to end up with the right value.
+fn real_literal(r: f64) -> String {
+    let lexes_to = |s: &str| Lexer::new(s.as_bytes()).next() == Some(Token::Real(r));
+    let shortest = r.to_string();
+    if lexes_to(&shortest) {
+        return shortest
+    }
+    if r.is_infinite() {
+        // Too many digits overflow, and it's the only way to write this.
+        return format!("1{}", "0".repeat(309))
+    }
+    // The lexer's result is (digits as read) * (0.1 for each digit after the point).
+    for places in 0..=24 {
+        let factor = (0..places).fold(1.0f64, |f, _| f / 10.0);
+        for mantissa in nearby(r / factor).filter(|m| m.fract() == 0.0 && m * factor == r) {
+            if let Some(digits) = lexer_digits(mantissa, &mut HashSet::new()) {
+                let digits = format!("{:0>width$}", digits, width = places + 1);
+                let (int, frac) = digits.split_at(digits.len() - places);
+                let s = if places == 0 { int.to_string() } else { format!("{}.{}", int, frac) };
+                if lexes_to(&s) {
+                    return s
+                }
+            }
+        }
+    }
+    shortest
+}
+
+/// Finds a string of digits which the lexer would read as the given whole number.
+/// Beyond 2^53 it accumulates rounding errors, so the exact decimal representation doesn't always work.
+fn lexer_digits(target: f64, dead_ends: &mut HashSet<u64>) -> Option<String> {
+    if target < 2f64.powi(53) {
+        return Some(format!("{:.0}", target))
+    }
+    if dead_ends.contains(&target.to_bits()) {
+        return None
+    }
+    // The last step of reading was `target = prefix * 10 + digit`, so look for a prefix which works.
+    let digits = (0..10u8).find_map(|digit| {
+        nearby((target - f64::from(digit)) / 10.0)
+            .filter(|prefix| prefix.fract() == 0.0 && prefix * 10.0 + f64::from(digit) == target)
+            .find_map(|prefix| lexer_digits(prefix, dead_ends))
+            .map(|digits| format!("{}{}", digits, digit))
+    });
+    if digits.is_none() {
+        dead_ends.insert(target.to_bits());
+    }
+    digits
+}
+
+/// Iterates over the whole numbers, or if they aren't representable the floats, closest to the given one.
+fn nearby(x: f64) -> impl Iterator<Item = f64> {
+    let x = x.round();
+    (0..8i64).map(move |i| {
+        let step = if i % 2 == 0 { i / 2 } else { -(i + 1) / 2 };
+        if x < 2f64.powi(53) { x + step as f64 } else { f64::from_bits((x.to_bits() as i64 + step) as u64) }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_prints(input: &str, compact: &str, normalised: &str) {
+        let ast = AST::new(input.as_bytes()).unwrap();
+        assert_eq!(String::from_utf8_lossy(&ast.print(PrintOptions { normalise: false })), compact);
+        assert_eq!(String::from_utf8_lossy(&ast.print(PrintOptions { normalise: true })), normalised);
+    }
+
+    #[test]
+    fn statements() {
+        assert_prints("a := 1 b = c = d", "a = 1; b = c == d", "a = 1;\nb = c == d;\n");
+        assert_prints("var a, b globalvar c", "var a, b; globalvar c", "var a, b;\nglobalvar c;\n");
+    }
+
+    #[test]
+    fn operators() {
+        assert_prints(
+            "x = (1 + 2) * -(3 - 4 - (5 - 6))",
+            "x = (1 + 2) * -(3 - 4 - (5 - 6))",
+            "x = (1 + 2) * -(3 - 4 - (5 - 6));\n",
+        );
+        assert_prints("(1).x = a.b[1, 2]", "(1).x = a.b[1, 2]", "(1).x = a.b[1, 2];\n");
+        assert_prints("s = 'say \"hi\"' + \"it's\"", "s = 'say \"hi\"' + \"it's\"", "s = 'say \"hi\"' + \"it's\";\n");
+    }
+
+    #[test]
+    fn control_flow() {
+        assert_prints(
+            "if a begin if b c = 1 end else d = 2",
+            "if (a) {if (b) c = 1} else d = 2",
+            "if (a) {\n    if (b)\n        c = 1;\n} else\n    d = 2;\n",
+        );
+        assert_prints(
+            "switch x { case 1: a = 1 break default: exit }",
+            "switch (x) {case 1: a = 1; break; default: exit}",
+            "switch (x) {\n    case 1:\n        a = 1;\n        break;\n    default:\n        exit;\n}\n",
+        );
+        assert_prints(
+            "for (i = 0 i < 3 i += 1) do n += i until n > 5",
+            "for (i = 0; i < 3; i += 1) do n += i until (n > 5)",
+            "for (i = 0; i < 3; i += 1)\n    do\n        n += i;\n    until (n > 5);\n",
+        );
+    }
+
+    #[test]
+    fn reals() {
+        for source in &["0.1", "0.55", "0.5.5", "123456789.987654321", "$FFFFFFFFFFFFFFFF", "18446744073709551615"] {
+            let r = match Lexer::new(source.as_bytes()).next() {
+                Some(Token::Real(r)) => r,
+                t => panic!("{:?} lexed to {:?}", source, t),
+            };
+            assert_eq!(Lexer::new(real_literal(r).as_bytes()).next(), Some(Token::Real(r)), "{}", source);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Conformance corpus for GM8's GML syntax.
+//!
+//! Each case pairs a piece of source with the AST GM8 would build for it, written out using `Expr`'s Display
+//! impl, one line per top-level expression. Most of these are quirks which real games are known to rely on.
+//! When a parser bug turns up in the wild, the offending snippet should be reduced and added here.
+//!
+//! Every accepted case is also printed back out, in both styles, and must parse back into the same AST.
+//!
+//! This is the parse tier. The run tier, which compiles snippets and checks what they return when run on an empty
+//! game, is in gm8emulator's `gml::runtime` tests, since running GML needs a whole `Game`.
+
+use gml_parser::{ast::AST, printer::PrintOptions};
+
+/// Source which must parse, and the expected AST for it.
+const ACCEPT: &[(&str, &str)] = &[
+    // Statement separators are optional, and can be repeated.
+    ("a = 1 b = 2", "(= a 1)\n(= b 2)"),
+    ("a = 1; b = 2;", "(= a 1)\n(= b 2)"),
+    ("{a=1;;;b=2}", "<(= a 1), (= b 2)>"),
+    ("x = 1;", "(= x 1)"),
+    // Pascal-style syntax.
+    ("a := 1", "(= a 1)"),
+    ("begin a = 1 end", "<(= a 1)>"),
+    ("begin end", "<>"),
+    ("a = 1 begin b = 2 end", "(= a 1)\n<(= b 2)>"),
+    ("if a = 1 then b = 2 else c = 3", "(if (== a 1) (= b 2) (= c 3))"),
+    ("while x < 10 do x += 1", "(while (< x 10) (+= x 1))"),
+    ("a = b and c or d xor e", "(= a (^^ (|| (&& b c) d) e))"),
+    ("x = !a == not b", "(= x (== (! a) (! b)))"),
+    ("x = 1 <> 2", "(= x (!= 1 2))"),
+    // `=` is comparison anywhere except at the start of a statement.
+    ("a=b=c", "(= a (== b c))"),
+    ("x=y=z=1", "(= x (== (== y z) 1))"),
+    ("x = 1 <> 2 = 3", "(= x (== (!= 1 2) 3))"),
+    // Compound assignment.
+    ("b += 2; c -= 3; d *= 4; e /= 5", "(+= b 2)\n(-= c 3)\n(*= d 4)\n(/= e 5)"),
+    ("x |= 3; y &= 4; z ^= 5", "(|= x 3)\n(&= y 4)\n(^= z 5)"),
+    ("obj.x += 1", "(+= (. obj x) 1)"),
+    // Numeric literals.
+    ("x = $FF", "(= x 255)"),
+    ("x=$ff+$A0", "(= x (+ 255 160))"),
+    ("x = $10 * 2", "(= x (* 16 2))"),
+    ("x = $", "(= x 0)"),
+    ("x = .5 + 5.", "(= x (+ 0.5 5))"),
+    ("x = 0.5.5", "(= x 0.55)"),
+    // String literals take either quote, have no escapes, and may span lines.
+    ("s = 'it\"s' + \"it's\"", "(= s (+ \"it\"s\" \"it's\"))"),
+    ("x = \"multi\nline\"", "(= x \"multi\nline\")"),
+    // Comments.
+    ("x = a/*c*/+b // c", "(= x (+ a b))"),
+    // Operator precedence. Operators on the same level are always evaluated left to right.
+    ("x = 2 + 3 * 4 - 5 / 6", "(= x (- (+ 2 (* 3 4)) (/ 5 6)))"),
+    ("x = a mod b div c", "(= x (div (mod a b) c))"),
+    ("x = 1 << 2 | 3 & 4 ^ 5", "(= x (^ (& (| (<< 1 2) 3) 4) 5))"),
+    ("x = a >> 1 >= b", "(= x (>= (>> a 1) b))"),
+    ("x = a < b == c > d", "(= x (> (== (< a b) c) d))"),
+    ("x = a != b && c <= d", "(= x (&& (!= a b) (<= c d)))"),
+    ("x = (a || b) && c", "(= x (&& (|| a b) c))"),
+    ("x=a^^b", "(= x (^^ a b))"),
+    ("x = 1 + (2 + 3)", "(= x (+ 1 (+ 2 3)))"),
+    // Unary operators.
+    ("x = -2 * 3", "(= x (* (- 2) 3))"),
+    ("x = 3 * -y", "(= x (* 3 (- y)))"),
+    ("x = a - -b", "(= x (- a (- b)))"),
+    ("x = ---a", "(= x (- (- (- a))))"),
+    ("x = a++b", "(= x (+ a (+ b)))"),
+    ("x = ~5", "(= x (~ 5))"),
+    ("x = 10 mod -3", "(= x (mod 10 (- 3)))"),
+    // Field access and arrays.
+    ("a.b.c = 3", "(= (. (. a b) c) 3)"),
+    ("global.x = self.x + other.y", "(= (. global x) (+ (. self x) (. other y)))"),
+    ("x = a.b[1].c", "(= x (. ([] (. a b) <1>) c))"),
+    ("x = a[0].b", "(= x (. ([] a <0>) b))"),
+    ("x[1, 2] = 3", "(= ([] x <1, 2>) 3)"),
+    ("x = a[i][j]", "(= x ([] ([] a <i>) <j>))"),
+    ("x = argument0 + argument[1]", "(= x (+ argument0 ([] argument <1>)))"),
+    // Function calls.
+    ("f()", "(@f )"),
+    ("x = sin(pi / 2) + max(1, 2, 3)", "(= x (+ (@sin (/ pi 2)) (@max 1 2 3)))"),
+    // Control flow.
+    ("if a <> b c = 1", "(if (!= a b) (= c 1))"),
+    ("if a b = 1; else c = 2", "(if a (= b 1) (= c 2))"),
+    ("if (a) {b=1} else if c d=2 else e=3", "(if a <(= b 1)> (if c (= d 2) (= e 3)))"),
+    ("if a if b c = 1 else d = 2", "(if a (if b (= c 1) (= d 2)))"),
+    ("repeat 3 x *= 2", "(repeat 3 (*= x 2))"),
+    ("repeat (n) { }", "(repeat n <>)"),
+    ("with obj_wall instance_destroy()", "(with obj_wall (@instance_destroy ))"),
+    ("with (other) { x = 1 }", "(with other <(= x 1)>)"),
+    ("do x -= 1 until x <= 0", "(do (-= x 1) until (<= x 0))"),
+    ("while (true) { if x break; continue }", "(while true <(if x (break)), (continue)>)"),
+    ("for (i = 0; i < 10; i += 1) s += i", "(for ((= i 0), (< i 10), (+= i 1)) (+= s i))"),
+    ("for (i := 0 i < 10 i += 1) {}", "(for ((= i 0), (< i 10), (+= i 1)) <>)"),
+    ("exit", "(exit)"),
+    ("return x * 2", "(return (* x 2))"),
+    // Switch bodies are a flat list, so cases fall through unless there's a break.
+    (
+        "switch x { case 1: a = 1 case 2: case 3: b = 2; break; default: c = 3 }",
+        "(switch x <(case 1), (= a 1), (case 2), (case 3), (= b 2), (break), (default), (= c 3)>)",
+    ),
+    ("switch (x) {}", "(switch x <>)"),
+    ("case 1:", "(case 1)"),
+    // Declarations.
+    ("var a, b; globalvar c;", "(var a b)\n(globalvar c)"),
+];
+
+/// Source which GM8 refuses to compile.
+const REJECT: &[&str] =
+    &["x = ", "x = (1", "if then", "7 div 2 mod 3", "x = 1 # 2", "return;", "x = a.b(1)", "global var = 1"];
+
+#[test]
+fn accept() {
+    let mut failures = Vec::new();
+    for (source, expected) in ACCEPT {
+        match AST::new(source.as_bytes()) {
+            Ok(ast) => {
+                let actual = ast.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n");
+                if actual != *expected {
+                    failures.push(format!("{:?}\n  expected: {}\n  actual:   {}", source, expected, actual));
+                }
+            },
+            Err(e) => failures.push(format!("{:?}\n  expected: {}\n  error:    {}", source, expected, e)),
+        }
+    }
+    assert!(failures.is_empty(), "{} case(s) failed:\n{}", failures.len(), failures.join("\n"));
+}
+
+#[test]
+fn reject() {
+    let accepted = REJECT.iter().filter(|source| AST::new(source.as_bytes()).is_ok()).collect::<Vec<_>>();
+    assert!(accepted.is_empty(), "invalid source was accepted: {:?}", accepted);
+}
+
+#[test]
+fn round_trip() {
+    let mut failures = Vec::new();
+    for (source, _) in ACCEPT {
+        let ast = AST::new(source.as_bytes()).unwrap();
+        for &normalise in &[false, true] {
+            let printed = ast.print(PrintOptions { normalise });
+            match AST::new(&printed) {
+                Ok(reparsed) if reparsed == ast => (),
+                result => failures.push(format!(
+                    "{:?}\n  printed:  {:?}\n  reparsed: {:?}",
+                    source,
+                    String::from_utf8_lossy(&printed),
+                    result.map(|ast| ast.iter().map(|e| e.to_string()).collect::<Vec<_>>()),
+                )),
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{} case(s) failed:\n{}", failures.len(), failures.join("\n"));
+}